@@ -0,0 +1,2 @@
+-- optional CEL-like expression that has to evaluate to true for the grant to apply
+ALTER TABLE groups_permissions ADD COLUMN condition VARCHAR(3000);
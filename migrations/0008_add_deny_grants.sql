@@ -0,0 +1,11 @@
+-- Effects:
+-- allow
+-- deny
+ALTER TABLE groups_permissions ADD COLUMN effect VARCHAR(5) NOT NULL DEFAULT 'allow';
+
+CREATE TABLE users_permissions (
+  user_login VARCHAR(255) REFERENCES users(login),
+  permission_name VARCHAR(255) REFERENCES permissions(name),
+  effect VARCHAR(5) NOT NULL DEFAULT 'allow',
+  condition VARCHAR(3000)
+);
@@ -0,0 +1,4 @@
+-- NULL means the grant never expires
+ALTER TABLE users_groups ADD COLUMN expires_at TIMESTAMPTZ;
+ALTER TABLE groups_permissions ADD COLUMN expires_at TIMESTAMPTZ;
+ALTER TABLE users_permissions ADD COLUMN expires_at TIMESTAMPTZ;
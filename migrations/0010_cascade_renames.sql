@@ -0,0 +1,18 @@
+-- Renaming groups and permissions cascades to all of the grants referencing them
+ALTER TABLE groups_permissions
+  DROP CONSTRAINT groups_permissions_group_name_fkey,
+  ADD CONSTRAINT groups_permissions_group_name_fkey
+    FOREIGN KEY (group_name) REFERENCES groups(name) ON UPDATE CASCADE,
+  DROP CONSTRAINT groups_permissions_permission_name_fkey,
+  ADD CONSTRAINT groups_permissions_permission_name_fkey
+    FOREIGN KEY (permission_name) REFERENCES permissions(name) ON UPDATE CASCADE;
+
+ALTER TABLE users_groups
+  DROP CONSTRAINT users_groups_group_name_fkey,
+  ADD CONSTRAINT users_groups_group_name_fkey
+    FOREIGN KEY (group_name) REFERENCES groups(name) ON UPDATE CASCADE;
+
+ALTER TABLE users_permissions
+  DROP CONSTRAINT users_permissions_permission_name_fkey,
+  ADD CONSTRAINT users_permissions_permission_name_fkey
+    FOREIGN KEY (permission_name) REFERENCES permissions(name) ON UPDATE CASCADE;
@@ -0,0 +1,3 @@
+-- Protected permissions and groups are created on startup and cannot be deleted or renamed
+ALTER TABLE permissions ADD COLUMN protected BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE groups ADD COLUMN protected BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,6 @@
+-- Managers can grant and revoke their group to and from other users
+CREATE TABLE groups_managers (
+  group_name VARCHAR(255) REFERENCES groups(name) ON UPDATE CASCADE,
+  user_login VARCHAR(255) REFERENCES users(login),
+  PRIMARY KEY (group_name, user_login)
+);
@@ -0,0 +1,11 @@
+-- Separation of duties: a user can belong to at most one group of each set
+CREATE TABLE exclusive_group_sets (
+  name VARCHAR(255) PRIMARY KEY,
+  description VARCHAR(3000)
+);
+
+CREATE TABLE exclusive_group_sets_groups (
+  set_name VARCHAR(255) REFERENCES exclusive_group_sets(name) ON DELETE CASCADE,
+  group_name VARCHAR(255) REFERENCES groups(name) ON UPDATE CASCADE,
+  PRIMARY KEY (set_name, group_name)
+);
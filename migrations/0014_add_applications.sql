@@ -0,0 +1,13 @@
+-- Registered applications own the "{namespace}:*" permissions and groups
+CREATE TABLE applications (
+  name VARCHAR(255) PRIMARY KEY,
+  namespace VARCHAR(255) NOT NULL UNIQUE,
+  description VARCHAR(3000)
+);
+
+-- App admins can create, delete and grant the permissions and groups inside the namespace
+CREATE TABLE applications_admins (
+  application_name VARCHAR(255) REFERENCES applications(name) ON DELETE CASCADE,
+  user_login VARCHAR(255) REFERENCES users(login),
+  PRIMARY KEY (application_name, user_login)
+);
@@ -0,0 +1,40 @@
+-- Organizations isolate group memberships and permission grants of their members,
+-- the existing ones are moved to the default organization
+CREATE TABLE organizations (
+  name VARCHAR(255) PRIMARY KEY,
+  description VARCHAR(3000)
+);
+
+INSERT INTO organizations (name, description) VALUES ('default', 'the default organization');
+
+CREATE TABLE organizations_users (
+  organization_name VARCHAR(255) REFERENCES organizations(name) ON UPDATE CASCADE ON DELETE CASCADE,
+  user_login VARCHAR(255) REFERENCES users(login),
+  PRIMARY KEY (organization_name, user_login)
+);
+
+INSERT INTO organizations_users (organization_name, user_login) SELECT 'default', login FROM users;
+
+ALTER TABLE users_groups
+  ADD COLUMN organization_name VARCHAR(255) NOT NULL DEFAULT 'default'
+    REFERENCES organizations(name) ON UPDATE CASCADE ON DELETE CASCADE;
+
+ALTER TABLE groups_permissions
+  ADD COLUMN organization_name VARCHAR(255) NOT NULL DEFAULT 'default'
+    REFERENCES organizations(name) ON UPDATE CASCADE ON DELETE CASCADE;
+
+ALTER TABLE users_permissions
+  ADD COLUMN organization_name VARCHAR(255) NOT NULL DEFAULT 'default'
+    REFERENCES organizations(name) ON UPDATE CASCADE ON DELETE CASCADE;
+
+-- Managers grant and revoke their group only inside the organization they manage it in
+ALTER TABLE groups_managers
+  ADD COLUMN organization_name VARCHAR(255) NOT NULL DEFAULT 'default'
+    REFERENCES organizations(name) ON UPDATE CASCADE ON DELETE CASCADE,
+  DROP CONSTRAINT groups_managers_pkey,
+  ADD PRIMARY KEY (group_name, user_login, organization_name);
+
+-- Active organization of the session, permissions are resolved inside of it
+ALTER TABLE login_sessions
+  ADD COLUMN organization_name VARCHAR(255) NOT NULL DEFAULT 'default'
+    REFERENCES organizations(name) ON UPDATE CASCADE ON DELETE CASCADE;
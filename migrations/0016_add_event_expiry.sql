@@ -0,0 +1,4 @@
+-- Pending events expire after the configured TTL and are purged in the background
+ALTER TABLE user_register_events ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE user_login_events ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE user_delete_events ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1,19 @@
+-- All the two-phase events share a single table, the event's data is kept as JSON
+-- whose shape depends on the event type. Changes made by committing an event are attributed
+-- to the actor, the user whose session prepared it
+CREATE TABLE pending_events (
+  id SERIAL PRIMARY KEY,
+  event_type VARCHAR(255) NOT NULL,
+  key VARCHAR NOT NULL,
+  payload JSONB NOT NULL,
+  actor VARCHAR(255),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX pending_events_created_at_idx ON pending_events (created_at);
+
+-- Pending events are short lived (see event_ttl in the config), so they are dropped
+-- instead of being moved, as their ids would change anyway
+DROP TABLE user_register_events;
+DROP TABLE user_login_events;
+DROP TABLE user_delete_events;
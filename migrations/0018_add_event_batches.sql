@@ -0,0 +1,10 @@
+-- Batches group pending events committed or cancelled together with a single key,
+-- their events are attributed to the user who prepared the batch
+CREATE TABLE event_batches (
+  id SERIAL PRIMARY KEY,
+  key VARCHAR NOT NULL,
+  actor VARCHAR(255),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE pending_events ADD COLUMN batch_id INT REFERENCES event_batches (id) ON DELETE CASCADE;
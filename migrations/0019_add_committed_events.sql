@@ -0,0 +1,11 @@
+-- Results of committed events and batches, so retried commits return the original result
+-- instead of failing, kept for the same TTL as the pending events. The output is not recorded
+-- for the results which have to reach only the first commit (e.g. session tokens)
+CREATE TABLE committed_events (
+  event_type VARCHAR(255) NOT NULL,
+  id INT NOT NULL,
+  key VARCHAR NOT NULL,
+  output JSONB,
+  committed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (event_type, id)
+);
@@ -0,0 +1,27 @@
+-- Four-eyes approvals: operations matching a rule become change requests,
+-- applied only once enough users other than the requester approve them
+CREATE TABLE approval_rules (
+  id SERIAL PRIMARY KEY,
+  -- event type of the operation, e.g. "users/delete"
+  operation VARCHAR(255) NOT NULL,
+  -- NULL matches all the operations of the type
+  target VARCHAR(255),
+  approvals INT NOT NULL DEFAULT 1 CHECK (approvals > 0),
+  UNIQUE NULLS NOT DISTINCT (operation, target)
+);
+
+CREATE TABLE change_requests (
+  id SERIAL PRIMARY KEY,
+  event_type VARCHAR(255) NOT NULL,
+  payload JSONB NOT NULL,
+  requested_by VARCHAR(255) REFERENCES users(login) ON UPDATE CASCADE ON DELETE SET NULL,
+  approvals_required INT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE change_request_approvals (
+  change_request_id INT REFERENCES change_requests(id) ON DELETE CASCADE,
+  login VARCHAR(255) REFERENCES users(login) ON UPDATE CASCADE ON DELETE CASCADE,
+  approved_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (change_request_id, login)
+);
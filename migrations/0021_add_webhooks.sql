@@ -0,0 +1,28 @@
+-- Webhook subscriptions, an empty list of event types subscribes to all of them
+CREATE TABLE webhooks (
+  id SERIAL PRIMARY KEY,
+  url VARCHAR(2048) NOT NULL,
+  event_types VARCHAR(255)[] NOT NULL DEFAULT '{}',
+  secret VARCHAR NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Outbox of the deliveries, written in the same transaction as the change they report.
+-- Delivered rows are deleted, the ones which failed too many times are kept as "dead".
+-- Deliveries are claimed by marking them "in_flight" until the lease ends, so they are sent
+-- without holding locks, the ones of a dispatcher which stopped before recording them are claimed again
+CREATE TABLE webhook_deliveries (
+  id SERIAL PRIMARY KEY,
+  webhook_id INT NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+  event_type VARCHAR(255) NOT NULL,
+  payload JSONB NOT NULL,
+  status VARCHAR(16) NOT NULL DEFAULT 'pending',
+  attempts INT NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  last_error VARCHAR,
+  leased_until TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhook_deliveries_due ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX webhook_deliveries_leased ON webhook_deliveries (leased_until) WHERE status = 'in_flight';
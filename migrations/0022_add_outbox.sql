@@ -0,0 +1,7 @@
+-- Domain events waiting to be published to the broker, deleted once they are published
+CREATE TABLE outbox_events (
+  id BIGSERIAL PRIMARY KEY,
+  event_type VARCHAR(255) NOT NULL,
+  payload JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
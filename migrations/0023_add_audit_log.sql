@@ -0,0 +1,93 @@
+-- Durable record of the changes and logins, the actor and ip are read from the cauth.actor and cauth.ip
+-- settings of the connection the change is made with
+CREATE TABLE audit_log (
+  id BIGSERIAL PRIMARY KEY,
+  actor VARCHAR(255),
+  action VARCHAR(255) NOT NULL,
+  target TEXT,
+  before JSONB,
+  after JSONB,
+  ip VARCHAR(255),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_log_actor_idx ON audit_log (actor);
+CREATE INDEX audit_log_action_idx ON audit_log (action);
+CREATE INDEX audit_log_created_at_idx ON audit_log (created_at);
+
+-- Records a changed row as "<resource>.<created|updated|deleted>", the first argument is the resource
+-- and the rest are the columns identifying the row, joined with "/" into the target.
+-- Secrets (password hashes, tokens, webhook secrets and event payloads) are never recorded
+CREATE FUNCTION audit_row_change() RETURNS TRIGGER AS $$
+DECLARE
+  before_state JSONB;
+  after_state JSONB;
+  target TEXT;
+  i INT;
+BEGIN
+  IF TG_OP <> 'INSERT' THEN
+    before_state := to_jsonb(OLD) - ARRAY['password_hash', 'token', 'secret', 'payload'];
+  END IF;
+
+  IF TG_OP <> 'DELETE' THEN
+    after_state := to_jsonb(NEW) - ARRAY['password_hash', 'token', 'secret', 'payload'];
+  END IF;
+
+  IF TG_OP = 'UPDATE' AND to_jsonb(OLD) = to_jsonb(NEW) THEN
+    RETURN NULL;
+  END IF;
+
+  FOR i IN 1 .. TG_NARGS - 1 LOOP
+    target := concat_ws('/', target, COALESCE(after_state, before_state) ->> TG_ARGV[i]);
+  END LOOP;
+
+  INSERT INTO audit_log (actor, action, target, before, after, ip)
+  VALUES (
+    NULLIF(current_setting('cauth.actor', true), ''),
+    TG_ARGV[0] || '.' || CASE TG_OP WHEN 'INSERT' THEN 'created' WHEN 'UPDATE' THEN 'updated' ELSE 'deleted' END,
+    target,
+    before_state,
+    after_state,
+    NULLIF(current_setting('cauth.ip', true), '')
+  );
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON users
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('user', 'login');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON groups
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('group', 'name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON permissions
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('permission', 'name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON users_groups
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('user_group', 'user_login', 'group_name', 'organization_name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON users_permissions
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('user_permission', 'user_login', 'permission_name', 'organization_name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON groups_permissions
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('group_permission', 'group_name', 'permission_name', 'organization_name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON groups_managers
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('group_manager', 'group_name', 'user_login', 'organization_name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON exclusive_group_sets
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('exclusive_group_set', 'name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON exclusive_group_sets_groups
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('exclusive_group_set_group', 'set_name', 'group_name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON applications
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('application', 'name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON applications_admins
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('application_admin', 'application_name', 'user_login');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON organizations
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('organization', 'name');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON organizations_users
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('organization_member', 'organization_name', 'user_login');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON login_sessions
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('session', 'user_login');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON approval_rules
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('approval_rule', 'operation', 'target');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON change_requests
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('change_request', 'id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON change_request_approvals
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('change_request_approval', 'change_request_id', 'login');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON webhooks
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change('webhook', 'id');
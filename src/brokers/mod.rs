@@ -0,0 +1,44 @@
+pub mod redis_streams;
+
+use crate::{
+    config::BrokerConfig,
+    models::outbox::OutboxEvent
+};
+use redis_streams::RedisStreamsBroker;
+
+/// Connection to the broker the domain events are published to
+pub enum Broker {
+    RedisStreams(RedisStreamsBroker)
+}
+
+impl Broker {
+    /// ## Broker::connect
+    ///
+    /// Connects to the configured broker
+    ///
+    /// Errors:
+    /// + when the broker is unreachable
+    ///
+    pub async fn connect(config: &BrokerConfig) -> Result<Broker, String> {
+        return match config {
+            BrokerConfig::RedisStreams { url, stream } => {
+                let broker = RedisStreamsBroker::connect(url, stream).await?;
+                Ok(Broker::RedisStreams(broker))
+            }
+        };
+    }
+
+    /// ## Broker::publish
+    ///
+    /// Publishes the event, it is published again when the outbox is not updated afterwards,
+    /// so consumers should deduplicate the events by their id
+    ///
+    /// Errors:
+    /// + when the broker do not accept the event
+    ///
+    pub async fn publish(&mut self, event: &OutboxEvent) -> Result<(), String> {
+        return match self {
+            Broker::RedisStreams(broker) => broker.publish(event).await
+        };
+    }
+}
@@ -0,0 +1,63 @@
+use redis::aio::MultiplexedConnection;
+use crate::models::outbox::OutboxEvent;
+
+/// Publishes the domain events as entries of a Redis stream,
+/// with `id`, `type`, `created_at` and JSON encoded `data` fields
+pub struct RedisStreamsBroker {
+    conn: MultiplexedConnection,
+    stream: String
+}
+
+impl RedisStreamsBroker {
+    /// ## RedisStreamsBroker::connect
+    ///
+    /// Connects to the Redis server under provided url
+    ///
+    /// Errors:
+    /// + when the url is invalid or the server is unreachable
+    ///
+    pub async fn connect(url: &str, stream: &str) -> Result<RedisStreamsBroker, String> {
+        let client = match redis::Client::open(url) {
+            Ok(client) => client,
+            Err(err) => return Err(err.to_string())
+        };
+
+        let conn = match client.get_multiplexed_tokio_connection().await {
+            Ok(conn) => conn,
+            Err(err) => return Err(err.to_string())
+        };
+
+        return Ok(RedisStreamsBroker {
+            conn,
+            stream: stream.to_string()
+        });
+    }
+
+    /// ## RedisStreamsBroker::publish
+    ///
+    /// Appends the event to the stream
+    ///
+    /// Errors:
+    /// + when the server do not accept the entry
+    ///
+    pub async fn publish(&mut self, event: &OutboxEvent) -> Result<(), String> {
+        let result: redis::RedisResult<String> = redis::cmd("XADD")
+            .arg(&self.stream)
+            .arg("*")
+            .arg("id")
+            .arg(event.id)
+            .arg("type")
+            .arg(&event.event_type)
+            .arg("created_at")
+            .arg(event.created_at.to_rfc3339())
+            .arg("data")
+            .arg(event.payload.to_string())
+            .query_async(&mut self.conn)
+            .await;
+
+        return match result {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err.to_string())
+        };
+    }
+}
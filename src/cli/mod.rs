@@ -5,7 +5,7 @@ use crate::{
         admin::AdminCommand, config::ConfigCommand, daemon::DaemonCommand, run::RunCommand,
     },
     config::CauthConfig,
-    models::{Group, Permission},
+    models::{organization::DEFAULT_ORGANIZATION, Group, GrantEffect, Permission},
 };
 use clap::{Parser, Subcommand};
 
@@ -18,7 +18,15 @@ pub struct CauthCli {
 
 impl CauthCli {
     pub fn run(self) {
-        let config = CauthConfig::parse_or_edit();
+        // changes made with the admin commands are audited as the system user running them
+        let audit_actor = match &self.action {
+            ActionType::Admin(_) => Some(format!(
+                "cli:{}",
+                std::env::var("USER").unwrap_or("unknown".to_string())
+            )),
+            _ => None,
+        };
+        let config = CauthConfig::parse_or_edit(audit_actor.as_ref());
 
         let _ = match self.action {
             ActionType::Run(cmd) => cmd.run(config),
@@ -37,89 +45,95 @@ pub enum ActionType {
     Admin(AdminCommand),
 }
 
+/// Permissions added on startup, all of them are protected and granted to the root group
+const DEFAULT_PERMISSIONS: [(&str, &str); 28] = [
+    ("cauth:permissions:get", "permission to retrieve the permission list from the database"),
+    ("cauth:permissions:post", "permission to post new permission to the database"),
+    ("cauth:permissions:delete", "permission to delete a permission from the database"),
+    ("cauth:permissions:update", "permission to update and rename permissions in the database"),
+    ("cauth:permissions:explain", "permission to explain permission decisions of any user"),
+    ("cauth:groups:get", "permission to retrieve the groups list from the database"),
+    ("cauth:groups:post", "permission to post new group to the database"),
+    ("cauth:groups:delete", "permission to delete a group from the database"),
+    ("cauth:groups:update", "permission to update/rename groups and grant/revoke permissions to groups"),
+    ("cauth:users:update", "permission to grant/revoke groups to users"),
+    ("cauth:users:delegate", "permission to grant/revoke the groups the user belongs to to other users"),
+    ("cauth:users:delete", "permission to delete ANY user on the service, use with caution."),
+    ("cauth:applications:get", "permission to retrieve the applications list from the database"),
+    ("cauth:applications:post", "permission to register new applications owning a namespace"),
+    ("cauth:applications:delete", "permission to delete an application from the database"),
+    ("cauth:applications:update", "permission to grant/revoke application admins"),
+    ("cauth:organizations:get", "permission to retrieve the organizations list and their members"),
+    ("cauth:organizations:post", "permission to create new organizations"),
+    ("cauth:organizations:delete", "permission to delete an organization along with everything granted inside of it"),
+    ("cauth:organizations:update", "permission to add/remove organization members"),
+    ("cauth:events:get", "permission to list and inspect pending events of all the users"),
+    ("cauth:approvals:get", "permission to list and inspect change requests waiting for approvals"),
+    ("cauth:approvals:update", "permission to approve/reject change requests, changes cannot be approved by their requester"),
+    ("cauth:webhooks:get", "permission to list webhooks and their pending and dead deliveries"),
+    ("cauth:webhooks:post", "permission to subscribe new webhooks to the service's events"),
+    ("cauth:webhooks:delete", "permission to delete a webhook along with it's deliveries"),
+    ("cauth:webhooks:update", "permission to retry dead webhook deliveries"),
+    ("cauth:audit:get", "permission to list the audit log of changes and logins"),
+];
+
 pub async fn init_defaults(config: &CauthConfig) {
     let mut tx = config.db_conn.begin().await.unwrap();
 
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:permissions:get".to_string(),
-        &"permission to retrieve the permission list from the database".to_string(),
-    )
-    .await;
-
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:permissions:post".to_string(),
-        &"permission to post new permission to the database".to_string(),
-    )
-    .await;
-
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:permissions:delete".to_string(),
-        &"permission to delete a permission from the database".to_string(),
-    )
-    .await;
-
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:groups:get".to_string(),
-        &"permission to retrieve the groups list from the database".to_string(),
-    )
-    .await;
-
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:groups:post".to_string(),
-        &"permission to post new group to the database".to_string(),
-    )
-    .await;
-
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:groups:delete".to_string(),
-        &"permission to post new group to the database".to_string(),
-    )
-    .await;
-
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:groups:update".to_string(),
-        &"permission to grant/revoke permissions to groups".to_string(),
-    )
-    .await;
-
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:users:update".to_string(),
-        &"permission to grant/revoke groups to users".to_string(),
-    )
-    .await;
-
-    let _ = Permission::insert(
-        &mut tx,
-        &"cauth:users:delete".to_string(),
-        &"permission to delete ANY user on the service, use with caution.".to_string(),
-    )
-    .await;
-
-    let _ = Group::insert(
-    &mut tx,
-    &"root".to_string(), 
-    &"the most privileged group, having to permissions to do everything. Caution: do not grant this group to any untrusted user as it can result in damages done to your system. Instead, create their own group fitting their needs.".to_string(),
-    &vec![
-      "cauth:permissions:get".to_string(),
-      "cauth:permissions:post".to_string(),
-      "cauth:permissions:delete".to_string(),
-      "cauth:groups:get".to_string(),
-      "cauth:groups:post".to_string(),
-      "cauth:groups:delete".to_string(),
-      "cauth:groups:update".to_string(),
-      "cauth:users:update".to_string(),
-      "cauth:users:delete".to_string()
-    ]
-  )
-  .await;
+    // a failed insert aborts the whole transaction so we only insert what's missing
+    for (name, description) in DEFAULT_PERMISSIONS {
+        let name = name.to_string();
+
+        if Permission::retrieve(&mut tx, &name).await.is_err() {
+            let _ = Permission::insert(&mut tx, &name, &description.to_string()).await;
+        }
+
+        Permission::protect(&mut tx, &name).await;
+    }
+
+    let root = "root".to_string();
+    let organization = DEFAULT_ORGANIZATION.to_string();
+
+    if Group::retrieve(&mut tx, &root, &organization).await.is_err() {
+        let _ = Group::insert(
+            &mut tx,
+            &root,
+            &"the most privileged group, having to permissions to do everything. Caution: do not grant this group to any untrusted user as it can result in damages done to your system. Instead, create their own group fitting their needs.".to_string(),
+            &vec![],
+            &organization,
+        )
+        .await;
+    }
+
+    Group::protect(&mut tx, &root).await;
+
+    // grants root also the permissions added in newer versions
+    for (name, _) in DEFAULT_PERMISSIONS {
+        let name = name.to_string();
+
+        if let Ok(false) = Group::has_permission(&mut tx, &root, &name, &organization).await {
+            let _ = Group::grant_permission(&mut tx, &root, &name, GrantEffect::Allow, None, None, &organization).await;
+        }
+    }
 
     let _ = tx.commit().await;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_permissions_use_the_same_verbs() {
+        // the routes changing an entity require "update", whichever http method they use
+        for (name, _) in DEFAULT_PERMISSIONS {
+            let verb = name.rsplit(':').next().unwrap();
+
+            assert!(
+                ["get", "post", "delete", "update", "delegate", "explain"].contains(&verb),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -1,35 +1,62 @@
 
+use chrono::{DateTime, Utc};
 use clap::{
   Args,
   Subcommand
 };
 use colored::Colorize;
 use futures::executor::block_on;
+use std::fs;
 
-use crate::{config::CauthConfig, models::{group::{Group, GroupGrantError, GroupRevokeError}, permission::Permission, user::{User, UserGrantError, UserRevokeError}}, util::io::input};
+use crate::{config::{CauthConfig, RegistrationSource}, models::{group::{Group, GroupGrantError, GroupGrantManagerError, GroupRevokeError, GroupRevokeManagerError}, permission::Permission, exclusive_group_set::ExclusiveGroupSet, application::{Application, ApplicationGrantAdminError, ApplicationRevokeAdminError}, organization::{Organization, DEFAULT_ORGANIZATION}, event::{stored_event::StoredEventFilter, change_request::ChangeRequestApproveError, ApprovalRule, ChangeRequest, StoredEvent}, policy::{Policy, PolicyApplyError}, webhook::{Webhook, WebhookDelivery}, audit::{AuditEntry, AuditFilter}, user::{User, UserInsertError, UserGrantError, UserGrantPermissionError, UserRevokeError, UserRevokePermissionError}, GrantEffect, RequestContext}, util::{io::input, string::json_value_to_pretty_string}};
 
 
 #[derive(Debug, Args)]
 pub struct AdminCommand {
   #[clap(subcommand)]
-  pub action: AdminAction
+  pub action: AdminAction,
+  /// Organization the groups and grants are managed in
+  #[clap(long, global = true, default_value = DEFAULT_ORGANIZATION)]
+  pub org: String
 }
 
 #[derive(Debug, Subcommand)]
 pub enum AdminAction {
   Create(AdminCreateCommand),
   Inspect(AdminInspectCommand),
+  List(AdminListCommand),
   Grant(AdminGrantCommand),
-  Revoke(AdminRevokeCommand)
+  Revoke(AdminRevokeCommand),
+  Edit(AdminEditCommand),
+  Sod(AdminSodCommand),
+  App(AdminAppCommand),
+  Org(AdminOrgCommand),
+  Approvals(AdminApprovalsCommand),
+  Webhooks(AdminWebhooksCommand),
+  Audit(AdminAuditCommand),
+  Apply(AdminApplyCommand),
+  ExportPolicy(AdminExportPolicyCommand),
+  Explain(AdminExplainCommand)
 }
 
 impl AdminCommand {
   pub fn run(self, config: CauthConfig) {
     let _ = match self.action {
-      AdminAction::Create(cmd) => cmd.run(config),
-      AdminAction::Inspect(cmd) => cmd.run(config),
-      AdminAction::Grant(cmd) => cmd.run(config),
-      AdminAction::Revoke(cmd) => cmd.run(config)
+      AdminAction::Create(cmd) => cmd.run(config, &self.org),
+      AdminAction::Inspect(cmd) => cmd.run(config, &self.org),
+      AdminAction::List(cmd) => cmd.run(config, &self.org),
+      AdminAction::Grant(cmd) => cmd.run(config, &self.org),
+      AdminAction::Revoke(cmd) => cmd.run(config, &self.org),
+      AdminAction::Edit(cmd) => cmd.run(config),
+      AdminAction::Sod(cmd) => cmd.run(config),
+      AdminAction::App(cmd) => cmd.run(config),
+      AdminAction::Org(cmd) => cmd.run(config),
+      AdminAction::Approvals(cmd) => cmd.run(config),
+      AdminAction::Webhooks(cmd) => cmd.run(config),
+      AdminAction::Audit(cmd) => cmd.run(config),
+      AdminAction::Apply(cmd) => cmd.run(config, &self.org),
+      AdminAction::ExportPolicy(cmd) => cmd.run(config, &self.org),
+      AdminAction::Explain(cmd) => cmd.run(config, &self.org)
     };
   }
 }
@@ -45,17 +72,22 @@ pub struct AdminCreateCommand {
 #[derive(Debug, Subcommand)]
 pub enum AdminCreateEntityType {
   Permission,
-  Group
+  Group,
+  /// User granted the admin default groups from the config
+  User
 }
 
 impl AdminCreateCommand {
-  pub fn run(self, config: CauthConfig) {
+  pub fn run(self, config: CauthConfig, organization: &String) {
     match self.entity_type {
       AdminCreateEntityType::Permission => {
         let _ = block_on(Self::create_permission(config));
       },
       AdminCreateEntityType::Group => {
-        let _ = block_on(Self::create_group(config));
+        let _ = block_on(Self::create_group(config, organization));
+      },
+      AdminCreateEntityType::User => {
+        let _ = block_on(Self::create_user(config));
       }
     }
   }
@@ -67,11 +99,11 @@ impl AdminCreateCommand {
     let mut executor = config.db_conn.acquire().await.unwrap();
     match Permission::insert(&mut executor, &name, &description).await {
       Ok(_) => (),
-      Err(_) => println!("{}", "This permission already exist".red())
+      Err(err) => println!("{}", err.to_string().red())
     };
   }
 
-  async fn create_group(config: CauthConfig) {
+  async fn create_group(config: CauthConfig, organization: &String) {
     let mut executor = config.db_conn.acquire().await.unwrap();
 
     let name = input(format!("{} Enter the name of the group: ", "+".green())).unwrap();
@@ -91,11 +123,43 @@ impl AdminCreateCommand {
       }
     }
 
-    match Group::insert(&mut executor, &name, &description, &permissions).await {
+    match Group::insert(&mut executor, &name, &description, &permissions, organization).await {
       Ok(_) => (),
       Err(_) => println!("{}", "This group already exist".red())
     };
   }
+
+  async fn create_user(config: CauthConfig) {
+    let login = input(format!("{} Enter the login of the user: ", "+".green())).unwrap();
+    let password = input(format!("{} Enter the password of the user: ", "+".green())).unwrap();
+
+    // the user is only kept if all of the default groups are granted
+    let mut tx = config.db_conn.begin().await.unwrap();
+    let result = User::insert(
+      &mut tx,
+      &login,
+      &password,
+      &serde_json::json!({}),
+      config.default_groups.for_source(RegistrationSource::Admin)
+    ).await;
+
+    match result {
+      Ok(_) => (),
+      Err(UserInsertError::NameError) => {
+        println!("{}", "This user already exist".red());
+        return;
+      },
+      Err(err) => {
+        println!("{}", err.to_string().red());
+        return;
+      }
+    };
+
+    match tx.commit().await {
+      Ok(_) => (),
+      Err(err) => println!("{}", format!("Cannot commit the changes: {}", err).red())
+    };
+  }
 }
 
 
@@ -110,7 +174,9 @@ pub struct AdminInspectCommand {
 pub enum AdminInspectEntityType {
   Permission(AdminInspectStringIDCommand),
   Group(AdminInspectStringIDCommand),
-  User(AdminInspectStringIDCommand)
+  User(AdminInspectStringIDCommand),
+  /// Pending event of any type, without it's key
+  Event(AdminInspectIntegerIDCommand)
 }
 
 #[derive(Debug, Args)]
@@ -124,7 +190,7 @@ pub struct AdminInspectIntegerIDCommand {
 }
 
 impl AdminInspectCommand {
-  pub fn run(self, config: CauthConfig) {
+  pub fn run(self, config: CauthConfig, organization: &String) {
     match self.entity_type {
       AdminInspectEntityType::Permission(id) => {
         let mut executor = block_on(config.db_conn.acquire()).unwrap();
@@ -140,7 +206,7 @@ impl AdminInspectCommand {
       },
       AdminInspectEntityType::Group(id) => {
         let mut executor = block_on(config.db_conn.acquire()).unwrap();
-        let group = match block_on(Group::retrieve(&mut executor, &id.id)) {
+        let group = match block_on(Group::retrieve(&mut executor, &id.id, organization)) {
           Ok(group) => group,
           Err(_) => {
             println!("{}", format!("Group \"{}\" not found.", id.id).red());
@@ -162,6 +228,122 @@ impl AdminInspectCommand {
 
         println!("{}", user.to_string());
       },
+      AdminInspectEntityType::Event(id) => {
+        let mut executor = block_on(config.db_conn.acquire()).unwrap();
+        let event = match block_on(StoredEvent::retrieve_any(&mut executor, &id.id)) {
+          Ok(event) => event,
+          Err(_) => {
+            println!("{}", format!("Event {} not found.", id.id).red());
+            return;
+          }
+        };
+
+        println!("{}", block_on(event.summarize(config.event_ttl)).to_string());
+      },
+    };
+  }
+}
+
+
+
+#[derive(Debug, Args)]
+pub struct AdminListCommand {
+  #[clap(subcommand)]
+  pub entity_type: AdminListEntityType
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminListEntityType {
+  /// Users belonging to a group
+  GroupMembers(AdminListCommandData),
+  /// Users managing a group
+  GroupManagers(AdminListCommandData),
+  /// Groups a permission is granted to
+  PermissionGroups(AdminListCommandData),
+  /// Users holding a permission, through their groups or directly
+  PermissionUsers(AdminListCommandData),
+  /// Pending events of every type, without their keys
+  Events(AdminListEventsCommandData)
+}
+
+#[derive(Debug, Args)]
+pub struct AdminListCommandData {
+  name: String,
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+#[derive(Debug, Args)]
+pub struct AdminListEventsCommandData {
+  /// Type of the events, e.g. "users/register"
+  #[clap(long = "type")]
+  event_type: Option<String>,
+  /// Login of the user the events concern
+  #[clap(long)]
+  login: Option<String>,
+  /// Minimal age of the events in seconds
+  #[clap(long)]
+  min_age: Option<u64>,
+  /// Maximal age of the events in seconds
+  #[clap(long)]
+  max_age: Option<u64>,
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+impl AdminListCommandData {
+  fn offset(&self) -> Option<usize> {
+    return Some(self.page * 10);
+  }
+}
+
+impl AdminListCommand {
+  pub fn run(self, config: CauthConfig, organization: &String) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+
+    let result = match self.entity_type {
+      AdminListEntityType::GroupMembers(data) => {
+        block_on(Group::list_members(&mut executor, &data.name, None, data.offset(), Some(10), organization))
+          .map(|members| serde_json::to_value(&members).unwrap())
+          .map_err(|_| format!("Group \"{}\" not found.", data.name))
+      },
+      AdminListEntityType::GroupManagers(data) => {
+        block_on(Group::list_managers(&mut executor, &data.name, organization))
+          .map(|managers| serde_json::to_value(&managers).unwrap())
+          .map_err(|_| format!("Group \"{}\" not found.", data.name))
+      },
+      AdminListEntityType::PermissionGroups(data) => {
+        block_on(Permission::list_groups(&mut executor, &data.name, None, data.offset(), Some(10), organization))
+          .map(|groups| serde_json::to_value(&groups).unwrap())
+          .map_err(|_| format!("Permission \"{}\" not found.", data.name))
+      },
+      AdminListEntityType::PermissionUsers(data) => {
+        block_on(Permission::list_users(&mut executor, &data.name, None, data.offset(), Some(10), organization))
+          .map(|users| serde_json::to_value(&users).unwrap())
+          .map_err(|_| format!("Permission \"{}\" not found.", data.name))
+      },
+      AdminListEntityType::Events(data) => {
+        let filter = StoredEventFilter {
+          event_type: data.event_type,
+          login: data.login,
+          min_age: data.min_age,
+          max_age: data.max_age
+        };
+        let events = block_on(StoredEvent::list(&mut executor, &filter, None, Some(data.page * 10), Some(10)));
+        let summaries: Vec<_> = events
+          .into_iter()
+          .map(|event| block_on(event.summarize(config.event_ttl)))
+          .collect();
+
+        Ok(serde_json::to_value(&summaries).unwrap())
+      }
+    };
+
+    match result {
+      Ok(value) => println!("{}", json_value_to_pretty_string(&value)),
+      Err(err) => println!("{}", err.red())
     };
   }
 }
@@ -176,21 +358,56 @@ pub struct AdminGrantCommand {
 
 #[derive(Debug, Subcommand)]
 pub enum AdminGrantCommandEntityType {
-  Group(AdminGrantCommandData),
-  User(AdminGrantCommandData)
+  Group(AdminGrantPermissionCommandData),
+  User(AdminGrantCommandData),
+  UserPermission(AdminGrantPermissionCommandData),
+  /// Name a user manager of a group, managers can grant and revoke the group
+  Manager(AdminGrantManagerCommandData),
+  /// Name a user admin of an application, app admins manage the application's namespace
+  AppAdmin(AdminGrantManagerCommandData)
 }
 
 #[derive(Debug, Args)]
 pub struct AdminGrantCommandData {
+  to: String,
+  value: String,
+  /// Time the grant expires at (RFC 3339, e.g. 2025-01-31T18:00:00Z)
+  #[clap(long)]
+  until: Option<DateTime<Utc>>
+}
+
+#[derive(Debug, Args)]
+pub struct AdminGrantManagerCommandData {
   to: String,
   value: String
 }
 
+#[derive(Debug, Args)]
+pub struct AdminGrantPermissionCommandData {
+  to: String,
+  value: String,
+  /// Condition that has to hold for the grant to apply, e.g. 'details.department == "finance"'
+  #[clap(long)]
+  condition: Option<String>,
+  /// Create a deny rule instead of a grant
+  #[clap(long)]
+  deny: bool,
+  /// Time the grant expires at (RFC 3339, e.g. 2025-01-31T18:00:00Z)
+  #[clap(long)]
+  until: Option<DateTime<Utc>>
+}
+
+impl AdminGrantPermissionCommandData {
+  fn effect(&self) -> GrantEffect {
+    return if self.deny { GrantEffect::Deny } else { GrantEffect::Allow };
+  }
+}
+
 impl AdminGrantCommand {
-  pub fn run(self, config: CauthConfig) {
+  pub fn run(self, config: CauthConfig, organization: &String) {
     match self.entity_type {
       AdminGrantCommandEntityType::Group(data) => {
-        let _ = match block_on(Self::grant_group_permission(config, &data)) {
+        let _ = match block_on(Self::grant_group_permission(config, &data, organization)) {
           Ok(_) => println!(
             "{}",
             format!("Successfully granted permission {} to group {}.", data.value, data.to)
@@ -203,8 +420,50 @@ impl AdminGrantCommand {
           )
         };
       }
+      AdminGrantCommandEntityType::UserPermission(data) => {
+        let _ = match block_on(Self::grant_user_permission(config, &data, organization)) {
+          Ok(_) => println!(
+            "{}",
+            format!("Successfully granted permission {} ({}) to user {}.", data.value, data.effect().to_string(), data.to)
+              .green()
+          ),
+          Err(err) => println!(
+            "{}",
+            format!("Error while granting permission {} to user {}.\n{}", data.value, data.to, err.to_string())
+              .green()
+          )
+        };
+      }
+      AdminGrantCommandEntityType::Manager(data) => {
+        let _ = match block_on(Self::grant_group_manager(config, &data, organization)) {
+          Ok(_) => println!(
+            "{}",
+            format!("Successfully named user {} manager of group {}.", data.to, data.value)
+              .green()
+          ),
+          Err(err) => println!(
+            "{}",
+            format!("Error while naming user {} manager of group {}.\n{}", data.to, data.value, err.to_string())
+              .green()
+          )
+        };
+      }
+      AdminGrantCommandEntityType::AppAdmin(data) => {
+        let _ = match block_on(Self::grant_app_admin(config, &data)) {
+          Ok(_) => println!(
+            "{}",
+            format!("Successfully named user {} admin of application {}.", data.to, data.value)
+              .green()
+          ),
+          Err(err) => println!(
+            "{}",
+            format!("Error while naming user {} admin of application {}.\n{}", data.to, data.value, err.to_string())
+              .red()
+          )
+        };
+      }
       AdminGrantCommandEntityType::User(data) => {
-        let _ = match block_on(Self::grant_user_group(config, &data)) {
+        let _ = match block_on(Self::grant_user_group(config, &data, organization)) {
           Ok(_) => println!(
             "{}",
             format!("Successfully granted group {} to user {}.", data.value, data.to)
@@ -220,16 +479,37 @@ impl AdminGrantCommand {
     }
   }
 
-  pub async fn grant_group_permission(config: CauthConfig, data: &AdminGrantCommandData) -> Result<(), GroupGrantError> {
+  pub async fn grant_group_permission(config: CauthConfig, data: &AdminGrantPermissionCommandData, organization: &String) -> Result<(), GroupGrantError> {
+    let mut executor = config.db_conn.acquire().await.unwrap();
+    Group::grant_permission(&mut executor, &data.to, &data.value, data.effect(), data.condition.as_ref(), data.until, organization).await?;
+
+    return Ok(());
+  }
+
+  pub async fn grant_user_permission(config: CauthConfig, data: &AdminGrantPermissionCommandData, organization: &String) -> Result<(), UserGrantPermissionError> {
+    let mut executor = config.db_conn.acquire().await.unwrap();
+    User::grant_permission(&mut executor, &data.to, &data.value, data.effect(), data.condition.as_ref(), data.until, organization).await?;
+
+    return Ok(());
+  }
+
+  pub async fn grant_group_manager(config: CauthConfig, data: &AdminGrantManagerCommandData, organization: &String) -> Result<(), GroupGrantManagerError> {
+    let mut executor = config.db_conn.acquire().await.unwrap();
+    Group::grant_manager(&mut executor, &data.value, &data.to, organization).await?;
+
+    return Ok(());
+  }
+
+  pub async fn grant_app_admin(config: CauthConfig, data: &AdminGrantManagerCommandData) -> Result<(), ApplicationGrantAdminError> {
     let mut executor = config.db_conn.acquire().await.unwrap();
-    Group::grant_permission(&mut executor, &data.to, &data.value).await?;
+    Application::grant_admin(&mut executor, &data.value, &data.to).await?;
 
     return Ok(());
   }
 
-  pub async fn grant_user_group(config: CauthConfig, data: &AdminGrantCommandData) -> Result<(), UserGrantError> {
+  pub async fn grant_user_group(config: CauthConfig, data: &AdminGrantCommandData, organization: &String) -> Result<(), UserGrantError> {
     let mut executor = config.db_conn.acquire().await.unwrap();
-    User::grant_group(&mut executor, &data.to, &data.value).await?;
+    User::grant_group(&mut executor, &data.to, &data.value, data.until, organization).await?;
 
     return Ok(());
   }
@@ -240,16 +520,19 @@ impl AdminGrantCommand {
 #[derive(Debug, Args)]
 pub struct AdminRevokeCommand {
   #[clap(subcommand)]
-  pub entity_type: AdminRevokeCommandEntityType,
-  pub to: String,
-  pub value: String
+  pub entity_type: AdminRevokeCommandEntityType
 }
 
 
 #[derive(Debug, Subcommand)]
 pub enum AdminRevokeCommandEntityType {
   Group(AdminRevokeCommandData),
-  User(AdminRevokeCommandData)
+  User(AdminRevokeCommandData),
+  UserPermission(AdminRevokeCommandData),
+  /// Revoke management of a group from a user
+  Manager(AdminRevokeCommandData),
+  /// Revoke administration of an application from a user
+  AppAdmin(AdminRevokeCommandData)
 }
 
 
@@ -260,10 +543,10 @@ pub struct AdminRevokeCommandData {
 }
 
 impl AdminRevokeCommand {
-  pub fn run(self, config: CauthConfig) {
+  pub fn run(self, config: CauthConfig, organization: &String) {
     match self.entity_type {
       AdminRevokeCommandEntityType::Group(data) => {
-        let _ = match block_on(Self::revoke_group_permission(config, &data)) {
+        let _ = match block_on(Self::revoke_group_permission(config, &data, organization)) {
           Ok(_) => println!(
             "{}",
             format!("Successfully revoked permission {} from group {}.", data.value, data.to)
@@ -276,8 +559,50 @@ impl AdminRevokeCommand {
           )
         };
       }
+      AdminRevokeCommandEntityType::UserPermission(data) => {
+        let _ = match block_on(Self::revoke_user_permission(config, &data, organization)) {
+          Ok(_) => println!(
+            "{}",
+            format!("Successfully revoked permission {} from user {}.", data.value, data.to)
+              .green()
+          ),
+          Err(err) => println!(
+            "{}",
+            format!("Error while revoking permission {} from user {}.\n{}", data.value, data.to, err.to_string())
+              .green()
+          )
+        };
+      }
+      AdminRevokeCommandEntityType::Manager(data) => {
+        let _ = match block_on(Self::revoke_group_manager(config, &data, organization)) {
+          Ok(_) => println!(
+            "{}",
+            format!("Successfully revoked management of group {} from user {}.", data.value, data.to)
+              .green()
+          ),
+          Err(err) => println!(
+            "{}",
+            format!("Error while revoking management of group {} from user {}.\n{}", data.value, data.to, err.to_string())
+              .green()
+          )
+        };
+      }
+      AdminRevokeCommandEntityType::AppAdmin(data) => {
+        let _ = match block_on(Self::revoke_app_admin(config, &data)) {
+          Ok(_) => println!(
+            "{}",
+            format!("Successfully revoked administration of application {} from user {}.", data.value, data.to)
+              .green()
+          ),
+          Err(err) => println!(
+            "{}",
+            format!("Error while revoking administration of application {} from user {}.\n{}", data.value, data.to, err.to_string())
+              .red()
+          )
+        };
+      }
       AdminRevokeCommandEntityType::User(data) => {
-        let _ = match block_on(Self::revoke_user_group(config, &data)) {
+        let _ = match block_on(Self::revoke_user_group(config, &data, organization)) {
           Ok(_) => println!(
             "{}",
             format!("Successfully revoked group {} from user {}.", data.value, data.to)
@@ -293,17 +618,812 @@ impl AdminRevokeCommand {
     }
   }
 
-  pub async fn revoke_group_permission(config: CauthConfig, data: &AdminRevokeCommandData) -> Result<(), GroupRevokeError> {
+  pub async fn revoke_group_permission(config: CauthConfig, data: &AdminRevokeCommandData, organization: &String) -> Result<(), GroupRevokeError> {
+    let mut executor = config.db_conn.acquire().await.unwrap();
+    Group::revoke_permission(&mut executor, &data.to, &data.value, organization).await?;
+
+    return Ok(());
+  }
+
+  pub async fn revoke_group_manager(config: CauthConfig, data: &AdminRevokeCommandData, organization: &String) -> Result<(), GroupRevokeManagerError> {
     let mut executor = config.db_conn.acquire().await.unwrap();
-    Group::revoke_permission(&mut executor, &data.to, &data.value).await?;
+    Group::revoke_manager(&mut executor, &data.value, &data.to, organization).await?;
 
     return Ok(());
   }
 
-  pub async fn revoke_user_group(config: CauthConfig, data: &AdminRevokeCommandData) -> Result<(), UserRevokeError> {
+  pub async fn revoke_app_admin(config: CauthConfig, data: &AdminRevokeCommandData) -> Result<(), ApplicationRevokeAdminError> {
     let mut executor = config.db_conn.acquire().await.unwrap();
-    User::revoke_group(&mut executor, &data.to, &data.value).await?;
+    Application::revoke_admin(&mut executor, &data.value, &data.to).await?;
 
     return Ok(());
   }
+
+  pub async fn revoke_user_group(config: CauthConfig, data: &AdminRevokeCommandData, organization: &String) -> Result<(), UserRevokeError> {
+    let mut executor = config.db_conn.acquire().await.unwrap();
+    User::revoke_group(&mut executor, &data.to, &data.value, organization).await?;
+
+    return Ok(());
+  }
+
+  pub async fn revoke_user_permission(config: CauthConfig, data: &AdminRevokeCommandData, organization: &String) -> Result<(), UserRevokePermissionError> {
+    let mut executor = config.db_conn.acquire().await.unwrap();
+    User::revoke_permission(&mut executor, &data.to, &data.value, organization).await?;
+
+    return Ok(());
+  }
+}
+
+
+
+#[derive(Debug, Args)]
+pub struct AdminEditCommand {
+  #[clap(subcommand)]
+  pub entity_type: AdminEditCommandEntityType
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminEditCommandEntityType {
+  Permission(AdminEditCommandData),
+  Group(AdminEditCommandData)
+}
+
+#[derive(Debug, Args)]
+pub struct AdminEditCommandData {
+  name: String,
+  /// New name, renaming cascades to all grants and memberships
+  #[clap(long)]
+  rename: Option<String>,
+  /// New description
+  #[clap(long)]
+  description: Option<String>
+}
+
+impl AdminEditCommand {
+  pub fn run(self, config: CauthConfig) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+
+    match self.entity_type {
+      AdminEditCommandEntityType::Permission(data) => {
+        match block_on(Permission::update(&mut executor, &data.name, data.rename.as_ref(), data.description.as_ref())) {
+          Ok(_) => println!("{}", format!("Successfully updated permission {}.", data.name).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while updating permission {}.\n{}", data.name, err.to_string()).red()
+          )
+        };
+      },
+      AdminEditCommandEntityType::Group(data) => {
+        match block_on(Group::update(&mut executor, &data.name, data.rename.as_ref(), data.description.as_ref())) {
+          Ok(_) => println!("{}", format!("Successfully updated group {}.", data.name).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while updating group {}.\n{}", data.name, err.to_string()).red()
+          )
+        };
+      }
+    }
+  }
+}
+
+
+
+#[derive(Debug, Args)]
+pub struct AdminSodCommand {
+  #[clap(subcommand)]
+  pub action: AdminSodAction
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminSodAction {
+  /// List the declared exclusive group sets
+  List(AdminSodListCommandData),
+  /// Declare groups mutually exclusive
+  Create(AdminSodCreateCommandData),
+  /// Delete an exclusive group set
+  Delete(AdminSodDeleteCommandData),
+  /// List users already belonging to more groups of a single set, in any organization
+  Audit
+}
+
+#[derive(Debug, Args)]
+pub struct AdminSodListCommandData {
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+#[derive(Debug, Args)]
+pub struct AdminSodCreateCommandData {
+  name: String,
+  #[clap(required = true, num_args = 2..)]
+  groups: Vec<String>,
+  /// Description of the set, e.g. the policy it enforces
+  #[clap(long, default_value = "")]
+  description: String
+}
+
+#[derive(Debug, Args)]
+pub struct AdminSodDeleteCommandData {
+  name: String
+}
+
+impl AdminSodCommand {
+  pub fn run(self, config: CauthConfig) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+
+    match self.action {
+      AdminSodAction::List(data) => {
+        let sets = block_on(ExclusiveGroupSet::list(&mut executor, None, Some(data.page * 10), Some(10)));
+        println!("{}", json_value_to_pretty_string(&serde_json::to_value(&sets).unwrap()));
+      },
+      AdminSodAction::Create(data) => {
+        match block_on(ExclusiveGroupSet::insert(&mut executor, &data.name, &data.description, &data.groups)) {
+          Ok(_) => println!("{}", format!("Successfully declared groups {} mutually exclusive.", data.groups.join(", ")).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while creating exclusive group set {}.\n{}", data.name, err.to_string()).red()
+          )
+        };
+      },
+      AdminSodAction::Delete(data) => {
+        match block_on(ExclusiveGroupSet::delete(&mut executor, &data.name)) {
+          Ok(_) => println!("{}", format!("Successfully deleted exclusive group set {}.", data.name).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while deleting exclusive group set {}.\n{}", data.name, err.to_string()).red()
+          )
+        };
+      },
+      AdminSodAction::Audit => {
+        let violations = block_on(ExclusiveGroupSet::list_violations(&mut executor));
+
+        if violations.is_empty() {
+          println!("{}", "No separation of duties violations found.".green());
+          return;
+        }
+
+        println!("{}", format!("Found {} separation of duties violation(s).", violations.len()).red());
+        println!("{}", json_value_to_pretty_string(&serde_json::to_value(&violations).unwrap()));
+      }
+    }
+  }
+}
+
+
+
+#[derive(Debug, Args)]
+pub struct AdminAppCommand {
+  #[clap(subcommand)]
+  pub action: AdminAppAction
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminAppAction {
+  /// List the registered applications and their admins
+  List(AdminAppListCommandData),
+  /// Register an application owning a namespace
+  Create(AdminAppCreateCommandData),
+  /// Delete an application, the permissions and groups inside its namespace are kept
+  Delete(AdminAppDeleteCommandData)
+}
+
+#[derive(Debug, Args)]
+pub struct AdminAppListCommandData {
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+#[derive(Debug, Args)]
+pub struct AdminAppCreateCommandData {
+  name: String,
+  /// Namespace owned by the application, its admins manage the "{namespace}:*" permissions and groups
+  namespace: String,
+  /// Description of the application
+  #[clap(long, default_value = "")]
+  description: String
+}
+
+#[derive(Debug, Args)]
+pub struct AdminAppDeleteCommandData {
+  name: String
+}
+
+impl AdminAppCommand {
+  pub fn run(self, config: CauthConfig) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+
+    match self.action {
+      AdminAppAction::List(data) => {
+        let applications = block_on(Application::list(&mut executor, None, Some(data.page * 10), Some(10)));
+        println!("{}", json_value_to_pretty_string(&serde_json::to_value(&applications).unwrap()));
+      },
+      AdminAppAction::Create(data) => {
+        match block_on(Application::insert(&mut executor, &data.name, &data.namespace, &data.description)) {
+          Ok(_) => println!("{}", format!("Successfully registered application {} owning namespace {}.", data.name, data.namespace).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while registering application {}.\n{}", data.name, err.to_string()).red()
+          )
+        };
+      },
+      AdminAppAction::Delete(data) => {
+        match block_on(Application::delete(&mut executor, &data.name)) {
+          Ok(_) => println!("{}", format!("Successfully deleted application {}.", data.name).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while deleting application {}.\n{}", data.name, err.to_string()).red()
+          )
+        };
+      }
+    }
+  }
+}
+
+
+
+#[derive(Debug, Args)]
+pub struct AdminOrgCommand {
+  #[clap(subcommand)]
+  pub action: AdminOrgAction
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminOrgAction {
+  /// List the organizations
+  List(AdminOrgListCommandData),
+  /// Create an organization, protected groups are granted their default permissions inside of it
+  Create(AdminOrgCreateCommandData),
+  /// Delete an organization along with everything granted inside of it
+  Delete(AdminOrgNameCommandData),
+  /// List users belonging to an organization
+  Members(AdminOrgMembersCommandData),
+  /// Add a user to an organization
+  AddMember(AdminOrgMemberCommandData),
+  /// Remove a user from an organization along with its groups and grants inside of it
+  RemoveMember(AdminOrgMemberCommandData)
+}
+
+#[derive(Debug, Args)]
+pub struct AdminOrgListCommandData {
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+#[derive(Debug, Args)]
+pub struct AdminOrgCreateCommandData {
+  name: String,
+  /// Description of the organization
+  #[clap(long, default_value = "")]
+  description: String
+}
+
+#[derive(Debug, Args)]
+pub struct AdminOrgNameCommandData {
+  name: String
+}
+
+#[derive(Debug, Args)]
+pub struct AdminOrgMembersCommandData {
+  name: String,
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+#[derive(Debug, Args)]
+pub struct AdminOrgMemberCommandData {
+  name: String,
+  login: String
+}
+
+impl AdminOrgCommand {
+  pub fn run(self, config: CauthConfig) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+
+    match self.action {
+      AdminOrgAction::List(data) => {
+        let organizations = block_on(Organization::list(&mut executor, None, Some(data.page * 10), Some(10)));
+        println!("{}", json_value_to_pretty_string(&serde_json::to_value(&organizations).unwrap()));
+      },
+      AdminOrgAction::Create(data) => {
+        match block_on(Organization::insert(&mut executor, &data.name, &data.description)) {
+          Ok(_) => println!("{}", format!("Successfully created organization {}.", data.name).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while creating organization {}.\n{}", data.name, err.to_string()).red()
+          )
+        };
+      },
+      AdminOrgAction::Delete(data) => {
+        match block_on(Organization::delete(&mut executor, &data.name)) {
+          Ok(_) => println!("{}", format!("Successfully deleted organization {}.", data.name).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while deleting organization {}.\n{}", data.name, err.to_string()).red()
+          )
+        };
+      },
+      AdminOrgAction::Members(data) => {
+        match block_on(Organization::list_members(&mut executor, &data.name, None, Some(data.page * 10), Some(10))) {
+          Ok(members) => println!("{}", json_value_to_pretty_string(&serde_json::to_value(&members).unwrap())),
+          Err(err) => println!("{}", err.to_string().red())
+        };
+      },
+      AdminOrgAction::AddMember(data) => {
+        match block_on(Organization::add_member(&mut executor, &data.name, &data.login)) {
+          Ok(_) => println!("{}", format!("Successfully added user {} to organization {}.", data.login, data.name).green()),
+          Err(err) => println!(
+            "{}",
+            format!("Error while adding user {} to organization {}.\n{}", data.login, data.name, err.to_string()).red()
+          )
+        };
+      },
+      AdminOrgAction::RemoveMember(data) => {
+        // the user's groups, grants and sessions inside the organization are removed with it
+        let mut tx = block_on(config.db_conn.begin()).unwrap();
+
+        match block_on(Organization::remove_member(&mut tx, &data.name, &data.login)) {
+          Ok(_) => {
+            block_on(tx.commit()).unwrap();
+            println!("{}", format!("Successfully removed user {} from organization {}.", data.login, data.name).green());
+          },
+          Err(err) => println!(
+            "{}",
+            format!("Error while removing user {} from organization {}.\n{}", data.login, data.name, err.to_string()).red()
+          )
+        };
+      }
+    }
+  }
+}
+
+
+
+
+#[derive(Debug, Args)]
+pub struct AdminApprovalsCommand {
+  #[clap(subcommand)]
+  pub action: AdminApprovalsAction
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminApprovalsAction {
+  /// List change requests waiting for approvals
+  List(AdminApprovalsListCommandData),
+  /// See a change request along with the users who approved it
+  Inspect(AdminInspectIntegerIDCommand),
+  /// Approve a change request as a user, applying it once it has the required number of approvals
+  Approve(AdminApprovalsApproveCommandData),
+  /// Reject a change request, deleting it without applying it
+  Reject(AdminInspectIntegerIDCommand),
+  /// Manage which operations require approvals
+  Rules(AdminApprovalRulesCommand)
+}
+
+#[derive(Debug, Args)]
+pub struct AdminApprovalsListCommandData {
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+#[derive(Debug, Args)]
+pub struct AdminApprovalsApproveCommandData {
+  id: i32,
+  /// Login of the approving user, it cannot be the user who requested the change
+  #[clap(long = "as")]
+  approver: String
+}
+
+#[derive(Debug, Args)]
+pub struct AdminApprovalRulesCommand {
+  #[clap(subcommand)]
+  pub action: AdminApprovalRulesAction
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminApprovalRulesAction {
+  /// List the operations requiring approvals
+  List,
+  /// Require approvals for an operation, replacing the number of approvals of an existing rule
+  Add(AdminApprovalRulesAddCommandData),
+  /// Stop requiring approvals for an operation, change requests created before are kept
+  Delete(AdminApprovalRulesDeleteCommandData)
+}
+
+#[derive(Debug, Args)]
+pub struct AdminApprovalRulesAddCommandData {
+  /// Event type of the operation, e.g. "users/delete"
+  operation: String,
+  /// Only the operations about this entity (e.g. the granted group) require approvals
+  #[clap(long)]
+  target: Option<String>,
+  /// Number of approvals required
+  #[clap(long, default_value_t = 1)]
+  approvals: i32
+}
+
+#[derive(Debug, Args)]
+pub struct AdminApprovalRulesDeleteCommandData {
+  operation: String,
+  #[clap(long)]
+  target: Option<String>
+}
+
+impl AdminApprovalsCommand {
+  pub fn run(self, config: CauthConfig) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+
+    match self.action {
+      AdminApprovalsAction::List(data) => {
+        let change_requests = block_on(ChangeRequest::list(&mut executor, None, Some(data.page * 10), Some(10)));
+        let summaries: Vec<_> = change_requests
+          .into_iter()
+          .map(|change_request| block_on(change_request.summarize()))
+          .collect();
+
+        println!("{}", json_value_to_pretty_string(&serde_json::to_value(&summaries).unwrap()));
+      },
+      AdminApprovalsAction::Inspect(id) => {
+        match block_on(ChangeRequest::retrieve(&mut executor, &id.id)) {
+          Ok(change_request) => println!("{}", block_on(change_request.summarize()).to_string()),
+          Err(_) => println!("{}", format!("Change request {} not found.", id.id).red())
+        };
+      },
+      AdminApprovalsAction::Approve(data) => {
+        let approver = match block_on(User::retrieve(&mut executor, &data.approver)) {
+          Ok(approver) => approver,
+          Err(_) => {
+            println!("{}", format!("User \"{}\" not found.", data.approver).red());
+            return;
+          }
+        };
+
+        // the approval is recorded only when the change can be applied
+        let mut tx = block_on(config.db_conn.begin()).unwrap();
+
+        match block_on(ChangeRequest::approve(&mut tx, &data.id, &approver, &RequestContext::now(), &config)) {
+          Ok(approval) => {
+            block_on(tx.commit()).unwrap();
+
+            if approval.applied {
+              println!("{}", format!("Successfully approved and applied change request {}.", data.id).green());
+            } else {
+              println!(
+                "{}",
+                format!(
+                  "Successfully approved change request {}, {} of {} approvals.",
+                  data.id,
+                  approval.approvals,
+                  approval.approvals_required
+                ).green()
+              );
+            }
+          },
+          Err(err) => {
+            let err = match err {
+              ChangeRequestApproveError::Operation(failure) => format!("{}: {}", failure.code, failure.details),
+              err => err.to_string()
+            };
+
+            println!(
+              "{}",
+              format!("Error while approving change request {}.\n{}", data.id, err).red()
+            );
+          }
+        };
+      },
+      AdminApprovalsAction::Reject(id) => {
+        match block_on(ChangeRequest::reject(&mut executor, &id.id, &"cli".to_string())) {
+          Ok(_) => println!("{}", format!("Successfully rejected change request {}.", id.id).green()),
+          Err(_) => println!("{}", format!("Change request {} not found.", id.id).red())
+        };
+      },
+      AdminApprovalsAction::Rules(cmd) => match cmd.action {
+        AdminApprovalRulesAction::List => {
+          let rules = block_on(ApprovalRule::list(&mut executor));
+          println!("{}", json_value_to_pretty_string(&serde_json::to_value(&rules).unwrap()));
+        },
+        AdminApprovalRulesAction::Add(data) => {
+          match block_on(ApprovalRule::insert(&mut executor, &data.operation, data.target.as_ref(), data.approvals)) {
+            Ok(_) => println!("{}", format!("Operation {} now requires {} approval(s).", data.operation, data.approvals).green()),
+            Err(err) => println!(
+              "{}",
+              format!("Error while adding approval rule for {}.\n{}", data.operation, err.to_string()).red()
+            )
+          };
+        },
+        AdminApprovalRulesAction::Delete(data) => {
+          match block_on(ApprovalRule::delete(&mut executor, &data.operation, data.target.as_ref())) {
+            Ok(_) => println!("{}", format!("Operation {} no longer requires approvals.", data.operation).green()),
+            Err(err) => println!(
+              "{}",
+              format!("Error while deleting approval rule for {}.\n{}", data.operation, err.to_string()).red()
+            )
+          };
+        }
+      }
+    }
+  }
+}
+
+
+#[derive(Debug, Args)]
+pub struct AdminWebhooksCommand {
+  #[clap(subcommand)]
+  pub action: AdminWebhooksAction
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminWebhooksAction {
+  /// List the webhooks
+  List(AdminWebhooksListCommandData),
+  /// Subscribe a url to the service's events, printing the secret the deliveries are signed with
+  Create(AdminWebhooksCreateCommandData),
+  /// Delete a webhook along with it's deliveries
+  Delete(AdminInspectIntegerIDCommand),
+  /// List the deliveries which were not delivered yet
+  Deliveries(AdminWebhooksDeliveriesCommandData),
+  /// Send a dead delivery again
+  Retry(AdminInspectIntegerIDCommand)
+}
+
+#[derive(Debug, Args)]
+pub struct AdminWebhooksListCommandData {
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+#[derive(Debug, Args)]
+pub struct AdminWebhooksCreateCommandData {
+  url: String,
+  /// Type of the events to deliver, can be repeated, all of them are delivered when omitted
+  #[clap(long = "event")]
+  event_types: Vec<String>,
+  /// Secret the deliveries are signed with, randomly generated when omitted
+  #[clap(long)]
+  secret: Option<String>
+}
+
+#[derive(Debug, Args)]
+pub struct AdminWebhooksDeliveriesCommandData {
+  /// Only list the dead deliveries
+  #[clap(long)]
+  dead: bool,
+  /// Only list the deliveries of this webhook
+  #[clap(long = "webhook")]
+  webhook_id: Option<i32>,
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+impl AdminWebhooksCommand {
+  pub fn run(self, config: CauthConfig) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+
+    match self.action {
+      AdminWebhooksAction::List(data) => {
+        let webhooks = block_on(Webhook::list(&mut executor, None, Some(data.page * 10), Some(10)));
+        println!("{}", json_value_to_pretty_string(&serde_json::to_value(&webhooks).unwrap()));
+      },
+      AdminWebhooksAction::Create(data) => {
+        match block_on(Webhook::insert(&mut executor, &data.url, &data.event_types, data.secret.as_ref())) {
+          Ok(credentials) => {
+            println!("{}", format!("Successfully created webhook {}.", credentials.id).green());
+            println!("Secret (it will not be shown again): {}", credentials.secret);
+          },
+          Err(err) => println!(
+            "{}",
+            format!("Error while creating webhook for {}.\n{}", data.url, err.to_string()).red()
+          )
+        };
+      },
+      AdminWebhooksAction::Delete(id) => {
+        match block_on(Webhook::delete(&mut executor, &id.id)) {
+          Ok(_) => println!("{}", format!("Successfully deleted webhook {}.", id.id).green()),
+          Err(_) => println!("{}", format!("Webhook {} not found.", id.id).red())
+        };
+      },
+      AdminWebhooksAction::Deliveries(data) => {
+        let status = if data.dead { Some("dead".to_string()) } else { None };
+        let deliveries = block_on(WebhookDelivery::list(
+          &mut executor,
+          status.as_ref(),
+          data.webhook_id,
+          None,
+          Some(data.page * 10),
+          Some(10)
+        ));
+
+        println!("{}", json_value_to_pretty_string(&serde_json::to_value(&deliveries).unwrap()));
+      },
+      AdminWebhooksAction::Retry(id) => {
+        match block_on(WebhookDelivery::retry(&mut executor, &id.id)) {
+          Ok(_) => println!("{}", format!("Delivery {} will be sent again.", id.id).green()),
+          Err(err) => println!("{}", err.to_string().red())
+        };
+      }
+    }
+  }
+}
+
+#[derive(Debug, Args)]
+pub struct AdminAuditCommand {
+  /// Login of the user who made the changes, "cli:<system user>" for the admin commands
+  #[clap(long)]
+  actor: Option<String>,
+  /// Whole action or just the resource, e.g. "user_group.created" or "user_group"
+  #[clap(long)]
+  action: Option<String>,
+  /// Whole target or one of it's parts, e.g. "user123"
+  #[clap(long)]
+  target: Option<String>,
+  /// Only list the entries recorded since this time, e.g. 2025-01-31T18:00:00Z
+  #[clap(long)]
+  since: Option<DateTime<Utc>>,
+  /// Only list the entries recorded before this time
+  #[clap(long)]
+  until: Option<DateTime<Utc>>,
+  /// Page to list, 10 entries per page
+  #[clap(long, default_value_t = 0)]
+  page: usize
+}
+
+impl AdminAuditCommand {
+  pub fn run(self, config: CauthConfig) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+
+    let filter = AuditFilter {
+      actor: self.actor,
+      action: self.action,
+      target: self.target,
+      since: self.since,
+      until: self.until
+    };
+    let entries = block_on(AuditEntry::list(&mut executor, &filter, None, Some(self.page * 10), Some(10)));
+
+    println!("{}", json_value_to_pretty_string(&serde_json::to_value(&entries).unwrap()));
+  }
+}
+
+#[derive(Debug, Args)]
+pub struct AdminApplyCommand {
+  /// Path of the policy file
+  path: String,
+  /// Only print the changes that would be applied
+  #[clap(long)]
+  dry_run: bool,
+  /// Also delete the permissions and groups which are not declared in the policy
+  #[clap(long)]
+  prune: bool
+}
+
+impl AdminApplyCommand {
+  pub fn run(self, config: CauthConfig, organization: &String) {
+    let content = match fs::read_to_string(&self.path) {
+      Ok(content) => content,
+      Err(err) => {
+        println!("{}", format!("Cannot read policy file \"{}\": {}", self.path, err).red());
+        return;
+      }
+    };
+
+    let policy = match serde_yml::from_str::<Policy>(&content) {
+      Ok(policy) => policy,
+      Err(err) => {
+        println!("{}", format!("Invalid policy file \"{}\": {}", self.path, err).red());
+        return;
+      }
+    };
+
+    // everything is applied in a single transaction, so a failing change leaves the database untouched
+    let mut tx = block_on(config.db_conn.begin()).unwrap();
+
+    let result = match self.dry_run {
+      true => block_on(policy.plan(&mut tx, organization, self.prune)).map_err(PolicyApplyError::from),
+      false => block_on(policy.apply(&mut tx, organization, self.prune))
+    };
+
+    let changes = match result {
+      Ok(changes) => changes,
+      Err(err) => {
+        println!("{}", format!("Error while applying policy.\n{}", err.to_string()).red());
+        return;
+      }
+    };
+
+    for change in &changes {
+      let line = change.to_string();
+      let line = match line.chars().next() {
+        Some('+') => line.green(),
+        Some('-') => line.red(),
+        _ => line.yellow()
+      };
+      println!("{}", line);
+    }
+
+    if changes.is_empty() {
+      println!("{}", "The database is up to date with the policy.".green());
+    } else if self.dry_run {
+      println!("{}", format!("{} change(s) would be applied.", changes.len()).yellow());
+    } else {
+      block_on(tx.commit()).unwrap();
+      println!("{}", format!("Successfully applied {} change(s).", changes.len()).green());
+    }
+  }
+}
+
+
+
+#[derive(Debug, Args)]
+pub struct AdminExportPolicyCommand {
+  /// Include group memberships of all users
+  #[clap(long)]
+  users: bool,
+  /// Path of the file to write the policy to (default: standard output)
+  #[clap(long)]
+  output: Option<String>
+}
+
+impl AdminExportPolicyCommand {
+  pub fn run(self, config: CauthConfig, organization: &String) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+    let policy = block_on(Policy::export(&mut executor, self.users, organization));
+    let content = serde_yml::to_string(&policy).unwrap();
+
+    match self.output {
+      Some(path) => match fs::write(&path, content) {
+        Ok(_) => println!("{}", format!("Successfully exported policy to {}.", path).green()),
+        Err(err) => println!("{}", format!("Cannot write policy file \"{}\": {}", path, err).red())
+      },
+      None => print!("{}", content)
+    };
+  }
+}
+
+
+
+#[derive(Debug, Args)]
+pub struct AdminExplainCommand {
+  login: String,
+  permission: String,
+  /// Ip address the request is explained as coming from
+  #[clap(long)]
+  ip: Option<String>,
+  /// Resource the permission is checked against
+  #[clap(long)]
+  resource: Option<String>
+}
+
+impl AdminExplainCommand {
+  pub fn run(self, config: CauthConfig, organization: &str) {
+    let mut executor = block_on(config.db_conn.acquire()).unwrap();
+    let user = match block_on(User::retrieve(&mut executor, &self.login)) {
+      Ok(user) => user,
+      Err(_) => {
+        println!("{}", format!("User \"{}\" not found.", self.login).red());
+        return;
+      }
+    };
+
+    let mut context = RequestContext::now()
+      .with_resource(self.resource.clone())
+      .with_organization(organization.to_string());
+    context.ip = self.ip.clone();
+
+    let explanation = block_on(user.explain_permission(&mut executor, self.permission.clone(), &context));
+
+    if explanation.decision.allowed {
+      println!("{}", format!("User {} has permission {}.", self.login, self.permission).green());
+    } else {
+      println!("{}", format!("User {} do not have permission {}.", self.login, self.permission).red());
+    }
+
+    println!("{}", json_value_to_pretty_string(&serde_json::to_value(&explanation).unwrap()));
+  }
 }
@@ -1,4 +1,6 @@
-use crate::{cli::init_defaults, config::CauthConfig, web::run_server};
+use crate::{
+    cli::init_defaults, config::CauthConfig, tasks::spawn_background_tasks, web::run_server,
+};
 use clap::Args;
 use futures::executor::block_on;
 
@@ -8,6 +10,7 @@ pub struct RunCommand;
 impl RunCommand {
     pub fn run(self, config: CauthConfig) {
         block_on(init_defaults(&config));
+        spawn_background_tasks(&config);
         let _ = block_on(run_server(config));
     }
 }
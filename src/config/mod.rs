@@ -3,7 +3,10 @@ use futures::executor::block_on;
 use serde::{Deserialize, Serialize};
 use serde_yml::Value;
 use simple_home_dir::home_dir;
-use sqlx::PgPool;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Executor, PgPool,
+};
 use std::{
     fs::{self, File},
     io::{self, Write},
@@ -15,12 +18,114 @@ use std::{
 pub struct CauthConfigRaw {
     database_url: String,
     port: u16,
+    #[serde(default)]
+    default_groups: DefaultGroups,
+    #[serde(default = "default_event_ttl")]
+    event_ttl: u64,
+    #[serde(default = "default_webhook_max_attempts")]
+    webhook_max_attempts: i32,
+    #[serde(default)]
+    broker: Option<BrokerConfig>,
 }
 
 #[derive(Clone)]
 pub struct CauthConfig {
     pub db_conn: PgPool,
     pub port: u16,
+    pub default_groups: DefaultGroups,
+    /// seconds pending two-phase events can be committed for
+    pub event_ttl: u64,
+    /// attempts after which a failing webhook delivery is dead-lettered
+    pub webhook_max_attempts: i32,
+    /// broker the domain events are published to, they are kept in the outbox when None
+    pub broker: Option<BrokerConfig>,
+}
+
+fn default_event_ttl() -> u64 {
+    return 15 * 60;
+}
+
+fn default_webhook_max_attempts() -> i32 {
+    return 10;
+}
+
+/// Broker the domain events recorded in the outbox are published to:
+/// ```yaml
+/// broker:
+///   type: redis_streams
+///   url: redis://127.0.0.1:6379
+///   stream: cauth:events
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BrokerConfig {
+    RedisStreams {
+        url: String,
+        #[serde(default = "default_redis_stream")]
+        stream: String,
+    },
+}
+
+fn default_redis_stream() -> String {
+    return "cauth:events".to_string();
+}
+
+/// Source a user is registered from
+#[derive(Clone, Copy, Debug)]
+pub enum RegistrationSource {
+    /// POST /users
+    Direct,
+    /// committed UserRegister event
+    Event,
+    /// `cauth admin create user`
+    Admin,
+}
+
+/// Groups granted to newly registered users, either the same for all of the sources:
+/// ```yaml
+/// default_groups: [members]
+/// ```
+/// or per source, sources which are not listed grant no groups:
+/// ```yaml
+/// default_groups:
+///   direct: [members]
+///   admin: [members, staff]
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum DefaultGroups {
+    All(Vec<String>),
+    PerSource(DefaultGroupsPerSource),
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DefaultGroupsPerSource {
+    #[serde(default)]
+    direct: Vec<String>,
+    #[serde(default)]
+    event: Vec<String>,
+    #[serde(default)]
+    admin: Vec<String>,
+}
+
+impl Default for DefaultGroups {
+    fn default() -> Self {
+        return Self::All(vec![]);
+    }
+}
+
+impl DefaultGroups {
+    pub fn for_source(&self, source: RegistrationSource) -> &Vec<String> {
+        return match self {
+            Self::All(groups) => groups,
+            Self::PerSource(groups) => match source {
+                RegistrationSource::Direct => &groups.direct,
+                RegistrationSource::Event => &groups.event,
+                RegistrationSource::Admin => &groups.admin,
+            },
+        };
+    }
 }
 
 #[derive(Debug)]
@@ -34,8 +139,8 @@ pub enum CauthParseError {
 }
 
 impl CauthConfig {
-    pub fn parse_or_edit() -> Self {
-        let config = Self::parse();
+    pub fn parse_or_edit(audit_actor: Option<&String>) -> Self {
+        let config = Self::parse(audit_actor);
 
         match config {
             Ok(config) => return config,
@@ -61,7 +166,12 @@ impl CauthConfig {
         };
     }
 
-    pub fn parse() -> Result<Self, CauthParseError> {
+    /// ## CauthConfig::parse
+    ///
+    /// Parses the config and connects to the database, the changes made with the connections
+    /// are audited as provided actor unless a request identifies another one
+    ///
+    pub fn parse(audit_actor: Option<&String>) -> Result<Self, CauthParseError> {
         let config_file = Self::get_config_file()?;
         let config_content = match io::read_to_string(config_file) {
             Ok(content) => content,
@@ -72,7 +182,26 @@ impl CauthConfig {
             Err(_) => return Err(CauthParseError::ParseError),
         };
 
-        let db_conn = match block_on(PgPool::connect(&config_raw.database_url)) {
+        let mut connect_options = match config_raw.database_url.parse::<PgConnectOptions>() {
+            Ok(connect_options) => connect_options,
+            Err(err) => return Err(CauthParseError::DatabaseError(err.to_string())),
+        };
+
+        if let Some(actor) = audit_actor {
+            connect_options = connect_options.options([("cauth.actor", actor)]);
+        }
+
+        let pool = PgPoolOptions::new()
+            // the actor and ip identified by a request are not kept for the next one
+            .after_release(|conn, _| {
+                Box::pin(async move {
+                    conn.execute("RESET cauth.actor; RESET cauth.ip;").await?;
+                    return Ok(true);
+                })
+            })
+            .connect_with(connect_options);
+
+        let db_conn = match block_on(pool) {
             Ok(db_conn) => db_conn,
             Err(err) => {
                 return Err(CauthParseError::DatabaseError(err.to_string()));
@@ -82,6 +211,10 @@ impl CauthConfig {
         let config = CauthConfig {
             db_conn,
             port: config_raw.port,
+            default_groups: config_raw.default_groups,
+            event_ttl: config_raw.event_ttl,
+            webhook_max_attempts: config_raw.webhook_max_attempts,
+            broker: config_raw.broker,
         };
 
         return Ok(config);
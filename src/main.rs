@@ -2,9 +2,11 @@ use chrono::{offset::Utc, DateTime};
 use clap::Parser;
 use cli::CauthCli;
 
+mod brokers;
 mod cli;
 mod config;
 mod models;
+mod tasks;
 mod util;
 mod web;
 
@@ -0,0 +1,458 @@
+#![allow(unused)]
+
+use crate::{
+    models::{Order, User},
+    util::{
+        logging::{log_database_interaction, DatabaseOperationLogStatus},
+        string::json_value_to_pretty_string,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{query, query_as, FromRow, PgConnection};
+
+/// Namespace of the service's own permissions and groups, no application can own it
+pub const RESERVED_NAMESPACE: &str = "cauth";
+
+/// Registered application owning the "{namespace}:*" permissions and groups
+#[derive(FromRow, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Application {
+    pub name: String,
+    pub namespace: String,
+    pub description: String,
+    pub admins: Vec<String>,
+}
+
+impl ToString for Application {
+    fn to_string(&self) -> String {
+        let formatted = json_value_to_pretty_string(&serde_json::to_value(&self).unwrap());
+
+        return formatted;
+    }
+}
+
+#[derive(Debug)]
+pub enum ApplicationRetrieveError {
+    /// Returned when an application with specified name do not exist
+    NotFound,
+}
+
+impl ToString for ApplicationRetrieveError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An application with this name do not exist.",
+        }
+        .to_string();
+    }
+}
+
+#[derive(Debug)]
+pub enum ApplicationInsertError {
+    /// Returned when the application with specified name already exist
+    /// or either the name or description is too long
+    NameError,
+    /// Returned when the namespace is owned by another application
+    NamespaceTaken,
+    /// Returned when the namespace is empty, too long or contains ":"
+    InvalidNamespace,
+    /// Returned when the namespace is reserved for the service itself
+    ReservedNamespace,
+}
+
+impl ToString for ApplicationInsertError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NameError => "Either application name or description is too long or application with this name already exist.".to_string(),
+            Self::NamespaceTaken => "This namespace is owned by another application.".to_string(),
+            Self::InvalidNamespace => "Namespace has to be non empty, at most 255 chars long and cannot contain \":\".".to_string(),
+            Self::ReservedNamespace => format!("Namespace \"{}\" is reserved.", RESERVED_NAMESPACE),
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum ApplicationDeleteError {
+    /// Returned when an application with specified name do not exist
+    NotFound,
+}
+
+impl ToString for ApplicationDeleteError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An application with this name do not exist.",
+        }
+        .to_string();
+    }
+}
+
+pub enum ApplicationGrantAdminError {
+    /// Returned when an application with provided name do not exist
+    NotFound,
+    /// Returned when a user with provided login do not exist
+    UserNotFound,
+    /// Returned when the user already administers the application
+    AlreadyGranted,
+}
+
+impl ToString for ApplicationGrantAdminError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An application with provided name do not exist",
+            Self::UserNotFound => "A user with provided login do not exist",
+            Self::AlreadyGranted => "The user with provided login already administers this application",
+        }
+        .to_string();
+    }
+}
+
+pub enum ApplicationRevokeAdminError {
+    /// Returned when an application with provided name do not exist
+    NotFound,
+    /// Returned when the user do not administer the application
+    NotGranted,
+}
+
+impl ToString for ApplicationRevokeAdminError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An application with provided name do not exist",
+            Self::NotGranted => "The user with provided login do not administer this application",
+        }
+        .to_string();
+    }
+}
+
+impl Application {
+    /// ## Application::namespace_of
+    ///
+    /// Returns the namespace of a permission or group name, that is the part before the first ":"
+    ///
+    pub fn namespace_of(name: &str) -> Option<&str> {
+        return name.split_once(':').map(|(namespace, _)| namespace);
+    }
+
+    /// ## Application::is_reserved
+    ///
+    /// Checks if a permission or group name lies in the namespace reserved for the service
+    ///
+    pub fn is_reserved(name: &str) -> bool {
+        return Self::namespace_of(name) == Some(RESERVED_NAMESPACE);
+    }
+
+    /// ## Application::list
+    ///
+    /// Lists number of applications in specified order with specified offset from the database
+    ///
+    pub async fn list(
+        conn: &mut PgConnection,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+    ) -> Vec<Self> {
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+    SELECT
+      a.name,
+      a.namespace,
+      COALESCE(a.description, '') AS description,
+      ARRAY_REMOVE(ARRAY_AGG(aa.user_login ORDER BY aa.user_login), NULL) AS admins
+    FROM applications a
+    LEFT JOIN applications_admins aa ON aa.application_name = a.name
+    GROUP BY a.name
+    ORDER BY a.name {}
+    OFFSET {} ROWS
+    LIMIT {};
+    ",
+            order.to_string(),
+            offset,
+            limit
+        );
+
+        return query_as(&sql).fetch_all(&mut *conn).await.unwrap();
+    }
+
+    /// ## Application::retrieve
+    ///
+    /// Retrieves an application with specified name
+    ///
+    /// Errors:
+    /// + When the application do not exist
+    ///
+    pub async fn retrieve(
+        conn: &mut PgConnection,
+        name: &String,
+    ) -> Result<Self, ApplicationRetrieveError> {
+        let sql = "
+    SELECT
+      a.name,
+      a.namespace,
+      COALESCE(a.description, '') AS description,
+      ARRAY_REMOVE(ARRAY_AGG(aa.user_login ORDER BY aa.user_login), NULL) AS admins
+    FROM applications a
+    LEFT JOIN applications_admins aa ON aa.application_name = a.name
+    WHERE a.name = $1
+    GROUP BY a.name;
+    ";
+
+        return match query_as(sql).bind(name).fetch_one(&mut *conn).await {
+            Ok(application) => Ok(application),
+            Err(_) => Err(ApplicationRetrieveError::NotFound),
+        };
+    }
+
+    /// ## Application::insert
+    ///
+    /// Registers an application owning provided namespace, the existing permissions
+    /// and groups inside the namespace become administrable by its admins
+    ///
+    /// Errors:
+    /// + when an application with provided name already exist
+    /// + when the name is longer than 255 chars or description is longer than 3000 chars
+    /// + when the namespace is invalid, reserved or owned by another application
+    ///
+    pub async fn insert(
+        conn: &mut PgConnection,
+        name: &String,
+        namespace: &String,
+        description: &String,
+    ) -> Result<(), ApplicationInsertError> {
+        if namespace.is_empty() || namespace.len() > 255 || namespace.contains(':') {
+            log_database_interaction(
+                "Inserting application into database.",
+                json!({ "name": name, "namespace": namespace }),
+                DatabaseOperationLogStatus::Err("Invalid namespace"),
+            );
+            return Err(ApplicationInsertError::InvalidNamespace);
+        }
+
+        if namespace == RESERVED_NAMESPACE {
+            log_database_interaction(
+                "Inserting application into database.",
+                json!({ "name": name, "namespace": namespace }),
+                DatabaseOperationLogStatus::Err("Reserved namespace"),
+            );
+            return Err(ApplicationInsertError::ReservedNamespace);
+        }
+
+        let sql = "SELECT EXISTS(SELECT 1 FROM applications WHERE namespace = $1);";
+        let (taken,): (bool,) = query_as(sql)
+            .bind(namespace)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+
+        if taken {
+            log_database_interaction(
+                "Inserting application into database.",
+                json!({ "name": name, "namespace": namespace }),
+                DatabaseOperationLogStatus::Err("Namespace taken"),
+            );
+            return Err(ApplicationInsertError::NamespaceTaken);
+        }
+
+        let sql = "INSERT INTO applications (name, namespace, description) VALUES ($1, $2, $3);";
+        let result = query(sql)
+            .bind(name)
+            .bind(namespace)
+            .bind(description)
+            .execute(&mut *conn)
+            .await;
+
+        if result.is_err() {
+            log_database_interaction(
+                "Inserting application into database.",
+                json!({ "name": name, "namespace": namespace }),
+                DatabaseOperationLogStatus::Err("Already exists"),
+            );
+            return Err(ApplicationInsertError::NameError);
+        }
+
+        log_database_interaction::<String>(
+            "Inserting application into database.",
+            json!({ "name": name, "namespace": namespace, "description": description }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Application::delete
+    ///
+    /// Deletes an application with provided name along with its admins,
+    /// the permissions and groups inside its namespace are kept
+    ///
+    /// Errors:
+    /// + when the application do not exist
+    ///
+    pub async fn delete(conn: &mut PgConnection, name: &String) -> Result<(), ApplicationDeleteError> {
+        let sql = "DELETE FROM applications WHERE name = $1;";
+        let result = query(sql).bind(name).execute(&mut *conn).await.unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Deleting application from database.",
+                json!({ "name": name }),
+                DatabaseOperationLogStatus::Err("Not found"),
+            );
+            return Err(ApplicationDeleteError::NotFound);
+        }
+
+        log_database_interaction::<String>(
+            "Deleting application from database.",
+            json!({ "name": name }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Application::grant_admin
+    ///
+    /// Names user with specified login an admin of an application with specified name
+    ///
+    /// Errors:
+    /// + When provided application or user do not exist
+    /// + When the user already administers the application
+    ///
+    pub async fn grant_admin(
+        conn: &mut PgConnection,
+        name: &String,
+        login: &String,
+    ) -> Result<(), ApplicationGrantAdminError> {
+        if Self::retrieve(conn, name).await.is_err() {
+            log_database_interaction(
+                "Granting application an admin.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("Application not found"),
+            );
+            return Err(ApplicationGrantAdminError::NotFound);
+        }
+
+        if User::retrieve(conn, login).await.is_err() {
+            log_database_interaction(
+                "Granting application an admin.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("User not found"),
+            );
+            return Err(ApplicationGrantAdminError::UserNotFound);
+        }
+
+        let sql = "INSERT INTO applications_admins (application_name, user_login) VALUES ($1, $2) ON CONFLICT DO NOTHING;";
+        let result = query(sql)
+            .bind(name)
+            .bind(login)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Granting application an admin.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("Already granted"),
+            );
+            return Err(ApplicationGrantAdminError::AlreadyGranted);
+        }
+
+        log_database_interaction::<String>(
+            "Granting application an admin.",
+            json!({ "name": name, "login": login }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Application::revoke_admin
+    ///
+    /// Revokes administration of an application with specified name from user with specified login
+    ///
+    /// Errors:
+    /// + When provided application do not exist
+    /// + When the user do not administer the application
+    ///
+    pub async fn revoke_admin(
+        conn: &mut PgConnection,
+        name: &String,
+        login: &String,
+    ) -> Result<(), ApplicationRevokeAdminError> {
+        if Self::retrieve(conn, name).await.is_err() {
+            log_database_interaction(
+                "Revoking an admin from application.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("Application not found"),
+            );
+            return Err(ApplicationRevokeAdminError::NotFound);
+        }
+
+        let sql = "DELETE FROM applications_admins WHERE application_name = $1 AND user_login = $2;";
+        let result = query(sql)
+            .bind(name)
+            .bind(login)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Revoking an admin from application.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("Not granted"),
+            );
+            return Err(ApplicationRevokeAdminError::NotGranted);
+        }
+
+        log_database_interaction::<String>(
+            "Revoking an admin from application.",
+            json!({ "name": name, "login": login }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Application::can_administer
+    ///
+    /// Checks if user with specified login administers the namespaces of all of the provided
+    /// permission and group names, names outside of any namespace are never administrable
+    ///
+    pub async fn can_administer(conn: &mut PgConnection, login: &String, names: &[&String]) -> bool {
+        if names.is_empty() {
+            return false;
+        }
+
+        let mut namespaces: Vec<String> = vec![];
+
+        for name in names {
+            match Self::namespace_of(name) {
+                Some(namespace) if namespace != RESERVED_NAMESPACE => {
+                    namespaces.push(namespace.to_string())
+                }
+                _ => return false,
+            };
+        }
+
+        namespaces.sort();
+        namespaces.dedup();
+
+        let sql = "
+    SELECT
+      COUNT(DISTINCT a.namespace)
+    FROM applications a
+    INNER JOIN applications_admins aa ON aa.application_name = a.name
+    WHERE aa.user_login = $1 AND a.namespace = ANY($2);
+    ";
+        let (count,): (i64,) = query_as(sql)
+            .bind(login)
+            .bind(&namespaces)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+
+        return count as usize == namespaces.len();
+    }
+}
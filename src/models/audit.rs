@@ -0,0 +1,214 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{
+    prelude::FromRow,
+    query,
+    query_as,
+    PgConnection
+};
+use crate::{
+    models::{LoginSession, Order, RequestContext},
+    util::string::json_value_to_pretty_string
+};
+
+/// Recorded change or login. Changes of the rows are recorded by the database itself,
+/// as "<resource>.<created|updated|deleted>" with the state before and after the change
+#[derive(FromRow, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// login of the user who made the change, "cli:<system user>" for the admin commands,
+    /// None for the changes made by the service itself or by anonymous requests
+    pub actor: Option<String>,
+    pub action: String,
+    /// columns identifying the changed row, joined with "/"
+    pub target: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>
+}
+
+impl ToString for AuditEntry {
+    fn to_string(&self) -> String {
+        let formatted = json_value_to_pretty_string(&serde_json::to_value(&self).unwrap());
+
+        return formatted;
+    }
+}
+
+/// Filters of AuditEntry::list, every provided one has to match
+#[derive(Default)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    /// either the whole action or just the resource, e.g. "user_group"
+    pub action: Option<String>,
+    /// either the whole target or one of it's parts, e.g. the group's name
+    pub target: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>
+}
+
+impl AuditEntry {
+    /// ## AuditEntry::identify
+    ///
+    /// Attributes the changes made with the connection to provided actor and ip,
+    /// until the connection is returned to the pool
+    ///
+    pub async fn identify(
+        conn: &mut PgConnection,
+        actor: Option<&String>,
+        ip: Option<&String>
+    ) {
+        let sql = "SELECT set_config('cauth.actor', COALESCE($1, ''), false), set_config('cauth.ip', COALESCE($2, ''), false);";
+
+        query(sql)
+            .bind(&actor)
+            .bind(&ip)
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## AuditEntry::identify_session
+    ///
+    /// Attributes the changes made with the connection to the user of the login session with provided token
+    /// and the ip of the request, to the ip alone when there is no such session. <br>
+    /// Should be called by the routes making changes, once the caller is authorized to make them
+    ///
+    pub async fn identify_session(
+        conn: &mut PgConnection,
+        token: Option<&String>,
+        context: &RequestContext
+    ) {
+        let actor = match token {
+            Some(token) => LoginSession::retrieve(conn, token)
+                .await
+                .ok()
+                .map(|session| session.user_login),
+            None => None
+        };
+
+        Self::identify(conn, actor.as_ref(), context.ip.as_ref()).await;
+    }
+
+    /// ## AuditEntry::record
+    ///
+    /// Records an action which do not change any row (e.g. a failed login)
+    /// as the actor the connection was identified with
+    ///
+    pub async fn record(
+        conn: &mut PgConnection,
+        action: &str,
+        target: Option<&String>
+    ) {
+        let sql = "
+        INSERT INTO
+            audit_log (actor, action, target, ip)
+        VALUES
+            (NULLIF(current_setting('cauth.actor', true), ''), $1, $2, NULLIF(current_setting('cauth.ip', true), ''));
+        ";
+
+        query(sql)
+            .bind(&action)
+            .bind(&target)
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## AuditEntry::list
+    ///
+    /// Lists number of entries matching the filter in specified order with specified offset
+    ///
+    pub async fn list(
+        conn: &mut PgConnection,
+        filter: &AuditFilter,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>
+    ) -> Vec<AuditEntry> {
+        let order = order.unwrap_or(Order::Descending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+        SELECT
+            *
+        FROM
+            audit_log
+        WHERE
+            ($1::VARCHAR IS NULL OR actor = $1)
+            AND ($2::VARCHAR IS NULL OR action = $2 OR action LIKE $2 || '.%')
+            AND ($3::TEXT IS NULL OR target = $3 OR $3 = ANY(string_to_array(target, '/')))
+            AND ($4::TIMESTAMPTZ IS NULL OR created_at >= $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR created_at < $5)
+        ORDER BY id {}
+        OFFSET {} ROWS
+        LIMIT {};
+        ",
+            order.to_string(),
+            offset,
+            limit
+        );
+
+        return query_as(&sql)
+            .bind(&filter.actor)
+            .bind(&filter.action)
+            .bind(&filter.target)
+            .bind(&filter.since)
+            .bind(&filter.until)
+            .fetch_all(conn)
+            .await
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::{
+        models::{
+            Permission,
+            User
+        },
+        util::testing
+    };
+
+    async fn actor_of(conn: &mut PgConnection, action: &str, target: &str) -> Option<String> {
+        let (actor,): (Option<String>,) = query_as("SELECT actor FROM audit_log WHERE action = $1 AND target = $2;")
+            .bind(action)
+            .bind(target)
+            .fetch_one(conn)
+            .await
+            .unwrap();
+        return actor;
+    }
+
+    #[tokio::test]
+    async fn attributes_the_changes_only_once_identified() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let context = RequestContext { ip: Some("10.0.0.1".to_string()), ..RequestContext::now() };
+
+        User::insert_unhashed(&mut conn, &"jane".to_string(), &String::new(), &json!({}), &vec![])
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        let token = LoginSession::insert(&mut conn, "jane".to_string())
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        // checking a permission do not identify the connection
+        LoginSession::has_permission(&mut conn, &token, "cauth:permissions:post", &context).await;
+        Permission::insert(&mut conn, &"billing:refund".to_string(), &String::new()).await.unwrap();
+        assert_eq!(actor_of(&mut conn, "permission.created", "billing:refund").await, None);
+
+        AuditEntry::identify_session(&mut conn, Some(&token), &context).await;
+        Permission::insert(&mut conn, &"billing:charge".to_string(), &String::new()).await.unwrap();
+        assert_eq!(actor_of(&mut conn, "permission.created", "billing:charge").await, Some("jane".to_string()));
+    }
+}
@@ -0,0 +1,244 @@
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{
+    prelude::FromRow,
+    query,
+    query_as,
+    PgConnection
+};
+use crate::{
+    models::event::{
+        with_event_type,
+        PendingEvent
+    },
+    util::{
+        logging::{log_database_interaction, DatabaseOperationLogStatus},
+        string::json_value_to_pretty_string
+    }
+};
+
+/// Operation which requires approvals of other users before it is applied,
+/// either every operation of the type or only the ones about the target
+#[derive(FromRow, Serialize)]
+pub struct ApprovalRule {
+    pub operation: String,
+    pub target: Option<String>,
+    pub approvals: i32
+}
+
+impl ToString for ApprovalRule {
+    fn to_string(&self) -> String {
+        let formatted = json_value_to_pretty_string(&serde_json::to_value(&self).unwrap());
+
+        return formatted;
+    }
+}
+
+pub enum ApprovalRuleInsertError {
+    /// Returned when there is no event type with the operation's name
+    UnknownOperation,
+    /// Returned when the operation's type cannot require approvals
+    NotApprovable,
+    /// Returned when less than one approval is required
+    InvalidApprovals
+}
+
+impl ToString for ApprovalRuleInsertError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::UnknownOperation => "An operation with this name do not exist.".to_string(),
+            Self::NotApprovable => "This operation cannot require approvals.".to_string(),
+            Self::InvalidApprovals => "At least one approval has to be required.".to_string()
+        };
+    }
+}
+
+pub enum ApprovalRuleDeleteError {
+    /// Returned when there is no rule for the operation and target
+    NotFound
+}
+
+impl ToString for ApprovalRuleDeleteError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An approval rule for this operation and target do not exist.".to_string()
+        };
+    }
+}
+
+async fn is_approvable<E: PendingEvent>() -> Option<bool> {
+    return Some(E::APPROVABLE);
+}
+
+impl ApprovalRule {
+    /// ## ApprovalRule::list
+    ///
+    /// Lists all the approval rules ordered by the operation
+    ///
+    pub async fn list(db_conn: &mut PgConnection) -> Vec<ApprovalRule> {
+        let sql = "
+        SELECT
+            operation,
+            target,
+            approvals
+        FROM
+            approval_rules
+        ORDER BY operation, target NULLS FIRST;
+        ";
+
+        return query_as(sql)
+            .fetch_all(db_conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## ApprovalRule::insert
+    ///
+    /// Requires provided number of approvals for the operation,
+    /// replacing the number of the rule for the same operation and target
+    ///
+    /// Errors:
+    /// + when the operation do not exist
+    /// + when the operation cannot require approvals
+    /// + when less than one approval is required
+    ///
+    pub async fn insert(
+        db_conn: &mut PgConnection,
+        operation: &String,
+        target: Option<&String>,
+        approvals: i32
+    ) -> Result<(), ApprovalRuleInsertError> {
+        let approvable = with_event_type!(
+            operation,
+            is_approvable(),
+            None
+        );
+
+        match approvable {
+            Some(true) => (),
+            Some(false) => return Err(ApprovalRuleInsertError::NotApprovable),
+            None => return Err(ApprovalRuleInsertError::UnknownOperation)
+        };
+
+        if approvals < 1 {
+            return Err(ApprovalRuleInsertError::InvalidApprovals);
+        }
+
+        let sql = "
+        INSERT INTO
+            approval_rules (operation, target, approvals)
+        VALUES
+            ($1, $2, $3)
+        ON CONFLICT (operation, target) DO UPDATE SET
+            approvals = EXCLUDED.approvals;
+        ";
+
+        query(sql)
+            .bind(&operation)
+            .bind(&target)
+            .bind(&approvals)
+            .execute(db_conn)
+            .await
+            .unwrap();
+
+        log_database_interaction::<String>(
+            "Inserting an approval rule into the database.",
+            json!({ "operation": operation, "target": target, "approvals": approvals }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## ApprovalRule::delete
+    ///
+    /// Deletes the rule for the operation and target, change requests created before are kept
+    ///
+    /// Errors:
+    /// + when there is no such rule
+    ///
+    pub async fn delete(
+        db_conn: &mut PgConnection,
+        operation: &String,
+        target: Option<&String>
+    ) -> Result<(), ApprovalRuleDeleteError> {
+        let sql = "
+        DELETE FROM
+            approval_rules
+        WHERE
+            operation = $1 AND target IS NOT DISTINCT FROM $2;
+        ";
+
+        let result = query(sql)
+            .bind(&operation)
+            .bind(&target)
+            .execute(db_conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            return Err(ApprovalRuleDeleteError::NotFound);
+        }
+
+        log_database_interaction::<String>(
+            "Deleting an approval rule from the database.",
+            json!({ "operation": operation, "target": target }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## ApprovalRule::exists
+    ///
+    /// Checks if any rule is declared for the operation, regardless of the target
+    ///
+    pub async fn exists(
+        db_conn: &mut PgConnection,
+        operation: &str
+    ) -> bool {
+        let sql = "
+        SELECT EXISTS (
+            SELECT 1 FROM approval_rules WHERE operation = $1
+        );
+        ";
+
+        return query_as::<_, (bool,)>(sql)
+            .bind(&operation)
+            .fetch_one(db_conn)
+            .await
+            .unwrap()
+            .0;
+    }
+
+    /// ## ApprovalRule::required
+    ///
+    /// Returns the number of approvals the event requires, the highest of all the matching rules,
+    /// None when it can be applied right away
+    ///
+    pub async fn required<E: PendingEvent>(
+        db_conn: &mut PgConnection,
+        event: &E
+    ) -> Option<i32> {
+        if !E::APPROVABLE {
+            return None;
+        }
+
+        let sql = "
+        SELECT
+            MAX(approvals)
+        FROM
+            approval_rules
+        WHERE
+            operation = $1 AND (target IS NULL OR target = $2);
+        ";
+
+        return query_as::<_, (Option<i32>,)>(sql)
+            .bind(&E::TYPE)
+            .bind(event.target())
+            .fetch_one(db_conn)
+            .await
+            .unwrap()
+            .0;
+    }
+}
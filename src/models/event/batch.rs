@@ -0,0 +1,470 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::{
+    prelude::FromRow,
+    query,
+    query_as,
+    Connection,
+    PgConnection
+};
+use crate::{
+    config::CauthConfig,
+    models::event::{
+        generate_key,
+        is_expired,
+        with_event_type,
+        committed_event::CommittedEventResultError,
+        ApprovalRule,
+        CommittedEvent,
+        EventCaller,
+        EventCredentials,
+        EventErrorKind,
+        EventFailure,
+        PendingEvent,
+        StoredEvent
+    },
+    models::webhook::{Webhook, WebhookEnqueueError},
+    util::logging::{log_database_interaction, DatabaseOperationLogStatus}
+};
+
+/// Type the results of committed batches are recorded under
+const COMMITTED_TYPE: &str = "batches";
+
+/// Pending events committed or cancelled together with a single id and key
+#[derive(FromRow)]
+pub struct EventBatch {
+    pub id: i32,
+    key: String,
+    pub created_at: DateTime<Utc>,
+    /// login of the user whose session prepared the batch
+    pub actor: Option<String>
+}
+
+/// Single event of a batch to create
+#[derive(Deserialize)]
+pub struct EventBatchEntry {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub input: Value
+}
+
+pub enum EventBatchRetrieveError {
+    /// Returned when the batch with specified id cannot be found
+    NotFound
+}
+
+pub enum EventBatchInsertError {
+    /// Returned when there are no events to batch
+    Empty,
+    /// Returned when one of the events has unknown type
+    UnknownEventType { index: usize, event_type: String },
+    /// Returned when input of one of the events do not match it's type
+    InvalidInput { index: usize, details: String },
+    /// Returned when one of the events cannot be prepared
+    Operation { index: usize, failure: EventFailure },
+    /// Returned when one of the events matches an approval rule, such events cannot be batched
+    ApprovalRequired { index: usize, event_type: String }
+}
+
+pub enum EventBatchCommitError {
+    /// Returned when the batch with specified id cannot be found
+    NotFound,
+    /// Returned when the key is invalid
+    Unauthorized,
+    /// Returned when the batch is older than the configured TTL
+    Expired,
+    /// Returned when the batch was already committed and its results cannot be returned again
+    AlreadyCommitted,
+    /// Returned when one of the events cannot be applied
+    Operation { index: usize, event_type: String, failure: EventFailure },
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError)
+}
+
+pub enum EventBatchCancelError {
+    /// Returned when the batch with specified id cannot be found
+    NotFound,
+    /// Returned when the key is invalid
+    Unauthorized,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
+}
+
+async fn prepare_entry<E: PendingEvent>(
+    db_conn: &mut PgConnection,
+    index: usize,
+    input: Value,
+    caller: &EventCaller,
+    batch_id: &i32
+) -> Result<(), EventBatchInsertError> {
+    let input: E::Input = match serde_json::from_value(input) {
+        Ok(input) => input,
+        Err(err) => return Err(EventBatchInsertError::InvalidInput { index, details: err.to_string() })
+    };
+
+    let event = match E::prepare(db_conn, input, caller).await {
+        Ok(event) => event,
+        Err(error) => return Err(EventBatchInsertError::Operation { index, failure: EventFailure::from_error(&error) })
+    };
+
+    if ApprovalRule::required(db_conn, &event).await.is_some() {
+        return Err(EventBatchInsertError::ApprovalRequired { index, event_type: E::TYPE.to_string() });
+    }
+
+    let _ = StoredEvent::store(
+        db_conn,
+        &event,
+        Some(batch_id),
+        None
+    )
+    .await;
+
+    return Ok(());
+}
+
+/// returns the output of the event along with the output recorded for the retried commits
+async fn apply_entry<E: PendingEvent>(
+    db_conn: &mut PgConnection,
+    stored: StoredEvent,
+    config: &CauthConfig
+) -> Result<(Value, Option<Value>), EventFailure> {
+    return match stored.apply::<E>(db_conn, config).await {
+        Ok(output) => Ok((json!(output), E::recorded_output(&output))),
+        Err(error) => Err(EventFailure::from_error(&error))
+    };
+}
+
+impl EventBatch {
+    /// ## EventBatch::retrieve
+    ///
+    /// Retrieves batch with specifed id
+    ///
+    /// Errors:
+    /// + When the batch is not found
+    ///
+    pub async fn retrieve(
+        db_conn: &mut PgConnection,
+        id: &i32
+    ) -> Result<EventBatch, EventBatchRetrieveError> {
+        let sql = "
+        SELECT
+            *
+        FROM
+            event_batches
+        WHERE
+            id = $1;
+        ";
+        let result = query_as(sql)
+            .bind(&id)
+            .fetch_one(db_conn)
+            .await;
+
+        return match result {
+            Ok(batch) => Ok(batch),
+            Err(_) => return Err(EventBatchRetrieveError::NotFound),
+        };
+    }
+
+    /// ## EventBatch::lock
+    ///
+    /// Retrieves batch with specified id, locking it until the end of the transaction
+    /// so concurrent commits and cancels wait for each other
+    ///
+    /// Errors:
+    /// + When the batch is not found
+    ///
+    async fn lock(
+        db_conn: &mut PgConnection,
+        id: &i32
+    ) -> Result<EventBatch, EventBatchRetrieveError> {
+        let sql = "
+        SELECT
+            *
+        FROM
+            event_batches
+        WHERE
+            id = $1
+        FOR UPDATE;
+        ";
+        let result = query_as(sql)
+            .bind(&id)
+            .fetch_one(db_conn)
+            .await;
+
+        return match result {
+            Ok(batch) => Ok(batch),
+            Err(_) => return Err(EventBatchRetrieveError::NotFound),
+        };
+    }
+
+    /// ## EventBatch::events
+    ///
+    /// Lists the events of batch with specified id, in the order they were added
+    ///
+    pub async fn events(
+        db_conn: &mut PgConnection,
+        id: &i32
+    ) -> Vec<StoredEvent> {
+        let sql = "
+        SELECT
+            *
+        FROM
+            pending_events
+        WHERE
+            batch_id = $1
+        ORDER BY
+            id;
+        ";
+
+        return query_as(sql)
+            .bind(&id)
+            .fetch_all(db_conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## EventBatch::insert
+    ///
+    /// Prepares all provided events and inserts them into database as a single batch,
+    /// returning it's key and id <br>
+    /// Should be run in a transaction, as the events prepared before the failing one are kept
+    ///
+    /// Errors:
+    /// + when there are no events
+    /// + when one of the events has unknown type or do not match it
+    /// + when one of the events cannot be prepared
+    ///
+    pub async fn insert(
+        db_conn: &mut PgConnection,
+        entries: Vec<EventBatchEntry>,
+        caller: &EventCaller
+    ) -> Result<EventCredentials, EventBatchInsertError> {
+        if entries.is_empty() {
+            return Err(EventBatchInsertError::Empty);
+        }
+
+        let key = generate_key(&format!("batch{}", entries.len()));
+        let actor = caller.login(db_conn).await;
+
+        let sql = "
+            INSERT INTO
+                event_batches (key, actor)
+            VALUES
+                ($1, $2)
+            RETURNING id, key;
+        ";
+
+        let credentials: EventCredentials = query_as(sql)
+            .bind(&key)
+            .bind(&actor)
+            .fetch_one(&mut *db_conn)
+            .await
+            .unwrap();
+
+        for (index, entry) in entries.into_iter().enumerate() {
+            with_event_type!(
+                &entry.event_type,
+                prepare_entry(&mut *db_conn, index, entry.input, caller, &credentials.id),
+                Err(EventBatchInsertError::UnknownEventType { index, event_type: entry.event_type.clone() })
+            )?;
+        }
+
+        log_database_interaction::<String>(
+            "Inserting a batch of pending events into the database.",
+            json!({ "id": credentials.id }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(credentials);
+    }
+
+    /// ## EventBatch::commit
+    ///
+    /// Applies all the events of batch with specified id in order, then deletes the batch,
+    /// returning what each of the events returned <br>
+    /// Should be run in a transaction, the transaction should be dropped when this fails
+    /// so none of the events is applied and the batch is kept. The batch is locked until the transaction ends,
+    /// and retrying a successful commit returns the original results without applying the events again
+    ///
+    /// Errors:
+    /// + when the batch is not found
+    /// + when the key is invalid
+    /// + when the batch was committed already and its results were not recorded
+    /// + when the batch is older than provided TTL (in seconds)
+    /// + when one of the events cannot be applied
+    ///
+    pub async fn commit(
+        db_conn: &mut PgConnection,
+        id: &i32,
+        key: &String,
+        ttl: u64,
+        config: &CauthConfig
+    ) -> Result<Vec<Value>, EventBatchCommitError> {
+        let batch = match Self::lock(db_conn, id).await {
+            Ok(batch) => batch,
+            // the batch might have been committed already
+            Err(_) => return match CommittedEvent::result(db_conn, COMMITTED_TYPE, id, key).await {
+                // results of batches are always recorded as arrays
+                Ok(output) => Ok(serde_json::from_value(output).unwrap()),
+                Err(CommittedEventResultError::Unauthorized) => Err(EventBatchCommitError::Unauthorized),
+                Err(CommittedEventResultError::NotFound) => Err(EventBatchCommitError::NotFound),
+                Err(CommittedEventResultError::NotRecorded) => Err(EventBatchCommitError::AlreadyCommitted)
+            }
+        };
+
+        if *key != batch.key {
+            return Err(EventBatchCommitError::Unauthorized);
+        }
+
+        if is_expired(&batch.created_at, ttl) {
+            return Err(EventBatchCommitError::Expired);
+        }
+
+        let mut outputs = vec![];
+        let mut recorded_outputs = vec![];
+        for (index, stored) in Self::events(db_conn, &batch.id).await.into_iter().enumerate() {
+            let event_type = stored.event_type.clone();
+
+            let result = with_event_type!(
+                &event_type,
+                apply_entry(&mut *db_conn, stored, config),
+                // the type was removed after the event was created
+                Err(EventFailure {
+                    code: "UNKNOWN_EVENT_TYPE",
+                    details: format!("Event type \"{}\" do not exist", event_type),
+                    kind: EventErrorKind::Internal,
+                    fields: json!({})
+                })
+            );
+
+            match result {
+                Ok((output, recorded)) => {
+                    outputs.push(output);
+                    recorded_outputs.push(recorded);
+                }
+                Err(failure) => return Err(EventBatchCommitError::Operation { index, event_type, failure })
+            };
+        }
+
+        // the results are recorded only when all of them can be returned again
+        let recorded_outputs: Option<Vec<Value>> = recorded_outputs.into_iter().collect();
+        CommittedEvent::insert(
+            db_conn,
+            COMMITTED_TYPE,
+            &batch.id,
+            key,
+            recorded_outputs.map(|outputs| json!(outputs)).as_ref()
+        )
+        .await;
+
+        Self::delete(db_conn, &batch.id).await;
+
+        if let Err(err) = Webhook::enqueue(db_conn, "event.committed", json!({ "event_type": COMMITTED_TYPE, "id": batch.id })).await {
+            return Err(EventBatchCommitError::Webhook(err));
+        }
+
+        return Ok(outputs);
+    }
+
+    /// ## EventBatch::cancel
+    ///
+    /// Deletes batch with specified id along with all of it's events
+    ///
+    /// Errors:
+    /// + When the batch is not found
+    /// + When the key is invalid
+    ///
+    pub async fn cancel(
+        db_conn: &mut PgConnection,
+        id: &i32,
+        key: &String
+    ) -> Result<(), EventBatchCancelError> {
+        let batch = match Self::retrieve(db_conn, id).await {
+            Ok(batch) => batch,
+            Err(_) => return Err(EventBatchCancelError::NotFound)
+        };
+
+        if *key != batch.key {
+            return Err(EventBatchCancelError::Unauthorized);
+        }
+
+        let mut tx = db_conn.begin().await.unwrap();
+        let db_conn = &mut *tx;
+
+        Self::delete(db_conn, &batch.id).await;
+
+        if let Err(err) = Webhook::enqueue(db_conn, "event.cancelled", json!({ "event_type": COMMITTED_TYPE, "id": batch.id })).await {
+            return Err(EventBatchCancelError::Webhook(err));
+        }
+
+        if let Err(err) = tx.commit().await {
+            return Err(EventBatchCancelError::Database(err.to_string()));
+        }
+
+        return Ok(());
+    }
+
+    async fn delete(
+        db_conn: &mut PgConnection,
+        id: &i32
+    ) {
+        // the events are deleted along with the batch
+        let sql = "
+        DELETE FROM
+            event_batches
+        WHERE
+            id = $1;
+        ";
+
+        let _ = query(sql)
+            .bind(&id)
+            .execute(db_conn)
+            .await
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::{user::User, RequestContext}, util::testing};
+
+    fn register(login: &str) -> EventBatchEntry {
+        return EventBatchEntry {
+            event_type: "users/register".to_string(),
+            input: json!({ "login": login, "password": "password123" })
+        };
+    }
+
+    #[tokio::test]
+    async fn commits_the_events_once() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let config = testing::config(pool.clone(), None);
+        let caller = EventCaller { session_token: None, context: RequestContext::now() };
+
+        let credentials = EventBatch::insert(&mut conn, vec![register("jane"), register("john")], &caller)
+            .await
+            .unwrap_or_else(|_| panic!("the batch should be inserted"));
+
+        let wrong_key = EventBatch::commit(&mut conn, &credentials.id, &"wrong".to_string(), 900, &config).await;
+        assert!(matches!(wrong_key, Err(EventBatchCommitError::Unauthorized)));
+
+        let outputs = EventBatch::commit(&mut conn, &credentials.id, &credentials.key, 900, &config)
+            .await
+            .unwrap_or_else(|_| panic!("the batch should be committed"));
+        assert_eq!(outputs.len(), 2);
+        assert!(User::retrieve(&mut conn, &"jane".to_string()).await.is_ok());
+        assert!(User::retrieve(&mut conn, &"john".to_string()).await.is_ok());
+
+        // retrying returns the recorded results and leaves no batch behind
+        let retried = EventBatch::commit(&mut conn, &credentials.id, &credentials.key, 900, &config)
+            .await
+            .unwrap_or_else(|_| panic!("the batch should be committed"));
+        assert_eq!(retried, outputs);
+        assert!(EventBatch::retrieve(&mut conn, &credentials.id).await.is_err());
+    }
+}
@@ -0,0 +1,646 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{
+    prelude::FromRow,
+    query,
+    query_as,
+    PgConnection
+};
+use crate::{
+    config::CauthConfig,
+    models::{
+        event::{
+            describe,
+            with_event_type,
+            ApprovalRule,
+            EventCaller,
+            EventErrorKind,
+            EventFailure,
+            PendingEvent
+        },
+        Order,
+        RequestContext,
+        User
+    },
+    util::{
+        logging::{log_database_interaction, DatabaseOperationLogStatus},
+        string::json_value_to_pretty_string
+    }
+};
+
+/// Event which requires approvals of other users, applied once enough of them approve it
+#[derive(FromRow)]
+pub struct ChangeRequest {
+    pub id: i32,
+    pub event_type: String,
+    payload: Value,
+    pub requested_by: Option<String>,
+    pub approvals_required: i32,
+    pub approved_by: Vec<String>,
+    pub created_at: DateTime<Utc>
+}
+
+/// Change request as shown to the approvers, without secrets of it's payload
+#[derive(Serialize)]
+pub struct ChangeRequestSummary {
+    pub id: i32,
+    pub event_type: String,
+    pub requested_by: Option<String>,
+    pub approvals_required: i32,
+    pub approved_by: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub details: Value
+}
+
+impl ToString for ChangeRequestSummary {
+    fn to_string(&self) -> String {
+        let formatted = json_value_to_pretty_string(&serde_json::to_value(&self).unwrap());
+
+        return formatted;
+    }
+}
+
+/// State of a change request after it was approved
+#[derive(Serialize)]
+pub struct ChangeRequestApproval {
+    pub approvals: i32,
+    pub approvals_required: i32,
+    /// whether the change was applied and the request deleted
+    pub applied: bool
+}
+
+pub enum ChangeRequestRetrieveError {
+    /// Returned when the change request with specified id cannot be found
+    NotFound
+}
+
+pub enum ChangeRequestApproveError {
+    /// Returned when the change request with specified id cannot be found
+    NotFound,
+    /// Returned when the approver requested the change
+    SelfApproval,
+    /// Returned when the change has no requester, so it cannot be told apart from a self approval
+    MissingRequester,
+    /// Returned when the approver is not permitted to make the change themselves
+    Unauthorized,
+    /// Returned when the approver already approved the change
+    AlreadyApproved,
+    /// Returned when the change cannot be applied
+    Operation(EventFailure)
+}
+
+impl ToString for ChangeRequestApproveError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "A change request with this id do not exist.".to_string(),
+            Self::SelfApproval => "Changes cannot be approved by the user who requested them.".to_string(),
+            Self::MissingRequester => "Changes without a requester cannot be approved.".to_string(),
+            Self::Unauthorized => "Changes can be approved only by the users permitted to make them.".to_string(),
+            Self::AlreadyApproved => "This change was already approved by the user.".to_string(),
+            Self::Operation(failure) => failure.details.clone()
+        };
+    }
+}
+
+pub enum ChangeRequestRejectError {
+    /// Returned when the change request with specified id cannot be found
+    NotFound
+}
+
+async fn authorize_change<E: PendingEvent>(
+    db_conn: &mut PgConnection,
+    payload: Value,
+    user: &User,
+    context: &RequestContext
+) -> bool {
+    // a payload which no longer matches its type cannot be authorized
+    let event: E = match serde_json::from_value(payload) {
+        Ok(event) => event,
+        Err(_) => return false
+    };
+
+    return event.authorize(db_conn, user, context).await;
+}
+
+async fn apply_change<E: PendingEvent>(
+    db_conn: &mut PgConnection,
+    payload: Value,
+    config: &CauthConfig
+) -> Result<(), EventFailure> {
+    // the payload was serialized from the same type
+    let event: E = serde_json::from_value(payload).unwrap();
+
+    return match event.apply(db_conn, config).await {
+        Ok(_) => Ok(()),
+        Err(error) => Err(EventFailure::from_error(&error))
+    };
+}
+
+impl ChangeRequest {
+    /// ## ChangeRequest::store_if_required
+    ///
+    /// Stores the prepared event as a change request when it matches an approval rule,
+    /// returning the id of the change request
+    ///
+    /// Errors:
+    /// + when the event matches an approval rule but the caller has no login session
+    ///
+    pub async fn store_if_required<E: PendingEvent>(
+        db_conn: &mut PgConnection,
+        event: &E,
+        caller: &EventCaller
+    ) -> Result<Option<i32>, EventFailure> {
+        let approvals_required = match ApprovalRule::required(db_conn, event).await {
+            Some(approvals_required) => approvals_required,
+            None => return Ok(None)
+        };
+
+        // the requester cannot approve the change, so it has to be known
+        let requested_by = match caller.login(db_conn).await {
+            Some(login) => login,
+            None => return Err(EventFailure {
+                code: "REQUESTER_REQUIRED",
+                details: "Changes requiring approvals have to be requested with a login session".to_string(),
+                kind: EventErrorKind::Unauthorized,
+                fields: json!({})
+            })
+        };
+
+        // events hold only serializable data, so this will never error
+        let payload = serde_json::to_value(event).unwrap();
+
+        let sql = "
+            INSERT INTO
+                change_requests (event_type, payload, requested_by, approvals_required)
+            VALUES
+                ($1, $2, $3, $4)
+            RETURNING id;
+        ";
+
+        let (id,): (i32,) = query_as(sql)
+            .bind(&E::TYPE)
+            .bind(&payload)
+            .bind(&requested_by)
+            .bind(&approvals_required)
+            .fetch_one(db_conn)
+            .await
+            .unwrap();
+
+        log_database_interaction::<String>(
+            "Inserting a change request into the database.",
+            json!({ "event_type": E::TYPE, "id": id, "requested_by": requested_by }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(Some(id));
+    }
+
+    /// ## ChangeRequest::intercept
+    ///
+    /// Prepares an event of type E from provided input and stores it as a change request
+    /// when it matches an approval rule, returning the id of the change request. <br>
+    /// Used by the routes applying the operations right away
+    ///
+    /// Errors:
+    /// + when there are approval rules for the type and the event cannot be prepared,
+    ///   so the operation is not applied without checking the rules
+    /// + when the event matches an approval rule but the caller has no login session
+    ///
+    pub async fn intercept<E: PendingEvent>(
+        db_conn: &mut PgConnection,
+        input: E::Input,
+        caller: &EventCaller
+    ) -> Result<Option<i32>, EventFailure> {
+        // most operations require no approvals, so they are not prepared twice
+        if !ApprovalRule::exists(db_conn, E::TYPE).await {
+            return Ok(None);
+        }
+
+        let event = match E::prepare(db_conn, input, caller).await {
+            Ok(event) => event,
+            Err(error) => return Err(EventFailure::from_error(&error))
+        };
+
+        return Self::store_if_required(db_conn, &event, caller).await;
+    }
+
+    /// ## ChangeRequest::list
+    ///
+    /// Lists change requests waiting for approvals in specified order with specified offset
+    ///
+    pub async fn list(
+        db_conn: &mut PgConnection,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>
+    ) -> Vec<ChangeRequest> {
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+        SELECT
+            cr.*,
+            ARRAY(
+                SELECT login FROM change_request_approvals
+                WHERE change_request_id = cr.id
+                ORDER BY approved_at
+            ) AS approved_by
+        FROM
+            change_requests cr
+        ORDER BY cr.id {}
+        OFFSET {} ROWS
+        LIMIT {};
+        ",
+            order.to_string(),
+            offset,
+            limit
+        );
+
+        return query_as(&sql)
+            .fetch_all(db_conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## ChangeRequest::retrieve
+    ///
+    /// Retrieves change request with specified id
+    ///
+    /// Errors:
+    /// + When the change request is not found
+    ///
+    pub async fn retrieve(
+        db_conn: &mut PgConnection,
+        id: &i32
+    ) -> Result<ChangeRequest, ChangeRequestRetrieveError> {
+        return Self::select(db_conn, id, false).await;
+    }
+
+    /// ## ChangeRequest::select
+    ///
+    /// Retrieves change request with specified id, optionally locking it until the end of the transaction
+    /// so concurrent approvals wait for each other
+    ///
+    async fn select(
+        db_conn: &mut PgConnection,
+        id: &i32,
+        lock: bool
+    ) -> Result<ChangeRequest, ChangeRequestRetrieveError> {
+        let sql = format!(
+            "
+        SELECT
+            cr.*,
+            ARRAY(
+                SELECT login FROM change_request_approvals
+                WHERE change_request_id = cr.id
+                ORDER BY approved_at
+            ) AS approved_by
+        FROM
+            change_requests cr
+        WHERE
+            cr.id = $1
+        {};
+        ",
+            if lock { "FOR UPDATE" } else { "" }
+        );
+
+        let result = query_as(&sql)
+            .bind(&id)
+            .fetch_one(db_conn)
+            .await;
+
+        return match result {
+            Ok(change_request) => Ok(change_request),
+            Err(_) => Err(ChangeRequestRetrieveError::NotFound)
+        };
+    }
+
+    /// ## ChangeRequest::summarize
+    ///
+    /// Describes the change request for the approvers, leaving out the secrets of it's payload
+    ///
+    pub async fn summarize(self) -> ChangeRequestSummary {
+        let details = with_event_type!(
+            &self.event_type,
+            describe(self.payload.clone()),
+            Value::Null
+        );
+
+        return ChangeRequestSummary {
+            id: self.id,
+            event_type: self.event_type,
+            requested_by: self.requested_by,
+            approvals_required: self.approvals_required,
+            approved_by: self.approved_by,
+            created_at: self.created_at,
+            details
+        };
+    }
+
+    /// ## ChangeRequest::approve
+    ///
+    /// Approves the change request as provided user, applying the change and deleting the request
+    /// once it has the required number of approvals. The approver has to be permitted
+    /// to make the change in provided request context. <br>
+    /// Should be run in a transaction, the transaction should be dropped when this fails
+    /// so the approval is not recorded and the request is kept
+    ///
+    /// Errors:
+    /// + when the change request is not found
+    /// + when the change has no requester
+    /// + when the approver requested the change
+    /// + when the approver is not permitted to make the change,
+    ///   which is also the case for changes of removed types or with unreadable payloads
+    /// + when the approver already approved the change
+    /// + when the change cannot be applied
+    ///
+    pub async fn approve(
+        db_conn: &mut PgConnection,
+        id: &i32,
+        approver: &User,
+        context: &RequestContext,
+        config: &CauthConfig
+    ) -> Result<ChangeRequestApproval, ChangeRequestApproveError> {
+        let change_request = match Self::select(db_conn, id, true).await {
+            Ok(change_request) => change_request,
+            Err(_) => return Err(ChangeRequestApproveError::NotFound)
+        };
+
+        let requested_by = match &change_request.requested_by {
+            Some(requested_by) => requested_by,
+            None => return Err(ChangeRequestApproveError::MissingRequester)
+        };
+
+        if *requested_by == approver.login {
+            return Err(ChangeRequestApproveError::SelfApproval);
+        }
+
+        let authorized = with_event_type!(
+            &change_request.event_type,
+            authorize_change(&mut *db_conn, change_request.payload.clone(), approver, context),
+            // the type was removed after the change was requested, nobody can be permitted to make it
+            false
+        );
+
+        if !authorized {
+            return Err(ChangeRequestApproveError::Unauthorized);
+        }
+
+        if change_request.approved_by.contains(&approver.login) {
+            return Err(ChangeRequestApproveError::AlreadyApproved);
+        }
+
+        let sql = "
+        INSERT INTO
+            change_request_approvals (change_request_id, login)
+        VALUES
+            ($1, $2);
+        ";
+
+        query(sql)
+            .bind(&id)
+            .bind(&approver.login)
+            .execute(&mut *db_conn)
+            .await
+            .unwrap();
+
+        log_database_interaction::<String>(
+            "Approving a change request.",
+            json!({ "id": id, "approver": approver.login }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        let approvals = change_request.approved_by.len() as i32 + 1;
+
+        if approvals < change_request.approvals_required {
+            return Ok(ChangeRequestApproval {
+                approvals,
+                approvals_required: change_request.approvals_required,
+                applied: false
+            });
+        }
+
+        let event_type = change_request.event_type.clone();
+
+        let result = with_event_type!(
+            &event_type,
+            apply_change(&mut *db_conn, change_request.payload, config),
+            // the type was removed after the change was requested
+            Err(EventFailure {
+                code: "UNKNOWN_EVENT_TYPE",
+                details: format!("Event type \"{}\" do not exist", event_type),
+                kind: EventErrorKind::Internal,
+                fields: json!({})
+            })
+        );
+
+        if let Err(failure) = result {
+            return Err(ChangeRequestApproveError::Operation(failure));
+        }
+
+        Self::delete(db_conn, id).await;
+
+        return Ok(ChangeRequestApproval {
+            approvals,
+            approvals_required: change_request.approvals_required,
+            applied: true
+        });
+    }
+
+    /// ## ChangeRequest::reject
+    ///
+    /// Deletes the change request without applying it
+    ///
+    /// Errors:
+    /// + when the change request is not found
+    ///
+    pub async fn reject(
+        db_conn: &mut PgConnection,
+        id: &i32,
+        rejected_by: &String
+    ) -> Result<(), ChangeRequestRejectError> {
+        if !Self::delete(db_conn, id).await {
+            return Err(ChangeRequestRejectError::NotFound);
+        }
+
+        log_database_interaction::<String>(
+            "Rejecting a change request.",
+            json!({ "id": id, "rejected_by": rejected_by }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// deletes the change request along with it's approvals, returning whether it existed
+    async fn delete(
+        db_conn: &mut PgConnection,
+        id: &i32
+    ) -> bool {
+        let sql = "
+        DELETE FROM
+            change_requests
+        WHERE
+            id = $1;
+        ";
+
+        let result = query(sql)
+            .bind(&id)
+            .execute(db_conn)
+            .await
+            .unwrap();
+
+        return result.rows_affected() > 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{
+            event::{
+                user_grant_group::{UserGrantGroupEvent, UserGrantGroupEventInput},
+                StoredEvent,
+                UserRegisterEvent
+            },
+            group::Group,
+            permission::Permission,
+            LoginSession
+        },
+        util::testing
+    };
+
+    async fn user(db_conn: &mut PgConnection, login: &str, groups: &[&str]) -> User {
+        User::insert_unhashed(db_conn, &login.to_string(), &String::new(), &json!({}), &vec![])
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        for group in groups {
+            User::grant_group(db_conn, &login.to_string(), &group.to_string(), None, &"default".to_string())
+                .await
+                .map_err(|err| err.to_string())
+                .unwrap();
+        }
+
+        return User::retrieve(db_conn, &login.to_string()).await.ok().unwrap();
+    }
+
+    fn caller(session_token: Option<String>) -> EventCaller {
+        return EventCaller { session_token, context: RequestContext::now() };
+    }
+
+    fn grant(login: &str) -> UserGrantGroupEventInput {
+        return UserGrantGroupEventInput { login: login.to_string(), group: "staff".to_string(), expires_at: None };
+    }
+
+    #[tokio::test]
+    async fn approvers_have_to_be_permitted_to_make_the_change() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let config = testing::config(pool.clone(), None);
+        let context = RequestContext::now();
+
+        Permission::insert(&mut conn, &"cauth:users:update".to_string(), &String::new()).await.unwrap();
+        for (group, permissions) in [("admins", vec!["cauth:users:update".to_string()]), ("staff", vec![])] {
+            Group::insert(&mut conn, &group.to_string(), &String::new(), &permissions, &"default".to_string())
+                .await
+                .map_err(|err| err.to_string())
+                .unwrap();
+        }
+        let alice = user(&mut conn, "alice", &["admins"]).await;
+        let bob = user(&mut conn, "bob", &["admins"]).await;
+        let carol = user(&mut conn, "carol", &[]).await;
+        user(&mut conn, "dave", &[]).await;
+        ApprovalRule::insert(&mut conn, &"users/grant-group".to_string(), None, 1)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        let token = LoginSession::insert(&mut conn, "alice".to_string()).await.unwrap();
+        let id = ChangeRequest::intercept::<UserGrantGroupEvent>(&mut conn, grant("dave"), &caller(Some(token)))
+            .await
+            .unwrap_or_else(|_| panic!("the change should be requested"))
+            .unwrap();
+
+        let result = ChangeRequest::approve(&mut conn, &id, &carol, &context, &config).await;
+        assert!(matches!(result, Err(ChangeRequestApproveError::Unauthorized)));
+        let result = ChangeRequest::approve(&mut conn, &id, &alice, &context, &config).await;
+        assert!(matches!(result, Err(ChangeRequestApproveError::SelfApproval)));
+
+        let approval = ChangeRequest::approve(&mut conn, &id, &bob, &context, &config)
+            .await
+            .unwrap_or_else(|_| panic!("the change should be approved"));
+        assert!(approval.applied);
+        assert!(ChangeRequest::retrieve(&mut conn, &id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn changes_need_a_requester() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let config = testing::config(pool.clone(), None);
+
+        ApprovalRule::insert(&mut conn, &"users/register".to_string(), None, 1)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        let input = serde_json::from_value(json!({ "login": "jane", "password": "password123" })).unwrap();
+        let result = StoredEvent::insert::<UserRegisterEvent>(&mut conn, input, &caller(None)).await;
+        assert!(matches!(result, Err(EventFailure { code: "REQUESTER_REQUIRED", .. })));
+
+        // change requests stored before requesters were required cannot be approved
+        let (id,): (i32,) = query_as(
+            "INSERT INTO change_requests (event_type, payload, approvals_required) VALUES ('users/register', '{}', 1) RETURNING id;"
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        let approver = user(&mut conn, "bob", &[]).await;
+        let result = ChangeRequest::approve(&mut conn, &id, &approver, &RequestContext::now(), &config).await;
+        assert!(matches!(result, Err(ChangeRequestApproveError::MissingRequester)));
+    }
+
+    #[tokio::test]
+    async fn unreadable_changes_cannot_be_approved() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let config = testing::config(pool.clone(), None);
+
+        user(&mut conn, "alice", &[]).await;
+        let approver = user(&mut conn, "bob", &[]).await;
+        for (event_type, payload) in [("users/removed", json!({})), ("users/grant-group", json!({ "login": 1 }))] {
+            let (id,): (i32,) = query_as(
+                "INSERT INTO change_requests (event_type, payload, approvals_required, requested_by) VALUES ($1, $2, 1, 'alice') RETURNING id;"
+            )
+            .bind(event_type)
+            .bind(&payload)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+
+            let result = ChangeRequest::approve(&mut conn, &id, &approver, &RequestContext::now(), &config).await;
+            assert!(matches!(result, Err(ChangeRequestApproveError::Unauthorized)));
+        }
+    }
+
+    #[tokio::test]
+    async fn operations_which_cannot_be_prepared_are_not_let_through() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+
+        // without rules the routes report their own errors
+        let result = ChangeRequest::intercept::<UserGrantGroupEvent>(&mut conn, grant("dave"), &caller(None)).await;
+        assert!(matches!(result, Ok(None)));
+
+        ApprovalRule::insert(&mut conn, &"users/grant-group".to_string(), Some(&"root".to_string()), 2)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        let result = ChangeRequest::intercept::<UserGrantGroupEvent>(&mut conn, grant("dave"), &caller(None)).await;
+        assert!(matches!(result, Err(EventFailure { code: "UNAUTHORIZED", .. })));
+    }
+}
@@ -0,0 +1,101 @@
+use serde_json::Value;
+use sqlx::{
+    prelude::FromRow,
+    query,
+    query_as,
+    PgConnection
+};
+
+/// Result of a committed event or batch, returned again when the commit is retried
+#[derive(FromRow)]
+pub struct CommittedEvent {
+    key: String,
+    /// not recorded for the results which have to reach only the first commit
+    pub output: Option<Value>
+}
+
+pub enum CommittedEventResultError {
+    /// Returned when no event with specified id and type was committed
+    NotFound,
+    /// Returned when the key is invalid
+    Unauthorized,
+    /// Returned when the event was committed but its result was not recorded
+    NotRecorded
+}
+
+impl CommittedEvent {
+    /// ## CommittedEvent::result
+    ///
+    /// Retrieves the result of committed event of specified type with specified id
+    ///
+    /// Errors:
+    /// + When the event was not committed
+    /// + When the key is invalid
+    /// + When the result was not recorded
+    ///
+    pub async fn result(
+        db_conn: &mut PgConnection,
+        event_type: &str,
+        id: &i32,
+        key: &String
+    ) -> Result<Value, CommittedEventResultError> {
+        let sql = "
+        SELECT
+            key,
+            output
+        FROM
+            committed_events
+        WHERE
+            event_type = $1 AND id = $2;
+        ";
+        let result: Result<CommittedEvent, _> = query_as(sql)
+            .bind(&event_type)
+            .bind(&id)
+            .fetch_one(db_conn)
+            .await;
+
+        let committed = match result {
+            Ok(committed) => committed,
+            Err(_) => return Err(CommittedEventResultError::NotFound)
+        };
+
+        if *key != committed.key {
+            return Err(CommittedEventResultError::Unauthorized);
+        }
+
+        return match committed.output {
+            Some(output) => Ok(output),
+            None => Err(CommittedEventResultError::NotRecorded)
+        };
+    }
+
+    /// ## CommittedEvent::insert
+    ///
+    /// Records the result of committed event of specified type with specified id,
+    /// or only that it was committed when no result is provided,
+    /// should be run in the same transaction as the commit
+    ///
+    pub async fn insert(
+        db_conn: &mut PgConnection,
+        event_type: &str,
+        id: &i32,
+        key: &String,
+        output: Option<&Value>
+    ) {
+        let sql = "
+            INSERT INTO
+                committed_events (event_type, id, key, output)
+            VALUES
+                ($1, $2, $3, $4);
+        ";
+
+        let _ = query(sql)
+            .bind(&event_type)
+            .bind(&id)
+            .bind(&key)
+            .bind(&output)
+            .execute(db_conn)
+            .await
+            .unwrap();
+    }
+}
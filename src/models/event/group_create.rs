@@ -0,0 +1,200 @@
+use serde::{
+    Deserialize,
+    Serialize
+};
+use sqlx::PgConnection;
+use crate::{
+    config::CauthConfig,
+    models::{
+        group::{
+            Group,
+            GroupInsertError
+        },
+        organization::DEFAULT_ORGANIZATION,
+        webhook::WebhookEnqueueError,
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
+        },
+        Application,
+        RequestContext,
+        User
+    }
+};
+
+/// Creates a group, granting it provided permissions in the caller's active organization
+#[derive(Serialize, Deserialize)]
+pub struct GroupCreateEvent {
+    name: String,
+    description: String,
+    permissions: Vec<String>,
+    organization: String
+}
+
+#[derive(Deserialize)]
+pub struct GroupCreateEventInput {
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<String>
+}
+
+pub enum GroupCreateEventError {
+    /// Returned when the caller can neither create groups nor administer the group and all of it's permissions
+    Unauthorized,
+    /// Returned when the group is inside of the reserved namespace
+    ReservedNamespace,
+    /// Returned when a group with the same name already exists
+    NameError,
+    /// Returned when one of the permissions do not exist
+    PermissionNotFound,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
+}
+
+impl ToString for GroupCreateEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Unauthorized => "You are not authorized to do that!".to_string(),
+            Self::ReservedNamespace => "The \"cauth\" namespace is reserved for the service itself.".to_string(),
+            Self::NameError => "A group with this name already exist.".to_string(),
+            Self::PermissionNotFound => "One of the listed permissions do not exist.".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details)
+        };
+    }
+}
+
+impl EventError for GroupCreateEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::ReservedNamespace => "RESERVED_NAMESPACE",
+            Self::NameError => "NAME_ERROR",
+            Self::PermissionNotFound => "PERMISSION_NOT_FOUND",
+            Self::Webhook(_) => "WEBHOOK_ERROR",
+            Self::Database(_) => "DATABASE_ERROR"
+        };
+    }
+
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::Unauthorized => EventErrorKind::Unauthorized,
+            Self::Webhook(_) => EventErrorKind::Internal,
+            Self::Database(_) => EventErrorKind::Internal,
+            _ => EventErrorKind::BadRequest
+        };
+    }
+}
+
+impl PendingEvent for GroupCreateEvent {
+    const TYPE: &'static str = "groups/create";
+
+    type Input = GroupCreateEventInput;
+    type Output = ();
+    type Error = GroupCreateEventError;
+
+    fn target(&self) -> Option<&str> {
+        return Some(&self.name);
+    }
+
+    /// ## GroupCreateEvent::prepare
+    ///
+    /// Checks the caller is permitted the same way as POST /groups,
+    /// resolving the organization the permissions are granted in. <br>
+    /// The permissions are not checked yet, so they can be created by events committed before it
+    ///
+    /// Errors:
+    /// + when the caller is not permitted
+    /// + when the group is inside of the reserved namespace
+    /// + when the group already exists
+    ///
+    async fn prepare(
+        db_conn: &mut PgConnection,
+        input: GroupCreateEventInput,
+        caller: &EventCaller
+    ) -> Result<GroupCreateEvent, GroupCreateEventError> {
+        let names: Vec<&String> = std::iter::once(&input.name)
+            .chain(input.permissions.iter())
+            .collect();
+        let permitted = caller.has_global_permission(db_conn, "cauth:groups:post").await
+            || caller.can_administer(db_conn, &names).await;
+
+        if !permitted {
+            return Err(GroupCreateEventError::Unauthorized);
+        }
+
+        if Application::is_reserved(&input.name) {
+            return Err(GroupCreateEventError::ReservedNamespace);
+        }
+
+        // groups are shared by all the organizations
+        if Group::retrieve(db_conn, &input.name, &DEFAULT_ORGANIZATION.to_string()).await.is_ok() {
+            return Err(GroupCreateEventError::NameError);
+        }
+
+        let organization = caller.organization(db_conn).await;
+
+        return Ok(GroupCreateEvent {
+            name: input.name,
+            description: input.description,
+            permissions: input.permissions,
+            organization
+        });
+    }
+
+    /// ## GroupCreateEvent::authorize
+    ///
+    /// Checks the permissions of GroupCreateEvent::prepare, groups are managed from the default organization
+    ///
+    async fn authorize(
+        &self,
+        db_conn: &mut PgConnection,
+        user: &User,
+        context: &RequestContext
+    ) -> bool {
+        let names: Vec<&String> = std::iter::once(&self.name)
+            .chain(self.permissions.iter())
+            .collect();
+        let context = context.with_organization(DEFAULT_ORGANIZATION.to_string());
+
+        return user.has_permission(db_conn, "cauth:groups:post".to_string(), &context).await
+            || Application::can_administer(db_conn, &user.login, &names).await;
+    }
+
+    /// ## GroupCreateEvent::apply
+    ///
+    /// Creates the group
+    ///
+    /// Errors:
+    /// + when the group was created in the meantime
+    /// + when one of the permissions do not exist
+    ///
+    async fn apply(
+        self,
+        db_conn: &mut PgConnection,
+        _config: &CauthConfig
+    ) -> Result<(), GroupCreateEventError> {
+        let result = Group::insert(
+            db_conn,
+            &self.name,
+            &self.description,
+            &self.permissions,
+            &self.organization
+        )
+        .await;
+
+        return match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(match error {
+                GroupInsertError::NameError => GroupCreateEventError::NameError,
+                GroupInsertError::PermissionNotFound => GroupCreateEventError::PermissionNotFound,
+                GroupInsertError::Webhook(err) => GroupCreateEventError::Webhook(err),
+                GroupInsertError::Database(details) => GroupCreateEventError::Database(details)
+            })
+        };
+    }
+}
@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use serde::{
+    Deserialize,
+    Serialize
+};
+use sqlx::PgConnection;
+use crate::{
+    config::CauthConfig,
+    models::{
+        group::{
+            Group,
+            GroupGrantError
+        },
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
+        },
+        Application,
+        RequestContext,
+        User,
+        GrantEffect
+    },
+    util::condition::Condition
+};
+
+/// Grants a group a permission in the caller's active organization
+#[derive(Serialize, Deserialize)]
+pub struct GroupGrantPermissionEvent {
+    group_name: String,
+    permission_name: String,
+    effect: GrantEffect,
+    condition: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    organization: String
+}
+
+#[derive(Deserialize)]
+pub struct GroupGrantPermissionEventInput {
+    pub group: String,
+    pub permission: String,
+    pub effect: Option<GrantEffect>,
+    pub condition: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>
+}
+
+pub enum GroupGrantPermissionEventError {
+    /// Returned when the caller can neither update groups nor administer the group and the permission
+    Unauthorized,
+    /// Returned when the group do not exist
+    NotFound,
+    /// Returned when the permission do not exist
+    PermissionNotFound,
+    /// Returned when the condition cannot be parsed
+    InvalidCondition(String),
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
+}
+
+impl ToString for GroupGrantPermissionEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Unauthorized => "You are not authorized to do that!".to_string(),
+            Self::NotFound => "A group with this name do not exist".to_string(),
+            Self::PermissionNotFound => "A permission with this name do not exist".to_string(),
+            Self::InvalidCondition(details) => details.clone(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details)
+        };
+    }
+}
+
+impl EventError for GroupGrantPermissionEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::NotFound => "NOT_FOUND",
+            Self::PermissionNotFound => "PERMISSION_NOT_FOUND",
+            Self::InvalidCondition(_) => "INVALID_CONDITION",
+            Self::Database(_) => "DATABASE_ERROR"
+        };
+    }
+
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::Unauthorized => EventErrorKind::Unauthorized,
+            Self::Database(_) => EventErrorKind::Internal,
+            _ => EventErrorKind::BadRequest
+        };
+    }
+}
+
+impl PendingEvent for GroupGrantPermissionEvent {
+    const TYPE: &'static str = "groups/grant-permission";
+
+    type Input = GroupGrantPermissionEventInput;
+    type Output = ();
+    type Error = GroupGrantPermissionEventError;
+
+    fn target(&self) -> Option<&str> {
+        return Some(&self.permission_name);
+    }
+
+    /// ## GroupGrantPermissionEvent::prepare
+    ///
+    /// Checks the caller is permitted the same way as POST /groups/{name}/{permission_name},
+    /// resolving the organization the permission is granted in. <br>
+    /// The group and the permission are not checked yet, so they can be created by events committed before it
+    ///
+    /// Errors:
+    /// + when the caller is not permitted
+    /// + when the condition cannot be parsed
+    ///
+    async fn prepare(
+        db_conn: &mut PgConnection,
+        input: GroupGrantPermissionEventInput,
+        caller: &EventCaller
+    ) -> Result<GroupGrantPermissionEvent, GroupGrantPermissionEventError> {
+        let permitted = caller.has_permission(db_conn, "cauth:groups:update").await
+            || caller.can_administer(db_conn, &[&input.group, &input.permission]).await;
+
+        if !permitted {
+            return Err(GroupGrantPermissionEventError::Unauthorized);
+        }
+
+        if let Some(condition) = &input.condition {
+            if let Err(err) = Condition::parse(condition) {
+                return Err(GroupGrantPermissionEventError::InvalidCondition(err.to_string()));
+            }
+        }
+
+        let organization = caller.organization(db_conn).await;
+
+        return Ok(GroupGrantPermissionEvent {
+            group_name: input.group,
+            permission_name: input.permission,
+            effect: input.effect.unwrap_or(GrantEffect::Allow),
+            condition: input.condition,
+            expires_at: input.expires_at,
+            organization
+        });
+    }
+
+    /// ## GroupGrantPermissionEvent::authorize
+    ///
+    /// Checks the permissions of GroupGrantPermissionEvent::prepare in the organization of the change
+    ///
+    async fn authorize(
+        &self,
+        db_conn: &mut PgConnection,
+        user: &User,
+        context: &RequestContext
+    ) -> bool {
+        let context = context.with_organization(self.organization.clone());
+
+        return user.has_permission(db_conn, "cauth:groups:update".to_string(), &context).await
+            || Application::can_administer(db_conn, &user.login, &[&self.group_name, &self.permission_name]).await;
+    }
+
+    /// ## GroupGrantPermissionEvent::apply
+    ///
+    /// Grants the group the permission
+    ///
+    /// Errors:
+    /// + when the group or the permission do not exist
+    ///
+    async fn apply(
+        self,
+        db_conn: &mut PgConnection,
+        _config: &CauthConfig
+    ) -> Result<(), GroupGrantPermissionEventError> {
+        let result = Group::grant_permission(
+            db_conn,
+            &self.group_name,
+            &self.permission_name,
+            self.effect,
+            self.condition.as_ref(),
+            self.expires_at,
+            &self.organization
+        )
+        .await;
+
+        return match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(match error {
+                GroupGrantError::NotFound => GroupGrantPermissionEventError::NotFound,
+                GroupGrantError::PermissionNotFound => GroupGrantPermissionEventError::PermissionNotFound,
+                GroupGrantError::InvalidCondition(details) => GroupGrantPermissionEventError::InvalidCondition(details),
+                GroupGrantError::Database(details) => GroupGrantPermissionEventError::Database(details)
+            })
+        };
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{
+    Deserialize,
+    Serialize
+};
+use sqlx::PgConnection;
+use crate::{
+    config::CauthConfig,
+    models::{
+        group::{
+            Group,
+            GroupRevokeError
+        },
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
+        },
+        Application,
+        RequestContext,
+        User
+    }
+};
+
+/// Revokes a permission from a group in the caller's active organization
+#[derive(Serialize, Deserialize)]
+pub struct GroupRevokePermissionEvent {
+    group_name: String,
+    permission_name: String,
+    organization: String
+}
+
+#[derive(Deserialize)]
+pub struct GroupRevokePermissionEventInput {
+    pub group: String,
+    pub permission: String
+}
+
+pub enum GroupRevokePermissionEventError {
+    /// Returned when the caller can neither update groups nor administer the group and the permission
+    Unauthorized,
+    /// Returned when the group do not exist
+    NotFound,
+    /// Returned when the permission do not exist
+    PermissionNotFound,
+    /// Returned when the group do not have the permission
+    PermissionNotGranted,
+    /// Returned when the permission is protected in the group
+    Protected,
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
+}
+
+impl ToString for GroupRevokePermissionEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Unauthorized => "You are not authorized to do that!".to_string(),
+            Self::NotFound => "A group with this name do not exist".to_string(),
+            Self::PermissionNotFound => "A permission with this name do not exist".to_string(),
+            Self::PermissionNotGranted => "This group never had that permission".to_string(),
+            Self::Protected => "Protected permissions cannot be revoked from a protected group.".to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details)
+        };
+    }
+}
+
+impl EventError for GroupRevokePermissionEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::NotFound => "NOT_FOUND",
+            Self::PermissionNotFound => "PERMISSION_NOT_FOUND",
+            Self::PermissionNotGranted => "PERMISSION_NOT_GRANTED",
+            Self::Protected => "PROTECTED_ENTITY",
+            Self::Database(_) => "DATABASE_ERROR"
+        };
+    }
+
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::Unauthorized => EventErrorKind::Unauthorized,
+            Self::Database(_) => EventErrorKind::Internal,
+            _ => EventErrorKind::BadRequest
+        };
+    }
+}
+
+impl PendingEvent for GroupRevokePermissionEvent {
+    const TYPE: &'static str = "groups/revoke-permission";
+
+    type Input = GroupRevokePermissionEventInput;
+    type Output = ();
+    type Error = GroupRevokePermissionEventError;
+
+    fn target(&self) -> Option<&str> {
+        return Some(&self.permission_name);
+    }
+
+    /// ## GroupRevokePermissionEvent::prepare
+    ///
+    /// Checks the caller is permitted the same way as DELETE /groups/{name}/{permission_name},
+    /// resolving the organization the permission is revoked in
+    ///
+    /// Errors:
+    /// + when the caller is not permitted
+    ///
+    async fn prepare(
+        db_conn: &mut PgConnection,
+        input: GroupRevokePermissionEventInput,
+        caller: &EventCaller
+    ) -> Result<GroupRevokePermissionEvent, GroupRevokePermissionEventError> {
+        let permitted = caller.has_permission(db_conn, "cauth:groups:update").await
+            || caller.can_administer(db_conn, &[&input.group, &input.permission]).await;
+
+        if !permitted {
+            return Err(GroupRevokePermissionEventError::Unauthorized);
+        }
+
+        let organization = caller.organization(db_conn).await;
+
+        return Ok(GroupRevokePermissionEvent {
+            group_name: input.group,
+            permission_name: input.permission,
+            organization
+        });
+    }
+
+    /// ## GroupRevokePermissionEvent::authorize
+    ///
+    /// Checks the permissions of GroupRevokePermissionEvent::prepare in the organization of the change
+    ///
+    async fn authorize(
+        &self,
+        db_conn: &mut PgConnection,
+        user: &User,
+        context: &RequestContext
+    ) -> bool {
+        let context = context.with_organization(self.organization.clone());
+
+        return user.has_permission(db_conn, "cauth:groups:update".to_string(), &context).await
+            || Application::can_administer(db_conn, &user.login, &[&self.group_name, &self.permission_name]).await;
+    }
+
+    /// ## GroupRevokePermissionEvent::apply
+    ///
+    /// Revokes the permission from the group
+    ///
+    /// Errors:
+    /// + when the group or the permission do not exist
+    /// + when the group do not have the permission
+    /// + when the permission is protected in the group
+    ///
+    async fn apply(
+        self,
+        db_conn: &mut PgConnection,
+        _config: &CauthConfig
+    ) -> Result<(), GroupRevokePermissionEventError> {
+        let result = Group::revoke_permission(
+            db_conn,
+            &self.group_name,
+            &self.permission_name,
+            &self.organization
+        )
+        .await;
+
+        return match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(match error {
+                GroupRevokeError::NotFound => GroupRevokePermissionEventError::NotFound,
+                GroupRevokeError::PermissionNotFound => GroupRevokePermissionEventError::PermissionNotFound,
+                GroupRevokeError::PermissionNotGranted => GroupRevokePermissionEventError::PermissionNotGranted,
+                GroupRevokeError::Protected => GroupRevokePermissionEventError::Protected,
+                GroupRevokeError::Database(details) => GroupRevokePermissionEventError::Database(details)
+            })
+        };
+    }
+}
@@ -1,17 +1,406 @@
+pub mod stored_event;
+pub mod batch;
+pub mod committed_event;
+pub mod approval_rule;
+pub mod change_request;
 pub mod user_register;
 pub mod user_login;
 pub mod user_delete;
+pub mod user_grant_group;
+pub mod user_revoke_group;
+pub mod group_create;
+pub mod group_grant_permission;
+pub mod group_revoke_permission;
+pub mod permission_create;
 
 pub use crate::models::event::{
+    stored_event::StoredEvent,
+    batch::EventBatch,
+    committed_event::CommittedEvent,
+    approval_rule::ApprovalRule,
+    change_request::ChangeRequest,
     user_register::UserRegisterEvent,
     user_login::UserLoginEvent,
-    user_delete::UserDeleteEvent
+    user_delete::UserDeleteEvent,
+    user_grant_group::UserGrantGroupEvent,
+    user_revoke_group::UserRevokeGroupEvent,
+    group_create::GroupCreateEvent,
+    group_grant_permission::GroupGrantPermissionEvent,
+    group_revoke_permission::GroupRevokePermissionEvent,
+    permission_create::PermissionCreateEvent
+};
+use std::time::{
+    self,
+    UNIX_EPOCH
+};
+use chrono::{DateTime, Duration, Utc};
+use crypto::{
+    digest::Digest,
+    sha3::Sha3
+};
+use sqlx::{prelude::FromRow, query, PgConnection};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use crate::{
+    config::CauthConfig,
+    models::{
+        organization::DEFAULT_ORGANIZATION,
+        LoginSession,
+        RequestContext,
+        User
+    },
+    util::logging::{log_database_interaction, DatabaseOperationLogStatus}
 };
-use sqlx::prelude::FromRow;
-use serde::Serialize;
 
 #[derive(FromRow, Serialize)]
 pub struct EventCredentials {
     id: i32,
     key: String
 }
+
+/// The request creating an event, checked by the events which are not open to everyone
+pub struct EventCaller {
+    pub session_token: Option<String>,
+    pub context: RequestContext
+}
+
+impl EventCaller {
+    /// ## EventCaller::has_permission
+    ///
+    /// Checks if the caller's session has provided permission, false when there is no session
+    ///
+    pub async fn has_permission(
+        &self,
+        db_conn: &mut PgConnection,
+        permission_name: &str
+    ) -> bool {
+        return match &self.session_token {
+            Some(session_token) => LoginSession::has_permission(db_conn, session_token, permission_name, &self.context).await,
+            None => false
+        };
+    }
+
+    /// ## EventCaller::has_global_permission
+    ///
+    /// Checks if the caller's session has provided permission in the default organization,
+    /// which manages the entities shared by all organizations, false when there is no session
+    ///
+    pub async fn has_global_permission(
+        &self,
+        db_conn: &mut PgConnection,
+        permission_name: &str
+    ) -> bool {
+        return match &self.session_token {
+            Some(session_token) => LoginSession::has_permission_in(db_conn, session_token, permission_name, &self.context, DEFAULT_ORGANIZATION).await,
+            None => false
+        };
+    }
+
+    /// ## EventCaller::can_delegate_group
+    ///
+    /// Checks if the caller's session can delegate provided group, false when there is no session
+    ///
+    pub async fn can_delegate_group(
+        &self,
+        db_conn: &mut PgConnection,
+        group_name: &String
+    ) -> bool {
+        return match &self.session_token {
+            Some(session_token) => LoginSession::can_delegate_group(db_conn, session_token, group_name, &self.context).await,
+            None => false
+        };
+    }
+
+    /// ## EventCaller::can_administer
+    ///
+    /// Checks if all provided names are inside of the caller's application namespaces,
+    /// false when there is no session
+    ///
+    pub async fn can_administer(
+        &self,
+        db_conn: &mut PgConnection,
+        names: &[&String]
+    ) -> bool {
+        return match &self.session_token {
+            Some(session_token) => LoginSession::can_administer(db_conn, session_token, names).await,
+            None => false
+        };
+    }
+
+    /// ## EventCaller::login
+    ///
+    /// Returns the login of the caller's session user, None when there is no session
+    ///
+    pub async fn login(
+        &self,
+        db_conn: &mut PgConnection
+    ) -> Option<String> {
+        return match &self.session_token {
+            Some(session_token) => LoginSession::retrieve(db_conn, session_token)
+                .await
+                .ok()
+                .map(|session| session.user_login),
+            None => None
+        };
+    }
+
+    /// ## EventCaller::organization
+    ///
+    /// Returns the active organization of the caller's session, the default one when there is no session
+    ///
+    pub async fn organization(
+        &self,
+        db_conn: &mut PgConnection
+    ) -> String {
+        return match &self.session_token {
+            Some(session_token) => LoginSession::context(db_conn, session_token, &self.context).await.organization,
+            None => DEFAULT_ORGANIZATION.to_string()
+        };
+    }
+}
+
+/// How an event error should be reported to the caller
+pub enum EventErrorKind {
+    /// The request cannot be fulfilled as it is
+    BadRequest,
+    /// The caller is not allowed to do that
+    Unauthorized,
+    /// The service is misconfigured
+    Internal
+}
+
+/// Error returned by an event type when preparing or applying the event
+pub trait EventError: ToString {
+    /// ## EventError::code
+    ///
+    /// Code the error is reported with, e.g. "USER_NOT_FOUND"
+    ///
+    fn code(&self) -> &'static str;
+
+    /// ## EventError::kind
+    ///
+    /// How the error should be reported, most errors are caused by the request
+    ///
+    fn kind(&self) -> EventErrorKind {
+        return EventErrorKind::BadRequest;
+    }
+
+    /// ## EventError::fields
+    ///
+    /// Additional fields reported along with the code and details
+    ///
+    fn fields(&self) -> Value {
+        return json!({});
+    }
+}
+
+/// Error of an event whose type is known only at runtime, e.g. inside of a batch
+pub struct EventFailure {
+    pub code: &'static str,
+    pub details: String,
+    pub kind: EventErrorKind,
+    pub fields: Value
+}
+
+impl EventFailure {
+    pub fn from_error<E: EventError>(error: &E) -> Self {
+        return Self {
+            code: error.code(),
+            details: error.to_string(),
+            kind: error.kind(),
+            fields: error.fields()
+        };
+    }
+}
+
+/// Two-phase operation, prepared when the event is created and applied when it is committed. <br>
+/// The prepared event is stored as JSON until it's committed, cancelled or expired,
+/// so it should hold everything needed to apply it.
+///
+/// New event types have to be listed in `with_event_type` to be reachable through the routes
+#[allow(async_fn_in_trait)]
+pub trait PendingEvent: Sized + Serialize + DeserializeOwned {
+    /// Name of the event type, used in the routes (`/events/{TYPE}`)
+    const TYPE: &'static str;
+
+    /// Data the event is created from
+    type Input: DeserializeOwned;
+    /// Data returned when the event is committed
+    type Output: Serialize;
+    type Error: EventError;
+
+    /// Whether approval rules can be declared for the type,
+    /// false for the types whose output should reach only the caller
+    const APPROVABLE: bool = true;
+
+    /// ## PendingEvent::target
+    ///
+    /// Name of the entity the event is about (e.g. the granted group), matched against approval rules
+    ///
+    fn target(&self) -> Option<&str> {
+        return None;
+    }
+
+    /// ## PendingEvent::details
+    ///
+    /// Data of the event shown to admins inspecting it, should leave out secrets like password hashes
+    ///
+    fn details(&self) -> Value {
+        return serde_json::to_value(self).unwrap();
+    }
+
+    /// ## PendingEvent::recorded_output
+    ///
+    /// Output recorded for the retried commits, None when the output has to reach only the first commit
+    /// (e.g. session tokens), retrying the commit fails then
+    ///
+    fn recorded_output(output: &Self::Output) -> Option<Value> {
+        return Some(json!(output));
+    }
+
+    /// ## PendingEvent::prepare
+    ///
+    /// Validates the input and the caller, creating the event to store
+    ///
+    async fn prepare(
+        db_conn: &mut PgConnection,
+        input: Self::Input,
+        caller: &EventCaller
+    ) -> Result<Self, Self::Error>;
+
+    /// ## PendingEvent::authorize
+    ///
+    /// Checks if provided user could make the prepared change, approvers of change requests
+    /// have to be permitted to make it themselves. Open to everyone by default
+    ///
+    async fn authorize(
+        &self,
+        _db_conn: &mut PgConnection,
+        _user: &User,
+        _context: &RequestContext
+    ) -> bool {
+        return true;
+    }
+
+    /// ## PendingEvent::apply
+    ///
+    /// Applies the changes of the event, runs in the same transaction as the event's deletion
+    ///
+    async fn apply(
+        self,
+        db_conn: &mut PgConnection,
+        config: &CauthConfig
+    ) -> Result<Self::Output, Self::Error>;
+}
+
+/// ## with_event_type
+///
+/// Calls a function generic over PendingEvent with the event type registered under provided name,
+/// evaluating to the fallback when there is no such event type
+///
+/// `with_event_type!(name, function(arguments), fallback)`
+///
+macro_rules! with_event_type {
+    ($event_type:expr, $function:ident $arguments:tt, $fallback:expr) => {
+        with_event_type!(
+            @types [
+                $crate::models::event::UserRegisterEvent,
+                $crate::models::event::UserLoginEvent,
+                $crate::models::event::UserDeleteEvent,
+                $crate::models::event::UserGrantGroupEvent,
+                $crate::models::event::UserRevokeGroupEvent,
+                $crate::models::event::GroupCreateEvent,
+                $crate::models::event::GroupGrantPermissionEvent,
+                $crate::models::event::GroupRevokePermissionEvent,
+                $crate::models::event::PermissionCreateEvent
+            ],
+            $event_type, $function $arguments, $fallback
+        )
+    };
+    (@types [$($event:ty),*], $event_type:expr, $function:ident $arguments:tt, $fallback:expr) => {
+        'dispatch: {
+            let event_type: &str = $event_type;
+            $(
+                if event_type == <$event as $crate::models::event::PendingEvent>::TYPE {
+                    break 'dispatch $function::<$event> $arguments.await;
+                }
+            )*
+            $fallback
+        }
+    };
+}
+pub(crate) use with_event_type;
+
+/// ## describe
+///
+/// Deserializes stored payload of an event of type E, returning it's details
+///
+pub async fn describe<E: PendingEvent>(payload: Value) -> Value {
+    // the payload was serialized from the same type
+    let event: E = serde_json::from_value(payload).unwrap();
+
+    return event.details();
+}
+
+/// ## generate_key
+///
+/// Generates a key of a pending event or batch from provided seed and the current time
+///
+pub fn generate_key(seed: &str) -> String {
+    let time_since_epoch = time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let key_raw = format!("{}{}", seed, time_since_epoch);
+
+    let mut hasher = Sha3::keccak256();
+    hasher.input_str(key_raw.as_str());
+
+    return hasher.result_str();
+}
+
+/// ## is_expired
+///
+/// Checks if an event created at provided time is older than provided TTL (in seconds)
+///
+pub fn is_expired(created_at: &DateTime<Utc>, ttl: u64) -> bool {
+    return *created_at + Duration::seconds(ttl as i64) <= Utc::now();
+}
+
+/// ## delete_expired
+///
+/// Deletes all the pending events and batches older than provided TTL (in seconds),
+/// along with the results of the events committed before that, returning the number of deleted rows
+///
+pub async fn delete_expired(conn: &mut PgConnection, ttl: u64) -> u64 {
+    let mut deleted = 0;
+
+    // events of a batch are deleted along with it
+    for (table, column) in [("event_batches", "created_at"), ("pending_events", "created_at"), ("committed_events", "committed_at")] {
+        let sql = format!(
+            "DELETE FROM {} WHERE {} + make_interval(secs => $1) <= NOW();",
+            table,
+            column
+        );
+        let result = query(&sql).bind(ttl as f64).execute(&mut *conn).await;
+
+        match result {
+            Ok(result) => deleted += result.rows_affected(),
+            Err(err) => log_database_interaction(
+                "Deleting expired events from the database.",
+                json!({ "table": table }),
+                DatabaseOperationLogStatus::Err(err.to_string()),
+            ),
+        };
+    }
+
+    if deleted > 0 {
+        log_database_interaction::<String>(
+            "Deleting expired events from the database.",
+            json!({ "deleted": deleted }),
+            DatabaseOperationLogStatus::Ok,
+        );
+    }
+
+    return deleted;
+}
@@ -0,0 +1,180 @@
+use serde::{
+    Deserialize,
+    Serialize
+};
+use sqlx::PgConnection;
+use crate::{
+    config::CauthConfig,
+    models::{
+        permission::{
+            is_valid_permission_name,
+            Permission,
+            PermissionInsertError
+        },
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
+        },
+        organization::DEFAULT_ORGANIZATION,
+        Application,
+        RequestContext,
+        User
+    }
+};
+
+/// Creates a permission
+#[derive(Serialize, Deserialize)]
+pub struct PermissionCreateEvent {
+    name: String,
+    description: String
+}
+
+#[derive(Deserialize)]
+pub struct PermissionCreateEventInput {
+    pub name: String,
+    pub description: String
+}
+
+pub enum PermissionCreateEventError {
+    /// Returned when the caller can neither create permissions nor administer the permission
+    Unauthorized,
+    /// Returned when the permission is inside of the reserved namespace
+    ReservedNamespace,
+    /// Returned when a permission with the same name already exists
+    NameError,
+    /// Returned when the name contains a "*" other than the one of a "{namespace}:*" wildcard
+    InvalidName,
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
+}
+
+impl ToString for PermissionCreateEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Unauthorized => "You are not authorized to do that!".to_string(),
+            Self::ReservedNamespace => "The \"cauth\" namespace is reserved for the service itself.".to_string(),
+            Self::NameError => "A permission with that name already exists.".to_string(),
+            Self::InvalidName => PermissionInsertError::InvalidName.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details)
+        };
+    }
+}
+
+impl EventError for PermissionCreateEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::ReservedNamespace => "RESERVED_NAMESPACE",
+            Self::NameError => "NAME_ERROR",
+            Self::InvalidName => "INVALID_NAME",
+            Self::Database(_) => "DATABASE_ERROR"
+        };
+    }
+
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::Unauthorized => EventErrorKind::Unauthorized,
+            Self::Database(_) => EventErrorKind::Internal,
+            _ => EventErrorKind::BadRequest
+        };
+    }
+}
+
+impl PendingEvent for PermissionCreateEvent {
+    const TYPE: &'static str = "permissions/create";
+
+    type Input = PermissionCreateEventInput;
+    type Output = ();
+    type Error = PermissionCreateEventError;
+
+    fn target(&self) -> Option<&str> {
+        return Some(&self.name);
+    }
+
+    /// ## PermissionCreateEvent::prepare
+    ///
+    /// Checks the caller is permitted the same way as POST /permissions
+    ///
+    /// Errors:
+    /// + when the caller is not permitted
+    /// + when the permission is inside of the reserved namespace
+    /// + when the name is not a valid permission name
+    /// + when the permission already exists
+    ///
+    async fn prepare(
+        db_conn: &mut PgConnection,
+        input: PermissionCreateEventInput,
+        caller: &EventCaller
+    ) -> Result<PermissionCreateEvent, PermissionCreateEventError> {
+        let permitted = caller.has_global_permission(db_conn, "cauth:permissions:post").await
+            || caller.can_administer(db_conn, &[&input.name]).await;
+
+        if !permitted {
+            return Err(PermissionCreateEventError::Unauthorized);
+        }
+
+        if Application::is_reserved(&input.name) {
+            return Err(PermissionCreateEventError::ReservedNamespace);
+        }
+
+        if !is_valid_permission_name(&input.name) {
+            return Err(PermissionCreateEventError::InvalidName);
+        }
+
+        if Permission::retrieve(db_conn, &input.name).await.is_ok() {
+            return Err(PermissionCreateEventError::NameError);
+        }
+
+        return Ok(PermissionCreateEvent {
+            name: input.name,
+            description: input.description
+        });
+    }
+
+    /// ## PermissionCreateEvent::authorize
+    ///
+    /// Checks the permissions of PermissionCreateEvent::prepare, permissions are managed from the default organization
+    ///
+    async fn authorize(
+        &self,
+        db_conn: &mut PgConnection,
+        user: &User,
+        context: &RequestContext
+    ) -> bool {
+        let context = context.with_organization(DEFAULT_ORGANIZATION.to_string());
+
+        return user.has_permission(db_conn, "cauth:permissions:post".to_string(), &context).await
+            || Application::can_administer(db_conn, &user.login, &[&self.name]).await;
+    }
+
+    /// ## PermissionCreateEvent::apply
+    ///
+    /// Creates the permission
+    ///
+    /// Errors:
+    /// + when the permission was created in the meantime
+    ///
+    async fn apply(
+        self,
+        db_conn: &mut PgConnection,
+        _config: &CauthConfig
+    ) -> Result<(), PermissionCreateEventError> {
+        let result = Permission::insert(
+            db_conn,
+            &self.name,
+            &self.description
+        )
+        .await;
+
+        return match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(match error {
+                PermissionInsertError::NameError => PermissionCreateEventError::NameError,
+                PermissionInsertError::InvalidName => PermissionCreateEventError::InvalidName,
+                PermissionInsertError::Database(details) => PermissionCreateEventError::Database(details)
+            })
+        };
+    }
+}
@@ -0,0 +1,594 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{
+    prelude::FromRow,
+    query,
+    query_as,
+    Connection,
+    PgConnection
+};
+use crate::{
+    config::CauthConfig,
+    models::event::{
+        describe,
+        generate_key,
+        is_expired,
+        with_event_type,
+        EventCaller,
+        ChangeRequest,
+        CommittedEvent,
+        EventCredentials,
+        EventFailure,
+        PendingEvent,
+        committed_event::CommittedEventResultError
+    },
+    models::{webhook::{Webhook, WebhookEnqueueError}, Order},
+    util::{
+        logging::{log_database_interaction, DatabaseOperationLogStatus},
+        string::json_value_to_pretty_string
+    }
+};
+
+/// Pending event of any type, as kept in the database
+#[derive(FromRow)]
+pub struct StoredEvent {
+    pub id: i32,
+    pub event_type: String,
+    key: String,
+    payload: Value,
+    pub created_at: DateTime<Utc>,
+    pub batch_id: Option<i32>,
+    /// login of the user whose session prepared the event, None for the events of batches
+    pub actor: Option<String>
+}
+
+/// Result of StoredEvent::insert
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum StoredEventInsertResult {
+    /// The event can be committed with it's id and key
+    Pending(EventCredentials),
+    /// The event matches an approval rule, so it was stored as a change request
+    ChangeRequest { change_request_id: i32 }
+}
+
+/// Pending event as shown to admins, without it's key and secrets of it's payload
+#[derive(Serialize)]
+pub struct EventSummary {
+    pub id: i32,
+    pub event_type: String,
+    pub batch_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub details: Value
+}
+
+impl ToString for EventSummary {
+    fn to_string(&self) -> String {
+        let formatted = json_value_to_pretty_string(&serde_json::to_value(&self).unwrap());
+
+        return formatted;
+    }
+}
+
+/// Filters of StoredEvent::list, every provided one has to match
+pub struct StoredEventFilter {
+    pub event_type: Option<String>,
+    /// Login of the user the event registers, logs in, deletes or grants/revokes a group
+    pub login: Option<String>,
+    /// Minimal age of the event in seconds
+    pub min_age: Option<u64>,
+    /// Maximal age of the event in seconds
+    pub max_age: Option<u64>
+}
+
+pub enum StoredEventRetrieveError {
+    /// Returned when the event with specified id and type cannot be found
+    NotFound
+}
+
+pub enum StoredEventCommitError<E> {
+    /// Returned when the event with specified id cannot be found
+    NotFound,
+    /// Returned when the key is invalid
+    Unauthorized,
+    /// Returned when the event is older than the configured TTL
+    Expired,
+    /// Returned when the event was already committed and its result cannot be returned again
+    AlreadyCommitted,
+    /// Returned when the event cannot be applied
+    Operation(E),
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError)
+}
+
+pub enum StoredEventCancelError {
+    /// Returned when the event with specified id cannot be found
+    NotFound,
+    /// Returned when the key is invalid
+    Unauthorized,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
+}
+
+impl StoredEvent {
+    /// ## StoredEvent::retrieve
+    ///
+    /// Retrieves event of specified type with specifed id,
+    /// events of a batch can be only committed or cancelled along with it
+    ///
+    /// Errors:
+    /// + When the event is not found
+    ///
+    pub async fn retrieve(
+        db_conn: &mut PgConnection,
+        event_type: &str,
+        id: &i32
+    ) -> Result<StoredEvent, StoredEventRetrieveError> {
+        let sql = "
+        SELECT
+            *
+        FROM
+            pending_events
+        WHERE
+            id = $1 AND event_type = $2 AND batch_id IS NULL;
+        ";
+        let result = query_as(sql)
+            .bind(&id)
+            .bind(&event_type)
+            .fetch_one(db_conn)
+            .await;
+
+        return match result {
+            Ok(event) => Ok(event),
+            Err(_) => return Err(StoredEventRetrieveError::NotFound),
+        };
+    }
+
+    /// ## StoredEvent::retrieve_any
+    ///
+    /// Retrieves event with specified id regardless of it's type, including events of batches
+    ///
+    /// Errors:
+    /// + When the event is not found
+    ///
+    pub async fn retrieve_any(
+        db_conn: &mut PgConnection,
+        id: &i32
+    ) -> Result<StoredEvent, StoredEventRetrieveError> {
+        let sql = "
+        SELECT
+            *
+        FROM
+            pending_events
+        WHERE
+            id = $1;
+        ";
+        let result = query_as(sql)
+            .bind(&id)
+            .fetch_one(db_conn)
+            .await;
+
+        return match result {
+            Ok(event) => Ok(event),
+            Err(_) => return Err(StoredEventRetrieveError::NotFound),
+        };
+    }
+
+    /// ## StoredEvent::list
+    ///
+    /// Lists pending events of every type matching provided filter, including events of batches,
+    /// ordered by their id
+    ///
+    pub async fn list(
+        db_conn: &mut PgConnection,
+        filter: &StoredEventFilter,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>
+    ) -> Vec<StoredEvent> {
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+        SELECT
+            *
+        FROM
+            pending_events
+        WHERE
+            ($1::VARCHAR IS NULL OR event_type = $1)
+            AND ($2::VARCHAR IS NULL OR payload->>'user_login' = $2)
+            AND ($3::BIGINT IS NULL OR created_at <= NOW() - make_interval(secs => $3))
+            AND ($4::BIGINT IS NULL OR created_at >= NOW() - make_interval(secs => $4))
+        ORDER BY id {}
+        OFFSET {} ROWS
+        LIMIT {};
+        ",
+            order.to_string(),
+            offset,
+            limit
+        );
+
+        let result = query_as(&sql)
+            .bind(&filter.event_type)
+            .bind(&filter.login)
+            .bind(filter.min_age.map(|age| age as i64))
+            .bind(filter.max_age.map(|age| age as i64))
+            .fetch_all(db_conn)
+            .await
+            .unwrap();
+
+        return result;
+    }
+
+    /// ## StoredEvent::summarize
+    ///
+    /// Describes the event for admins, leaving out it's key and the secrets of it's payload. <br>
+    /// Events of types which no longer exist are described without details
+    ///
+    pub async fn summarize(self, ttl: u64) -> EventSummary {
+        let details = with_event_type!(
+            &self.event_type,
+            describe(self.payload.clone()),
+            Value::Null
+        );
+
+        return EventSummary {
+            id: self.id,
+            expires_at: self.created_at + Duration::seconds(ttl as i64),
+            event_type: self.event_type,
+            batch_id: self.batch_id,
+            created_at: self.created_at,
+            details
+        };
+    }
+
+    /// ## StoredEvent::lock
+    ///
+    /// Retrieves event of specified type with specified id, locking it until the end of the transaction
+    /// so concurrent commits and cancels wait for each other
+    ///
+    /// Errors:
+    /// + When the event is not found
+    ///
+    async fn lock(
+        db_conn: &mut PgConnection,
+        event_type: &str,
+        id: &i32
+    ) -> Result<StoredEvent, StoredEventRetrieveError> {
+        let sql = "
+        SELECT
+            *
+        FROM
+            pending_events
+        WHERE
+            id = $1 AND event_type = $2 AND batch_id IS NULL
+        FOR UPDATE;
+        ";
+        let result = query_as(sql)
+            .bind(&id)
+            .bind(&event_type)
+            .fetch_one(db_conn)
+            .await;
+
+        return match result {
+            Ok(event) => Ok(event),
+            Err(_) => return Err(StoredEventRetrieveError::NotFound),
+        };
+    }
+
+    /// ## StoredEvent::insert
+    ///
+    /// Prepares an event of type E from provided input and inserts it into database,
+    /// returning it's key and id. <br>
+    /// Events matching an approval rule are stored as change requests instead
+    ///
+    /// Errors:
+    /// + when the event cannot be prepared
+    /// + when the event matches an approval rule but the caller has no login session
+    ///
+    pub async fn insert<E: PendingEvent>(
+        db_conn: &mut PgConnection,
+        input: E::Input,
+        caller: &EventCaller
+    ) -> Result<StoredEventInsertResult, EventFailure> {
+        let event = match E::prepare(db_conn, input, caller).await {
+            Ok(event) => event,
+            Err(error) => return Err(EventFailure::from_error(&error))
+        };
+
+        if let Some(change_request_id) = ChangeRequest::store_if_required(db_conn, &event, caller).await? {
+            return Ok(StoredEventInsertResult::ChangeRequest { change_request_id });
+        }
+
+        let actor = caller.login(db_conn).await;
+        let result = Self::store(
+            db_conn,
+            &event,
+            None,
+            actor.as_ref()
+        )
+        .await;
+
+        return Ok(StoredEventInsertResult::Pending(result));
+    }
+
+    /// ## StoredEvent::store
+    ///
+    /// Inserts an already prepared event into database, optionally as a part of a batch,
+    /// along with the login of the user who prepared it, returning it's key and id
+    ///
+    pub async fn store<E: PendingEvent>(
+        db_conn: &mut PgConnection,
+        event: &E,
+        batch_id: Option<&i32>,
+        actor: Option<&String>
+    ) -> EventCredentials {
+        // events hold only serializable data, so this will never error
+        let payload = serde_json::to_value(event).unwrap();
+        let key = generate_key(&format!("{}{}", E::TYPE, payload));
+
+        let sql = "
+            INSERT INTO
+                pending_events (event_type, key, payload, batch_id, actor)
+            VALUES
+                ($1, $2, $3, $4, $5)
+            RETURNING id, key;
+        ";
+
+        let result: EventCredentials = query_as(sql)
+            .bind(&E::TYPE)
+            .bind(&key)
+            .bind(&payload)
+            .bind(&batch_id)
+            .bind(&actor)
+            .fetch_one(db_conn)
+            .await
+            .unwrap();
+
+        log_database_interaction::<String>(
+            "Inserting a pending event into the database.",
+            json!({ "event_type": E::TYPE, "id": result.id, "batch_id": batch_id }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return result;
+    }
+
+    /// ## StoredEvent::apply
+    ///
+    /// Applies the event as type E, without deleting it
+    ///
+    /// Errors:
+    /// + when the event cannot be applied
+    ///
+    pub async fn apply<E: PendingEvent>(
+        self,
+        db_conn: &mut PgConnection,
+        config: &CauthConfig
+    ) -> Result<E::Output, E::Error> {
+        // the payload was serialized from the same type
+        let event: E = serde_json::from_value(self.payload).unwrap();
+
+        return event.apply(db_conn, config).await;
+    }
+
+    /// ## StoredEvent::commit
+    ///
+    /// Applies the event of type E with specified id and deletes it, returning what the event returned. <br>
+    /// Should be run in a transaction, the transaction should be dropped when this fails
+    /// so the event is kept and nothing is applied. The event is locked until the transaction ends,
+    /// and retrying a successful commit returns the original result without applying the event again
+    ///
+    /// Errors:
+    /// + when the event is not found
+    /// + when the key is invalid
+    /// + when the event was committed already and its result was not recorded
+    /// + when the event is older than provided TTL (in seconds)
+    /// + when the event cannot be applied
+    ///
+    pub async fn commit<E: PendingEvent>(
+        db_conn: &mut PgConnection,
+        id: &i32,
+        key: &String,
+        ttl: u64,
+        config: &CauthConfig
+    ) -> Result<Value, StoredEventCommitError<E::Error>>
+    {
+        let locked = Self::lock(
+            db_conn,
+            E::TYPE,
+            id
+        )
+        .await;
+
+        let stored = match locked {
+            Ok(stored) => stored,
+            // the event might have been committed already
+            Err(_) => return match CommittedEvent::result(db_conn, E::TYPE, id, key).await {
+                Ok(output) => Ok(output),
+                Err(CommittedEventResultError::Unauthorized) => Err(StoredEventCommitError::Unauthorized),
+                Err(CommittedEventResultError::NotFound) => Err(StoredEventCommitError::NotFound),
+                Err(CommittedEventResultError::NotRecorded) => Err(StoredEventCommitError::AlreadyCommitted)
+            }
+        };
+
+        if *key != stored.key {
+            return Err(StoredEventCommitError::Unauthorized);
+        }
+
+        if is_expired(&stored.created_at, ttl) {
+            return Err(StoredEventCommitError::Expired);
+        }
+
+        let output = match stored.apply::<E>(db_conn, config).await {
+            Ok(output) => output,
+            Err(err) => return Err(StoredEventCommitError::Operation(err))
+        };
+
+        CommittedEvent::insert(
+            db_conn,
+            E::TYPE,
+            id,
+            key,
+            E::recorded_output(&output).as_ref()
+        )
+        .await;
+
+        Self::delete(db_conn, id).await;
+
+        if let Err(err) = Webhook::enqueue(db_conn, "event.committed", json!({ "event_type": E::TYPE, "id": id })).await {
+            return Err(StoredEventCommitError::Webhook(err));
+        }
+
+        return Ok(json!(output));
+    }
+
+    /// ## StoredEvent::cancel
+    ///
+    /// Deletes event of specified type with specified id from the database
+    ///
+    /// Errors:
+    /// + When the event is not found
+    /// + When the key is invalid
+    ///
+    pub async fn cancel(
+        db_conn: &mut PgConnection,
+        event_type: &str,
+        id: &i32,
+        key: &String
+    ) -> Result<(), StoredEventCancelError>
+    {
+        let retrieved = Self::retrieve(
+            db_conn,
+            event_type,
+            id
+        )
+        .await;
+
+        let stored = match retrieved {
+            Ok(stored) => stored,
+            Err(_) => return Err(StoredEventCancelError::NotFound)
+        };
+
+        if *key != stored.key {
+            return Err(StoredEventCancelError::Unauthorized);
+        }
+
+        let mut tx = db_conn.begin().await.unwrap();
+        let db_conn = &mut *tx;
+
+        if !Self::delete(db_conn, id).await {
+            return Err(StoredEventCancelError::NotFound);
+        }
+
+        if let Err(err) = Webhook::enqueue(db_conn, "event.cancelled", json!({ "event_type": event_type, "id": id })).await {
+            return Err(StoredEventCancelError::Webhook(err));
+        }
+
+        if let Err(err) = tx.commit().await {
+            return Err(StoredEventCancelError::Database(err.to_string()));
+        }
+
+        return Ok(());
+    }
+
+    /// deletes the event, returning whether it existed
+    async fn delete(
+        db_conn: &mut PgConnection,
+        id: &i32
+    ) -> bool {
+        let sql = "
+        DELETE FROM
+            pending_events
+        WHERE
+            id = $1;
+        ";
+
+        let result = query(sql)
+            .bind(&id)
+            .execute(db_conn)
+            .await
+            .unwrap();
+
+        return result.rows_affected() > 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{event::UserLoginEvent, user::User, LoginSession, RequestContext},
+        util::testing
+    };
+
+    #[tokio::test]
+    async fn does_not_record_session_tokens() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let config = testing::config(pool.clone(), None);
+        let caller = EventCaller { session_token: None, context: RequestContext::now() };
+
+        User::insert(&mut conn, &"jane".to_string(), &"password123".to_string(), &json!({}), &vec![])
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        let input = serde_json::from_value(json!({ "login": "jane", "password": "password123" })).unwrap();
+        let credentials = match StoredEvent::insert::<UserLoginEvent>(&mut conn, input, &caller).await {
+            Ok(StoredEventInsertResult::Pending(credentials)) => credentials,
+            _ => panic!("the login should be pending")
+        };
+
+        let output = StoredEvent::commit::<UserLoginEvent>(&mut conn, &credentials.id, &credentials.key, 900, &config)
+            .await
+            .unwrap_or_else(|_| panic!("the login should be committed"));
+        let token = output["token"].as_str().unwrap().to_string();
+        assert!(LoginSession::get_user(&mut conn, &token).await.is_ok());
+
+        let recorded: (Option<Value>,) = query_as("SELECT output FROM committed_events WHERE event_type = 'users/login';")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert!(recorded.0.is_none());
+
+        // the retried commit cannot return the token again, so it fails instead of succeeding without it
+        let retried = StoredEvent::commit::<UserLoginEvent>(&mut conn, &credentials.id, &credentials.key, 900, &config).await;
+        assert!(matches!(retried, Err(StoredEventCommitError::AlreadyCommitted)));
+    }
+
+    #[tokio::test]
+    async fn remembers_who_prepared_the_events() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+
+        User::insert(&mut conn, &"jane".to_string(), &"password123".to_string(), &json!({}), &vec![])
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        let token = LoginSession::insert(&mut conn, "jane".to_string())
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        for (session_token, actor) in [(Some(token), Some("jane".to_string())), (None, None)] {
+            let caller = EventCaller { session_token, context: RequestContext::now() };
+            let input = serde_json::from_value(json!({ "login": "jane", "password": "password123" })).unwrap();
+            let id = match StoredEvent::insert::<UserLoginEvent>(&mut conn, input, &caller).await {
+                Ok(StoredEventInsertResult::Pending(credentials)) => credentials.id,
+                _ => panic!("the login should be pending")
+            };
+
+            let stored = StoredEvent::retrieve(&mut conn, "users/login", &id)
+                .await
+                .unwrap_or_else(|_| panic!("the event should be stored"));
+            assert_eq!(stored.actor, actor);
+        }
+    }
+}
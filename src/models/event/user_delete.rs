@@ -1,241 +1,175 @@
-use std::{
-    time::{
-        self,
-        UNIX_EPOCH
-    },
-    error::Error
-};
-use serde_json::Value;
-use sqlx::{
-    prelude::FromRow,
-    query,
-    query_as,
-    PgConnection
-};
-use crypto::{
-    digest::Digest,
-    sha3::Sha3
+use serde::{
+    Deserialize,
+    Serialize
 };
+use sqlx::PgConnection;
 use crate::{
-    util::string::json_value_to_pretty_string,
+    config::CauthConfig,
     models::{
         user::{
             User,
-            UserRetrieveError
+            UserDeleteError
+        },
+        login_session::LoginSession,
+        webhook::WebhookEnqueueError,
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
         },
-        event::EventCredentials
+        RequestContext
     }
 };
 
-#[derive(FromRow)]
+/// Deletes a user, requested either by the user itself or by someone with "cauth:users:delete"
+#[derive(Serialize, Deserialize)]
 pub struct UserDeleteEvent {
-    id: i32,
-    key: String,
     user_login: String
 }
 
-pub enum UserDeleteEventRetrieveError {
-    /// Returned when the event with specified id cannot be found
-    NotFound
+#[derive(Deserialize)]
+pub struct UserDeleteEventInput {
+    pub login: String
 }
 
-pub enum UserDeleteEventCommitError {
-    /// Returned when the key is invalid
+pub enum UserDeleteEventError {
+    /// Returned when the caller is neither the user nor has "cauth:users:delete" permission
     Unauthorized,
-    /// Returned when the event with specified id cannot be found
-    NotFound,
-}
-
-pub enum UserDeleteEventInsertError {
-    /// Returned when user with login specified in the event's data is not found
+    /// Returned when user with specified login is not found
     UserNotFound,
+    /// Returned when the user is the last member of a protected group
+    Protected,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
 }
 
-pub enum UserDeleteEventCancelError {
-    /// Returned when the key is invalid
-    Unauthorized,
-    /// Returned when the event with specified id cannot be found
-    NotFound
+impl ToString for UserDeleteEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Unauthorized => "You are not authorized to do that!".to_string(),
+            Self::UserNotFound => "User with this login do not exist".to_string(),
+            Self::Protected => "This user is the last member of a protected group.".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details)
+        };
+    }
 }
 
-impl UserDeleteEvent {
-    /// ## UserDeleteEvent::retrieve
-    ///
-    /// Retrieves UserDelete event with specifed id
-    ///
-    /// Errors:
-    /// + When the event is not found
-    ///
-    pub async fn retrieve(
-        db_conn: &mut PgConnection,
-        id: &i32
-    ) -> Result<UserDeleteEvent, UserDeleteEventRetrieveError> {
-        let sql = "
-        SELECT
-            *
-        FROM
-            user_delete_events
-        WHERE
-            id = $1;
-        ";
-        let result = query_as(sql)
-            .bind(&id)
-            .fetch_one(db_conn)
-            .await;
-
-        return match result {
-            Ok(event) => Ok(event),
-            Err(_) => return Err(UserDeleteEventRetrieveError::NotFound),
+impl EventError for UserDeleteEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::UserNotFound => "USER_NOT_FOUND",
+            Self::Protected => "PROTECTED_ENTITY",
+            Self::Webhook(_) => "WEBHOOK_ERROR",
+            Self::Database(_) => "DATABASE_ERROR"
         };
     }
-    
-    /// UserDeleteEvent::insert
-    ///
-    /// Inserts a new UserDelete event into database, returning it's key and id
-    ///
-    /// Errors:
-    /// + when the user does not exist
-    /// + when the password is invalid
-    ///
-    pub async fn insert(
-        db_conn: &mut PgConnection,
-        user_login: &String
-    ) -> Result<EventCredentials, UserDeleteEventInsertError> {
-        let result = User::retrieve(
-            db_conn,
-            user_login
-        )
-        .await;
 
-        let user = match result {
-            Ok(user) => user,
-            Err(err) => match err {
-                UserRetrieveError::NotFound => return Err(UserDeleteEventInsertError::UserNotFound)
-            }
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::Unauthorized => EventErrorKind::Unauthorized,
+            Self::Webhook(_) => EventErrorKind::Internal,
+            Self::Database(_) => EventErrorKind::Internal,
+            _ => EventErrorKind::BadRequest
         };
+    }
+}
 
-        let time_since_epoch = time::SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let key_raw = format!("{}{}", user_login, time_since_epoch);
-        
-        let mut hasher = Sha3::keccak256();
-        hasher.input_str(key_raw.as_str());
-        let key = hasher.result_str();
-        
-        let sql = "
-            INSERT INTO 
-                user_delete_events (key, user_login)
-            VALUES
-                ($1, $2)
-            RETURNING id, key;
-        ";
-
-        let result = query_as(sql)
-            .bind(&key)
-            .bind(&user_login)
-            .fetch_one(db_conn)
-            .await
-            .unwrap();
-
-        return Ok(result);
+impl PendingEvent for UserDeleteEvent {
+    const TYPE: &'static str = "users/delete";
+
+    type Input = UserDeleteEventInput;
+    type Output = ();
+    type Error = UserDeleteEventError;
+
+    fn target(&self) -> Option<&str> {
+        return Some(&self.user_login);
     }
 
-    /// UserDeleteEvent::commit
+    /// ## UserDeleteEvent::prepare
     ///
-    /// Commits the changes in single UserDelete event to the database
+    /// Checks that the caller can delete the user
     ///
     /// Errors:
-    /// + when the event is not found
-    /// + when the key is invalid
-    /// + when the user with specified login is not found 
+    /// + when the caller is neither the user nor has "cauth:users:delete" permission
+    /// + when the user does not exist
     ///
-    pub async fn commit(
+    async fn prepare(
         db_conn: &mut PgConnection,
-        id: &i32,
-        key: &String
-    ) -> Result<(), UserDeleteEventCommitError>
-    {
-        let retrieved = Self::retrieve(
+        input: UserDeleteEventInput,
+        caller: &EventCaller
+    ) -> Result<UserDeleteEvent, UserDeleteEventError> {
+        let has_permission = caller.has_permission(
             db_conn,
-            id
+            "cauth:users:delete"
         )
         .await;
 
-        let event = match retrieved {
-            Ok(event) => event,
-            Err(_) => return Err(UserDeleteEventCommitError::NotFound)
+        let has_same_username = match &caller.session_token {
+            Some(session_token) => match LoginSession::retrieve(db_conn, session_token).await {
+                Ok(session) => session.user_login == input.login,
+                Err(_) => false
+            },
+            None => false
         };
 
-        if *key != event.key {
-            return Err(UserDeleteEventCommitError::Unauthorized);
+        if !has_permission && !has_same_username {
+            return Err(UserDeleteEventError::Unauthorized);
         }
 
-        let result = User::delete(
-            db_conn,
-            event.user_login
-        ).await;
-        
-        let _ = Self::cancel(
-            db_conn,
-            id,
-            key
-        )
-        .await;
+        if User::retrieve(db_conn, &input.login).await.is_err() {
+            return Err(UserDeleteEventError::UserNotFound);
+        }
 
-        return match result {
-            Ok(_) => Ok(()),
-            Err(_) => return Err(UserDeleteEventCommitError::NotFound)
-        };
+        return Ok(UserDeleteEvent {
+            user_login: input.login
+        });
+    }
+
+    /// ## UserDeleteEvent::authorize
+    ///
+    /// Only the users with "cauth:users:delete" can approve deleting a user, being the user is not enough
+    ///
+    async fn authorize(
+        &self,
+        db_conn: &mut PgConnection,
+        user: &User,
+        context: &RequestContext
+    ) -> bool {
+        return user.has_permission(db_conn, "cauth:users:delete".to_string(), context).await;
     }
 
-    /// UserDeleteEvent::cancel
+    /// ## UserDeleteEvent::apply
+    ///
+    /// Deletes the user
     ///
-    /// Deletes UserDelete event with specified id from the database
-    /// 
     /// Errors:
-    /// + When the event is not found
-    /// + When the key is invalid
+    /// + when the user was deleted in the meantime
+    /// + when the user is the last member of a protected group
     ///
-    pub async fn cancel(
+    async fn apply(
+        self,
         db_conn: &mut PgConnection,
-        id: &i32,
-        key: &String
-    ) ->Result<(), UserDeleteEventCancelError>
-    {
-        let retrieved = Self::retrieve(
+        _config: &CauthConfig
+    ) -> Result<(), UserDeleteEventError> {
+        let result = User::delete(
             db_conn,
-            id
+            self.user_login
         )
         .await;
 
-        let event = match retrieved {
-            Ok(event) => event,
-            Err(_) => return Err(UserDeleteEventCancelError::NotFound)
+        return match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(match error {
+                UserDeleteError::NotFound => UserDeleteEventError::UserNotFound,
+                UserDeleteError::Protected => UserDeleteEventError::Protected,
+                UserDeleteError::Webhook(err) => UserDeleteEventError::Webhook(err),
+                UserDeleteError::Database(details) => UserDeleteEventError::Database(details)
+            })
         };
-
-        if *key != event.key {
-            return Err(UserDeleteEventCancelError::Unauthorized);
-        }
-
-        let sql = "
-        DELETE FROM
-            user_delete_events
-        WHERE
-            id = $1;
-        ";
-
-        let result = query(sql)
-            .bind(&id)
-            .execute(db_conn)
-            .await
-            .unwrap();
-
-        if result.rows_affected() == 0 {
-            return Err(UserDeleteEventCancelError::NotFound);
-        } else {
-            return Ok(());
-        }
     }
 }
@@ -0,0 +1,203 @@
+use chrono::{DateTime, Utc};
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::{json, Value};
+use sqlx::PgConnection;
+use crate::{
+    config::CauthConfig,
+    models::{
+        user::{
+            User,
+            UserGrantError
+        },
+        webhook::WebhookEnqueueError,
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
+        },
+        Application,
+        RequestContext,
+        SodConflict
+    }
+};
+
+/// Grants a user a group in the caller's active organization
+#[derive(Serialize, Deserialize)]
+pub struct UserGrantGroupEvent {
+    user_login: String,
+    group_name: String,
+    expires_at: Option<DateTime<Utc>>,
+    organization: String
+}
+
+#[derive(Deserialize)]
+pub struct UserGrantGroupEventInput {
+    pub login: String,
+    pub group: String,
+    pub expires_at: Option<DateTime<Utc>>
+}
+
+pub enum UserGrantGroupEventError {
+    /// Returned when the caller can neither update users, delegate the group nor administer it
+    Unauthorized,
+    /// Returned when the user do not exist
+    NotFound,
+    /// Returned when the group do not exist
+    GroupNotFound,
+    /// Returned when the group is mutually exclusive with one of the user's groups
+    SodConflict(SodConflict),
+    /// Returned when the user do not belong to the organization
+    NotMember,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
+}
+
+impl ToString for UserGrantGroupEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Unauthorized => "You are not authorized to do that!".to_string(),
+            Self::NotFound => "User with specified login do not exist".to_string(),
+            Self::GroupNotFound => "Group with specified name do not exist".to_string(),
+            Self::SodConflict(conflict) => conflict.to_string(),
+            Self::NotMember => "User with specified login do not belong to the active organization".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details)
+        };
+    }
+}
+
+impl EventError for UserGrantGroupEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::NotFound => "NOT_FOUND",
+            Self::GroupNotFound => "GROUP_NOT_FOUND",
+            Self::SodConflict(_) => "SOD_CONFLICT",
+            Self::NotMember => "NOT_MEMBER",
+            Self::Webhook(_) => "WEBHOOK_ERROR",
+            Self::Database(_) => "DATABASE_ERROR"
+        };
+    }
+
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::Unauthorized => EventErrorKind::Unauthorized,
+            Self::Webhook(_) => EventErrorKind::Internal,
+            Self::Database(_) => EventErrorKind::Internal,
+            _ => EventErrorKind::BadRequest
+        };
+    }
+
+    fn fields(&self) -> Value {
+        return match self {
+            Self::SodConflict(conflict) => json!({
+                "set_name": conflict.set_name,
+                "groups": conflict.groups
+            }),
+            _ => json!({})
+        };
+    }
+}
+
+impl PendingEvent for UserGrantGroupEvent {
+    const TYPE: &'static str = "users/grant-group";
+
+    type Input = UserGrantGroupEventInput;
+    type Output = ();
+    type Error = UserGrantGroupEventError;
+
+    fn target(&self) -> Option<&str> {
+        return Some(&self.group_name);
+    }
+
+    /// ## UserGrantGroupEvent::prepare
+    ///
+    /// Checks the caller is permitted the same way as POST /users/{login}/{group},
+    /// resolving the organization the group is granted in. <br>
+    /// The user and the group are not checked yet, so they can be created by events committed before it
+    ///
+    /// Errors:
+    /// + when the caller is not permitted
+    ///
+    async fn prepare(
+        db_conn: &mut PgConnection,
+        input: UserGrantGroupEventInput,
+        caller: &EventCaller
+    ) -> Result<UserGrantGroupEvent, UserGrantGroupEventError> {
+        let permitted = caller.has_permission(db_conn, "cauth:users:update").await
+            || caller.can_delegate_group(db_conn, &input.group).await
+            || caller.can_administer(db_conn, &[&input.group]).await;
+
+        if !permitted {
+            return Err(UserGrantGroupEventError::Unauthorized);
+        }
+
+        let organization = caller.organization(db_conn).await;
+
+        return Ok(UserGrantGroupEvent {
+            user_login: input.login,
+            group_name: input.group,
+            expires_at: input.expires_at,
+            organization
+        });
+    }
+
+    /// ## UserGrantGroupEvent::authorize
+    ///
+    /// Checks the permissions of UserGrantGroupEvent::prepare in the organization of the change
+    ///
+    async fn authorize(
+        &self,
+        db_conn: &mut PgConnection,
+        user: &User,
+        context: &RequestContext
+    ) -> bool {
+        let context = context.with_organization(self.organization.clone());
+
+        return user.has_permission(db_conn, "cauth:users:update".to_string(), &context).await
+            || user.can_delegate_group(db_conn, &self.group_name, &context).await
+            || Application::can_administer(db_conn, &user.login, &[&self.group_name]).await;
+    }
+
+    /// ## UserGrantGroupEvent::apply
+    ///
+    /// Grants the user the group
+    ///
+    /// Errors:
+    /// + when the user or the group do not exist
+    /// + when the group is mutually exclusive with one of the user's groups
+    /// + when the user do not belong to the organization
+    ///
+    async fn apply(
+        self,
+        db_conn: &mut PgConnection,
+        _config: &CauthConfig
+    ) -> Result<(), UserGrantGroupEventError> {
+        let result = User::grant_group(
+            db_conn,
+            &self.user_login,
+            &self.group_name,
+            self.expires_at,
+            &self.organization
+        )
+        .await;
+
+        return match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(match error {
+                UserGrantError::NotFound => UserGrantGroupEventError::NotFound,
+                UserGrantError::GroupNotFound => UserGrantGroupEventError::GroupNotFound,
+                UserGrantError::SodConflict(conflict) => UserGrantGroupEventError::SodConflict(conflict),
+                UserGrantError::NotMember => UserGrantGroupEventError::NotMember,
+                UserGrantError::Webhook(err) => UserGrantGroupEventError::Webhook(err),
+                UserGrantError::Database(details) => UserGrantGroupEventError::Database(details)
+            })
+        };
+    }
+}
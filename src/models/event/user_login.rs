@@ -1,23 +1,11 @@
-use std::{
-    time::{
-        self,
-        UNIX_EPOCH
-    },
-    error::Error
+use serde::{
+    Deserialize,
+    Serialize
 };
 use serde_json::Value;
-use sqlx::{
-    prelude::FromRow,
-    query,
-    query_as,
-    PgConnection
-};
-use crypto::{
-    digest::Digest,
-    sha3::Sha3
-};
+use sqlx::PgConnection;
 use crate::{
-    util::string::json_value_to_pretty_string,
+    config::CauthConfig,
     models::{
         user::{
             User,
@@ -25,224 +13,160 @@ use crate::{
         },
         login_session::{
             LoginSession,
+            LoginSessionInsertError
         },
-        event::EventCredentials
+        webhook::WebhookEnqueueError,
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
+        }
     }
 };
 
-#[derive(FromRow)]
+/// Logs in to a user account, creating a new session once the password was verified
+#[derive(Serialize, Deserialize)]
 pub struct UserLoginEvent {
-    id: i32,
-    key: String,
     user_login: String
 }
 
-pub enum UserLoginEventRetrieveError {
-    /// Returned when the event with specified id cannot be found
-    NotFound
+#[derive(Deserialize)]
+pub struct UserLoginEventInput {
+    login: String,
+    password: String
 }
 
-pub enum UserLoginEventCommitError {
-    /// Returned when the key is invalid
-    Unauthorized,
-    /// Returned when the event with specified id cannot be found
-    NotFound,
+#[derive(Serialize)]
+pub struct UserLoginEventOutput {
+    token: String
 }
 
-pub enum UserLoginEventInsertError {
-    /// Returned when user with login specified in the event's data is not found
+pub enum UserLoginEventError {
+    /// Returned when user with specified login is not found
     UserNotFound,
     /// Returned when the provided password is invalid
-    Unauthorized
+    Unauthorized,
+    /// Returned when the session token cannot be created
+    CannotHash(String),
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
 }
 
-pub enum UserLoginEventCancelError {
-    /// Returned when the key is invalid
-    Unauthorized,
-    /// Returned when the event with specified id cannot be found
-    NotFound
+impl ToString for UserLoginEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::UserNotFound => "User with this login do not exist".to_string(),
+            Self::Unauthorized => "Invalid password!".to_string(),
+            Self::CannotHash(details) => format!("Couldn't create the session token: {}", details),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details)
+        };
+    }
 }
 
-impl UserLoginEvent {
-    /// ## UserLoginEvent::retrieve
-    ///
-    /// Retrieves UserLogin event with specifed id
-    ///
-    /// Errors:
-    /// + When the event is not found
-    ///
-    pub async fn retrieve(
-        db_conn: &mut PgConnection,
-        id: &i32
-    ) -> Result<UserLoginEvent, UserLoginEventRetrieveError> {
-        let sql = "
-        SELECT
-            *
-        FROM
-            user_login_events
-        WHERE
-            id = $1;
-        ";
-        let result = query_as(sql)
-            .bind(&id)
-            .fetch_one(db_conn)
-            .await;
+impl EventError for UserLoginEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::UserNotFound => "USER_NOT_FOUND",
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::CannotHash(_) => "CANNOT_HASH",
+            Self::Webhook(_) => "WEBHOOK_ERROR",
+            Self::Database(_) => "DATABASE_ERROR"
+        };
+    }
 
-        return match result {
-            Ok(event) => Ok(event),
-            Err(_) => return Err(UserLoginEventRetrieveError::NotFound),
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::UserNotFound => EventErrorKind::BadRequest,
+            Self::Unauthorized => EventErrorKind::Unauthorized,
+            Self::CannotHash(_) => EventErrorKind::Internal,
+            Self::Webhook(_) => EventErrorKind::Internal,
+            Self::Database(_) => EventErrorKind::Internal
         };
     }
-    
-    /// UserLoginEvent::insert
+}
+
+impl PendingEvent for UserLoginEvent {
+    const TYPE: &'static str = "users/login";
+
+    type Input = UserLoginEventInput;
+    type Output = UserLoginEventOutput;
+    type Error = UserLoginEventError;
+
+    // the session token has to reach only the user logging in
+    const APPROVABLE: bool = false;
+
+    /// ## UserLoginEvent::recorded_output
+    ///
+    /// Records nothing, the session token is not kept so retried commits cannot return it again
+    ///
+    fn recorded_output(_output: &UserLoginEventOutput) -> Option<Value> {
+        return None;
+    }
+
+    /// ## UserLoginEvent::prepare
     ///
-    /// Inserts a new UserLogin event into database, returning it's key and id
+    /// Verifies the password of the user
     ///
     /// Errors:
     /// + when the user does not exist
     /// + when the password is invalid
     ///
-    pub async fn insert(
+    async fn prepare(
         db_conn: &mut PgConnection,
-        user_login: &String,
-        password: &String
-    ) -> Result<EventCredentials, UserLoginEventInsertError> {
+        input: UserLoginEventInput,
+        _caller: &EventCaller
+    ) -> Result<UserLoginEvent, UserLoginEventError> {
         let result = User::verify_password(
             db_conn,
-            user_login,
-            password
+            &input.login,
+            &input.password
         )
         .await;
 
-        let user = match result {
-            Ok(user) => user,
+        match result {
+            Ok(_) => (),
             Err(err) => match err {
-                UserVerifyPasswordError::NotFound => return Err(UserLoginEventInsertError::UserNotFound),
-                UserVerifyPasswordError::Unauthorized => return Err(UserLoginEventInsertError::Unauthorized),
+                UserVerifyPasswordError::NotFound => return Err(UserLoginEventError::UserNotFound),
+                UserVerifyPasswordError::Unauthorized => return Err(UserLoginEventError::Unauthorized),
             }
         };
 
-        let time_since_epoch = time::SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let key_raw = format!("{}{}", user_login, time_since_epoch);
-        
-        let mut hasher = Sha3::keccak256();
-        hasher.input_str(key_raw.as_str());
-        let key = hasher.result_str();
-        
-        let sql = "
-            INSERT INTO 
-                user_login_events (key, user_login)
-            VALUES
-                ($1, $2)
-            RETURNING id, key;
-        ";
-
-        let result = query_as(sql)
-            .bind(&key)
-            .bind(&user_login)
-            .fetch_one(db_conn)
-            .await
-            .unwrap();
-
-        return Ok(result);
+        return Ok(UserLoginEvent {
+            user_login: input.login
+        });
     }
 
-    /// UserLoginEvent::commit
+    /// ## UserLoginEvent::apply
     ///
-    /// Commits the changes in single UserLogin event to the database, returning token
-    /// of the created session
+    /// Creates a new session of the user, returning it's token
     ///
     /// Errors:
-    /// + when the event is not found
-    /// + when the key is invalid
-    /// + when the user with specified login is not found 
+    /// + when the user was deleted in the meantime
+    /// + when the token cannot be created
     ///
-    pub async fn commit(
-        db_conn: &mut PgConnection,
-        id: &i32,
-        key: &String
-    ) -> Result<String, UserLoginEventCommitError>
-    {
-        let retrieved = Self::retrieve(
-            db_conn,
-            id
-        )
-        .await;
-
-        let event = match retrieved {
-            Ok(event) => event,
-            Err(_) => return Err(UserLoginEventCommitError::NotFound)
-        };
-
-        if *key != event.key {
-            return Err(UserLoginEventCommitError::Unauthorized);
-        }
-
-        let token = LoginSession::insert(
-            db_conn,
-            event.user_login
-        ).await
-        .unwrap();
-        
-        let _ = Self::cancel(
-            db_conn,
-            id,
-            key
-        )
-        .await;
-
-        return Ok(token);
-    }
-
-    /// UserLoginEvent::cancel
-    ///
-    /// Deletes UserLogin event with specified id from the database
-    /// 
-    /// Errors:
-    /// + When the event is not found
-    /// + When the key is invalid
-    ///
-    pub async fn cancel(
+    async fn apply(
+        self,
         db_conn: &mut PgConnection,
-        id: &i32,
-        key: &String
-    ) ->Result<(), UserLoginEventCancelError>
-    {
-        let retrieved = Self::retrieve(
+        _config: &CauthConfig
+    ) -> Result<UserLoginEventOutput, UserLoginEventError> {
+        let result = LoginSession::insert(
             db_conn,
-            id
+            self.user_login
         )
         .await;
 
-        let event = match retrieved {
-            Ok(event) => event,
-            Err(_) => return Err(UserLoginEventCancelError::NotFound)
+        return match result {
+            Ok(token) => Ok(UserLoginEventOutput { token }),
+            Err(error) => Err(match error {
+                LoginSessionInsertError::UserNotFound => UserLoginEventError::UserNotFound,
+                LoginSessionInsertError::CannotHash(details) => UserLoginEventError::CannotHash(details),
+                LoginSessionInsertError::Webhook(err) => UserLoginEventError::Webhook(err),
+                LoginSessionInsertError::Database(details) => UserLoginEventError::Database(details)
+            })
         };
-
-        if *key != event.key {
-            return Err(UserLoginEventCancelError::Unauthorized);
-        }
-
-        let sql = "
-        DELETE FROM
-            user_login_events
-        WHERE
-            id = $1;
-        ";
-
-        let result = query(sql)
-            .bind(&id)
-            .execute(db_conn)
-            .await
-            .unwrap();
-
-        if result.rows_affected() == 0 {
-            return Err(UserLoginEventCancelError::NotFound);
-        } else {
-            return Ok(());
-        }
     }
 }
@@ -1,252 +1,182 @@
-use std::{
-    time::{
-        self,
-        UNIX_EPOCH
-    },
-    error::Error
-};
-use serde_json::Value;
-use sqlx::{
-    prelude::FromRow,
-    query,
-    query_as,
-    PgConnection
-};
-use crypto::{
-    digest::Digest,
-    sha3::Sha3
+use serde::{
+    Deserialize,
+    Serialize
 };
+use serde_json::{json, Value};
+use sqlx::PgConnection;
 use crate::{
-    util::string::json_value_to_pretty_string,
+    config::{
+        CauthConfig,
+        RegistrationSource
+    },
     models::{
         user::{
             User,
+            UserInsertError,
             hash_password
         },
-        event::EventCredentials
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
+        },
+        SodConflict
     }
 };
 
-#[derive(FromRow)]
+/// Registers a new user, granting it the "event" default groups from the config
+#[derive(Serialize, Deserialize)]
 pub struct UserRegisterEvent {
-    id: i32,
-    key: String,
     user_login: String,
     password_hash: String,
-    details: serde_json::Value
+    details: Value
 }
 
-pub enum UserRegisterEventRetrieveError {
-    /// Returned when the event with specified id cannot be found
-    NotFound
+#[derive(Deserialize)]
+pub struct UserRegisterEventInput {
+    pub login: String,
+    pub password: String,
+    pub details: Option<Value>
 }
 
-pub enum UserRegisterEventInsertError {
-    /// Returned when the mentioned user already exists
+pub enum UserRegisterEventError {
+    /// Returned when a user with the same login already exists,
+    /// also when it was registered after the event was created
     AlreadyExists,
     /// Returned when the password cannot be hashed
-    CannotHash(String)
+    CannotHash(String),
+    /// Returned when one of the default groups do not exist
+    GroupNotFound(String),
+    /// Returned when the default groups are mutually exclusive
+    SodConflict(SodConflict)
 }
 
-pub enum UserRegisterEventCommitError {
-    /// Returned when the key is invalid
-    Unauthorized,
-    /// Returned when the event with specified id cannot be found
-    NotFound
+impl ToString for UserRegisterEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::AlreadyExists => "User with this login already exists".to_string(),
+            Self::CannotHash(details) => format!("Couldn't hash user password: {}", details),
+            Self::GroupNotFound(group_name) => format!("Default group \"{}\" do not exist", group_name),
+            Self::SodConflict(conflict) => conflict.to_string()
+        };
+    }
 }
 
-pub enum UserRegisterEventCancelError {
-    /// Returned when the key is invalid
-    Unauthorized,
-    /// Returned when the event with specified id cannot be found
-    NotFound
-}
+impl EventError for UserRegisterEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::AlreadyExists => "ALREADY_EXISTS",
+            Self::CannotHash(_) => "CANNOT_HASH",
+            Self::GroupNotFound(_) => "GROUP_NOT_FOUND",
+            Self::SodConflict(_) => "SOD_CONFLICT"
+        };
+    }
 
-impl UserRegisterEvent {
-    /// ## UserRegisterEvent::retrieve
-    ///
-    /// Retrieves UserRegister event with specifed id
-    ///
-    /// Errors:
-    /// + When the event is not found
-    ///
-    pub async fn retrieve(
-        db_conn: &mut PgConnection,
-        id: &i32
-    ) -> Result<UserRegisterEvent, UserRegisterEventRetrieveError> {
-        let sql = "
-        SELECT
-            *
-        FROM
-            user_register_events
-        WHERE
-            id = $1;
-        ";
-        let result = query_as(sql)
-            .bind(&id)
-            .fetch_one(db_conn)
-            .await;
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::AlreadyExists => EventErrorKind::BadRequest,
+            _ => EventErrorKind::Internal
+        };
+    }
 
-        return match result {
-            Ok(event) => Ok(event),
-            Err(_) => return Err(UserRegisterEventRetrieveError::NotFound),
+    fn fields(&self) -> Value {
+        return match self {
+            Self::SodConflict(conflict) => json!({
+                "set_name": conflict.set_name,
+                "groups": conflict.groups
+            }),
+            _ => json!({})
         };
     }
-    
-    /// UserRegisterEvent::insert
+}
+
+impl PendingEvent for UserRegisterEvent {
+    const TYPE: &'static str = "users/register";
+
+    type Input = UserRegisterEventInput;
+    type Output = ();
+    type Error = UserRegisterEventError;
+
+    fn target(&self) -> Option<&str> {
+        return Some(&self.user_login);
+    }
+
+    fn details(&self) -> Value {
+        return json!({
+            "user_login": self.user_login,
+            "details": self.details
+        });
+    }
+
+    /// ## UserRegisterEvent::prepare
     ///
-    /// Inserts a new UserRegister event into database, returning it's key and id
+    /// Hashes the password of the user to register
     ///
     /// Errors:
     /// + when the user with specified login already exists
-    pub async fn insert(
+    /// + when the password cannot be hashed
+    ///
+    async fn prepare(
         db_conn: &mut PgConnection,
-        user_login: &String,
-        password: &String,
-        details: &serde_json::Value
-    ) -> Result<EventCredentials, UserRegisterEventInsertError> {
+        input: UserRegisterEventInput,
+        _caller: &EventCaller
+    ) -> Result<UserRegisterEvent, UserRegisterEventError> {
         let user = User::retrieve(
             db_conn,
-            user_login
+            &input.login
         )
         .await;
 
         match user {
             // found
-            Ok(_) => return Err(UserRegisterEventInsertError::AlreadyExists),
+            Ok(_) => return Err(UserRegisterEventError::AlreadyExists),
             // not found
             Err(_) => ()
         };
 
-        let time_since_epoch = time::SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let key_raw = format!("{}{}", user_login, time_since_epoch);
-        
-        let mut hasher = Sha3::keccak256();
-        hasher.input_str(key_raw.as_str());
-        let key = hasher.result_str();
-
-        let password_hash = match hash_password(password.clone()) {
+        let password_hash = match hash_password(input.password) {
             Ok(hash) => hash,
-            Err(err) => return Err(UserRegisterEventInsertError::CannotHash(err))
+            Err(err) => return Err(UserRegisterEventError::CannotHash(err))
         };
 
-        let sql = "
-            INSERT INTO 
-                user_register_events (key, user_login, password_hash, details)
-            VALUES
-                ($1, $2, $3, $4)
-            RETURNING id, key;
-        ";
-
-        let result = query_as(sql)
-            .bind(&key)
-            .bind(&user_login)
-            .bind(&password_hash)
-            .bind(&details)
-            .fetch_one(db_conn)
-            .await
-            .unwrap();
-
-        return Ok(result);
+        return Ok(UserRegisterEvent {
+            user_login: input.login,
+            password_hash,
+            details: input.details.unwrap_or(json!({}))
+        });
     }
 
-    /// UserRegisterEvent::commit
+    /// ## UserRegisterEvent::apply
     ///
-    /// Commits the changes in single UserRegister event to the database
+    /// Inserts the user, granting it the "event" default groups. <br>
+    /// The user is kept when granting one of the groups fails, the transaction should be dropped then
     ///
     /// Errors:
-    /// + when the event is not found
-    /// + when the key is invalid
+    /// + when the user already exists
+    /// + when one of the default groups do not exist or they are mutually exclusive
     ///
-    pub async fn commit(
-        db_conn: &mut PgConnection,
-        id: &i32,
-        key: &String
-    ) -> Result<(), UserRegisterEventCommitError>
-    {
-        let retrieved = Self::retrieve(
-            db_conn,
-            id
-        )
-        .await;
-
-        let event = match retrieved {
-            Ok(event) => event,
-            Err(_) => return Err(UserRegisterEventCommitError::NotFound)
-        };
-
-        if *key != event.key {
-            return Err(UserRegisterEventCommitError::Unauthorized);
-        }
-
-        let token = User::insert_unhashed(
-            db_conn,
-            &event.user_login,
-            &event.password_hash,
-            &event.details
-        )
-        .await
-        // we know the user is unique already is it won't panic
-        .unwrap();
-        
-        let _ = Self::cancel(
-            db_conn,
-            id,
-            key
-        )
-        .await;
-
-        return Ok(token);
-    }
-
-    /// UserRegisterEvent::cancel
-    ///
-    /// Deletes UserRegister event with specified id from the database
-    /// 
-    /// Errors:
-    /// + When the event is not found
-    ///
-    pub async fn cancel(
+    async fn apply(
+        self,
         db_conn: &mut PgConnection,
-        id: &i32,
-        key: &String
-    ) ->Result<(), UserRegisterEventCancelError>
-    {
-        let retrieved = Self::retrieve(
+        config: &CauthConfig
+    ) -> Result<(), UserRegisterEventError> {
+        let result = User::insert_unhashed(
             db_conn,
-            id
+            &self.user_login,
+            &self.password_hash,
+            &self.details,
+            config.default_groups.for_source(RegistrationSource::Event)
         )
         .await;
 
-        let event = match retrieved {
-            Ok(event) => event,
-            Err(_) => return Err(UserRegisterEventCancelError::NotFound)
+        return match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(match error {
+                UserInsertError::GroupNotFound(group_name) => UserRegisterEventError::GroupNotFound(group_name),
+                UserInsertError::SodConflict(conflict) => UserRegisterEventError::SodConflict(conflict),
+                _ => UserRegisterEventError::AlreadyExists
+            })
         };
-
-        if *key != event.key {
-            return Err(UserRegisterEventCancelError::Unauthorized);
-        }
-
-        let sql = "
-        DELETE FROM
-            user_register_events
-        WHERE
-            id = $1;
-        ";
-
-        let result = query(sql)
-            .bind(&id)
-            .execute(db_conn)
-            .await
-            .unwrap();
-
-        if result.rows_affected() == 0 {
-            return Err(UserRegisterEventCancelError::NotFound);
-        } else {
-            return Ok(());
-        }
     }
 }
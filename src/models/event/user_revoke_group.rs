@@ -0,0 +1,185 @@
+use serde::{
+    Deserialize,
+    Serialize
+};
+use sqlx::PgConnection;
+use crate::{
+    config::CauthConfig,
+    models::{
+        user::{
+            User,
+            UserRevokeError
+        },
+        webhook::WebhookEnqueueError,
+        event::{
+            EventCaller,
+            EventError,
+            EventErrorKind,
+            PendingEvent
+        },
+        Application,
+        RequestContext
+    }
+};
+
+/// Revokes a group from a user in the caller's active organization
+#[derive(Serialize, Deserialize)]
+pub struct UserRevokeGroupEvent {
+    user_login: String,
+    group_name: String,
+    organization: String
+}
+
+#[derive(Deserialize)]
+pub struct UserRevokeGroupEventInput {
+    pub login: String,
+    pub group: String
+}
+
+pub enum UserRevokeGroupEventError {
+    /// Returned when the caller can neither update users, delegate the group nor administer it
+    Unauthorized,
+    /// Returned when the user do not exist
+    NotFound,
+    /// Returned when the group do not exist
+    GroupNotFound,
+    /// Returned when the user do not have the group
+    NotGranted,
+    /// Returned when the user is the last member of a protected group
+    Protected,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String)
+}
+
+impl ToString for UserRevokeGroupEventError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Unauthorized => "You are not authorized to do that!".to_string(),
+            Self::NotFound => "User with specified login do not exist".to_string(),
+            Self::GroupNotFound => "Group with specified name do not exist".to_string(),
+            Self::NotGranted => "Provided user never had this group".to_string(),
+            Self::Protected => "This user is the last member of this protected group.".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details)
+        };
+    }
+}
+
+impl EventError for UserRevokeGroupEventError {
+    fn code(&self) -> &'static str {
+        return match self {
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::NotFound => "NOT_FOUND",
+            Self::GroupNotFound => "GROUP_NOT_FOUND",
+            Self::NotGranted => "NOT_GRANTED",
+            Self::Protected => "PROTECTED_ENTITY",
+            Self::Webhook(_) => "WEBHOOK_ERROR",
+            Self::Database(_) => "DATABASE_ERROR"
+        };
+    }
+
+    fn kind(&self) -> EventErrorKind {
+        return match self {
+            Self::Unauthorized => EventErrorKind::Unauthorized,
+            Self::Webhook(_) => EventErrorKind::Internal,
+            Self::Database(_) => EventErrorKind::Internal,
+            _ => EventErrorKind::BadRequest
+        };
+    }
+}
+
+impl PendingEvent for UserRevokeGroupEvent {
+    const TYPE: &'static str = "users/revoke-group";
+
+    type Input = UserRevokeGroupEventInput;
+    type Output = ();
+    type Error = UserRevokeGroupEventError;
+
+    fn target(&self) -> Option<&str> {
+        return Some(&self.group_name);
+    }
+
+    /// ## UserRevokeGroupEvent::prepare
+    ///
+    /// Checks the caller is permitted the same way as DELETE /users/{login}/{group},
+    /// resolving the organization the group is revoked in
+    ///
+    /// Errors:
+    /// + when the caller is not permitted
+    ///
+    async fn prepare(
+        db_conn: &mut PgConnection,
+        input: UserRevokeGroupEventInput,
+        caller: &EventCaller
+    ) -> Result<UserRevokeGroupEvent, UserRevokeGroupEventError> {
+        let permitted = caller.has_permission(db_conn, "cauth:users:update").await
+            || caller.can_delegate_group(db_conn, &input.group).await
+            || caller.can_administer(db_conn, &[&input.group]).await;
+
+        if !permitted {
+            return Err(UserRevokeGroupEventError::Unauthorized);
+        }
+
+        let organization = caller.organization(db_conn).await;
+
+        return Ok(UserRevokeGroupEvent {
+            user_login: input.login,
+            group_name: input.group,
+            organization
+        });
+    }
+
+    /// ## UserRevokeGroupEvent::authorize
+    ///
+    /// Checks the permissions of UserRevokeGroupEvent::prepare in the organization of the change
+    ///
+    async fn authorize(
+        &self,
+        db_conn: &mut PgConnection,
+        user: &User,
+        context: &RequestContext
+    ) -> bool {
+        let context = context.with_organization(self.organization.clone());
+
+        return user.has_permission(db_conn, "cauth:users:update".to_string(), &context).await
+            || user.can_delegate_group(db_conn, &self.group_name, &context).await
+            || Application::can_administer(db_conn, &user.login, &[&self.group_name]).await;
+    }
+
+    /// ## UserRevokeGroupEvent::apply
+    ///
+    /// Revokes the group from the user
+    ///
+    /// Errors:
+    /// + when the user or the group do not exist
+    /// + when the user do not have the group
+    /// + when the user is the last member of a protected group
+    ///
+    async fn apply(
+        self,
+        db_conn: &mut PgConnection,
+        _config: &CauthConfig
+    ) -> Result<(), UserRevokeGroupEventError> {
+        let result = User::revoke_group(
+            db_conn,
+            &self.user_login,
+            &self.group_name,
+            &self.organization
+        )
+        .await;
+
+        return match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(match error {
+                UserRevokeError::NotFound => UserRevokeGroupEventError::NotFound,
+                UserRevokeError::GroupNotFound => UserRevokeGroupEventError::GroupNotFound,
+                UserRevokeError::NotGranted => UserRevokeGroupEventError::NotGranted,
+                UserRevokeError::Protected => UserRevokeGroupEventError::Protected,
+                UserRevokeError::Webhook(err) => UserRevokeGroupEventError::Webhook(err),
+                UserRevokeError::Database(details) => UserRevokeGroupEventError::Database(details)
+            })
+        };
+    }
+}
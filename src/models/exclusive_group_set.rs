@@ -0,0 +1,275 @@
+#![allow(unused)]
+
+use crate::{
+    models::{organization::DEFAULT_ORGANIZATION, Group, Order},
+    util::{
+        logging::{log_database_interaction, DatabaseOperationLogStatus},
+        string::json_value_to_pretty_string,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{query, query_as, FromRow, PgConnection};
+
+/// Set of mutually exclusive groups, a user can belong to at most one of them
+#[derive(FromRow, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct ExclusiveGroupSet {
+    pub name: String,
+    pub description: String,
+    pub groups: Vec<String>,
+}
+
+impl ToString for ExclusiveGroupSet {
+    fn to_string(&self) -> String {
+        let formatted = json_value_to_pretty_string(&serde_json::to_value(&self).unwrap());
+
+        return formatted;
+    }
+}
+
+/// Groups of a single exclusive set held (or about to be held) together
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug)]
+pub struct SodConflict {
+    pub set_name: String,
+    pub groups: Vec<String>,
+}
+
+impl ToString for SodConflict {
+    fn to_string(&self) -> String {
+        return format!(
+            "Groups {} are mutually exclusive (set \"{}\")",
+            self.groups.join(", "),
+            self.set_name
+        );
+    }
+}
+
+/// Existing separation of duties violation, as listed by ExclusiveGroupSet::list_violations
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug)]
+pub struct SodViolation {
+    pub login: String,
+    pub organization: String,
+    pub set_name: String,
+    pub groups: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ExclusiveGroupSetInsertError {
+    /// Returned when the set with specified name already exist
+    /// or either the name or description is too long
+    NameError,
+    /// Returned when one of the listed groups do not exist
+    GroupNotFound(String),
+    /// Returned when less than two groups are listed
+    TooFewGroups,
+}
+
+impl ToString for ExclusiveGroupSetInsertError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NameError => "Either set name or description is too long or set with this name already exist.".to_string(),
+            Self::GroupNotFound(name) => format!("Group \"{}\" do not exist.", name),
+            Self::TooFewGroups => "At least two groups have to be listed.".to_string(),
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum ExclusiveGroupSetDeleteError {
+    /// Returned when the set with specified name do not exist
+    NotFound,
+}
+
+impl ToString for ExclusiveGroupSetDeleteError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An exclusive group set with this name do not exist.",
+        }
+        .to_string();
+    }
+}
+
+impl ExclusiveGroupSet {
+    /// ## ExclusiveGroupSet::list
+    ///
+    /// Lists number of exclusive group sets in specified order with specified offset from the database
+    ///
+    pub async fn list(
+        conn: &mut PgConnection,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+    ) -> Vec<Self> {
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+    SELECT
+      s.name,
+      s.description,
+      ARRAY_REMOVE(ARRAY_AGG(sg.group_name ORDER BY sg.group_name), NULL) AS groups
+    FROM exclusive_group_sets s
+    LEFT JOIN exclusive_group_sets_groups sg ON sg.set_name = s.name
+    GROUP BY s.name
+    ORDER BY s.name {}
+    OFFSET {} ROWS
+    LIMIT {};
+    ",
+            order.to_string(),
+            offset,
+            limit
+        );
+
+        return query_as(&sql).fetch_all(&mut *conn).await.unwrap();
+    }
+
+    /// ## ExclusiveGroupSet::insert
+    ///
+    /// Declares provided groups mutually exclusive <br>
+    /// Users already belonging to more of them are not affected, see ExclusiveGroupSet::list_violations
+    ///
+    /// Errors:
+    /// + when a set with provided name already exist
+    /// + when the name is longer than 255 chars or description is longer than 3000 chars
+    /// + when one of the groups do not exist
+    /// + when less than two groups are provided
+    ///
+    pub async fn insert(
+        conn: &mut PgConnection,
+        name: &String,
+        description: &String,
+        groups: &Vec<String>,
+    ) -> Result<(), ExclusiveGroupSetInsertError> {
+        let mut unique_groups = groups.clone();
+        unique_groups.sort();
+        unique_groups.dedup();
+
+        if unique_groups.len() < 2 {
+            log_database_interaction(
+                "Inserting exclusive group set into database.",
+                json!({ "name": name, "groups": groups }),
+                DatabaseOperationLogStatus::Err("Too few groups"),
+            );
+            return Err(ExclusiveGroupSetInsertError::TooFewGroups);
+        }
+
+        for group_name in &unique_groups {
+            if Group::retrieve(conn, group_name, &DEFAULT_ORGANIZATION.to_string()).await.is_err() {
+                log_database_interaction(
+                    "Inserting exclusive group set into database.",
+                    json!({ "name": name, "groups": groups }),
+                    DatabaseOperationLogStatus::Err(format!("Group \"{}\" not found", group_name)),
+                );
+                return Err(ExclusiveGroupSetInsertError::GroupNotFound(group_name.clone()));
+            }
+        }
+
+        let sql = "INSERT INTO exclusive_group_sets (name, description) VALUES ($1, $2);";
+        if query(sql).bind(name).bind(description).execute(&mut *conn).await.is_err() {
+            log_database_interaction(
+                "Inserting exclusive group set into database.",
+                json!({ "name": name, "groups": groups }),
+                DatabaseOperationLogStatus::Err("Already exists"),
+            );
+            return Err(ExclusiveGroupSetInsertError::NameError);
+        }
+
+        let sql = "INSERT INTO exclusive_group_sets_groups (set_name, group_name) SELECT $1, UNNEST($2::VARCHAR[]);";
+        query(sql)
+            .bind(name)
+            .bind(&unique_groups)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        log_database_interaction::<String>(
+            "Inserting exclusive group set into database.",
+            json!({ "name": name, "description": description, "groups": unique_groups }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## ExclusiveGroupSet::delete
+    ///
+    /// Deletes an exclusive group set with provided name, the groups are not affected
+    ///
+    pub async fn delete(
+        conn: &mut PgConnection,
+        name: &String,
+    ) -> Result<(), ExclusiveGroupSetDeleteError> {
+        let sql = "DELETE FROM exclusive_group_sets WHERE name = $1;";
+        let result = query(sql).bind(name).execute(&mut *conn).await.unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Deleting exclusive group set from database.",
+                json!({ "name": name }),
+                DatabaseOperationLogStatus::Err("Not found"),
+            );
+            return Err(ExclusiveGroupSetDeleteError::NotFound);
+        }
+
+        log_database_interaction::<String>(
+            "Deleting exclusive group set from database.",
+            json!({ "name": name }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## ExclusiveGroupSet::find_conflict
+    ///
+    /// Checks whether provided groups can be held together by a single user,
+    /// returning the first set more of them belong to
+    ///
+    pub async fn find_conflict(
+        conn: &mut PgConnection,
+        groups: &Vec<String>,
+    ) -> Option<SodConflict> {
+        let sql = "
+    SELECT
+      set_name,
+      ARRAY_AGG(group_name ORDER BY group_name) AS groups
+    FROM exclusive_group_sets_groups
+    WHERE group_name = ANY($1)
+    GROUP BY set_name
+    HAVING COUNT(*) > 1
+    ORDER BY set_name
+    LIMIT 1;
+    ";
+
+        return query_as(sql)
+            .bind(groups)
+            .fetch_optional(&mut *conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## ExclusiveGroupSet::list_violations
+    ///
+    /// Lists users belonging to more groups of a single exclusive set inside any organization,
+    /// e.g. the ones granted before the set was declared
+    ///
+    pub async fn list_violations(conn: &mut PgConnection) -> Vec<SodViolation> {
+        let sql = "
+    SELECT
+      ug.user_login AS login,
+      ug.organization_name AS organization,
+      sg.set_name,
+      ARRAY_AGG(DISTINCT ug.group_name) AS groups
+    FROM users_groups ug
+    INNER JOIN exclusive_group_sets_groups sg ON sg.group_name = ug.group_name
+    WHERE ug.expires_at IS NULL OR ug.expires_at > NOW()
+    GROUP BY ug.user_login, ug.organization_name, sg.set_name
+    HAVING COUNT(DISTINCT ug.group_name) > 1
+    ORDER BY ug.user_login, ug.organization_name, sg.set_name;
+    ";
+
+        return query_as(sql).fetch_all(&mut *conn).await.unwrap();
+    }
+}
@@ -0,0 +1,572 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{prelude::FromRow, query, query_as, PgConnection};
+
+use crate::util::{
+    condition::Condition,
+    logging::{log_database_interaction, DatabaseOperationLogStatus},
+};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum GrantEffect {
+    Allow,
+    Deny,
+}
+
+impl ToString for GrantEffect {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Allow => "allow".to_string(),
+            Self::Deny => "deny".to_string(),
+        };
+    }
+}
+
+/// A single grant of a permission to a user, either through a group or directly
+#[derive(FromRow, Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Grant {
+    /// "group" when the grant comes from one of the user's groups, "user" for direct grants
+    pub source_type: String,
+    /// name of the group or login of the user holding the grant
+    pub source_name: String,
+    pub permission_name: String,
+    pub effect: String,
+    pub condition: Option<String>,
+    /// when the grant stops applying, NULL if it never expires
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Where an effective permission comes from
+#[derive(Serialize, Clone, Debug)]
+pub struct GrantSource {
+    /// "group" or "user" (direct grant)
+    pub source_type: String,
+    pub source_name: String,
+}
+
+/// A permission the user effectively holds together with all the grants providing it
+#[derive(Serialize, Clone, Debug)]
+pub struct EffectivePermission {
+    pub name: String,
+    pub sources: Vec<GrantSource>,
+    /// permissions matching the wildcard which are denied, empty for the other permissions
+    pub except: Vec<String>,
+}
+
+/// How a single grant was evaluated while explaining a permission decision
+#[derive(Serialize, Clone, Debug)]
+pub struct GrantEvaluation {
+    pub grant: Grant,
+    /// "exact" or "wildcard"
+    pub matched_by: String,
+    /// result of the grant's condition, null when the grant has no condition
+    pub condition_result: Option<bool>,
+    pub expired: bool,
+    /// whether the grant took part in the decision
+    pub applied: bool,
+}
+
+/// Full explanation of a permission decision for a user
+#[derive(Serialize, Clone, Debug)]
+pub struct PermissionExplanation {
+    pub login: String,
+    pub permission_name: String,
+    pub decision: PermissionDecision,
+    /// groups the user currently belongs to
+    pub groups: Vec<String>,
+    /// every grant and deny rule matching the permission, with how it was evaluated
+    pub grants: Vec<GrantEvaluation>,
+}
+
+/// Result of resolving a permission check against the user's grants
+#[derive(Serialize, Clone, Debug)]
+pub struct PermissionDecision {
+    pub allowed: bool,
+    /// the grant that allowed the permission
+    pub allowed_by: Option<Grant>,
+    /// the deny rule that blocked the permission
+    pub denied_by: Option<Grant>,
+}
+
+/// prefix a "{namespace}:*" wildcard matches, None for the other permission names
+fn wildcard_prefix(permission_name: &str) -> Option<&str> {
+    return match permission_name.strip_suffix('*') {
+        Some(prefix) if prefix.len() > 1 && prefix.ends_with(':') => Some(prefix),
+        _ => None,
+    };
+}
+
+/// checks if a granted permission name matches provided permission, either exactly or as a wildcard
+fn permission_matches(granted: &str, permission_name: &str) -> bool {
+    return match wildcard_prefix(granted) {
+        Some(prefix) => permission_name.starts_with(prefix),
+        None => granted == permission_name,
+    };
+}
+
+impl Grant {
+    /// ## Grant::list_for_user
+    ///
+    /// Lists all the grants (both from groups and direct) of provided permissions
+    /// that a user with specified login holds in provided organization, skipping the expired ones
+    ///
+    pub async fn list_for_user(
+        conn: &mut PgConnection,
+        login: &String,
+        permission_names: &Vec<String>,
+        organization: &String,
+    ) -> Vec<Self> {
+        return Self::list(conn, login, Some(permission_names), false, organization).await;
+    }
+
+    /// ## Grant::list_all_for_user
+    ///
+    /// Lists all the grants (both from groups and direct) that a user with specified login holds
+    /// in provided organization, skipping the expired ones
+    ///
+    pub async fn list_all_for_user(
+        conn: &mut PgConnection,
+        login: &String,
+        organization: &String,
+    ) -> Vec<Self> {
+        return Self::list(conn, login, None, false, organization).await;
+    }
+
+    /// ## Grant::list_for_explanation
+    ///
+    /// Lists all the grants of provided permission that a user with specified login holds
+    /// in provided organization, including the expired ones that weren't cleaned up yet
+    ///
+    pub async fn list_for_explanation(
+        conn: &mut PgConnection,
+        login: &String,
+        permission_name: &str,
+        organization: &String,
+    ) -> Vec<Self> {
+        return Self::list(conn, login, Some(&vec![permission_name.to_string()]), true, organization).await;
+    }
+
+    /// Grants coming from a group expire when either the group membership
+    /// or the group's grant expires, no permission names means all permissions.
+    /// Wildcard grants (e.g. "billing:*") are returned for every permission they match,
+    /// see Grant::matches
+    /// Only the memberships and grants inside provided organization are taken into account
+    async fn list(
+        conn: &mut PgConnection,
+        login: &String,
+        permission_names: Option<&Vec<String>>,
+        include_expired: bool,
+        organization: &String,
+    ) -> Vec<Self> {
+        let sql = "
+      SELECT
+        'group' AS source_type,
+        gp.group_name AS source_name,
+        gp.permission_name,
+        gp.effect,
+        gp.condition,
+        LEAST(ug.expires_at, gp.expires_at) AS expires_at
+      FROM
+        users_groups ug
+      INNER JOIN
+        groups_permissions gp
+      ON
+        ug.group_name = gp.group_name
+        AND
+        gp.organization_name = ug.organization_name
+      WHERE
+        ug.user_login = $1
+      AND
+        ug.organization_name = $4
+      AND
+        (
+          $2::VARCHAR[] IS NULL
+          OR
+          gp.permission_name = ANY($2)
+          OR
+          (
+            RIGHT(gp.permission_name, 2) = ':*'
+            AND
+            LENGTH(gp.permission_name) > 2
+            AND
+            EXISTS (
+              SELECT 1 FROM UNNEST($2::VARCHAR[]) AS requested(name)
+              WHERE STARTS_WITH(requested.name, LEFT(gp.permission_name, -1))
+            )
+          )
+        )
+      AND
+        ($3 OR ug.expires_at IS NULL OR ug.expires_at > NOW())
+      AND
+        ($3 OR gp.expires_at IS NULL OR gp.expires_at > NOW())
+      UNION ALL
+      SELECT
+        'user' AS source_type,
+        up.user_login AS source_name,
+        up.permission_name,
+        up.effect,
+        up.condition,
+        up.expires_at
+      FROM
+        users_permissions up
+      WHERE
+        up.user_login = $1
+      AND
+        up.organization_name = $4
+      AND
+        (
+          $2::VARCHAR[] IS NULL
+          OR
+          up.permission_name = ANY($2)
+          OR
+          (
+            RIGHT(up.permission_name, 2) = ':*'
+            AND
+            LENGTH(up.permission_name) > 2
+            AND
+            EXISTS (
+              SELECT 1 FROM UNNEST($2::VARCHAR[]) AS requested(name)
+              WHERE STARTS_WITH(requested.name, LEFT(up.permission_name, -1))
+            )
+          )
+        )
+      AND
+        ($3 OR up.expires_at IS NULL OR up.expires_at > NOW());
+    ";
+
+        return query_as(sql)
+            .bind(login)
+            .bind(permission_names)
+            .bind(include_expired)
+            .bind(organization)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## Grant::delete_expired
+    ///
+    /// Deletes all the expired group memberships and permission grants,
+    /// returning the number of deleted rows
+    ///
+    pub async fn delete_expired(conn: &mut PgConnection) -> u64 {
+        let mut deleted = 0;
+
+        for table in ["users_groups", "groups_permissions", "users_permissions"] {
+            let sql = format!("DELETE FROM {} WHERE expires_at <= NOW();", table);
+            let result = query(&sql).execute(&mut *conn).await;
+
+            match result {
+                Ok(result) => deleted += result.rows_affected(),
+                Err(err) => log_database_interaction(
+                    "Deleting expired grants from the database.",
+                    json!({ "table": table }),
+                    DatabaseOperationLogStatus::Err(err.to_string()),
+                ),
+            };
+        }
+
+        if deleted > 0 {
+            log_database_interaction::<String>(
+                "Deleting expired grants from the database.",
+                json!({ "deleted": deleted }),
+                DatabaseOperationLogStatus::Ok,
+            );
+        }
+
+        return deleted;
+    }
+
+    /// ## Grant::matches
+    ///
+    /// Checks if the grant is for provided permission,
+    /// either exactly or through a wildcard (e.g. "billing:*" matches "billing:refund")
+    ///
+    pub fn matches(&self, permission_name: &str) -> bool {
+        return permission_matches(&self.permission_name, permission_name);
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        return wildcard_prefix(&self.permission_name).is_some();
+    }
+
+    pub fn is_expired(&self) -> bool {
+        return match self.expires_at {
+            Some(expires_at) => expires_at <= Utc::now(),
+            None => false,
+        };
+    }
+
+    pub fn is_deny(&self) -> bool {
+        return self.effect == GrantEffect::Deny.to_string();
+    }
+
+    /// ## Grant::applies
+    ///
+    /// Checks if the grant's condition (if any) holds for provided attributes,
+    /// conditions which cannot be parsed or evaluated fail closed:
+    /// allow rules do not apply while deny rules do
+    ///
+    pub fn applies(&self, attributes: &Value) -> bool {
+        let condition = match &self.condition {
+            Some(condition) => condition,
+            None => return true,
+        };
+
+        // conditions are validated on grant, but the attributes can have any type
+        return match Condition::parse(condition).map(|condition| condition.try_evaluate(attributes)) {
+            Ok(Some(result)) => result,
+            _ => self.is_deny(),
+        };
+    }
+
+    /// ## Grant::resolve
+    ///
+    /// Resolves a permission against provided grants,
+    /// deny rules are evaluated before allow rules so any applying deny blocks the permission
+    ///
+    pub fn resolve(grants: &[Self], permission_name: &str, attributes: &Value) -> PermissionDecision {
+        let applying: Vec<&Self> = grants
+            .iter()
+            .filter(|grant| grant.matches(permission_name) && !grant.is_expired() && grant.applies(attributes))
+            .collect();
+
+        if let Some(deny) = applying.iter().find(|grant| grant.is_deny()) {
+            return PermissionDecision {
+                allowed: false,
+                allowed_by: None,
+                denied_by: Some((*deny).clone()),
+            };
+        }
+
+        let allow = applying.iter().find(|grant| !grant.is_deny());
+
+        return PermissionDecision {
+            allowed: allow.is_some(),
+            allowed_by: allow.map(|grant| (*grant).clone()),
+            denied_by: None,
+        };
+    }
+
+    /// ## Grant::explain
+    ///
+    /// Resolves a permission against provided grants like Grant::resolve does,
+    /// additionally returning how every single grant was evaluated
+    ///
+    pub fn explain(
+        grants: &[Self],
+        permission_name: &str,
+        attributes: &Value,
+    ) -> (PermissionDecision, Vec<GrantEvaluation>) {
+        let decision = Self::resolve(grants, permission_name, attributes);
+        let deciding = match (&decision.denied_by, &decision.allowed_by) {
+            (Some(deny), _) => Some(deny),
+            (None, allow) => allow.as_ref(),
+        };
+
+        let evaluations = grants
+            .iter()
+            .filter(|grant| grant.matches(permission_name))
+            .map(|grant| {
+                let condition_result = grant
+                    .condition
+                    .as_ref()
+                    .map(|_| grant.applies(attributes));
+                let expired = grant.is_expired();
+
+                GrantEvaluation {
+                    grant: grant.clone(),
+                    matched_by: if grant.is_wildcard() { "wildcard" } else { "exact" }.to_string(),
+                    condition_result,
+                    expired,
+                    applied: Some(grant) == deciding,
+                }
+            })
+            .collect();
+
+        return (decision, evaluations);
+    }
+
+    /// ## Grant::effective_permissions
+    ///
+    /// Resolves the deduplicated set of permissions provided grants give the same way
+    /// as Grant::resolve does, with all the applying grants listed as sources of each permission
+    ///
+    pub fn effective_permissions(grants: &[Self], attributes: &Value) -> Vec<EffectivePermission> {
+        // wildcard grants are listed under the wildcard name
+        let mut names: Vec<&String> = grants.iter().map(|grant| &grant.permission_name).collect();
+        names.sort();
+        names.dedup();
+
+        let mut permissions = vec![];
+
+        for name in &names {
+            if !Self::resolve(grants, name, attributes).allowed {
+                continue;
+            }
+
+            let sources = grants
+                .iter()
+                .filter(|grant| {
+                    grant.matches(name) && !grant.is_deny() && !grant.is_expired() && grant.applies(attributes)
+                })
+                .map(|grant| GrantSource {
+                    source_type: grant.source_type.clone(),
+                    source_name: grant.source_name.clone(),
+                })
+                .collect();
+
+            // permissions matching a wildcard can still be denied one by one
+            let except = names
+                .iter()
+                .filter(|other| {
+                    *other != name
+                        && permission_matches(name, other)
+                        && !Self::resolve(grants, other, attributes).allowed
+                })
+                .map(|other| (*other).clone())
+                .collect();
+
+            permissions.push(EffectivePermission {
+                name: (*name).clone(),
+                sources,
+                except,
+            });
+        }
+
+        return permissions;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(permission_name: &str, effect: GrantEffect, condition: Option<&str>) -> Grant {
+        return Grant {
+            source_type: "group".to_string(),
+            source_name: "staff".to_string(),
+            permission_name: permission_name.to_string(),
+            effect: effect.to_string(),
+            condition: condition.map(|condition| condition.to_string()),
+            expires_at: None,
+        };
+    }
+
+    fn attributes() -> Value {
+        return json!({
+            "details": { "department": "finance", "level": 3 },
+            "request": { "ip": "10.0.0.7", "hour": 9 }
+        });
+    }
+
+    #[test]
+    fn deny_rules_win_over_grants() {
+        let grants = vec![
+            grant("billing:refund", GrantEffect::Allow, None),
+            grant("billing:refund", GrantEffect::Deny, Some("request.hour < 12")),
+        ];
+        let decision = Grant::resolve(&grants, &"billing:refund".to_string(), &attributes());
+
+        assert!(!decision.allowed);
+        assert!(decision.denied_by.is_some());
+    }
+
+    #[test]
+    fn deny_rules_fail_closed() {
+        // the department is a string, so the comparison cannot be evaluated
+        let grants = vec![
+            grant("billing:refund", GrantEffect::Allow, None),
+            grant("billing:refund", GrantEffect::Deny, Some("details.department > 2")),
+        ];
+        let decision = Grant::resolve(&grants, &"billing:refund".to_string(), &attributes());
+        assert!(!decision.allowed);
+
+        // conditions stored before they were validated
+        let grants = vec![
+            grant("billing:refund", GrantEffect::Allow, None),
+            grant("billing:refund", GrantEffect::Deny, Some("details.department ==")),
+        ];
+        let decision = Grant::resolve(&grants, &"billing:refund".to_string(), &attributes());
+        assert!(!decision.allowed);
+    }
+
+    #[test]
+    fn grants_fail_closed() {
+        let grants = vec![grant("billing:refund", GrantEffect::Allow, Some("details.department > 2"))];
+        assert!(!Grant::resolve(&grants, &"billing:refund".to_string(), &attributes()).allowed);
+
+        let grants = vec![grant("billing:refund", GrantEffect::Allow, Some("details.department =="))];
+        assert!(!Grant::resolve(&grants, &"billing:refund".to_string(), &attributes()).allowed);
+
+        let grants = vec![grant("billing:refund", GrantEffect::Allow, Some("details.level > 2"))];
+        assert!(Grant::resolve(&grants, &"billing:refund".to_string(), &attributes()).allowed);
+    }
+
+    #[test]
+    fn deny_rules_with_false_conditions_do_not_apply() {
+        let grants = vec![
+            grant("billing:refund", GrantEffect::Allow, None),
+            grant("billing:refund", GrantEffect::Deny, Some("details.department == \"sales\"")),
+        ];
+        let decision = Grant::resolve(&grants, &"billing:refund".to_string(), &attributes());
+
+        assert!(decision.allowed);
+        assert!(decision.denied_by.is_none());
+    }
+
+    #[test]
+    fn wildcards_need_a_namespace() {
+        let wildcard = grant("billing:*", GrantEffect::Allow, None);
+        assert!(wildcard.is_wildcard());
+        assert!(wildcard.matches("billing:refund"));
+        assert!(wildcard.matches("billing:refund:partial"));
+        assert!(!wildcard.matches("billing"));
+        assert!(!wildcard.matches("shop:billing:refund"));
+
+        // stored before "*" was reserved, they only match themselves
+        for name in ["*", ":*", "bill*"] {
+            let grant = grant(name, GrantEffect::Allow, None);
+            assert!(!grant.is_wildcard(), "{}", name);
+            assert!(!grant.matches("billing:refund"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn specific_deny_rules_beat_wildcard_grants() {
+        let grants = vec![
+            grant("billing:*", GrantEffect::Allow, None),
+            grant("billing:refund", GrantEffect::Deny, None),
+        ];
+
+        assert!(!Grant::resolve(&grants, &"billing:refund".to_string(), &attributes()).allowed);
+        assert!(Grant::resolve(&grants, &"billing:void".to_string(), &attributes()).allowed);
+
+        let permissions = Grant::effective_permissions(&grants, &attributes());
+        assert_eq!(permissions.len(), 1);
+        assert_eq!(permissions[0].name, "billing:*");
+        assert_eq!(permissions[0].except, vec!["billing:refund".to_string()]);
+    }
+
+    #[test]
+    fn effective_permissions_agree_with_resolve() {
+        let grants = vec![
+            grant("billing:refund", GrantEffect::Allow, None),
+            grant("billing:void", GrantEffect::Allow, None),
+            grant("billing:*", GrantEffect::Deny, Some("request.hour < 12")),
+            grant("shop:orders", GrantEffect::Allow, Some("details.department == \"finance\"")),
+            grant("shop:refunds", GrantEffect::Allow, Some("details.department == \"sales\"")),
+        ];
+        let permissions = Grant::effective_permissions(&grants, &attributes());
+
+        for name in ["billing:refund", "billing:void", "billing:*", "shop:orders", "shop:refunds"] {
+            let listed = permissions.iter().any(|permission| permission.name == name);
+            let allowed = Grant::resolve(&grants, &name.to_string(), &attributes()).allowed;
+
+            assert_eq!(listed, allowed, "{}", name);
+        }
+        assert!(permissions.iter().any(|permission| permission.name == "shop:orders"));
+    }
+}
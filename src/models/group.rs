@@ -1,22 +1,30 @@
 #![allow(unused)]
 
 use crate::{
-    models::{Order, Permission},
+    models::{
+        organization::DEFAULT_ORGANIZATION,
+        outbox::{DomainEvent, OutboxEvent},
+        webhook::{Webhook, WebhookEnqueueError},
+        GrantEffect, Order, Permission, User,
+    },
     util::{
         logging::{log_database_interaction, DatabaseOperationLogStatus},
+        condition::Condition,
         string::json_value_to_pretty_string,
     },
 };
+use chrono::{DateTime, Utc};
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{query, query_as, FromRow, PgConnection};
+use sqlx::{query, query_as, Connection, FromRow, PgConnection};
 use std::error::Error;
 
 #[derive(FromRow, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Group {
     pub name: String,
     pub description: String,
+    pub protected: bool,
     pub permissions: Vec<String>,
 }
 
@@ -28,6 +36,13 @@ impl ToString for Group {
     }
 }
 
+/// Member of a group, as listed by Group::list_members
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug)]
+pub struct GroupMember {
+    pub login: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug)]
 pub enum GroupListError {}
 
@@ -49,29 +64,68 @@ pub enum GroupInsertError {
     NameError,
     /// Returned when one of the permissions listed do not exist in the database
     PermissionNotFound,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for GroupInsertError {
     fn to_string(&self) -> String {
         return match self {
-            Self::NameError => "A group with provided name do not exist.",
-            Self::PermissionNotFound => "One of the listed permissions to not exists",
-        }
-        .to_string();
+            Self::NameError => "A group with provided name do not exist.".to_string(),
+            Self::PermissionNotFound => "One of the listed permissions to not exists".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
+    }
+}
+
+pub enum GroupUpdateError {
+    /// Returned when the group with specified name do not exist
+    NotFound,
+    /// Returned when the new name or description is too long
+    /// or when a group with the new name already exist
+    NameError,
+    /// Returned when renaming a protected group
+    Protected,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
+}
+
+impl ToString for GroupUpdateError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "A group with this name do not exist.".to_string(),
+            Self::NameError => "Either new group name or description is too long or group with this name already exist.".to_string(),
+            Self::Protected => "This group is protected and cannot be renamed.".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
     }
 }
 
 pub enum GroupDeleteError {
     /// Returned when the group with specified name do not exist
     NotFound,
+    /// Returned when the group is protected
+    Protected,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for GroupDeleteError {
     fn to_string(&self) -> String {
         return match self {
-            Self::NotFound => "A group with this name do not exist.",
-        }
-        .to_string();
+            Self::NotFound => "A group with this name do not exist.".to_string(),
+            Self::Protected => "This group is protected and cannot be deleted.".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
     }
 }
 
@@ -80,15 +134,20 @@ pub enum GroupGrantError {
     NotFound,
     /// Returned when permission with provided name do not exist
     PermissionNotFound,
+    /// Returned when the provided condition cannot be parsed
+    InvalidCondition(String),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for GroupGrantError {
     fn to_string(&self) -> String {
         return match self {
-            Self::NotFound => "A group with provided name do not exist",
-            Self::PermissionNotFound => "A permission with provided name do not exist",
-        }
-        .to_string();
+            Self::NotFound => "A group with provided name do not exist".to_string(),
+            Self::PermissionNotFound => "A permission with provided name do not exist".to_string(),
+            Self::InvalidCondition(details) => format!("Invalid grant condition: {}", details),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
     }
 }
 
@@ -99,14 +158,56 @@ pub enum GroupRevokeError {
     PermissionNotFound,
     /// Returned when the provided permission wasn't granted
     PermissionNotGranted,
+    /// Returned when revoking a protected permission from a protected group
+    Protected,
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for GroupRevokeError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "A group with provided name do not exist".to_string(),
+            Self::PermissionNotFound => "A permission with provided name do not exist".to_string(),
+            Self::PermissionNotGranted => "The group with provided name never had this permission".to_string(),
+            Self::Protected => "Protected permissions cannot be revoked from a protected group".to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
+    }
+}
+
+pub enum GroupGrantManagerError {
+    /// Returned when a group with provided name do not exist
+    NotFound,
+    /// Returned when a user with provided login do not exist
+    UserNotFound,
+    /// Returned when the user already manages the group
+    AlreadyGranted,
+}
+
+impl ToString for GroupGrantManagerError {
     fn to_string(&self) -> String {
         return match self {
             Self::NotFound => "A group with provided name do not exist",
-            Self::PermissionNotFound => "A permission with provided name do not exist",
-            Self::PermissionNotGranted => "The group with provided name never had this permission",
+            Self::UserNotFound => "A user with provided login do not exist",
+            Self::AlreadyGranted => "The user with provided login already manages this group",
+        }
+        .to_string();
+    }
+}
+
+pub enum GroupRevokeManagerError {
+    /// Returned when a group with provided name do not exist
+    NotFound,
+    /// Returned when the user do not manage the group
+    NotGranted,
+}
+
+impl ToString for GroupRevokeManagerError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "A group with provided name do not exist",
+            Self::NotGranted => "The user with provided login do not manage this group",
         }
         .to_string();
     }
@@ -115,13 +216,15 @@ impl ToString for GroupRevokeError {
 impl Group {
     /// ## Group::list
     ///
-    /// Lists number of groups in specified order with specified offset from the database
+    /// Lists number of groups in specified order with specified offset from the database,
+    /// along with the permissions they are granted in provided organization
     ///
     pub async fn list(
         conn: &mut PgConnection,
         order: Option<Order>,
         offset: Option<usize>,
         limit: Option<usize>,
+        organization: &String,
     ) -> Result<Vec<Self>, GroupListError> {
         let order = order.unwrap_or(Order::Ascending);
         let offset = offset.unwrap_or(0);
@@ -132,9 +235,12 @@ impl Group {
     SELECT 
       g.name,
       g.description,
+      g.protected,
       ARRAY_REMOVE(ARRAY_AGG(gp.permission_name), NULL) AS permissions
     FROM groups g
-    INNER JOIN groups_permissions gp ON gp.group_name = g.name
+    LEFT JOIN groups_permissions gp ON gp.group_name = g.name
+      AND gp.organization_name = $1
+      AND (gp.expires_at IS NULL OR gp.expires_at > NOW())
     GROUP BY g.name
     ORDER BY g.name {}
     OFFSET {} ROWS
@@ -145,14 +251,19 @@ impl Group {
             limit
         );
 
-        let result = query_as(&sql).fetch_all(&mut *conn).await.unwrap();
+        let result = query_as(&sql)
+            .bind(organization)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
 
         return Ok(result);
     }
 
     /// ## Group::retrieve
     ///
-    /// Retrieves a group with specified name from the database
+    /// Retrieves a group with specified name from the database,
+    /// along with the permissions it is granted in provided organization
     ///
     /// Errors:
     /// + when group with specified name do not exist
@@ -160,24 +271,34 @@ impl Group {
     pub async fn retrieve(
         conn: &mut PgConnection,
         name: &String,
+        organization: &String,
     ) -> Result<Self, GroupRetrieveError> {
         let sql = "
     SELECT 
       g.name,
       g.description,
+      g.protected,
       ARRAY_REMOVE(ARRAY_AGG(gp.permission_name), NULL) AS permissions
     FROM 
       groups g
-    INNER JOIN
+    LEFT JOIN
       groups_permissions gp
     ON
       gp.group_name = g.name
+      AND
+      gp.organization_name = $2
+      AND
+      (gp.expires_at IS NULL OR gp.expires_at > NOW())
     WHERE
-      name = $1
+      g.name = $1
     GROUP BY
       g.name;
     ";
-        let result = query_as(&sql).bind(&name).fetch_one(&mut *conn).await;
+        let result = query_as(&sql)
+            .bind(&name)
+            .bind(organization)
+            .fetch_one(&mut *conn)
+            .await;
 
         match result {
             Ok(result) => return Ok(result),
@@ -187,7 +308,7 @@ impl Group {
 
     /// ## Group::insert
     ///
-    /// Inserts a group with provided data into database <br>
+    /// Inserts a group with provided data into database, assigned permissions are granted in provided organization <br>
     ///
     /// Errors:
     /// + when a group with provided name already exist
@@ -199,7 +320,12 @@ impl Group {
         name: &String,
         description: &String,
         permissions: &Vec<String>,
+        organization: &String,
     ) -> Result<(), GroupInsertError> {
+        // nested, so the outbox event is committed along with the change even outside of a transaction
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
         let sql = "INSERT INTO groups (name, description) VALUES ($1, $2);".to_string();
         let q = query(&sql).bind(&name).bind(&description);
 
@@ -215,8 +341,28 @@ impl Group {
             }
         };
 
+        // recorded before the grants of the assigned permissions
+        OutboxEvent::record(
+            conn,
+            DomainEvent::GroupCreated {
+                name: name.clone(),
+                description: description.clone(),
+            },
+        )
+        .await;
+
         for permission_name in permissions {
-            match Self::grant_permission(&mut *conn, &name, permission_name).await {
+            match Self::grant_permission(
+                &mut *conn,
+                &name,
+                permission_name,
+                GrantEffect::Allow,
+                None,
+                None,
+                organization,
+            )
+            .await
+            {
                 Ok(_) => (),
                 Err(err) => {
                     log_database_interaction(
@@ -237,17 +383,161 @@ impl Group {
             DatabaseOperationLogStatus::Ok,
         );
 
+        let enqueued = Webhook::enqueue(
+            conn,
+            "group.created",
+            json!({ "name": name, "description": description, "permissions": permissions, "organization": organization }),
+        )
+        .await;
+        if let Err(err) = enqueued {
+            return Err(GroupInsertError::Webhook(err));
+        }
+
+        if let Err(err) = tx.commit().await {
+            return Err(GroupInsertError::Database(err.to_string()));
+        }
+
         return Ok(());
     }
 
+    /// ## Group::update
+    ///
+    /// Updates description and/or renames a group with provided name,
+    /// renaming cascades to the group's permissions and members
+    ///
+    /// Errors:
+    /// + when group with provided name do not exist
+    /// + when a group with the new name already exist
+    /// + when the new name is longer than 255 chars or description is longer than 3000 chars
+    /// + when renaming a protected group
+    ///
+    pub async fn update(
+        conn: &mut PgConnection,
+        name: &String,
+        new_name: Option<&String>,
+        description: Option<&String>,
+    ) -> Result<(), GroupUpdateError> {
+        // nested, so the outbox event and the deliveries are committed along with the rename
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Ok(group) = Self::retrieve(conn, name, &DEFAULT_ORGANIZATION.to_string()).await {
+            if group.protected && new_name.is_some_and(|new_name| new_name != name) {
+                log_database_interaction(
+                    "Updating group in the database.",
+                    json!({ "name": name, "new_name": new_name, "description": description }),
+                    DatabaseOperationLogStatus::Err("Protected"),
+                );
+                return Err(GroupUpdateError::Protected);
+            }
+        }
+
+        let sql = "UPDATE groups SET name = COALESCE($2, name), description = COALESCE($3, description) WHERE name = $1;";
+        let result = query(sql)
+            .bind(name)
+            .bind(new_name)
+            .bind(description)
+            .execute(&mut *conn)
+            .await;
+
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {
+                log_database_interaction::<String>(
+                    "Updating group in the database.",
+                    json!({ "name": name, "new_name": new_name, "description": description }),
+                    DatabaseOperationLogStatus::Ok,
+                );
+
+                if let Some(new_name) = new_name.filter(|new_name| *new_name != name) {
+                    let enqueued = Webhook::enqueue(
+                        conn,
+                        "group.renamed",
+                        json!({ "name": name, "new_name": new_name }),
+                    )
+                    .await;
+                    if let Err(err) = enqueued {
+                        return Err(GroupUpdateError::Webhook(err));
+                    }
+                    OutboxEvent::record(
+                        conn,
+                        DomainEvent::GroupRenamed {
+                            name: name.clone(),
+                            new_name: new_name.clone(),
+                        },
+                    )
+                    .await;
+                }
+
+                if let Err(err) = tx.commit().await {
+                    return Err(GroupUpdateError::Database(err.to_string()));
+                }
+
+                return Ok(());
+            }
+            Ok(_) => {
+                log_database_interaction(
+                    "Updating group in the database.",
+                    json!({ "name": name, "new_name": new_name, "description": description }),
+                    DatabaseOperationLogStatus::Err("Not found"),
+                );
+                return Err(GroupUpdateError::NotFound);
+            }
+            Err(_) => {
+                log_database_interaction(
+                    "Updating group in the database.",
+                    json!({ "name": name, "new_name": new_name, "description": description }),
+                    DatabaseOperationLogStatus::Err("Already exists"),
+                );
+                return Err(GroupUpdateError::NameError);
+            }
+        };
+    }
+
+    /// ## Group::protect
+    ///
+    /// Marks a group with provided name as protected from being deleted or renamed,
+    /// its protected permissions cannot be revoked and its last member cannot be removed
+    ///
+    pub async fn protect(conn: &mut PgConnection, name: &String) {
+        let sql = "UPDATE groups SET protected = TRUE WHERE name = $1;";
+        let _ = query(sql).bind(name).execute(&mut *conn).await;
+    }
+
     /// ## Group::delete
     ///
-    /// Deletes a group and all of it's related data from the database
+    /// Deletes a group and all of it's related data (grants, memberships and managers) from the database
+    ///
+    /// Errors:
+    /// + when group with provided name do not exist
+    /// + when the group is protected
     ///
     pub async fn delete(conn: &mut PgConnection, name: &String) -> Result<(), GroupDeleteError> {
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Ok(Group { protected: true, .. }) =
+            Self::retrieve(conn, name, &DEFAULT_ORGANIZATION.to_string()).await
+        {
+            log_database_interaction(
+                "Deleting group from the database.",
+                json!({ "name": name }),
+                DatabaseOperationLogStatus::Err("Protected"),
+            );
+            return Err(GroupDeleteError::Protected);
+        }
+
         let sql = "DELETE FROM groups_permissions WHERE group_name = $1;";
         let _ = query(&sql).bind(&name).execute(&mut *conn).await;
 
+        let sql = "DELETE FROM users_groups WHERE group_name = $1;";
+        let _ = query(&sql).bind(&name).execute(&mut *conn).await;
+
+        let sql = "DELETE FROM groups_managers WHERE group_name = $1;";
+        let _ = query(&sql).bind(&name).execute(&mut *conn).await;
+
+        let sql = "DELETE FROM exclusive_group_sets_groups WHERE group_name = $1;";
+        let _ = query(&sql).bind(&name).execute(&mut *conn).await;
+
         let sql = "DELETE FROM groups WHERE name = $1;".to_string();
         let result = query(&sql).bind(&name).execute(&mut *conn).await.unwrap();
 
@@ -257,6 +547,15 @@ impl Group {
                 json!({ "name": name }),
                 DatabaseOperationLogStatus::Ok,
             );
+            if let Err(err) = Webhook::enqueue(conn, "group.deleted", json!({ "name": name })).await {
+                return Err(GroupDeleteError::Webhook(err));
+            }
+            OutboxEvent::record(conn, DomainEvent::GroupDeleted { name: name.clone() }).await;
+
+            if let Err(err) = tx.commit().await {
+                return Err(GroupDeleteError::Database(err.to_string()));
+            }
+
             return Ok(());
         } else {
             log_database_interaction(
@@ -268,33 +567,260 @@ impl Group {
         }
     }
 
+    /// ## Group::list_members
+    ///
+    /// Lists number of users belonging to a group with specified name inside provided organization
+    /// in specified order with specified offset, expired memberships are omitted
+    ///
+    /// Errors:
+    /// + when group with specified name do not exist
+    ///
+    pub async fn list_members(
+        conn: &mut PgConnection,
+        name: &String,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+        organization: &String,
+    ) -> Result<Vec<GroupMember>, GroupRetrieveError> {
+        Self::retrieve(conn, name, organization).await?;
+
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+    SELECT
+      user_login AS login,
+      expires_at
+    FROM users_groups
+    WHERE group_name = $1
+      AND organization_name = $2
+      AND (expires_at IS NULL OR expires_at > NOW())
+    ORDER BY user_login {}
+    OFFSET {} ROWS
+    LIMIT {};
+    ",
+            order.to_string(),
+            offset,
+            limit
+        );
+        let result = query_as(&sql)
+            .bind(name)
+            .bind(organization)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+
+        return Ok(result);
+    }
+
+    /// ## Group::list_managers
+    ///
+    /// Lists logins of all the managers of a group with specified name inside provided organization
+    ///
+    /// Errors:
+    /// + when group with specified name do not exist
+    ///
+    pub async fn list_managers(
+        conn: &mut PgConnection,
+        name: &String,
+        organization: &String,
+    ) -> Result<Vec<String>, GroupRetrieveError> {
+        Self::retrieve(conn, name, &DEFAULT_ORGANIZATION.to_string()).await?;
+
+        let sql = "SELECT user_login FROM groups_managers WHERE group_name = $1 AND organization_name = $2 ORDER BY user_login;";
+        let rows: Vec<(String,)> = query_as(sql)
+            .bind(name)
+            .bind(organization)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+
+        return Ok(rows.into_iter().map(|(login,)| login).collect());
+    }
+
+    /// ## Group::is_manager
+    ///
+    /// Checks if user with specified login manages a group with specified name inside provided organization
+    ///
+    pub async fn is_manager(
+        conn: &mut PgConnection,
+        name: &String,
+        login: &String,
+        organization: &String,
+    ) -> bool {
+        let sql = "SELECT EXISTS(SELECT 1 FROM groups_managers WHERE group_name = $1 AND user_login = $2 AND organization_name = $3);";
+        let (exists,): (bool,) = query_as(sql)
+            .bind(name)
+            .bind(login)
+            .bind(organization)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+
+        return exists;
+    }
+
+    /// ## Group::grant_manager
+    ///
+    /// Names user with specified login a manager of a group with specified name inside provided organization,
+    /// managers can grant and revoke the group to and from other users of that organization only
+    ///
+    /// Errors:
+    /// + When provided group or user do not exist
+    /// + When the user already manages the group
+    ///
+    pub async fn grant_manager(
+        conn: &mut PgConnection,
+        name: &String,
+        login: &String,
+        organization: &String,
+    ) -> Result<(), GroupGrantManagerError> {
+        if let Err(_) = Group::retrieve(conn, name, &DEFAULT_ORGANIZATION.to_string()).await {
+            log_database_interaction(
+                "Granting group a manager.",
+                json!({ "name": name, "login": login, "organization": organization }),
+                DatabaseOperationLogStatus::Err("Group not found"),
+            );
+            return Err(GroupGrantManagerError::NotFound);
+        }
+
+        if let Err(_) = User::retrieve(conn, login).await {
+            log_database_interaction(
+                "Granting group a manager.",
+                json!({ "name": name, "login": login, "organization": organization }),
+                DatabaseOperationLogStatus::Err("User not found"),
+            );
+            return Err(GroupGrantManagerError::UserNotFound);
+        }
+
+        let sql = "INSERT INTO groups_managers (group_name, user_login, organization_name) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING;";
+        let result = query(sql)
+            .bind(name)
+            .bind(login)
+            .bind(organization)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Granting group a manager.",
+                json!({ "name": name, "login": login, "organization": organization }),
+                DatabaseOperationLogStatus::Err("Already granted"),
+            );
+            return Err(GroupGrantManagerError::AlreadyGranted);
+        }
+
+        log_database_interaction::<String>(
+            "Granting group a manager.",
+            json!({ "name": name, "login": login, "organization": organization }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Group::revoke_manager
+    ///
+    /// Revokes management of a group with specified name from user with specified login inside provided organization
+    ///
+    /// Errors:
+    /// + When provided group do not exist
+    /// + When the user do not manage the group
+    ///
+    pub async fn revoke_manager(
+        conn: &mut PgConnection,
+        name: &String,
+        login: &String,
+        organization: &String,
+    ) -> Result<(), GroupRevokeManagerError> {
+        if let Err(_) = Group::retrieve(conn, name, &DEFAULT_ORGANIZATION.to_string()).await {
+            log_database_interaction(
+                "Revoking a manager from group.",
+                json!({ "name": name, "login": login, "organization": organization }),
+                DatabaseOperationLogStatus::Err("Group not found"),
+            );
+            return Err(GroupRevokeManagerError::NotFound);
+        }
+
+        let sql = "DELETE FROM groups_managers WHERE group_name = $1 AND user_login = $2 AND organization_name = $3;";
+        let result = query(sql)
+            .bind(name)
+            .bind(login)
+            .bind(organization)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Revoking a manager from group.",
+                json!({ "name": name, "login": login, "organization": organization }),
+                DatabaseOperationLogStatus::Err("Not granted"),
+            );
+            return Err(GroupRevokeManagerError::NotGranted);
+        }
+
+        log_database_interaction::<String>(
+            "Revoking a manager from group.",
+            json!({ "name": name, "login": login, "organization": organization }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
     /// ## Group::has_permission
     ///
-    /// Checks if group has a specified permission
+    /// Checks if group has a specified permission in provided organization
     ///
     pub async fn has_permission(
         conn: &mut PgConnection,
         name: &String,
         permission_name: &String,
+        organization: &String,
     ) -> Result<bool, GroupRetrieveError> {
-        let data = Self::retrieve(conn, &name).await?;
+        let data = Self::retrieve(conn, &name, organization).await?;
 
         return Ok(data.permissions.contains(&permission_name));
     }
 
     /// ## Group::grant_permission
     ///
-    /// Grants group a permission with specified name
+    /// Grants (or denies, depending on the effect) group a permission with specified name inside provided organization,
+    /// optionally only applying when provided condition holds and until provided expiry time
     ///
     /// Errors:
     /// + When provided group do not exist
     /// + When the provided permission do not exist
+    /// + When the provided condition cannot be parsed
     ///
     pub async fn grant_permission(
         conn: &mut PgConnection,
         name: &String,
         permission_name: &String,
+        effect: GrantEffect,
+        condition: Option<&String>,
+        expires_at: Option<DateTime<Utc>>,
+        organization: &String,
     ) -> Result<(), GroupGrantError> {
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Some(condition) = condition {
+            if let Err(err) = Condition::parse(condition) {
+                log_database_interaction(
+                    "Granting group a permission.",
+                    json!({ "name": name, "permission_name": permission_name, "condition": condition }),
+                    DatabaseOperationLogStatus::Err("Invalid condition"),
+                );
+                return Err(GroupGrantError::InvalidCondition(err.to_string()));
+            }
+        }
+
         if let Err(_) = Permission::retrieve(conn, permission_name).await {
             log_database_interaction(
                 "Granting group a permission.",
@@ -304,7 +830,7 @@ impl Group {
             return Err(GroupGrantError::PermissionNotFound);
         }
 
-        if let Err(err) = Group::retrieve(conn, name).await {
+        if let Err(err) = Group::retrieve(conn, name, organization).await {
             log_database_interaction(
                 "Granting group a permission.",
                 json!({ "name": name, "permission_name": permission_name }),
@@ -313,58 +839,109 @@ impl Group {
             return Err(GroupGrantError::NotFound);
         }
 
-        let sql = "INSERT INTO groups_permissions (group_name, permission_name) VALUES ($1, $2);";
+        let sql = "INSERT INTO groups_permissions (group_name, permission_name, effect, condition, expires_at, organization_name) VALUES ($1, $2, $3, $4, $5, $6);";
         let result = query(sql)
             .bind(name)
             .bind(permission_name)
+            .bind(effect.to_string())
+            .bind(condition)
+            .bind(expires_at)
+            .bind(organization)
             .execute(&mut *conn)
             .await;
 
+        if let Err(err) = result {
+            log_database_interaction(
+                "Granting group a permission.",
+                json!({ "name": name, "permission_name": permission_name, "effect": effect, "condition": condition, "expires_at": expires_at, "organization": organization }),
+                DatabaseOperationLogStatus::Err(err.to_string()),
+            );
+            return Err(GroupGrantError::Database(err.to_string()));
+        }
+
         log_database_interaction::<String>(
             "Granting group a permission.",
-            json!({ "name": name, "permission_name": permission_name }),
+            json!({ "name": name, "permission_name": permission_name, "effect": effect, "condition": condition, "expires_at": expires_at, "organization": organization }),
             DatabaseOperationLogStatus::Ok,
         );
 
+        OutboxEvent::record(
+            conn,
+            DomainEvent::GroupPermissionGranted {
+                group_name: name.clone(),
+                permission_name: permission_name.clone(),
+                effect,
+                expires_at,
+                organization: organization.clone(),
+            },
+        )
+        .await;
+
+        if let Err(err) = tx.commit().await {
+            return Err(GroupGrantError::Database(err.to_string()));
+        }
+
         return Ok(());
     }
 
     /// ## Group::revoke_permission
     ///
-    /// Revokes a permission from group with specified name
+    /// Revokes a permission (both grants and deny rules) from group with specified name inside provided organization
     ///
     /// Errors:
     /// + When provided group or permission do not exist
+    /// + When both the group and the permission are protected
     ///
     pub async fn revoke_permission(
         conn: &mut PgConnection,
         name: &String,
         permission_name: &String,
+        organization: &String,
     ) -> Result<(), GroupRevokeError> {
-        if let Err(_) = Permission::retrieve(conn, permission_name).await {
-            log_database_interaction(
-                "Revoking a permission from group.",
-                json!({ "name": name, "permission_name": permission_name }),
-                DatabaseOperationLogStatus::Err("Permission not found"),
-            );
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
 
-            return Err(GroupRevokeError::PermissionNotFound);
-        }
+        let permission = match Permission::retrieve(conn, permission_name).await {
+            Ok(permission) => permission,
+            Err(_) => {
+                log_database_interaction(
+                    "Revoking a permission from group.",
+                    json!({ "name": name, "permission_name": permission_name }),
+                    DatabaseOperationLogStatus::Err("Permission not found"),
+                );
+
+                return Err(GroupRevokeError::PermissionNotFound);
+            }
+        };
+
+        let group = match Group::retrieve(conn, name, organization).await {
+            Ok(group) => group,
+            Err(_) => {
+                log_database_interaction(
+                    "Revoking a permission from group.",
+                    json!({ "name": name, "permission_name": permission_name }),
+                    DatabaseOperationLogStatus::Err("Group not found"),
+                );
+
+                return Err(GroupRevokeError::NotFound);
+            }
+        };
 
-        if let Err(_) = Group::retrieve(conn, name).await {
+        if group.protected && permission.protected {
             log_database_interaction(
                 "Revoking a permission from group.",
                 json!({ "name": name, "permission_name": permission_name }),
-                DatabaseOperationLogStatus::Err("Group not found"),
+                DatabaseOperationLogStatus::Err("Protected"),
             );
 
-            return Err(GroupRevokeError::NotFound);
+            return Err(GroupRevokeError::Protected);
         }
 
-        let sql = "DELETE FROM groups_permissions WHERE group_name = $1 AND permission_name = $2;";
+        let sql = "DELETE FROM groups_permissions WHERE group_name = $1 AND permission_name = $2 AND organization_name = $3;";
         let result = query(sql)
             .bind(name)
             .bind(permission_name)
+            .bind(organization)
             .execute(&mut *conn)
             .await
             .unwrap();
@@ -385,6 +962,20 @@ impl Group {
             DatabaseOperationLogStatus::Ok,
         );
 
+        OutboxEvent::record(
+            conn,
+            DomainEvent::GroupPermissionRevoked {
+                group_name: name.clone(),
+                permission_name: permission_name.clone(),
+                organization: organization.clone(),
+            },
+        )
+        .await;
+
+        if let Err(err) = tx.commit().await {
+            return Err(GroupRevokeError::Database(err.to_string()));
+        }
+
         return Ok(());
     }
 }
@@ -16,21 +16,32 @@ use serde::{
 use sqlx::{
   query,
   query_as,
+  Connection,
   FromRow,PgConnection
 };
 use crate::{
-  models::user::{
-    User,
-    UserRetrieveError
+  models::{
+    user::{
+      User,
+      UserRetrieveError
+    },
+    request_context::RequestContext,
+    grant::PermissionDecision,
+    application::Application,
+    organization::Organization,
+    webhook::{Webhook, WebhookEnqueueError}
   },
   util::string::json_value_to_pretty_string
 };
+use serde_json::{json, Value};
 
 #[derive(FromRow, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct LoginSession {
   pub id: i32,
   pub user_login: String,
-  pub token: String
+  pub token: String,
+  /// organization the session's permissions are resolved in
+  pub organization_name: String
 }
 
 impl ToString for LoginSession {
@@ -60,14 +71,20 @@ pub enum LoginSessionInsertError {
   /// Returned when the user attached to the session does not exist
   UserNotFound,
   /// Returned when the token hash cannot be created
-  CannotHash(String)
+  CannotHash(String),
+  /// Returned when the webhook deliveries of the change cannot be queued
+  Webhook(WebhookEnqueueError),
+  /// Returned when the changes cannot be saved in the database
+  Database(String)
 }
 
 impl ToString for LoginSessionInsertError {
   fn to_string(&self) -> String {
     return match self {
       Self::UserNotFound => "Mentioned user not found".to_string(),
-      Self::CannotHash(err) => format!("Cannot hash the token. Details;\n{}", err)
+      Self::CannotHash(err) => format!("Cannot hash the token. Details;\n{}", err),
+      Self::Webhook(err) => err.to_string(),
+      Self::Database(details) => format!("Cannot save the changes: {}", details)
     }
   }
 }
@@ -75,13 +92,19 @@ impl ToString for LoginSessionInsertError {
 #[derive(Debug)]
 pub enum LoginSessionDeleteError {
   /// Returned when the session wasn't deleted because it never existed
-  NotFound
+  NotFound,
+  /// Returned when the webhook deliveries of the change cannot be queued
+  Webhook(WebhookEnqueueError),
+  /// Returned when the changes cannot be saved in the database
+  Database(String)
 }
 
 impl ToString for LoginSessionDeleteError {
   fn to_string(&self) -> String {
     return match self {
-      Self::NotFound => "Login session not found".to_string()
+      Self::NotFound => "Login session not found".to_string(),
+      Self::Webhook(err) => err.to_string(),
+      Self::Database(details) => format!("Cannot save the changes: {}", details)
     }
   }
 }
@@ -92,6 +115,23 @@ pub enum LoginSessionGetUserError {
     NotFound
 }
 
+#[derive(Debug)]
+pub enum LoginSessionSetOrganizationError {
+  /// Returned when the session do not exist
+  NotFound,
+  /// Returned when the user do not belong to the organization
+  NotMember
+}
+
+impl ToString for LoginSessionSetOrganizationError {
+  fn to_string(&self) -> String {
+    return match self {
+      Self::NotFound => "Login session not found".to_string(),
+      Self::NotMember => "The user do not belong to this organization".to_string()
+    }
+  }
+}
+
 impl LoginSession {
   /// ## LoginSession::retrieve
   /// 
@@ -116,13 +156,14 @@ impl LoginSession {
 
     let raw: LoginSession = match q.fetch_one(&mut *conn).await {
       Ok(raw) => raw,
-      Err(_) => return Err(LoginSessionRetrieveError::NotFound)
+      Err(_) => return Err(LoginSessionRetrieveError::NotFound),
     };
 
     let session = LoginSession {
       id: raw.id,
       user_login: raw.user_login,
-      token: raw.token
+      token: raw.token,
+      organization_name: raw.organization_name
     };
 
     return Ok(session);
@@ -144,7 +185,7 @@ impl LoginSession {
         login_sessions (user_login, token)
       VALUES
         ($1, $2)
-      RETURNING token;
+      RETURNING id, token;
     ";
     
     let time_since_epoch = match time::SystemTime::now().duration_since(UNIX_EPOCH) {
@@ -159,47 +200,32 @@ impl LoginSession {
     hasher.input_str(to_hash.as_str());
     let token = hasher.result_str();
 
+    let mut tx = conn.begin().await.unwrap();
+    let conn = &mut *tx;
+
     let result = query_as(sql)
       .bind(&user_login)
       .bind(&token)
       .fetch_one(&mut *conn)
       .await;
 
-    let row: (String,) = match result {
+    let (id, token): (i32, String) = match result {
       Ok(row) => row,
       Err(_) => return Err(LoginSessionInsertError::UserNotFound)
     };
-    let token = row.0;
-
-    return Ok(token);
-  }
-
-
-  /// ## LoginSession::delete
-  /// 
-  /// Deletes a user's login session from the database (e.g. logs out the user)
-  /// 
-  pub async fn delete(
-    conn: &mut PgConnection,
-    session_id: i32
-  ) -> Result<(), LoginSessionDeleteError> {
-    let sql = "DELETE FROM login_sessions WHERE id = $1";
-    let result = query(sql)
-      .bind(&session_id)
-      .execute(&mut *conn)
-      .await;
 
-    let rows_affected = result
-      .unwrap()
-      .rows_affected();
+    if let Err(err) = Webhook::enqueue(conn, "session.created", json!({ "id": id, "user_login": user_login })).await {
+      return Err(LoginSessionInsertError::Webhook(err));
+    }
 
-    if rows_affected == 0 {
-      return Err(LoginSessionDeleteError::NotFound)
+    if let Err(err) = tx.commit().await {
+      return Err(LoginSessionInsertError::Database(err.to_string()));
     }
 
-    return Ok(());
+    return Ok(token);
   }
 
+
   /// ## LoginSession::delete_by_token
   /// 
   /// Deletes a user login session from the database
@@ -211,18 +237,27 @@ impl LoginSession {
     conn: &mut PgConnection,
     token: &String
   ) -> Result<(), LoginSessionDeleteError> {
-    let sql = "DELETE FROM login_sessions WHERE token = $1;";
-    let result = query(sql)
+    let mut tx = conn.begin().await.unwrap();
+    let conn = &mut *tx;
+
+    let sql = "DELETE FROM login_sessions WHERE token = $1 RETURNING id, user_login;";
+    let result: Option<(i32, String)> = query_as(sql)
       .bind(&token)
-      .execute(&mut *conn)
-      .await;
+      .fetch_optional(&mut *conn)
+      .await
+      .unwrap();
 
-    let rows_affected = result
-      .unwrap()
-      .rows_affected();
+    let (id, user_login) = match result {
+      Some(row) => row,
+      None => return Err(LoginSessionDeleteError::NotFound)
+    };
 
-    if rows_affected == 0 {
-      return Err(LoginSessionDeleteError::NotFound)
+    if let Err(err) = Webhook::enqueue(conn, "session.deleted", json!({ "id": id, "user_login": user_login })).await {
+      return Err(LoginSessionDeleteError::Webhook(err));
+    }
+
+    if let Err(err) = tx.commit().await {
+      return Err(LoginSessionDeleteError::Database(err.to_string()));
     }
 
     return Ok(());
@@ -235,7 +270,6 @@ impl LoginSession {
   ///
   /// Errors:
   /// + When a session with specified token do not exist
-  /// + When session with provided token is not commited
   ///
   pub async fn get_user(
     conn: &mut PgConnection,
@@ -253,9 +287,7 @@ impl LoginSession {
     ON
       u.login = ls.user_login
     WHERE
-      ls.token = $1
-      AND
-      ls.status = 'Commited';
+      ls.token = $1;
     ";
     let result = query_as(&sql)
       .bind(&token)
@@ -270,14 +302,159 @@ impl LoginSession {
     return Ok(user);
   }
 
+  /// ## LoginSession::get_user_with_context
+  ///
+  /// Retrieve a user associated with provided session token, along with a copy of provided
+  /// request context resolving grants in the session's active organization
+  ///
+  /// Errors:
+  /// + When a session with specified token do not exist
+  ///
+  pub async fn get_user_with_context(
+    conn: &mut PgConnection,
+    token: &String,
+    context: &RequestContext
+  ) -> Result<(User, RequestContext), LoginSessionGetUserError> {
+    let sql = "
+    SELECT
+      u.login,
+      u.password_hash,
+      u.details,
+      ls.organization_name
+    FROM
+      users u
+    INNER JOIN
+      login_sessions ls
+    ON
+      u.login = ls.user_login
+    WHERE
+      ls.token = $1;
+    ";
+    let result: Result<(String, String, Value, String), _> = query_as(&sql)
+      .bind(&token)
+      .fetch_one(&mut *conn)
+      .await;
+
+    let (login, password_hash, details, organization) = match result {
+      Ok(result) => result,
+      Err(_) => return Err(LoginSessionGetUserError::NotFound)
+    };
+    let user = User {
+      login,
+      password_hash,
+      details
+    };
+
+    return Ok((user, context.with_organization(organization)));
+  }
+
+  /// ## LoginSession::get_organization
+  ///
+  /// Retrieve the active organization of a session with provided token
+  ///
+  /// Errors:
+  /// + When a session with specified token do not exist
+  ///
+  pub async fn get_organization(
+    conn: &mut PgConnection,
+    token: &String
+  ) -> Result<String, LoginSessionRetrieveError> {
+    let session = Self::retrieve(
+      conn,
+      token
+    ).await?;
+
+    return Ok(session.organization_name);
+  }
+
+  /// ## LoginSession::set_organization
+  ///
+  /// Switches the active organization of a session with provided token,
+  /// the user has to belong to the organization
+  ///
+  /// Errors:
+  /// + When a session with specified token do not exist
+  /// + When the user do not belong to the organization
+  ///
+  pub async fn set_organization(
+    conn: &mut PgConnection,
+    token: &String,
+    organization_name: &String
+  ) -> Result<(), LoginSessionSetOrganizationError> {
+    let session = match Self::retrieve(conn, token).await {
+      Ok(session) => session,
+      Err(_) => return Err(LoginSessionSetOrganizationError::NotFound)
+    };
+
+    if !Organization::is_member(conn, organization_name, &session.user_login).await {
+      return Err(LoginSessionSetOrganizationError::NotMember);
+    }
+
+    let sql = "UPDATE login_sessions SET organization_name = $2 WHERE token = $1;";
+    query(sql)
+      .bind(&token)
+      .bind(&organization_name)
+      .execute(&mut *conn)
+      .await
+      .unwrap();
+
+    return Ok(());
+  }
+
+  /// ## LoginSession::context
+  ///
+  /// Returns a copy of provided request context resolving grants
+  /// in the active organization of a session with provided token
+  pub async fn context(
+    conn: &mut PgConnection,
+    token: &String,
+    context: &RequestContext
+  ) -> RequestContext {
+    return match Self::get_organization(conn, token).await {
+      Ok(organization) => context.with_organization(organization),
+      Err(_) => context.clone()
+    };
+  }
+
   /// ## LoginSession::has_permission
   ///
   /// Check if the user associated with provided token 
-  /// has provided permission
+  /// has provided permission in provided request context
   pub async fn has_permission(
     conn: &mut PgConnection,
     token: &String,
-    permission_name: &str
+    permission_name: &str,
+    context: &RequestContext
+  ) -> bool {
+    let (user, context) = match Self::get_user_with_context(
+      conn,
+      &token,
+      context
+    ).await {
+      Ok(user) => user,
+      Err(_) => return false
+    };
+
+    return user
+      .has_permission(
+        conn,
+        permission_name.to_string(),
+        &context
+      )
+      .await;
+  }
+
+  /// ## LoginSession::has_permission_in
+  ///
+  /// Check if the user associated with provided token has provided permission
+  /// in provided organization rather than the session's active one,
+  /// used for the entities shared by all organizations and for managing other organizations
+  pub async fn has_permission_in(
+    conn: &mut PgConnection,
+    token: &String,
+    permission_name: &str,
+    context: &RequestContext,
+    organization: &str
   ) -> bool {
     let user = match Self::get_user(
       conn,
@@ -290,8 +467,243 @@ impl LoginSession {
     return user
       .has_permission(
         conn,
-        permission_name.to_string()
+        permission_name.to_string(),
+        &context.with_organization(organization.to_string())
+      )
+      .await;
+  }
+
+  /// ## LoginSession::check_permission
+  ///
+  /// Resolves provided permission for the user associated with provided token,
+  /// returning the grant that allowed it or the deny rule that blocked it
+  ///
+  /// Errors:
+  /// + When a session with specified token do not exist
+  ///
+  pub async fn check_permission(
+    conn: &mut PgConnection,
+    token: &String,
+    permission_name: &str,
+    context: &RequestContext
+  ) -> Result<PermissionDecision, LoginSessionGetUserError> {
+    let (user, context) = Self::get_user_with_context(
+      conn,
+      &token,
+      context
+    ).await?;
+
+    let decision = user
+      .check_permission(
+        conn,
+        permission_name.to_string(),
+        &context
       )
       .await;
+
+    return Ok(decision);
+  }
+
+  /// ## LoginSession::can_delegate_group
+  ///
+  /// Check if the user associated with provided token
+  /// can grant and revoke provided group in delegated mode
+  pub async fn can_delegate_group(
+    conn: &mut PgConnection,
+    token: &String,
+    group_name: &String,
+    context: &RequestContext
+  ) -> bool {
+    let user = match Self::get_user(
+      conn,
+      &token
+    ).await {
+      Ok(user) => user,
+      Err(_) => return false
+    };
+
+    let context = Self::context(conn, token, context).await;
+
+    return user
+      .can_delegate_group(
+        conn,
+        group_name,
+        &context
+      )
+      .await;
+  }
+
+  /// ## LoginSession::can_administer
+  ///
+  /// Check if the user associated with provided token administers
+  /// the application namespaces of all of the provided permission and group names
+  pub async fn can_administer(
+    conn: &mut PgConnection,
+    token: &String,
+    names: &[&String]
+  ) -> bool {
+    let user = match Self::get_user(
+      conn,
+      &token
+    ).await {
+      Ok(user) => user,
+      Err(_) => return false
+    };
+
+    return Application::can_administer(
+      conn,
+      &user.login,
+      names
+    )
+    .await;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use sqlx::query;
+  use super::*;
+  use crate::{models::{group::Group, permission::Permission}, util::testing};
+
+  #[tokio::test]
+  async fn authenticates_the_existing_sessions() {
+    let Some(pool) = testing::database().await else { return; };
+    let mut conn = pool.acquire().await.unwrap();
+
+    query("INSERT INTO users (login, password_hash, details) VALUES ('jane', '', '{}')")
+      .execute(&mut *conn)
+      .await
+      .unwrap();
+    let token = LoginSession::insert(&mut conn, "jane".to_string()).await.unwrap();
+
+    let user = LoginSession::get_user(&mut conn, &token).await.unwrap();
+    assert_eq!(user.login, "jane");
+
+    LoginSession::delete_by_token(&mut conn, &token).await.unwrap();
+    assert!(LoginSession::get_user(&mut conn, &token).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn resolves_the_user_in_the_active_organization() {
+    let Some(pool) = testing::database().await else { return; };
+    let mut conn = pool.acquire().await.unwrap();
+
+    User::insert_unhashed(&mut conn, &"jane".to_string(), &String::new(), &serde_json::json!({}), &vec![])
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    Organization::insert(&mut conn, &"acme".to_string(), &String::new())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    Organization::add_member(&mut conn, &"acme".to_string(), &"jane".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    let token = LoginSession::insert(&mut conn, "jane".to_string()).await.unwrap();
+
+    let (user, context) = LoginSession::get_user_with_context(&mut conn, &token, &RequestContext::now())
+      .await
+      .unwrap();
+    assert_eq!(user.login, "jane");
+    assert_eq!(context.organization, "default");
+
+    LoginSession::set_organization(&mut conn, &token, &"acme".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    let (_, context) = LoginSession::get_user_with_context(&mut conn, &token, &RequestContext::now())
+      .await
+      .unwrap();
+    assert_eq!(context.organization, "acme");
+
+    assert!(LoginSession::get_user_with_context(&mut conn, &"unknown".to_string(), &RequestContext::now())
+      .await
+      .is_err());
+  }
+
+  #[tokio::test]
+  async fn roots_of_other_organizations_do_not_manage_the_shared_entities() {
+    let Some(pool) = testing::database().await else { return; };
+    let mut conn = pool.acquire().await.unwrap();
+
+    User::insert_unhashed(&mut conn, &"jane".to_string(), &String::new(), &serde_json::json!({}), &vec![])
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    Organization::insert(&mut conn, &"acme".to_string(), &String::new())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    Organization::add_member(&mut conn, &"acme".to_string(), &"jane".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    for permission in ["cauth:groups:delete", "cauth:organizations:update"] {
+      Permission::insert(&mut conn, &permission.to_string(), &String::new()).await.unwrap();
+    }
+    let permissions = vec!["cauth:groups:delete".to_string(), "cauth:organizations:update".to_string()];
+    Group::insert(&mut conn, &"admins".to_string(), &String::new(), &permissions, &"acme".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    User::grant_group(&mut conn, &"jane".to_string(), &"admins".to_string(), None, &"acme".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    let token = LoginSession::insert(&mut conn, "jane".to_string()).await.unwrap();
+    LoginSession::set_organization(&mut conn, &token, &"acme".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+
+    let context = RequestContext::now();
+    assert!(LoginSession::has_permission(&mut conn, &token, "cauth:groups:delete", &context).await);
+    assert!(LoginSession::has_permission_in(&mut conn, &token, "cauth:organizations:update", &context, "acme").await);
+    assert!(!LoginSession::has_permission_in(&mut conn, &token, "cauth:groups:delete", &context, "default").await);
+    assert!(!LoginSession::has_permission_in(&mut conn, &token, "cauth:organizations:update", &context, "default").await);
+  }
+
+  #[tokio::test]
+  async fn managers_delegate_only_in_their_organization() {
+    let Some(pool) = testing::database().await else { return; };
+    let mut conn = pool.acquire().await.unwrap();
+
+    User::insert_unhashed(&mut conn, &"jane".to_string(), &String::new(), &serde_json::json!({}), &vec![])
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    Organization::insert(&mut conn, &"acme".to_string(), &String::new())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    Organization::add_member(&mut conn, &"acme".to_string(), &"jane".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    Group::insert(&mut conn, &"staff".to_string(), &String::new(), &vec![], &"default".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    Group::grant_manager(&mut conn, &"staff".to_string(), &"jane".to_string(), &"default".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    let token = LoginSession::insert(&mut conn, "jane".to_string()).await.unwrap();
+
+    let context = RequestContext::now();
+    assert!(LoginSession::can_delegate_group(&mut conn, &token, &"staff".to_string(), &context).await);
+
+    // the group is not managed by jane in the other organization
+    LoginSession::set_organization(&mut conn, &token, &"acme".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    assert!(!LoginSession::can_delegate_group(&mut conn, &token, &"staff".to_string(), &context).await);
+    let managers = Group::list_managers(&mut conn, &"staff".to_string(), &"acme".to_string())
+      .await
+      .map_err(|err| err.to_string())
+      .unwrap();
+    assert!(managers.is_empty());
   }
 }
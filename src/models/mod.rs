@@ -3,6 +3,15 @@ pub mod group;
 pub mod user;
 pub mod login_session;
 pub mod event;
+pub mod grant;
+pub mod exclusive_group_set;
+pub mod application;
+pub mod organization;
+pub mod policy;
+pub mod request_context;
+pub mod webhook;
+pub mod outbox;
+pub mod audit;
 
 use serde::Deserialize;
 use sqlx::PgConnection;
@@ -10,7 +19,13 @@ pub use crate::models::{
     permission::Permission,
     group::Group,
     user::User,
-    login_session::LoginSession
+    login_session::LoginSession,
+    exclusive_group_set::SodConflict,
+    application::Application,
+    grant::{EffectivePermission, Grant, GrantEffect, PermissionExplanation},
+    request_context::RequestContext,
+    webhook::{Webhook, WebhookDelivery},
+    audit::{AuditEntry, AuditFilter}
 };
 
 #[derive(Deserialize, Clone, Copy)]
@@ -0,0 +1,453 @@
+#![allow(unused)]
+
+use crate::{
+    models::{Order, User},
+    util::{
+        logging::{log_database_interaction, DatabaseOperationLogStatus},
+        string::json_value_to_pretty_string,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{query, query_as, FromRow, PgConnection};
+
+/// Organization every user joins on registration, existing grants were moved to it
+pub const DEFAULT_ORGANIZATION: &str = "default";
+
+/// Tenant isolating group memberships and permission grants of its members
+#[derive(FromRow, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Organization {
+    pub name: String,
+    pub description: String,
+}
+
+impl ToString for Organization {
+    fn to_string(&self) -> String {
+        let formatted = json_value_to_pretty_string(&serde_json::to_value(&self).unwrap());
+
+        return formatted;
+    }
+}
+
+#[derive(Debug)]
+pub enum OrganizationRetrieveError {
+    /// Returned when an organization with specified name do not exist
+    NotFound,
+}
+
+impl ToString for OrganizationRetrieveError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An organization with this name do not exist.",
+        }
+        .to_string();
+    }
+}
+
+#[derive(Debug)]
+pub enum OrganizationInsertError {
+    /// Returned when the organization with specified name already exist
+    /// or either the name or description is too long
+    NameError,
+}
+
+impl ToString for OrganizationInsertError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NameError => "Either organization name or description is too long or organization with this name already exist.",
+        }
+        .to_string();
+    }
+}
+
+#[derive(Debug)]
+pub enum OrganizationDeleteError {
+    /// Returned when an organization with specified name do not exist
+    NotFound,
+    /// Returned when deleting the default organization
+    Protected,
+}
+
+impl ToString for OrganizationDeleteError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An organization with this name do not exist.",
+            Self::Protected => "The default organization cannot be deleted.",
+        }
+        .to_string();
+    }
+}
+
+#[derive(Debug)]
+pub enum OrganizationAddMemberError {
+    /// Returned when an organization with provided name do not exist
+    NotFound,
+    /// Returned when a user with provided login do not exist
+    UserNotFound,
+    /// Returned when the user already belongs to the organization
+    AlreadyMember,
+}
+
+impl ToString for OrganizationAddMemberError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An organization with provided name do not exist",
+            Self::UserNotFound => "A user with provided login do not exist",
+            Self::AlreadyMember => "The user with provided login already belongs to this organization",
+        }
+        .to_string();
+    }
+}
+
+#[derive(Debug)]
+pub enum OrganizationRemoveMemberError {
+    /// Returned when an organization with provided name do not exist
+    NotFound,
+    /// Returned when the user do not belong to the organization
+    NotMember,
+    /// Returned when the user is the last member of a protected group inside the organization
+    Protected,
+}
+
+impl ToString for OrganizationRemoveMemberError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "An organization with provided name do not exist",
+            Self::NotMember => "The user with provided login do not belong to this organization",
+            Self::Protected => "The user is the last member of a protected group inside this organization",
+        }
+        .to_string();
+    }
+}
+
+impl Organization {
+    /// ## Organization::list
+    ///
+    /// Lists number of organizations in specified order with specified offset from the database
+    ///
+    pub async fn list(
+        conn: &mut PgConnection,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+    ) -> Vec<Self> {
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+    SELECT
+      name,
+      COALESCE(description, '') AS description
+    FROM organizations
+    ORDER BY name {}
+    OFFSET {} ROWS
+    LIMIT {};
+    ",
+            order.to_string(),
+            offset,
+            limit
+        );
+
+        return query_as(&sql).fetch_all(&mut *conn).await.unwrap();
+    }
+
+    /// ## Organization::retrieve
+    ///
+    /// Retrieves an organization with specified name
+    ///
+    /// Errors:
+    /// + When the organization do not exist
+    ///
+    pub async fn retrieve(
+        conn: &mut PgConnection,
+        name: &String,
+    ) -> Result<Self, OrganizationRetrieveError> {
+        let sql = "SELECT name, COALESCE(description, '') AS description FROM organizations WHERE name = $1;";
+
+        return match query_as(sql).bind(name).fetch_one(&mut *conn).await {
+            Ok(organization) => Ok(organization),
+            Err(_) => Err(OrganizationRetrieveError::NotFound),
+        };
+    }
+
+    /// ## Organization::insert
+    ///
+    /// Inserts an organization with provided name and description into the database,
+    /// protected groups are granted their permissions from the default organization inside of it
+    ///
+    /// Errors:
+    /// + when an organization with provided name already exist
+    /// + when the name is longer than 255 chars or description is longer than 3000 chars
+    ///
+    pub async fn insert(
+        conn: &mut PgConnection,
+        name: &String,
+        description: &String,
+    ) -> Result<(), OrganizationInsertError> {
+        let sql = "INSERT INTO organizations (name, description) VALUES ($1, $2);";
+        let result = query(sql)
+            .bind(name)
+            .bind(description)
+            .execute(&mut *conn)
+            .await;
+
+        if result.is_err() {
+            log_database_interaction(
+                "Inserting organization into database.",
+                json!({ "name": name }),
+                DatabaseOperationLogStatus::Err("Already exists"),
+            );
+            return Err(OrganizationInsertError::NameError);
+        }
+
+        let sql = "
+    INSERT INTO groups_permissions (group_name, permission_name, effect, condition, expires_at, organization_name)
+    SELECT gp.group_name, gp.permission_name, gp.effect, gp.condition, gp.expires_at, $1
+    FROM groups_permissions gp
+    INNER JOIN groups g ON g.name = gp.group_name
+    WHERE g.protected AND gp.organization_name = $2;
+    ";
+        query(sql)
+            .bind(name)
+            .bind(DEFAULT_ORGANIZATION)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        log_database_interaction::<String>(
+            "Inserting organization into database.",
+            json!({ "name": name, "description": description }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Organization::delete
+    ///
+    /// Deletes an organization with provided name along with all of the memberships,
+    /// group memberships, permission grants and sessions inside of it
+    ///
+    /// Errors:
+    /// + when the organization do not exist
+    /// + when deleting the default organization
+    ///
+    pub async fn delete(conn: &mut PgConnection, name: &String) -> Result<(), OrganizationDeleteError> {
+        if name == DEFAULT_ORGANIZATION {
+            log_database_interaction(
+                "Deleting organization from database.",
+                json!({ "name": name }),
+                DatabaseOperationLogStatus::Err("Default organization is protected"),
+            );
+            return Err(OrganizationDeleteError::Protected);
+        }
+
+        // memberships, grants and sessions are deleted by the foreign keys
+        let sql = "DELETE FROM organizations WHERE name = $1;";
+        let result = query(sql).bind(name).execute(&mut *conn).await.unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Deleting organization from database.",
+                json!({ "name": name }),
+                DatabaseOperationLogStatus::Err("Not found"),
+            );
+            return Err(OrganizationDeleteError::NotFound);
+        }
+
+        log_database_interaction::<String>(
+            "Deleting organization from database.",
+            json!({ "name": name }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Organization::list_members
+    ///
+    /// Lists logins of users belonging to an organization with specified name
+    ///
+    /// Errors:
+    /// + When the organization do not exist
+    ///
+    pub async fn list_members(
+        conn: &mut PgConnection,
+        name: &String,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, OrganizationRetrieveError> {
+        Self::retrieve(conn, name).await?;
+
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+    SELECT
+      user_login
+    FROM organizations_users
+    WHERE organization_name = $1
+    ORDER BY user_login {}
+    OFFSET {} ROWS
+    LIMIT {};
+    ",
+            order.to_string(),
+            offset,
+            limit
+        );
+        let rows: Vec<(String,)> = query_as(&sql).bind(name).fetch_all(&mut *conn).await.unwrap();
+
+        return Ok(rows.into_iter().map(|(login,)| login).collect());
+    }
+
+    /// ## Organization::list_for_user
+    ///
+    /// Lists names of the organizations user with specified login belongs to
+    ///
+    pub async fn list_for_user(conn: &mut PgConnection, login: &String) -> Vec<String> {
+        let sql = "SELECT organization_name FROM organizations_users WHERE user_login = $1 ORDER BY organization_name;";
+        let rows: Vec<(String,)> = query_as(sql).bind(login).fetch_all(&mut *conn).await.unwrap();
+
+        return rows.into_iter().map(|(name,)| name).collect();
+    }
+
+    /// ## Organization::is_member
+    ///
+    /// Checks if user with specified login belongs to an organization with specified name
+    ///
+    pub async fn is_member(conn: &mut PgConnection, name: &String, login: &String) -> bool {
+        let sql = "SELECT EXISTS(SELECT 1 FROM organizations_users WHERE organization_name = $1 AND user_login = $2);";
+        let (exists,): (bool,) = query_as(sql)
+            .bind(name)
+            .bind(login)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+
+        return exists;
+    }
+
+    /// ## Organization::add_member
+    ///
+    /// Adds user with specified login to an organization with specified name,
+    /// it can be granted groups and permissions inside of it then
+    ///
+    /// Errors:
+    /// + When provided organization or user do not exist
+    /// + When the user already belongs to the organization
+    ///
+    pub async fn add_member(
+        conn: &mut PgConnection,
+        name: &String,
+        login: &String,
+    ) -> Result<(), OrganizationAddMemberError> {
+        if Self::retrieve(conn, name).await.is_err() {
+            log_database_interaction(
+                "Adding organization a member.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("Organization not found"),
+            );
+            return Err(OrganizationAddMemberError::NotFound);
+        }
+
+        if User::retrieve(conn, login).await.is_err() {
+            log_database_interaction(
+                "Adding organization a member.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("User not found"),
+            );
+            return Err(OrganizationAddMemberError::UserNotFound);
+        }
+
+        let sql = "INSERT INTO organizations_users (organization_name, user_login) VALUES ($1, $2) ON CONFLICT DO NOTHING;";
+        let result = query(sql)
+            .bind(name)
+            .bind(login)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Adding organization a member.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("Already a member"),
+            );
+            return Err(OrganizationAddMemberError::AlreadyMember);
+        }
+
+        log_database_interaction::<String>(
+            "Adding organization a member.",
+            json!({ "name": name, "login": login }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Organization::remove_member
+    ///
+    /// Removes user with specified login from an organization with specified name,
+    /// along with its group memberships, permission grants and sessions inside of it
+    ///
+    /// Errors:
+    /// + When provided organization do not exist
+    /// + When the user do not belong to the organization
+    /// + When the user is the last member of a protected group inside the organization
+    ///
+    pub async fn remove_member(
+        conn: &mut PgConnection,
+        name: &String,
+        login: &String,
+    ) -> Result<(), OrganizationRemoveMemberError> {
+        if Self::retrieve(conn, name).await.is_err() {
+            log_database_interaction(
+                "Removing a member from organization.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("Organization not found"),
+            );
+            return Err(OrganizationRemoveMemberError::NotFound);
+        }
+
+        if !Self::is_member(conn, name, login).await {
+            log_database_interaction(
+                "Removing a member from organization.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("Not a member"),
+            );
+            return Err(OrganizationRemoveMemberError::NotMember);
+        }
+
+        if !User::list_sole_protected_groups(conn, login, Some(name)).await.is_empty() {
+            log_database_interaction(
+                "Removing a member from organization.",
+                json!({ "name": name, "login": login }),
+                DatabaseOperationLogStatus::Err("User is the last member of a protected group."),
+            );
+            return Err(OrganizationRemoveMemberError::Protected);
+        }
+
+        for table in ["users_groups", "users_permissions", "groups_managers", "login_sessions", "organizations_users"] {
+            let sql = format!("DELETE FROM {} WHERE organization_name = $1 AND user_login = $2;", table);
+            query(&sql)
+                .bind(name)
+                .bind(login)
+                .execute(&mut *conn)
+                .await
+                .unwrap();
+        }
+
+        log_database_interaction::<String>(
+            "Removing a member from organization.",
+            json!({ "name": name, "login": login }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{
+    prelude::FromRow,
+    query,
+    query_as,
+    PgConnection
+};
+use crate::models::GrantEffect;
+
+/// Key of the advisory lock held by the publisher, so the events are published in order by a single instance
+const PUBLISHER_LOCK: i64 = 0x63617574685f6f62;
+
+/// Change of the service's data, published to the configured broker
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum DomainEvent {
+    UserRegistered {
+        login: String
+    },
+    UserDeleted {
+        login: String
+    },
+    GroupGranted {
+        login: String,
+        group_name: String,
+        expires_at: Option<DateTime<Utc>>,
+        organization: String
+    },
+    GroupRevoked {
+        login: String,
+        group_name: String,
+        organization: String
+    },
+    GroupCreated {
+        name: String,
+        description: String
+    },
+    GroupRenamed {
+        name: String,
+        new_name: String
+    },
+    GroupDeleted {
+        name: String
+    },
+    PermissionCreated {
+        name: String,
+        description: String
+    },
+    PermissionRenamed {
+        name: String,
+        new_name: String
+    },
+    PermissionDeleted {
+        name: String
+    },
+    GroupPermissionGranted {
+        group_name: String,
+        permission_name: String,
+        effect: GrantEffect,
+        expires_at: Option<DateTime<Utc>>,
+        organization: String
+    },
+    GroupPermissionRevoked {
+        group_name: String,
+        permission_name: String,
+        organization: String
+    },
+    UserPermissionGranted {
+        login: String,
+        permission_name: String,
+        effect: GrantEffect,
+        expires_at: Option<DateTime<Utc>>,
+        organization: String
+    },
+    UserPermissionRevoked {
+        login: String,
+        permission_name: String,
+        organization: String
+    }
+}
+
+/// Domain event recorded in the outbox, waiting to be published
+#[derive(FromRow)]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub event_type: String,
+    pub payload: Value,
+    pub created_at: DateTime<Utc>
+}
+
+impl OutboxEvent {
+    /// ## OutboxEvent::record
+    ///
+    /// Records the domain event in the outbox. <br>
+    /// Has to be called with the connection (transaction) the change is made with,
+    /// so the event is published only when the change is committed
+    ///
+    pub async fn record(
+        conn: &mut PgConnection,
+        event: DomainEvent
+    ) {
+        // domain events hold only serializable data, so this will never error
+        let mut value = serde_json::to_value(&event).unwrap();
+        let event_type = value["type"].take();
+        let payload = value["data"].take();
+
+        let sql = "
+        INSERT INTO
+            outbox_events (event_type, payload)
+        VALUES
+            ($1, $2);
+        ";
+
+        query(sql)
+            .bind(&event_type.as_str())
+            .bind(&payload)
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## OutboxEvent::claim
+    ///
+    /// Retrieves the oldest events in the order they were recorded,
+    /// None when another publisher is publishing them. <br>
+    /// Should be run in a transaction, the publisher is the only one until it ends
+    ///
+    pub async fn claim(
+        conn: &mut PgConnection,
+        limit: i64
+    ) -> Option<Vec<OutboxEvent>> {
+        let sql = "SELECT pg_try_advisory_xact_lock($1);";
+        let (locked,): (bool,) = query_as(sql)
+            .bind(&PUBLISHER_LOCK)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+
+        if !locked {
+            return None;
+        }
+
+        let sql = "
+        SELECT
+            *
+        FROM
+            outbox_events
+        ORDER BY id
+        LIMIT $1;
+        ";
+
+        let events = query_as(sql)
+            .bind(&limit)
+            .fetch_all(conn)
+            .await
+            .unwrap();
+
+        return Some(events);
+    }
+
+    /// ## OutboxEvent::delete
+    ///
+    /// Deletes the published events
+    ///
+    pub async fn delete(
+        conn: &mut PgConnection,
+        ids: &Vec<i64>
+    ) {
+        let sql = "DELETE FROM outbox_events WHERE id = ANY($1);";
+        query(sql)
+            .bind(&ids)
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+}
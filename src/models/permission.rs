@@ -1,22 +1,44 @@
 #![allow(unused)]
 
 use crate::{
-    models::{Group, LoginSession, Order},
+    models::{
+        outbox::{DomainEvent, OutboxEvent},
+        webhook::{Webhook, WebhookEnqueueError},
+        Group, LoginSession, Order,
+    },
     util::{
         logging::{log_database_interaction, DatabaseOperationLogStatus},
         string::json_value_to_pretty_string,
     },
 };
+use chrono::{DateTime, Utc};
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{query, query_as, FromRow, PgConnection};
+use sqlx::{query, query_as, Connection, FromRow, PgConnection};
 use std::error::Error;
 
+const INVALID_NAME_DETAILS: &str = "\"*\" is reserved for wildcards, which have to end with it after a namespace (e.g. \"billing:*\").";
+
+/// ## is_valid_permission_name
+///
+/// Checks that "*" is only used at the end of a "{namespace}:*" wildcard,
+/// so that no permission (e.g. "*" or ":*") matches every other one
+///
+pub fn is_valid_permission_name(name: &str) -> bool {
+    return match name.find('*') {
+        None => true,
+        Some(position) => {
+            position == name.len() - 1 && name[..position].ends_with(':') && position > 1
+        }
+    };
+}
+
 #[derive(FromRow, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub struct Permission {
     pub name: String,
     pub description: String,
+    pub protected: bool,
 }
 
 impl ToString for Permission {
@@ -27,6 +49,27 @@ impl ToString for Permission {
     }
 }
 
+/// Group a permission is granted to (or denied for), as listed by Permission::list_groups
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug)]
+pub struct PermissionGroupHolder {
+    pub group_name: String,
+    pub effect: String,
+    pub condition: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// User holding a permission, either through one of their groups ("group" source)
+/// or directly ("user" source), as listed by Permission::list_users
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug)]
+pub struct PermissionUserHolder {
+    pub login: String,
+    pub source_type: String,
+    pub source_name: String,
+    pub effect: String,
+    pub condition: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug)]
 pub enum PermissionListError {}
 
@@ -55,25 +98,69 @@ pub enum PermissionInsertError {
     /// Returned when the permission either has too long name or description
     /// or when a permission with provided name already exist
     NameError,
+    /// Returned when the name contains a "*" other than the one of a "{namespace}:*" wildcard
+    InvalidName,
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for PermissionInsertError {
     fn to_string(&self) -> String {
         return match self {
-      Self::NameError => "Either permission name or description is too long or permission with this name already exist.".to_string()
+      Self::NameError => "Either permission name or description is too long or permission with this name already exist.".to_string(),
+      Self::InvalidName => INVALID_NAME_DETAILS.to_string(),
+      Self::Database(details) => format!("Cannot save the changes: {}", details)
     };
     }
 }
 
+#[derive(Debug)]
+pub enum PermissionUpdateError {
+    /// Returned when the permission with specified name do not exist
+    NotFound,
+    /// Returned when the new name or description is too long
+    /// or when a permission with the new name already exist
+    NameError,
+    /// Returned when the new name contains a "*" other than the one of a "{namespace}:*" wildcard
+    InvalidName,
+    /// Returned when renaming a protected permission
+    Protected,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
+}
+
+impl ToString for PermissionUpdateError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "Permission with this name cannot be found.".to_string(),
+            Self::NameError => "Either new permission name or description is too long or permission with this name already exist.".to_string(),
+            Self::InvalidName => INVALID_NAME_DETAILS.to_string(),
+            Self::Protected => "This permission is protected and cannot be renamed.".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
+    }
+}
+
 #[derive(Debug)]
 pub enum PermissionDeleteError {
     /// Returned when the permission with specified name do not exist
     NotFound,
+    /// Returned when the permission is protected
+    Protected,
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for PermissionDeleteError {
     fn to_string(&self) -> String {
-        return "".to_string();
+        return match self {
+            Self::NotFound => "Permission with this name cannot be found.".to_string(),
+            Self::Protected => "This permission is protected and cannot be deleted.".to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
     }
 }
 
@@ -123,6 +210,128 @@ impl Permission {
         };
     }
 
+    /// ## Permission::list_groups
+    ///
+    /// Lists number of groups the permission with specified name is granted to (including deny rules)
+    /// inside provided organization in specified order with specified offset, expired grants are omitted
+    ///
+    /// Errors:
+    /// + when permission with specified name do not exist
+    ///
+    pub async fn list_groups(
+        conn: &mut PgConnection,
+        name: &String,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+        organization: &String,
+    ) -> Result<Vec<PermissionGroupHolder>, PermissionRetrieveError> {
+        Self::retrieve(conn, name).await?;
+
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+    SELECT
+      group_name,
+      effect,
+      condition,
+      expires_at
+    FROM groups_permissions
+    WHERE permission_name = $1
+      AND organization_name = $2
+      AND (expires_at IS NULL OR expires_at > NOW())
+    ORDER BY group_name {}
+    OFFSET {} ROWS
+    LIMIT {};
+    ",
+            order.to_string(),
+            offset,
+            limit
+        );
+        let result = query_as(&sql)
+            .bind(name)
+            .bind(organization)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+
+        return Ok(result);
+    }
+
+    /// ## Permission::list_users
+    ///
+    /// Lists number of users holding the permission with specified name (including deny rules),
+    /// through their groups or directly inside provided organization, in specified order with specified offset,
+    /// expired grants are omitted
+    ///
+    /// Errors:
+    /// + when permission with specified name do not exist
+    ///
+    pub async fn list_users(
+        conn: &mut PgConnection,
+        name: &String,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+        organization: &String,
+    ) -> Result<Vec<PermissionUserHolder>, PermissionRetrieveError> {
+        Self::retrieve(conn, name).await?;
+
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+    SELECT * FROM (
+      SELECT
+        ug.user_login AS login,
+        'group' AS source_type,
+        gp.group_name AS source_name,
+        gp.effect,
+        gp.condition,
+        LEAST(ug.expires_at, gp.expires_at) AS expires_at
+      FROM groups_permissions gp
+      INNER JOIN users_groups ug ON ug.group_name = gp.group_name
+        AND ug.organization_name = gp.organization_name
+      WHERE gp.permission_name = $1
+        AND gp.organization_name = $2
+        AND (ug.expires_at IS NULL OR ug.expires_at > NOW())
+        AND (gp.expires_at IS NULL OR gp.expires_at > NOW())
+      UNION ALL
+      SELECT
+        user_login AS login,
+        'user' AS source_type,
+        user_login AS source_name,
+        effect,
+        condition,
+        expires_at
+      FROM users_permissions
+      WHERE permission_name = $1
+        AND organization_name = $2
+        AND (expires_at IS NULL OR expires_at > NOW())
+    ) holders
+    ORDER BY login {}, source_type, source_name
+    OFFSET {} ROWS
+    LIMIT {};
+    ",
+            order.to_string(),
+            offset,
+            limit
+        );
+        let result = query_as(&sql)
+            .bind(name)
+            .bind(organization)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+
+        return Ok(result);
+    }
+
     /// ## Permission::insert
     ///
     /// Inserts a permission with provided data into the database <br>
@@ -136,6 +345,19 @@ impl Permission {
         name: &String,
         description: &String,
     ) -> Result<(), PermissionInsertError> {
+        if !is_valid_permission_name(name) {
+            log_database_interaction(
+                "Inserting permission into database.",
+                json!({ "name": name, "description": description }),
+                DatabaseOperationLogStatus::Err("Invalid name"),
+            );
+            return Err(PermissionInsertError::InvalidName);
+        }
+
+        // nested, so the outbox event is committed along with the change even outside of a transaction
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
         let sql = "INSERT INTO permissions (name, description) VALUES ($1, $2);".to_string();
         let q = query(&sql).bind(&name).bind(&description);
 
@@ -146,6 +368,20 @@ impl Permission {
                     json!({ "name": name, "description": description }),
                     DatabaseOperationLogStatus::Ok,
                 );
+
+                OutboxEvent::record(
+                    conn,
+                    DomainEvent::PermissionCreated {
+                        name: name.clone(),
+                        description: description.clone(),
+                    },
+                )
+                .await;
+
+                if let Err(err) = tx.commit().await {
+                    return Err(PermissionInsertError::Database(err.to_string()));
+                }
+
                 return Ok(());
             }
             Err(err) => {
@@ -159,14 +395,147 @@ impl Permission {
         };
     }
 
+    /// ## Permission::update
+    ///
+    /// Updates description and/or renames a permission with provided name,
+    /// renaming cascades to all of the groups and users the permission is granted to
+    ///
+    /// Errors:
+    /// + when permission with provided name do not exist
+    /// + when a permission with the new name already exist
+    /// + when the new name is longer than 255 chars or description is longer than 3000 chars
+    /// + when renaming a protected permission
+    ///
+    pub async fn update(
+        conn: &mut PgConnection,
+        name: &String,
+        new_name: Option<&String>,
+        description: Option<&String>,
+    ) -> Result<(), PermissionUpdateError> {
+        if new_name.is_some_and(|new_name| !is_valid_permission_name(new_name)) {
+            log_database_interaction(
+                "Updating permission in database.",
+                json!({ "name": name, "new_name": new_name, "description": description }),
+                DatabaseOperationLogStatus::Err("Invalid name"),
+            );
+            return Err(PermissionUpdateError::InvalidName);
+        }
+
+        // nested, so the outbox event and the deliveries are committed along with the rename
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Ok(permission) = Self::retrieve(conn, name).await {
+            if permission.protected && new_name.is_some_and(|new_name| new_name != name) {
+                log_database_interaction(
+                    "Updating permission in database.",
+                    json!({ "name": name, "new_name": new_name, "description": description }),
+                    DatabaseOperationLogStatus::Err("Protected"),
+                );
+                return Err(PermissionUpdateError::Protected);
+            }
+        }
+
+        let sql = "UPDATE permissions SET name = COALESCE($2, name), description = COALESCE($3, description) WHERE name = $1;";
+        let result = query(sql)
+            .bind(name)
+            .bind(new_name)
+            .bind(description)
+            .execute(&mut *conn)
+            .await;
+
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {
+                log_database_interaction::<String>(
+                    "Updating permission in database.",
+                    json!({ "name": name, "new_name": new_name, "description": description }),
+                    DatabaseOperationLogStatus::Ok,
+                );
+
+                if let Some(new_name) = new_name.filter(|new_name| *new_name != name) {
+                    let enqueued = Webhook::enqueue(
+                        conn,
+                        "permission.renamed",
+                        json!({ "name": name, "new_name": new_name }),
+                    )
+                    .await;
+                    if let Err(err) = enqueued {
+                        return Err(PermissionUpdateError::Webhook(err));
+                    }
+                    OutboxEvent::record(
+                        conn,
+                        DomainEvent::PermissionRenamed {
+                            name: name.clone(),
+                            new_name: new_name.clone(),
+                        },
+                    )
+                    .await;
+                }
+
+                if let Err(err) = tx.commit().await {
+                    return Err(PermissionUpdateError::Database(err.to_string()));
+                }
+
+                return Ok(());
+            }
+            Ok(_) => {
+                log_database_interaction(
+                    "Updating permission in database.",
+                    json!({ "name": name, "new_name": new_name, "description": description }),
+                    DatabaseOperationLogStatus::Err("Not found"),
+                );
+                return Err(PermissionUpdateError::NotFound);
+            }
+            Err(_) => {
+                log_database_interaction(
+                    "Updating permission in database.",
+                    json!({ "name": name, "new_name": new_name, "description": description }),
+                    DatabaseOperationLogStatus::Err("Already exists"),
+                );
+                return Err(PermissionUpdateError::NameError);
+            }
+        };
+    }
+
+    /// ## Permission::protect
+    ///
+    /// Marks a permission with provided name as protected from being deleted or renamed
+    ///
+    pub async fn protect(conn: &mut PgConnection, name: &String) {
+        let sql = "UPDATE permissions SET protected = TRUE WHERE name = $1;";
+        let _ = query(sql).bind(name).execute(&mut *conn).await;
+    }
+
     /// ## Permission::delete
     ///
-    /// Deletes a permission with provided name from the database
+    /// Deletes a permission with provided name and all of it's grants from the database
+    ///
+    /// Errors:
+    /// + when permission with provided name do not exist
+    /// + when the permission is protected
     ///
     pub async fn delete(
         conn: &mut PgConnection,
         name: &String,
     ) -> Result<(), PermissionDeleteError> {
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Ok(Permission { protected: true, .. }) = Self::retrieve(conn, name).await {
+            log_database_interaction(
+                "Deleting permission from database.",
+                json!({ "name": name }),
+                DatabaseOperationLogStatus::Err("Protected"),
+            );
+            return Err(PermissionDeleteError::Protected);
+        }
+
+        let sql = "DELETE FROM groups_permissions WHERE permission_name = $1;";
+        let _ = query(sql).bind(&name).execute(&mut *conn).await;
+
+        let sql = "DELETE FROM users_permissions WHERE permission_name = $1;";
+        let _ = query(sql).bind(&name).execute(&mut *conn).await;
+
         let sql = "DELETE FROM permissions WHERE name = $1;";
         let result = query(&sql).bind(&name).execute(&mut *conn).await.unwrap();
 
@@ -176,6 +545,13 @@ impl Permission {
                 json!({ "name": name }),
                 DatabaseOperationLogStatus::Ok,
             );
+
+            OutboxEvent::record(conn, DomainEvent::PermissionDeleted { name: name.clone() }).await;
+
+            if let Err(err) = tx.commit().await {
+                return Err(PermissionDeleteError::Database(err.to_string()));
+            }
+
             return Ok(());
         } else {
             log_database_interaction(
@@ -187,3 +563,82 @@ impl Permission {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing;
+
+    #[test]
+    fn reserves_the_asterisk_for_wildcards() {
+        for name in ["billing:refund", "billing:*", "shop:orders:*", "a:*"] {
+            assert!(is_valid_permission_name(name), "{}", name);
+        }
+        for name in ["*", ":*", "billing*", "bill*ing:refund", "billing:*:refund", "billing:**"] {
+            assert!(!is_valid_permission_name(name), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_reserved_names() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+
+        let result = Permission::insert(&mut conn, &"*".to_string(), &String::new()).await;
+        assert!(matches!(result, Err(PermissionInsertError::InvalidName)));
+
+        Permission::insert(&mut conn, &"billing:*".to_string(), &String::new()).await.unwrap();
+        let result = Permission::update(&mut conn, &"billing:*".to_string(), Some(&"*".to_string()), None).await;
+        assert!(matches!(result, Err(PermissionUpdateError::InvalidName)));
+    }
+
+    #[tokio::test]
+    async fn records_the_renames() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+
+        Webhook::insert(&mut conn, &"http://127.0.0.1:1/hook".to_string(), &vec![], None)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        Permission::insert(&mut conn, &"billing:refund".to_string(), &String::new()).await.unwrap();
+        Group::insert(&mut conn, &"staff".to_string(), &String::new(), &vec![], &"default".to_string())
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        query("DELETE FROM outbox_events;").execute(&mut *conn).await.unwrap();
+        query("DELETE FROM webhook_deliveries;").execute(&mut *conn).await.unwrap();
+
+        Permission::update(&mut conn, &"billing:refund".to_string(), Some(&"billing:refunds".to_string()), None)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        Group::update(&mut conn, &"staff".to_string(), Some(&"employees".to_string()), None)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        // only the description changes
+        Group::update(&mut conn, &"employees".to_string(), None, Some(&"Everyone".to_string()))
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        let recorded: Vec<(String, serde_json::Value)> = query_as("SELECT event_type, payload FROM outbox_events ORDER BY id;")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(recorded, vec![
+            ("PermissionRenamed".to_string(), json!({ "name": "billing:refund", "new_name": "billing:refunds" })),
+            ("GroupRenamed".to_string(), json!({ "name": "staff", "new_name": "employees" })),
+        ]);
+
+        let queued: Vec<(String, serde_json::Value)> = query_as("SELECT event_type, payload FROM webhook_deliveries ORDER BY id;")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(queued, vec![
+            ("permission.renamed".to_string(), json!({ "name": "billing:refund", "new_name": "billing:refunds" })),
+            ("group.renamed".to_string(), json!({ "name": "staff", "new_name": "employees" })),
+        ]);
+    }
+}
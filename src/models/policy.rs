@@ -0,0 +1,656 @@
+#![allow(unused)]
+
+use crate::{
+    models::{GrantEffect, Group, Permission, User},
+    util::condition::Condition,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, PgConnection};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Desired state of permissions, groups, their grants and optionally user memberships,
+/// read by `cauth admin apply` and written by `cauth admin export-policy`
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct Policy {
+    #[serde(default)]
+    pub permissions: Vec<PolicyPermission>,
+    #[serde(default)]
+    pub groups: Vec<PolicyGroup>,
+    /// groups of the listed users, users not listed are left untouched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub users: Option<BTreeMap<String, Vec<String>>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PolicyPermission {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PolicyGroup {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub permissions: Vec<PolicyGrant>,
+}
+
+/// Grant of a permission to a group, either just the permission name for plain grants
+/// or the full rule for deny rules, conditional and expiring grants
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PolicyGrant {
+    Name(String),
+    Rule(PolicyGrantRule),
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct PolicyGrantRule {
+    pub name: String,
+    #[serde(default = "default_effect")]
+    pub effect: GrantEffect,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn default_effect() -> GrantEffect {
+    return GrantEffect::Allow;
+}
+
+impl PolicyGrant {
+    fn rule(&self) -> PolicyGrantRule {
+        return match self {
+            Self::Name(name) => PolicyGrantRule {
+                name: name.clone(),
+                effect: GrantEffect::Allow,
+                condition: None,
+                expires_at: None,
+            },
+            Self::Rule(rule) => rule.clone(),
+        };
+    }
+
+    fn from_rule(rule: PolicyGrantRule) -> Self {
+        if rule.effect == GrantEffect::Allow && rule.condition.is_none() && rule.expires_at.is_none() {
+            return Self::Name(rule.name);
+        }
+
+        return Self::Rule(rule);
+    }
+}
+
+impl PolicyGrantRule {
+    /// key the rules of a single permission are compared and ordered by
+    fn key(&self) -> (String, Option<String>, Option<DateTime<Utc>>) {
+        return (self.effect.to_string(), self.condition.clone(), self.expires_at);
+    }
+}
+
+impl ToString for PolicyGrantRule {
+    fn to_string(&self) -> String {
+        let mut formatted = format!("{} ({})", self.name, self.effect.to_string());
+
+        if let Some(condition) = &self.condition {
+            formatted.push_str(&format!(" if {}", condition));
+        }
+        if let Some(expires_at) = &self.expires_at {
+            formatted.push_str(&format!(" until {}", expires_at.to_rfc3339()));
+        }
+
+        return formatted;
+    }
+}
+
+/// Single change needed to reconcile the database with a policy
+#[derive(Clone, Debug)]
+pub enum PolicyChange {
+    InsertPermission { name: String, description: String },
+    UpdatePermission { name: String, description: String },
+    DeletePermission { name: String },
+    InsertGroup { name: String, description: String },
+    UpdateGroup { name: String, description: String },
+    DeleteGroup { name: String },
+    GrantGroupPermission { group_name: String, rule: PolicyGrantRule },
+    RevokeGroupPermission { group_name: String, permission_name: String },
+    GrantUserGroup { login: String, group_name: String },
+    RevokeUserGroup { login: String, group_name: String },
+}
+
+impl ToString for PolicyChange {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::InsertPermission { name, .. } => format!("+ insert permission {}", name),
+            Self::UpdatePermission { name, .. } => format!("~ update description of permission {}", name),
+            Self::DeletePermission { name } => format!("- delete permission {}", name),
+            Self::InsertGroup { name, .. } => format!("+ insert group {}", name),
+            Self::UpdateGroup { name, .. } => format!("~ update description of group {}", name),
+            Self::DeleteGroup { name } => format!("- delete group {}", name),
+            Self::GrantGroupPermission { group_name, rule } => {
+                format!("+ grant group {} permission {}", group_name, rule.to_string())
+            }
+            Self::RevokeGroupPermission { group_name, permission_name } => {
+                format!("- revoke permission {} from group {}", permission_name, group_name)
+            }
+            Self::GrantUserGroup { login, group_name } => format!("+ grant user {} group {}", login, group_name),
+            Self::RevokeUserGroup { login, group_name } => format!("- revoke group {} from user {}", group_name, login),
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum PolicyPlanError {
+    /// Returned when a permission or group is declared more than once
+    Duplicate(String),
+    /// Returned when a group is granted a permission which is neither declared nor protected
+    PermissionNotDeclared { group_name: String, permission_name: String },
+    /// Returned when a grant condition cannot be parsed
+    InvalidCondition { group_name: String, permission_name: String, details: String },
+    /// Returned when a listed user do not exist
+    UserNotFound(String),
+    /// Returned when a user is granted a group which is neither declared nor protected
+    GroupNotDeclared { login: String, group_name: String },
+}
+
+impl ToString for PolicyPlanError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Duplicate(name) => format!("\"{}\" is declared more than once", name),
+            Self::PermissionNotDeclared { group_name, permission_name } => format!(
+                "Group \"{}\" is granted permission \"{}\" which is not declared",
+                group_name, permission_name
+            ),
+            Self::InvalidCondition { group_name, permission_name, details } => format!(
+                "Invalid condition of permission \"{}\" granted to group \"{}\": {}",
+                permission_name, group_name, details
+            ),
+            Self::UserNotFound(login) => format!("User \"{}\" do not exist", login),
+            Self::GroupNotDeclared { login, group_name } => format!(
+                "User \"{}\" is granted group \"{}\" which is not declared",
+                login, group_name
+            ),
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum PolicyApplyError {
+    /// Returned when the policy cannot be planned
+    Plan(PolicyPlanError),
+    /// Returned when one of the changes fails, with the change and the reason
+    Change(String, String),
+}
+
+impl ToString for PolicyApplyError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Plan(err) => err.to_string(),
+            Self::Change(change, details) => format!("Cannot apply \"{}\": {}", change, details),
+        };
+    }
+}
+
+impl From<PolicyPlanError> for PolicyApplyError {
+    fn from(err: PolicyPlanError) -> Self {
+        return Self::Plan(err);
+    }
+}
+
+/// Current rules of a group, by permission name
+type GroupRules = BTreeMap<String, Vec<PolicyGrantRule>>;
+
+/// Group name, permission name, effect, condition and expiration of a grant
+type RuleRow = (String, String, String, Option<String>, Option<DateTime<Utc>>);
+
+impl Policy {
+    /// ## Policy::export
+    ///
+    /// Exports all of the permissions, groups and their grants (and user memberships when asked to)
+    /// in provided organization from the database, expired grants and memberships are omitted
+    ///
+    pub async fn export(conn: &mut PgConnection, include_users: bool, organization: &String) -> Self {
+        let permissions = Self::current_permissions(conn).await
+            .into_values()
+            .map(|permission| PolicyPermission {
+                name: permission.name,
+                description: permission.description,
+            })
+            .collect();
+
+        let mut rules = Self::current_rules(conn, organization).await;
+        let groups = Self::current_groups(conn).await
+            .into_iter()
+            .map(|(name, (description, _))| {
+                let permissions = rules
+                    .remove(&name)
+                    .unwrap_or_default()
+                    .into_values()
+                    .flatten()
+                    .map(PolicyGrant::from_rule)
+                    .collect();
+
+                PolicyGroup { name, description, permissions }
+            })
+            .collect();
+
+        let users = match include_users {
+            true => Some(
+                Self::current_memberships(conn, organization).await
+                    .into_iter()
+                    .map(|(login, groups)| (login, groups.into_iter().collect()))
+                    .collect(),
+            ),
+            false => None,
+        };
+
+        return Self { permissions, groups, users };
+    }
+
+    /// ## Policy::plan
+    ///
+    /// Lists changes needed to reconcile the database with the policy in provided organization <br>
+    /// Permissions and groups are shared by every organization, so the ones which are not declared
+    /// are deleted only when pruning, unless protected <br>
+    /// Protected permissions are also never revoked from protected groups
+    ///
+    /// Errors:
+    /// + when a permission or group is declared more than once
+    /// + when a group is granted an undeclared permission or a user an undeclared group
+    /// + when a grant condition cannot be parsed
+    /// + when a listed user do not exist
+    ///
+    pub async fn plan(
+        &self,
+        conn: &mut PgConnection,
+        organization: &String,
+        prune: bool,
+    ) -> Result<Vec<PolicyChange>, PolicyPlanError> {
+        let current_permissions = Self::current_permissions(conn).await;
+        let current_groups = Self::current_groups(conn).await;
+        let mut current_rules = Self::current_rules(conn, organization).await;
+
+        let mut permission_changes = vec![];
+        let mut group_changes = vec![];
+        let mut revokes = vec![];
+        let mut grants = vec![];
+        let mut deletes = vec![];
+
+        // permissions
+        let mut declared_permissions = BTreeSet::new();
+        for permission in &self.permissions {
+            if !declared_permissions.insert(permission.name.clone()) {
+                return Err(PolicyPlanError::Duplicate(permission.name.clone()));
+            }
+
+            match current_permissions.get(&permission.name) {
+                None => permission_changes.push(PolicyChange::InsertPermission {
+                    name: permission.name.clone(),
+                    description: permission.description.clone(),
+                }),
+                Some(current) if current.description != permission.description => {
+                    permission_changes.push(PolicyChange::UpdatePermission {
+                        name: permission.name.clone(),
+                        description: permission.description.clone(),
+                    })
+                }
+                Some(_) => (),
+            };
+        }
+
+        let is_protected_permission = |name: &String| {
+            current_permissions.get(name).is_some_and(|permission| permission.protected)
+        };
+
+        // groups and their grants
+        let mut declared_groups = BTreeSet::new();
+        for group in &self.groups {
+            if !declared_groups.insert(group.name.clone()) {
+                return Err(PolicyPlanError::Duplicate(group.name.clone()));
+            }
+
+            let group_protected = match current_groups.get(&group.name) {
+                None => {
+                    group_changes.push(PolicyChange::InsertGroup {
+                        name: group.name.clone(),
+                        description: group.description.clone(),
+                    });
+                    false
+                }
+                Some((description, protected)) => {
+                    if *description != group.description {
+                        group_changes.push(PolicyChange::UpdateGroup {
+                            name: group.name.clone(),
+                            description: group.description.clone(),
+                        });
+                    }
+                    *protected
+                }
+            };
+
+            let mut desired: GroupRules = BTreeMap::new();
+            for grant in &group.permissions {
+                let rule = grant.rule();
+
+                if !declared_permissions.contains(&rule.name) && !is_protected_permission(&rule.name) {
+                    return Err(PolicyPlanError::PermissionNotDeclared {
+                        group_name: group.name.clone(),
+                        permission_name: rule.name,
+                    });
+                }
+                if let Some(condition) = &rule.condition {
+                    if let Err(err) = Condition::parse(condition) {
+                        return Err(PolicyPlanError::InvalidCondition {
+                            group_name: group.name.clone(),
+                            permission_name: rule.name,
+                            details: err.to_string(),
+                        });
+                    }
+                }
+
+                desired.entry(rule.name.clone()).or_default().push(rule);
+            }
+            for rules in desired.values_mut() {
+                rules.sort_by_key(|rule| rule.key());
+                rules.dedup();
+            }
+
+            let current = current_rules.remove(&group.name).unwrap_or_default();
+            let permission_names: BTreeSet<&String> = desired.keys().chain(current.keys()).collect();
+
+            for permission_name in permission_names {
+                let desired_rules = desired.get(permission_name).cloned().unwrap_or_default();
+                let current_rules = current.get(permission_name).cloned().unwrap_or_default();
+
+                if desired_rules == current_rules {
+                    continue;
+                }
+                if desired_rules.is_empty() && group_protected && is_protected_permission(permission_name) {
+                    continue;
+                }
+
+                if !current_rules.is_empty() {
+                    revokes.push(PolicyChange::RevokeGroupPermission {
+                        group_name: group.name.clone(),
+                        permission_name: permission_name.clone(),
+                    });
+                }
+                for rule in desired_rules {
+                    grants.push(PolicyChange::GrantGroupPermission {
+                        group_name: group.name.clone(),
+                        rule,
+                    });
+                }
+            }
+        }
+
+        // user memberships
+        if let Some(users) = &self.users {
+            let mut memberships = Self::current_memberships(conn, organization).await;
+
+            for (login, groups) in users {
+                if User::retrieve(conn, login).await.is_err() {
+                    return Err(PolicyPlanError::UserNotFound(login.clone()));
+                }
+
+                let desired: BTreeSet<String> = groups.iter().cloned().collect();
+                let current = memberships.remove(login).unwrap_or_default();
+
+                for group_name in &desired {
+                    let protected = current_groups.get(group_name).is_some_and(|(_, protected)| *protected);
+                    if !declared_groups.contains(group_name) && !protected {
+                        return Err(PolicyPlanError::GroupNotDeclared {
+                            login: login.clone(),
+                            group_name: group_name.clone(),
+                        });
+                    }
+                }
+
+                for group_name in current.difference(&desired) {
+                    revokes.push(PolicyChange::RevokeUserGroup {
+                        login: login.clone(),
+                        group_name: group_name.clone(),
+                    });
+                }
+                for group_name in desired.difference(&current) {
+                    grants.push(PolicyChange::GrantUserGroup {
+                        login: login.clone(),
+                        group_name: group_name.clone(),
+                    });
+                }
+            }
+        }
+
+        // undeclared groups and permissions
+        if prune {
+            for (name, (_, protected)) in &current_groups {
+                if !protected && !declared_groups.contains(name) {
+                    deletes.push(PolicyChange::DeleteGroup { name: name.clone() });
+                }
+            }
+            for (name, permission) in &current_permissions {
+                if !permission.protected && !declared_permissions.contains(name) {
+                    deletes.push(PolicyChange::DeletePermission { name: name.clone() });
+                }
+            }
+        }
+
+        // revokes go first so swapping mutually exclusive groups do not conflict
+        let mut changes = permission_changes;
+        changes.append(&mut group_changes);
+        changes.append(&mut revokes);
+        changes.append(&mut grants);
+        changes.append(&mut deletes);
+
+        return Ok(changes);
+    }
+
+    /// ## Policy::apply
+    ///
+    /// Reconciles the database with the policy in provided organization, returning the applied changes <br>
+    /// Should be run in a transaction, as the changes applied before a failing one are not reverted
+    ///
+    /// Errors:
+    /// + when the policy cannot be planned, see Policy::plan
+    /// + when one of the changes fails, e.g. because of a separation of duties conflict
+    ///
+    pub async fn apply(
+        &self,
+        conn: &mut PgConnection,
+        organization: &String,
+        prune: bool,
+    ) -> Result<Vec<PolicyChange>, PolicyApplyError> {
+        let changes = self.plan(conn, organization, prune).await?;
+
+        for change in &changes {
+            if let Err(details) = change.apply(conn, organization).await {
+                return Err(PolicyApplyError::Change(change.to_string(), details));
+            }
+        }
+
+        return Ok(changes);
+    }
+
+    async fn current_permissions(conn: &mut PgConnection) -> BTreeMap<String, Permission> {
+        let sql = "SELECT * FROM permissions ORDER BY name;";
+        let permissions: Vec<Permission> = query_as(sql).fetch_all(&mut *conn).await.unwrap();
+
+        return permissions
+            .into_iter()
+            .map(|permission| (permission.name.clone(), permission))
+            .collect();
+    }
+
+    /// groups by name, with their description and whether they are protected
+    async fn current_groups(conn: &mut PgConnection) -> BTreeMap<String, (String, bool)> {
+        let sql = "SELECT name, description, protected FROM groups ORDER BY name;";
+        let groups: Vec<(String, String, bool)> = query_as(sql).fetch_all(&mut *conn).await.unwrap();
+
+        return groups
+            .into_iter()
+            .map(|(name, description, protected)| (name, (description, protected)))
+            .collect();
+    }
+
+    /// non-expired rules of all of the groups in the organization by group name
+    async fn current_rules(conn: &mut PgConnection, organization: &String) -> BTreeMap<String, GroupRules> {
+        let sql = "
+    SELECT
+      group_name,
+      permission_name,
+      effect,
+      condition,
+      expires_at
+    FROM groups_permissions
+    WHERE organization_name = $1
+      AND (expires_at IS NULL OR expires_at > NOW());
+    ";
+        let rows: Vec<RuleRow> =
+            query_as(sql).bind(organization).fetch_all(&mut *conn).await.unwrap();
+
+        let mut rules: BTreeMap<String, GroupRules> = BTreeMap::new();
+        for (group_name, permission_name, effect, condition, expires_at) in rows {
+            let effect = match effect.as_str() {
+                "deny" => GrantEffect::Deny,
+                _ => GrantEffect::Allow,
+            };
+
+            rules
+                .entry(group_name)
+                .or_default()
+                .entry(permission_name.clone())
+                .or_default()
+                .push(PolicyGrantRule { name: permission_name, effect, condition, expires_at });
+        }
+
+        for group_rules in rules.values_mut() {
+            for rules in group_rules.values_mut() {
+                rules.sort_by_key(|rule| rule.key());
+                rules.dedup();
+            }
+        }
+
+        return rules;
+    }
+
+    /// non-expired group names of all of the users in the organization by login
+    async fn current_memberships(
+        conn: &mut PgConnection,
+        organization: &String,
+    ) -> BTreeMap<String, BTreeSet<String>> {
+        let sql = "
+    SELECT
+      user_login,
+      group_name
+    FROM users_groups
+    WHERE organization_name = $1
+      AND (expires_at IS NULL OR expires_at > NOW());
+    ";
+        let rows: Vec<(String, String)> =
+            query_as(sql).bind(organization).fetch_all(&mut *conn).await.unwrap();
+
+        let mut memberships: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (login, group_name) in rows {
+            memberships.entry(login).or_default().insert(group_name);
+        }
+
+        return memberships;
+    }
+}
+
+impl PolicyChange {
+    /// ## PolicyChange::apply
+    ///
+    /// Applies the change to the database in provided organization, returning the reason when it fails
+    ///
+    pub async fn apply(&self, conn: &mut PgConnection, organization: &String) -> Result<(), String> {
+        let result = match self {
+            Self::InsertPermission { name, description } => {
+                Permission::insert(conn, name, description).await.map_err(|err| err.to_string())
+            }
+            Self::UpdatePermission { name, description } => {
+                Permission::update(conn, name, None, Some(description)).await.map_err(|err| err.to_string())
+            }
+            Self::DeletePermission { name } => {
+                Permission::delete(conn, name).await.map_err(|err| err.to_string())
+            }
+            Self::InsertGroup { name, description } => {
+                Group::insert(conn, name, description, &vec![], organization).await.map_err(|err| err.to_string())
+            }
+            Self::UpdateGroup { name, description } => {
+                Group::update(conn, name, None, Some(description)).await.map_err(|err| err.to_string())
+            }
+            Self::DeleteGroup { name } => Group::delete(conn, name).await.map_err(|err| err.to_string()),
+            Self::GrantGroupPermission { group_name, rule } => Group::grant_permission(
+                conn,
+                group_name,
+                &rule.name,
+                rule.effect,
+                rule.condition.as_ref(),
+                rule.expires_at,
+                organization,
+            )
+            .await
+            .map_err(|err| err.to_string()),
+            Self::RevokeGroupPermission { group_name, permission_name } => {
+                Group::revoke_permission(conn, group_name, permission_name, organization)
+                    .await
+                    .map_err(|err| err.to_string())
+            }
+            Self::GrantUserGroup { login, group_name } => {
+                User::grant_group(conn, login, group_name, None, organization)
+                    .await
+                    .map_err(|err| err.to_string())
+            }
+            Self::RevokeUserGroup { login, group_name } => {
+                User::revoke_group(conn, login, group_name, organization)
+                    .await
+                    .map_err(|err| err.to_string())
+            }
+        };
+
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::organization::DEFAULT_ORGANIZATION, util::testing};
+
+    #[tokio::test]
+    async fn keeps_undeclared_entities_unless_pruning() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let organization = DEFAULT_ORGANIZATION.to_string();
+
+        Permission::insert(&mut conn, &"billing:refund".to_string(), &String::new()).await.unwrap();
+        Group::insert(&mut conn, &"accountant".to_string(), &String::new(), &vec![], &organization)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        let policy = Policy {
+            permissions: vec![PolicyPermission { name: "shop:orders".to_string(), description: String::new() }],
+            groups: vec![],
+            users: None,
+        };
+
+        let changes = policy.apply(&mut conn, &organization, false).await.map_err(|err| err.to_string()).unwrap();
+        assert_eq!(
+            changes.iter().map(|change| change.to_string()).collect::<Vec<_>>(),
+            vec!["+ insert permission shop:orders"]
+        );
+        assert!(Permission::retrieve(&mut conn, &"billing:refund".to_string()).await.is_ok());
+
+        let changes = policy.apply(&mut conn, &organization, true).await.map_err(|err| err.to_string()).unwrap();
+        assert_eq!(
+            changes.iter().map(|change| change.to_string()).collect::<Vec<_>>(),
+            vec!["- delete group accountant", "- delete permission billing:refund"]
+        );
+        assert!(Permission::retrieve(&mut conn, &"billing:refund".to_string()).await.is_err());
+    }
+}
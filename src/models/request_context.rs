@@ -0,0 +1,79 @@
+use actix_web::HttpRequest;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::{models::organization::DEFAULT_ORGANIZATION, util::condition::condition_attributes};
+
+/// Request-time attributes available to grant conditions
+#[derive(Clone, Debug)]
+pub struct RequestContext {
+    pub ip: Option<String>,
+    pub time: DateTime<Utc>,
+    /// resource the permission is checked against, if any
+    pub resource: Option<String>,
+    /// organization the grants are resolved in, the session's active one for requests
+    pub organization: String,
+}
+
+impl RequestContext {
+    /// ## RequestContext::from_request
+    ///
+    /// Builds the context of an incoming http request
+    ///
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let ip = req
+            .connection_info()
+            .realip_remote_addr()
+            .map(|addr| addr.to_string());
+
+        return Self {
+            ip,
+            time: Utc::now(),
+            resource: None,
+            organization: DEFAULT_ORGANIZATION.to_string(),
+        };
+    }
+
+    /// ## RequestContext::now
+    ///
+    /// Builds a context without any request attached (e.g. for the CLI)
+    ///
+    pub fn now() -> Self {
+        return Self {
+            ip: None,
+            time: Utc::now(),
+            resource: None,
+            organization: DEFAULT_ORGANIZATION.to_string(),
+        };
+    }
+
+    /// ## RequestContext::with_resource
+    ///
+    /// Returns a copy of the context checking against provided resource
+    ///
+    pub fn with_resource(&self, resource: Option<String>) -> Self {
+        let mut context = self.clone();
+        context.resource = resource;
+
+        return context;
+    }
+
+    /// ## RequestContext::with_organization
+    ///
+    /// Returns a copy of the context resolving grants in provided organization
+    ///
+    pub fn with_organization(&self, organization: String) -> Self {
+        let mut context = self.clone();
+        context.organization = organization;
+
+        return context;
+    }
+
+    /// ## RequestContext::attributes
+    ///
+    /// Returns the attributes conditions are evaluated against for provided user details
+    ///
+    pub fn attributes(&self, details: &Value) -> Value {
+        return condition_attributes(details, &self.ip, &self.time, &self.resource);
+    }
+}
@@ -4,19 +4,28 @@ use crate::{
             LoginSession, LoginSessionDeleteError, LoginSessionInsertError,
             LoginSessionRetrieveError,
         },
-        Order,
+        exclusive_group_set::{ExclusiveGroupSet, SodConflict},
+        organization::{Organization, DEFAULT_ORGANIZATION},
+        grant::{EffectivePermission, Grant, GrantEffect, PermissionDecision, PermissionExplanation},
+        outbox::{DomainEvent, OutboxEvent},
+        webhook::{Webhook, WebhookEnqueueError},
+        Order, Permission, RequestContext,
+    },
+    util::{
+        condition::Condition,
+        logging::{log_database_interaction, DatabaseOperationLogStatus},
     },
-    util::logging::{log_database_interaction, DatabaseOperationLogStatus},
 };
 use crate::{util::string::json_value_to_pretty_string, web::ServerResponse};
 use actix_web::http::StatusCode;
+use chrono::{DateTime, Utc};
 use argon2::{
     password_hash::{self, rand_core::OsRng, SaltString},
     Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use sqlx::{prelude::FromRow, query, query_as, PgConnection};
+use sqlx::{prelude::FromRow, query, query_as, Connection, PgConnection};
 
 use super::Group;
 
@@ -65,13 +74,25 @@ pub enum UserInsertError {
     NameError,
     /// Returned when the provided password cannot be hashed
     CannotHash(String),
+    /// Returned when one of the default groups do not exist
+    GroupNotFound(String),
+    /// Returned when the default groups are mutually exclusive
+    SodConflict(SodConflict),
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for UserInsertError {
     fn to_string(&self) -> String {
         return match self {
       Self::NameError => "Either the provided login is too long, this user already exist or one of the provided groups do not exist.".to_string(),
-      Self::CannotHash(err) => format!("Password hashing error: {}.", err)
+      Self::CannotHash(err) => format!("Password hashing error: {}.", err),
+      Self::GroupNotFound(group_name) => format!("Default group \"{}\" do not exist.", group_name),
+      Self::SodConflict(conflict) => conflict.to_string(),
+      Self::Webhook(err) => err.to_string(),
+      Self::Database(details) => format!("Cannot save the changes: {}", details)
     };
     }
 }
@@ -79,6 +100,23 @@ impl ToString for UserInsertError {
 pub enum UserDeleteError {
     /// Returned when the user with specified login do not exist
     NotFound,
+    /// Returned when the user is the last member of a protected group
+    Protected,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
+}
+
+impl ToString for UserDeleteError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "Provided user do not exist".to_string(),
+            Self::Protected => "Provided user is the last member of a protected group".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
+    }
 }
 
 pub enum UserHasPermissionError {
@@ -102,6 +140,10 @@ pub enum UserLoginError {
     InvalidCredentials,
     /// Returned when the token hash cannot be created
     CannotHash(String),
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 pub enum UserGrantError {
@@ -109,6 +151,14 @@ pub enum UserGrantError {
     NotFound,
     /// Returned when the group with specified name do not exist
     GroupNotFound,
+    /// Returned when the group is mutually exclusive with one of the user's groups
+    SodConflict(SodConflict),
+    /// Returned when the user do not belong to the organization
+    NotMember,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for UserGrantError {
@@ -116,6 +166,10 @@ impl ToString for UserGrantError {
         return match self {
             Self::NotFound => "Provided user do not exist".to_string(),
             Self::GroupNotFound => "Provided group do not exist".to_string(),
+            Self::SodConflict(conflict) => conflict.to_string(),
+            Self::NotMember => "Provided user do not belong to this organization".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
         };
     }
 }
@@ -127,6 +181,12 @@ pub enum UserRevokeError {
     GroupNotFound,
     /// Returned when the group didn't had specified permission granted
     NotGranted,
+    /// Returned when the user is the last member of the protected group
+    Protected,
+    /// Returned when the webhook deliveries of the change cannot be queued
+    Webhook(WebhookEnqueueError),
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
 }
 
 impl ToString for UserRevokeError {
@@ -135,6 +195,56 @@ impl ToString for UserRevokeError {
             Self::NotFound => "Provided user do not exist".to_string(),
             Self::GroupNotFound => "Provided group do not exist".to_string(),
             Self::NotGranted => "Provided group do not had this permission".to_string(),
+            Self::Protected => "Provided user is the last member of this protected group".to_string(),
+            Self::Webhook(err) => err.to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
+    }
+}
+
+pub enum UserGrantPermissionError {
+    /// Returned when the user with specified login do not exist
+    NotFound,
+    /// Returned when the permission with specified name do not exist
+    PermissionNotFound,
+    /// Returned when the provided condition cannot be parsed
+    InvalidCondition(String),
+    /// Returned when the user do not belong to the organization
+    NotMember,
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
+}
+
+impl ToString for UserGrantPermissionError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "Provided user do not exist".to_string(),
+            Self::PermissionNotFound => "Provided permission do not exist".to_string(),
+            Self::InvalidCondition(details) => format!("Invalid grant condition: {}", details),
+            Self::NotMember => "Provided user do not belong to this organization".to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
+        };
+    }
+}
+
+pub enum UserRevokePermissionError {
+    /// Returned when the user with specified login do not exist
+    NotFound,
+    /// Returned when the permission with specified name do not exist
+    PermissionNotFound,
+    /// Returned when the user didn't had specified permission granted directly
+    NotGranted,
+    /// Returned when the changes cannot be saved in the database
+    Database(String),
+}
+
+impl ToString for UserRevokePermissionError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "Provided user do not exist".to_string(),
+            Self::PermissionNotFound => "Provided permission do not exist".to_string(),
+            Self::NotGranted => "Provided user was never directly granted this permission".to_string(),
+            Self::Database(details) => format!("Cannot save the changes: {}", details),
         };
     }
 }
@@ -193,17 +303,21 @@ impl User {
 
     /// ## User::insert
     ///
-    /// Inserts a user with provided data into the database <br>
+    /// Inserts a user with provided data into the database, adds it to the default organization
+    /// and grants it provided default groups inside of it <br>
+    /// Should be run in a transaction, as the user is kept when granting one of the groups fails
     ///
     /// Errors:
     /// + when a user with provided login already exist
     /// + when the login is longer than 255 chars
+    /// + when one of the default groups do not exist or they are mutually exclusive
     ///
     pub async fn insert(
         conn: &mut PgConnection,
         login: &String,
         password: &String,
         details: &Value,
+        default_groups: &Vec<String>,
     ) -> Result<(), UserInsertError> {
         let password_hash = match hash_password(password.to_string()) {
             Ok(hash) => hash,
@@ -218,15 +332,24 @@ impl User {
             }
         };
 
-        return Self::insert_unhashed(conn, login, &password_hash, details).await;
+        return Self::insert_unhashed(conn, login, &password_hash, details, default_groups).await;
     }
 
+    /// ## User::insert_unhashed
+    ///
+    /// Same as User::insert, but with already hashed password
+    ///
     pub async fn insert_unhashed(
         conn: &mut PgConnection,
         login: &String,
         password: &String,
         details: &Value,
+        default_groups: &Vec<String>,
     ) -> Result<(), UserInsertError> {
+        // nested, so the outbox event is committed along with the change even outside of a transaction
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
         let sql = "
       INSERT INTO
         users (login, password_hash, details)
@@ -255,12 +378,50 @@ impl User {
             }
         };
 
+        // recorded before the grants of the default groups
+        OutboxEvent::record(conn, DomainEvent::UserRegistered { login: login.clone() }).await;
+
+        let default_organization = DEFAULT_ORGANIZATION.to_string();
+        let sql = "INSERT INTO organizations_users (organization_name, user_login) VALUES ($1, $2);";
+        query(sql)
+            .bind(&default_organization)
+            .bind(&login)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        for group_name in default_groups {
+            let result = Self::grant_group(conn, login, group_name, None, &default_organization).await;
+
+            let error = match result {
+                Ok(_) => continue,
+                Err(UserGrantError::SodConflict(conflict)) => UserInsertError::SodConflict(conflict),
+                Err(_) => UserInsertError::GroupNotFound(group_name.clone()),
+            };
+
+            log_database_interaction(
+                "Inserting a user into database.",
+                json!({ "login": login, "details": details, "default_groups": default_groups }),
+                DatabaseOperationLogStatus::Err(error.to_string()),
+            );
+
+            return Err(error);
+        }
+
         log_database_interaction::<String>(
             "Inserting a user into database.",
-            json!({ "login": login, "details": details }),
+            json!({ "login": login, "details": details, "default_groups": default_groups }),
             DatabaseOperationLogStatus::Ok,
         );
 
+        if let Err(err) = Webhook::enqueue(conn, "user.registered", json!({ "login": login, "details": details })).await {
+            return Err(UserInsertError::Webhook(err));
+        }
+
+        if let Err(err) = tx.commit().await {
+            return Err(UserInsertError::Database(err.to_string()));
+        }
+
         return Ok(());
     }
 
@@ -268,10 +429,39 @@ impl User {
     ///
     /// Deletes a user and all of it's related data from the database
     ///
+    /// Errors:
+    /// + when user with provided login do not exist
+    /// + when the user is the last member of a protected group
+    ///
     pub async fn delete(conn: &mut PgConnection, login: String) -> Result<(), UserDeleteError> {
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if !Self::list_sole_protected_groups(conn, &login, None).await.is_empty() {
+            log_database_interaction(
+                "Deleting user from the database.",
+                json!({ "login": login }),
+                DatabaseOperationLogStatus::Err("User is the last member of a protected group."),
+            );
+
+            return Err(UserDeleteError::Protected);
+        }
+
         let sql = "DELETE FROM users_groups WHERE user_login = $1";
         let _ = query(sql).bind(&login).execute(&mut *conn).await;
 
+        let sql = "DELETE FROM users_permissions WHERE user_login = $1";
+        let _ = query(sql).bind(&login).execute(&mut *conn).await;
+
+        let sql = "DELETE FROM groups_managers WHERE user_login = $1";
+        let _ = query(sql).bind(&login).execute(&mut *conn).await;
+
+        let sql = "DELETE FROM applications_admins WHERE user_login = $1";
+        let _ = query(sql).bind(&login).execute(&mut *conn).await;
+
+        let sql = "DELETE FROM organizations_users WHERE user_login = $1";
+        let _ = query(sql).bind(&login).execute(&mut *conn).await;
+
         let sql = "DELETE FROM login_sessions WHERE user_login = $1";
         let _ = query(sql).bind(&login).execute(&mut *conn).await;
 
@@ -294,6 +484,15 @@ impl User {
             DatabaseOperationLogStatus::Ok,
         );
 
+        if let Err(err) = Webhook::enqueue(conn, "user.deleted", json!({ "login": login })).await {
+            return Err(UserDeleteError::Webhook(err));
+        }
+        OutboxEvent::record(conn, DomainEvent::UserDeleted { login: login.clone() }).await;
+
+        if let Err(err) = tx.commit().await {
+            return Err(UserDeleteError::Database(err.to_string()));
+        }
+
         return Ok(());
     }
 
@@ -359,6 +558,8 @@ impl User {
 
                     return Err(UserLoginError::InvalidCredentials);
                 }
+                LoginSessionInsertError::Webhook(err) => return Err(UserLoginError::Webhook(err)),
+                LoginSessionInsertError::Database(details) => return Err(UserLoginError::Database(details)),
             },
         };
 
@@ -373,56 +574,238 @@ impl User {
 
     /// ## User::has_permission
     ///
-    /// Check if a user has a specified permission
+    /// Check if a user has a specified permission in provided request context
     ///
     pub async fn has_permission(
         self: &Self,
         conn: &mut PgConnection,
         permission_name: String,
+        context: &RequestContext,
+    ) -> bool {
+        return self
+            .check_permission(conn, permission_name, context)
+            .await
+            .allowed;
+    }
+
+    /// ## User::check_permission
+    ///
+    /// Resolves a permission for the user, returning the grant that allowed it
+    /// or the deny rule that blocked it. <br>
+    /// Deny rules are evaluated before allow rules and grants with a condition
+    /// only count when the condition holds for provided request context
+    ///
+    pub async fn check_permission(
+        self: &Self,
+        conn: &mut PgConnection,
+        permission_name: String,
+        context: &RequestContext,
+    ) -> PermissionDecision {
+        let grants = Grant::list_for_user(
+            conn,
+            &self.login,
+            &vec![permission_name.clone()],
+            &context.organization,
+        )
+        .await;
+        let attributes = context.attributes(&self.details);
+
+        return Grant::resolve(&grants, &permission_name, &attributes);
+    }
+
+    /// ## User::check_permissions
+    ///
+    /// Resolves multiple permissions (each optionally against a resource) at once,
+    /// fetching all the needed grants with a single query. <br>
+    /// Returns the decisions in the same order as provided permissions
+    ///
+    pub async fn check_permissions(
+        self: &Self,
+        conn: &mut PgConnection,
+        permissions: &[(String, Option<String>)],
+        context: &RequestContext,
+    ) -> Vec<PermissionDecision> {
+        let names = permissions.iter().map(|(name, _)| name.clone()).collect();
+        let grants = Grant::list_for_user(conn, &self.login, &names, &context.organization).await;
+
+        return permissions
+            .iter()
+            .map(|(name, resource)| {
+                let attributes = context
+                    .with_resource(resource.clone())
+                    .attributes(&self.details);
+
+                Grant::resolve(&grants, name, &attributes)
+            })
+            .collect();
+    }
+
+    /// ## User::explain_permission
+    ///
+    /// Resolves a permission for the user, explaining which groups, grants,
+    /// wildcards and deny rules took part in the decision
+    ///
+    pub async fn explain_permission(
+        self: &Self,
+        conn: &mut PgConnection,
+        permission_name: String,
+        context: &RequestContext,
+    ) -> PermissionExplanation {
+        let grants =
+            Grant::list_for_explanation(conn, &self.login, &permission_name, &context.organization)
+                .await;
+        let attributes = context.attributes(&self.details);
+        let (decision, evaluations) = Grant::explain(&grants, &permission_name, &attributes);
+        let groups = Self::list_groups(conn, &self.login, &context.organization).await;
+
+        return PermissionExplanation {
+            login: self.login.clone(),
+            permission_name,
+            decision,
+            groups,
+            grants: evaluations,
+        };
+    }
+
+    /// ## User::list_groups
+    ///
+    /// Lists names of the groups user with specified login currently belongs to in provided organization
+    ///
+    pub async fn list_groups(
+        conn: &mut PgConnection,
+        login: &String,
+        organization: &String,
+    ) -> Vec<String> {
+        let sql = "
+      SELECT
+        group_name
+      FROM
+        users_groups
+      WHERE
+        user_login = $1
+      AND
+        organization_name = $2
+      AND
+        (expires_at IS NULL OR expires_at > NOW())
+      ORDER BY
+        group_name;
+    ";
+        let rows: Vec<(String,)> = query_as(sql)
+            .bind(login)
+            .bind(organization)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+
+        return rows.into_iter().map(|(group_name,)| group_name).collect();
+    }
+
+    /// ## User::can_delegate_group
+    ///
+    /// Checks if the user can grant and revoke a group with specified name to and from other users
+    /// without holding "cauth:users:update", that is when the user manages the group inside the context's organization
+    /// or belongs to it and holds the "cauth:users:delegate" permission
+    ///
+    pub async fn can_delegate_group(
+        self: &Self,
+        conn: &mut PgConnection,
+        group_name: &String,
+        context: &RequestContext,
     ) -> bool {
+        if Group::is_manager(conn, group_name, &self.login, &context.organization).await {
+            return true;
+        }
+
+        return Self::list_groups(conn, &self.login, &context.organization)
+            .await
+            .contains(group_name)
+            && self
+                .has_permission(conn, "cauth:users:delegate".to_string(), context)
+                .await;
+    }
+
+    /// ## User::list_sole_protected_groups
+    ///
+    /// Lists protected groups the user with specified login is the only permanent member of
+    /// inside provided organization, or inside any of them when none is provided. <br>
+    /// Memberships of the other users which expire do not count, as they cannot keep the group held
+    ///
+    pub async fn list_sole_protected_groups(
+        conn: &mut PgConnection,
+        login: &String,
+        organization: Option<&String>,
+    ) -> Vec<String> {
         let sql = "
       SELECT
-        gp.permission_name
+        g.name
       FROM
-        users u
+        groups g
       INNER JOIN
         users_groups ug
       ON
-        u.login = ug.user_login
-      INNER JOIN
-        groups_permissions gp
-      ON
-        ug.group_name = gp.group_name
+        ug.group_name = g.name
+        AND
+        ug.user_login = $1
+        AND
+        ($2::VARCHAR IS NULL OR ug.organization_name = $2)
+        AND
+        (ug.expires_at IS NULL OR ug.expires_at > NOW())
       WHERE
-        u.login = $1
-      AND
-        gp.permission_name  = $2
-      LIMIT
-        1;
+        g.protected
+      AND NOT EXISTS (
+        SELECT 1 FROM users_groups other
+        WHERE other.group_name = g.name
+          AND other.organization_name = ug.organization_name
+          AND other.user_login <> $1
+          AND other.expires_at IS NULL
+      );
     ";
-        let q = query(sql).bind(&self.login).bind(&permission_name);
-        let num_rows = q.execute(&mut *conn).await.unwrap().rows_affected();
+        let rows: Vec<(String,)> = query_as(sql)
+            .bind(login)
+            .bind(organization)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
 
-        if num_rows == 0 {
-            return false;
-        }
+        return rows.into_iter().map(|(name,)| name).collect();
+    }
 
-        return true;
+    /// ## User::effective_permissions
+    ///
+    /// Lists all the permissions the user effectively holds in provided request context,
+    /// each with the groups and direct grants it came from
+    ///
+    pub async fn effective_permissions(
+        self: &Self,
+        conn: &mut PgConnection,
+        context: &RequestContext,
+    ) -> Vec<EffectivePermission> {
+        let grants = Grant::list_all_for_user(conn, &self.login, &context.organization).await;
+        let attributes = context.attributes(&self.details);
+
+        return Grant::effective_permissions(&grants, &attributes);
     }
 
     /// ## User::grant_group
     ///
-    /// Grants user a group with specified name
+    /// Grants user a group with specified name inside provided organization, optionally until provided expiry time
     ///
     /// Errors:
     /// + When provided user or group do not exist
+    /// + When the user do not belong to the organization
+    /// + When the group is mutually exclusive with one of the user's groups
     ///
     pub async fn grant_group(
         conn: &mut PgConnection,
         login: &String,
         group_name: &String,
+        expires_at: Option<DateTime<Utc>>,
+        organization: &String,
     ) -> Result<(), UserGrantError> {
-        if let Err(_) = Group::retrieve(conn, group_name).await {
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Err(_) = Group::retrieve(conn, group_name, organization).await {
             log_database_interaction(
                 "Granting user a group.",
                 json!({ "login": login }),
@@ -442,35 +825,99 @@ impl User {
             return Err(UserGrantError::NotFound);
         }
 
-        let sql = "INSERT INTO users_groups (user_login, group_name) VALUES ($1, $2);";
+        if !Organization::is_member(conn, organization, login).await {
+            log_database_interaction(
+                "Granting user a group.",
+                json!({ "login": login, "organization": organization }),
+                DatabaseOperationLogStatus::Err("User do not belong to the organization."),
+            );
+
+            return Err(UserGrantError::NotMember);
+        }
+
+        let mut groups = Self::list_groups(conn, login, organization).await;
+        groups.push(group_name.clone());
+
+        if let Some(conflict) = ExclusiveGroupSet::find_conflict(conn, &groups).await {
+            log_database_interaction(
+                "Granting user a group.",
+                json!({ "login": login, "group_name": group_name, "conflict": conflict }),
+                DatabaseOperationLogStatus::Err("Separation of duties conflict."),
+            );
+
+            return Err(UserGrantError::SodConflict(conflict));
+        }
+
+        let sql = "INSERT INTO users_groups (user_login, group_name, expires_at, organization_name) VALUES ($1, $2, $3, $4);";
         let result = query(sql)
             .bind(login)
             .bind(group_name)
+            .bind(expires_at)
+            .bind(organization)
             .execute(&mut *conn)
             .await;
 
+        if let Err(err) = result {
+            log_database_interaction(
+                "Granting user a group.",
+                json!({ "login": login, "group_name": group_name, "expires_at": expires_at, "organization": organization }),
+                DatabaseOperationLogStatus::Err(err.to_string()),
+            );
+
+            return Err(UserGrantError::Database(err.to_string()));
+        }
+
         log_database_interaction::<String>(
-            "Inserting user login session to the database.",
-            json!({ "login": login }),
+            "Granting user a group.",
+            json!({ "login": login, "group_name": group_name, "expires_at": expires_at, "organization": organization }),
             DatabaseOperationLogStatus::Ok,
         );
 
+        let enqueued = Webhook::enqueue(
+            conn,
+            "user.group_granted",
+            json!({ "login": login, "group_name": group_name, "expires_at": expires_at, "organization": organization }),
+        )
+        .await;
+        if let Err(err) = enqueued {
+            return Err(UserGrantError::Webhook(err));
+        }
+        OutboxEvent::record(
+            conn,
+            DomainEvent::GroupGranted {
+                login: login.clone(),
+                group_name: group_name.clone(),
+                expires_at,
+                organization: organization.clone(),
+            },
+        )
+        .await;
+
+        if let Err(err) = tx.commit().await {
+            return Err(UserGrantError::Database(err.to_string()));
+        }
+
         return Ok(());
     }
 
     /// ## User::revoke_group
     ///
-    /// Revokes a group from user with specified login
+    /// Revokes a group from user with specified login inside provided organization
     ///
     /// Errors:
     /// + When provided user or group do not exist
+    /// + When the user is the last member of the protected group
     ///
     pub async fn revoke_group(
         conn: &mut PgConnection,
         login: &String,
         group_name: &String,
+        organization: &String,
     ) -> Result<(), UserRevokeError> {
-        if let Err(_) = Group::retrieve(conn, group_name).await {
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Err(_) = Group::retrieve(conn, group_name, organization).await {
             log_database_interaction(
                 "Revoking group from a user.",
                 json!({ "login": login }),
@@ -490,10 +937,24 @@ impl User {
             return Err(UserRevokeError::NotFound);
         }
 
-        let sql = "DELETE FROM users_groups WHERE user_login = $1 AND group_name = $2;";
+        if Self::list_sole_protected_groups(conn, login, Some(organization))
+            .await
+            .contains(group_name)
+        {
+            log_database_interaction(
+                "Revoking group from a user.",
+                json!({ "login": login }),
+                DatabaseOperationLogStatus::Err("User is the last member of a protected group."),
+            );
+
+            return Err(UserRevokeError::Protected);
+        }
+
+        let sql = "DELETE FROM users_groups WHERE user_login = $1 AND group_name = $2 AND organization_name = $3;";
         let result = query(sql)
             .bind(login)
             .bind(group_name)
+            .bind(organization)
             .execute(&mut *conn)
             .await
             .unwrap();
@@ -514,6 +975,219 @@ impl User {
             DatabaseOperationLogStatus::Ok,
         );
 
+        let enqueued = Webhook::enqueue(
+            conn,
+            "user.group_revoked",
+            json!({ "login": login, "group_name": group_name, "organization": organization }),
+        )
+        .await;
+        if let Err(err) = enqueued {
+            return Err(UserRevokeError::Webhook(err));
+        }
+        OutboxEvent::record(
+            conn,
+            DomainEvent::GroupRevoked {
+                login: login.clone(),
+                group_name: group_name.clone(),
+                organization: organization.clone(),
+            },
+        )
+        .await;
+
+        if let Err(err) = tx.commit().await {
+            return Err(UserRevokeError::Database(err.to_string()));
+        }
+
+        return Ok(());
+    }
+
+    /// ## User::grant_permission
+    ///
+    /// Grants (or denies, depending on the effect) a permission directly to user with specified login
+    /// inside provided organization, optionally only applying when provided condition holds and until provided expiry time
+    ///
+    /// Errors:
+    /// + When provided user or permission do not exist
+    /// + When the user do not belong to the organization
+    /// + When the provided condition cannot be parsed
+    ///
+    pub async fn grant_permission(
+        conn: &mut PgConnection,
+        login: &String,
+        permission_name: &String,
+        effect: GrantEffect,
+        condition: Option<&String>,
+        expires_at: Option<DateTime<Utc>>,
+        organization: &String,
+    ) -> Result<(), UserGrantPermissionError> {
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Some(condition) = condition {
+            if let Err(err) = Condition::parse(condition) {
+                log_database_interaction(
+                    "Granting user a permission.",
+                    json!({ "login": login, "permission_name": permission_name, "condition": condition }),
+                    DatabaseOperationLogStatus::Err("Invalid condition"),
+                );
+
+                return Err(UserGrantPermissionError::InvalidCondition(err.to_string()));
+            }
+        }
+
+        if let Err(_) = Permission::retrieve(conn, permission_name).await {
+            log_database_interaction(
+                "Granting user a permission.",
+                json!({ "login": login, "permission_name": permission_name }),
+                DatabaseOperationLogStatus::Err("Permission with this name do not exist."),
+            );
+
+            return Err(UserGrantPermissionError::PermissionNotFound);
+        }
+
+        if let Err(_) = User::retrieve(conn, login).await {
+            log_database_interaction(
+                "Granting user a permission.",
+                json!({ "login": login, "permission_name": permission_name }),
+                DatabaseOperationLogStatus::Err("User with this login do not exist."),
+            );
+
+            return Err(UserGrantPermissionError::NotFound);
+        }
+
+        if !Organization::is_member(conn, organization, login).await {
+            log_database_interaction(
+                "Granting user a permission.",
+                json!({ "login": login, "permission_name": permission_name, "organization": organization }),
+                DatabaseOperationLogStatus::Err("User do not belong to the organization."),
+            );
+
+            return Err(UserGrantPermissionError::NotMember);
+        }
+
+        let sql = "INSERT INTO users_permissions (user_login, permission_name, effect, condition, expires_at, organization_name) VALUES ($1, $2, $3, $4, $5, $6);";
+        let result = query(sql)
+            .bind(login)
+            .bind(permission_name)
+            .bind(effect.to_string())
+            .bind(condition)
+            .bind(expires_at)
+            .bind(organization)
+            .execute(&mut *conn)
+            .await;
+
+        if let Err(err) = result {
+            log_database_interaction(
+                "Granting user a permission.",
+                json!({ "login": login, "permission_name": permission_name, "effect": effect, "condition": condition, "expires_at": expires_at, "organization": organization }),
+                DatabaseOperationLogStatus::Err(err.to_string()),
+            );
+
+            return Err(UserGrantPermissionError::Database(err.to_string()));
+        }
+
+        log_database_interaction::<String>(
+            "Granting user a permission.",
+            json!({ "login": login, "permission_name": permission_name, "effect": effect, "condition": condition, "expires_at": expires_at, "organization": organization }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        OutboxEvent::record(
+            conn,
+            DomainEvent::UserPermissionGranted {
+                login: login.clone(),
+                permission_name: permission_name.clone(),
+                effect,
+                expires_at,
+                organization: organization.clone(),
+            },
+        )
+        .await;
+
+        if let Err(err) = tx.commit().await {
+            return Err(UserGrantPermissionError::Database(err.to_string()));
+        }
+
+        return Ok(());
+    }
+
+    /// ## User::revoke_permission
+    ///
+    /// Revokes all the direct grants and deny rules of a permission from user with specified login
+    /// inside provided organization
+    ///
+    /// Errors:
+    /// + When provided user or permission do not exist
+    /// + When the permission was never granted directly to the user
+    ///
+    pub async fn revoke_permission(
+        conn: &mut PgConnection,
+        login: &String,
+        permission_name: &String,
+        organization: &String,
+    ) -> Result<(), UserRevokePermissionError> {
+        let mut tx = conn.begin().await.unwrap();
+        let conn = &mut *tx;
+
+        if let Err(_) = Permission::retrieve(conn, permission_name).await {
+            log_database_interaction(
+                "Revoking permission from a user.",
+                json!({ "login": login, "permission_name": permission_name }),
+                DatabaseOperationLogStatus::Err("Permission with this name do not exist."),
+            );
+
+            return Err(UserRevokePermissionError::PermissionNotFound);
+        }
+
+        if let Err(_) = User::retrieve(conn, login).await {
+            log_database_interaction(
+                "Revoking permission from a user.",
+                json!({ "login": login, "permission_name": permission_name }),
+                DatabaseOperationLogStatus::Err("User with this login do not exist."),
+            );
+
+            return Err(UserRevokePermissionError::NotFound);
+        }
+
+        let sql = "DELETE FROM users_permissions WHERE user_login = $1 AND permission_name = $2 AND organization_name = $3;";
+        let result = query(sql)
+            .bind(login)
+            .bind(permission_name)
+            .bind(organization)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            log_database_interaction(
+                "Revoking permission from a user.",
+                json!({ "login": login, "permission_name": permission_name }),
+                DatabaseOperationLogStatus::Err("Permission was never granted."),
+            );
+
+            return Err(UserRevokePermissionError::NotGranted);
+        }
+
+        log_database_interaction::<String>(
+            "Revoking permission from a user.",
+            json!({ "login": login, "permission_name": permission_name }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        OutboxEvent::record(
+            conn,
+            DomainEvent::UserPermissionRevoked {
+                login: login.clone(),
+                permission_name: permission_name.clone(),
+                organization: organization.clone(),
+            },
+        )
+        .await;
+
+        if let Err(err) = tx.commit().await {
+            return Err(UserRevokePermissionError::Database(err.to_string()));
+        }
+
         return Ok(());
     }
 
@@ -558,3 +1232,218 @@ pub fn hash_password(password: String) -> Result<String, String> {
 
     return Ok(password_hash);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing;
+
+    async fn setup(conn: &mut PgConnection) -> User {
+        let organization = DEFAULT_ORGANIZATION.to_string();
+
+        Permission::insert(conn, &"billing:refund".to_string(), &String::new())
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        Group::insert(conn, &"staff".to_string(), &String::new(), &vec!["billing:refund".to_string()], &organization)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        User::insert_unhashed(
+            conn,
+            &"jane".to_string(),
+            &String::new(),
+            &json!({ "department": "finance" }),
+            &vec!["staff".to_string()],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .unwrap();
+
+        return User::retrieve(conn, &"jane".to_string()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_conditions_when_granted() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        setup(&mut conn).await;
+
+        for condition in ["details.department ==", "1 && true", "details.department < \"a\" < 1"] {
+            let result = User::grant_permission(
+                &mut conn,
+                &"jane".to_string(),
+                &"billing:refund".to_string(),
+                GrantEffect::Deny,
+                Some(&condition.to_string()),
+                None,
+                &DEFAULT_ORGANIZATION.to_string(),
+            )
+            .await;
+
+            assert!(matches!(result, Err(UserGrantPermissionError::InvalidCondition(_))), "{}", condition);
+        }
+    }
+
+    #[tokio::test]
+    async fn deny_rules_which_cannot_be_evaluated_block_the_permission() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let user = setup(&mut conn).await;
+        let context = RequestContext::now();
+
+        assert!(user.has_permission(&mut conn, "billing:refund".to_string(), &context).await);
+
+        // the department is a string, so it cannot be compared to a number
+        User::grant_permission(
+            &mut conn,
+            &"jane".to_string(),
+            &"billing:refund".to_string(),
+            GrantEffect::Deny,
+            Some(&"details.department > 2".to_string()),
+            None,
+            &DEFAULT_ORGANIZATION.to_string(),
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .unwrap();
+
+        let decision = user.check_permission(&mut conn, "billing:refund".to_string(), &context).await;
+        assert!(!decision.allowed);
+        assert_eq!(decision.denied_by.unwrap().source_type, "user");
+    }
+
+    #[tokio::test]
+    async fn wildcard_grants_are_resolved_like_the_other_grants() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let user = setup(&mut conn).await;
+        let context = RequestContext::now();
+        let organization = DEFAULT_ORGANIZATION.to_string();
+
+        for name in ["billing:*", "billing:void"] {
+            Permission::insert(&mut conn, &name.to_string(), &String::new())
+                .await
+                .map_err(|err| err.to_string())
+                .unwrap();
+        }
+        Group::grant_permission(&mut conn, &"staff".to_string(), &"billing:*".to_string(), GrantEffect::Allow, None, None, &organization)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        User::grant_permission(&mut conn, &"jane".to_string(), &"billing:refund".to_string(), GrantEffect::Deny, None, None, &organization)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        assert!(user.has_permission(&mut conn, "billing:void".to_string(), &context).await);
+        assert!(!user.has_permission(&mut conn, "billing:refund".to_string(), &context).await);
+
+        let permissions = user.effective_permissions(&mut conn, &context).await;
+        let wildcard = permissions.iter().find(|permission| permission.name == "billing:*").unwrap();
+        assert_eq!(wildcard.except, vec!["billing:refund".to_string()]);
+        assert!(!permissions.iter().any(|permission| permission.name == "billing:refund"));
+    }
+
+    #[tokio::test]
+    async fn expiring_memberships_do_not_keep_protected_groups_held() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let organization = DEFAULT_ORGANIZATION.to_string();
+        let root = "root".to_string();
+
+        Group::insert(&mut conn, &root, &String::new(), &vec![], &organization)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        Group::protect(&mut conn, &root).await;
+        for login in ["admin", "contractor"] {
+            User::insert_unhashed(&mut conn, &login.to_string(), &String::new(), &json!({}), &vec![])
+                .await
+                .map_err(|err| err.to_string())
+                .unwrap();
+        }
+        User::grant_group(&mut conn, &"admin".to_string(), &root, None, &organization)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        User::grant_group(&mut conn, &"contractor".to_string(), &root, Some(Utc::now() + chrono::Duration::hours(1)), &organization)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        // the contractor's membership is about to expire, so the admin is the last permanent holder
+        let sole = User::list_sole_protected_groups(&mut conn, &"admin".to_string(), Some(&organization)).await;
+        assert_eq!(sole, vec![root.clone()]);
+        assert!(User::revoke_group(&mut conn, &"admin".to_string(), &root, &organization).await.is_err());
+        assert!(User::delete(&mut conn, "admin".to_string()).await.is_err());
+
+        // while the contractor can be removed
+        assert!(User::list_sole_protected_groups(&mut conn, &"contractor".to_string(), Some(&organization)).await.is_empty());
+        assert!(User::revoke_group(&mut conn, &"contractor".to_string(), &root, &organization).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rolls_back_the_change_when_its_deliveries_cannot_be_queued() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+
+        Webhook::insert(&mut conn, &"http://127.0.0.1:1/hook".to_string(), &vec![], None)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        query("ALTER TABLE webhook_deliveries ADD CONSTRAINT rejects_all CHECK (false) NOT VALID;")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        let result = User::insert_unhashed(&mut conn, &"jane".to_string(), &String::new(), &json!({}), &vec![]).await;
+        assert!(matches!(result, Err(UserInsertError::Webhook(_))));
+        assert!(User::retrieve(&mut conn, &"jane".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn reports_the_grants_which_cannot_be_saved() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let organization = DEFAULT_ORGANIZATION.to_string();
+        setup(&mut conn).await;
+
+        Group::insert(&mut conn, &"auditors".to_string(), &String::new(), &vec![], &organization)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        for table in ["users_groups", "users_permissions"] {
+            query(&format!("ALTER TABLE {} ADD CONSTRAINT rejects_all CHECK (false) NOT VALID;", table))
+                .execute(&mut *conn)
+                .await
+                .unwrap();
+        }
+        let (recorded_before,): (i64,) = query_as("SELECT COUNT(*) FROM outbox_events;")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+
+        let result = User::grant_group(&mut conn, &"jane".to_string(), &"auditors".to_string(), None, &organization).await;
+        assert!(matches!(result, Err(UserGrantError::Database(_))));
+        let result = User::grant_permission(
+            &mut conn,
+            &"jane".to_string(),
+            &"billing:refund".to_string(),
+            GrantEffect::Allow,
+            None,
+            None,
+            &organization,
+        )
+        .await;
+        assert!(matches!(result, Err(UserGrantPermissionError::Database(_))));
+
+        // neither the grants nor their events are saved
+        assert!(!User::list_groups(&mut conn, &"jane".to_string(), &organization).await.contains(&"auditors".to_string()));
+        let (recorded_after,): (i64,) = query_as("SELECT COUNT(*) FROM outbox_events;")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(recorded_before, recorded_after);
+    }
+}
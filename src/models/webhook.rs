@@ -0,0 +1,563 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, Utc};
+use crypto::{
+    hmac::Hmac,
+    mac::Mac,
+    sha2::Sha256
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{
+    prelude::FromRow,
+    query,
+    query_as,
+    PgConnection
+};
+use crate::{
+    models::Order,
+    util::{
+        logging::{log_database_interaction, DatabaseOperationLogStatus},
+        string::json_value_to_pretty_string
+    }
+};
+
+/// Types of the events webhooks can subscribe to
+pub const WEBHOOK_EVENT_TYPES: [&str; 12] = [
+    "user.registered",
+    "user.deleted",
+    "user.group_granted",
+    "user.group_revoked",
+    "group.created",
+    "group.renamed",
+    "group.deleted",
+    "permission.renamed",
+    "session.created",
+    "session.deleted",
+    "event.committed",
+    "event.cancelled",
+];
+
+/// Seconds the first retry of a failed delivery waits for, doubled with every attempt
+const RETRY_BACKOFF: i32 = 10;
+/// Maximal number of seconds a retry waits for
+const MAX_RETRY_BACKOFF: i32 = 60 * 60;
+/// Number of random bytes of the generated secrets
+const SECRET_LENGTH: usize = 32;
+
+/// Subscription delivering the events of the listed types to the url, all of them when none are listed. <br>
+/// The secret is never retrieved, only the deliveries are signed with it
+#[derive(FromRow, Serialize)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub created_at: DateTime<Utc>
+}
+
+impl ToString for Webhook {
+    fn to_string(&self) -> String {
+        let formatted = json_value_to_pretty_string(&serde_json::to_value(&self).unwrap());
+
+        return formatted;
+    }
+}
+
+/// Id and secret of a created webhook, the secret is not shown anymore after that
+#[derive(FromRow, Serialize)]
+pub struct WebhookCredentials {
+    pub id: i32,
+    pub secret: String
+}
+
+/// Event waiting to be delivered to a webhook, or one which failed too many times
+#[derive(FromRow, Serialize)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event_type: String,
+    pub payload: Value,
+    /// "pending", "in_flight" while it's being sent or "dead"
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// when the delivery can be claimed again if the outcome of sending it is not recorded
+    pub leased_until: Option<DateTime<Utc>>
+}
+
+/// Due delivery along with the webhook it's sent to, as claimed by WebhookDelivery::claim_due
+#[derive(FromRow)]
+pub struct WebhookDispatch {
+    #[sqlx(flatten)]
+    pub delivery: WebhookDelivery,
+    pub url: String,
+    secret: String
+}
+
+pub enum WebhookInsertError {
+    /// Returned when the url is not a http(s) url
+    InvalidUrl,
+    /// Returned when one of the event types do not exist
+    UnknownEventType(String)
+}
+
+impl ToString for WebhookInsertError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::InvalidUrl => "The url has to be a valid http or https url.".to_string(),
+            Self::UnknownEventType(event_type) => format!("Event type \"{}\" do not exist.", event_type)
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum WebhookEnqueueError {
+    /// Returned when the deliveries cannot be queued, the change should not be committed without them
+    Database(String)
+}
+
+impl ToString for WebhookEnqueueError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Database(details) => format!("Cannot queue webhook deliveries: {}", details)
+        };
+    }
+}
+
+pub enum WebhookDeleteError {
+    /// Returned when the webhook with specified id do not exist
+    NotFound
+}
+
+impl ToString for WebhookDeleteError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "A webhook with this id do not exist.".to_string()
+        };
+    }
+}
+
+pub enum WebhookDeliveryRetryError {
+    /// Returned when there is no dead delivery with specified id
+    NotFound
+}
+
+impl ToString for WebhookDeliveryRetryError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::NotFound => "A dead delivery with this id do not exist.".to_string()
+        };
+    }
+}
+
+/// ## generate_secret
+///
+/// Generates a secret of 32 random bytes, hex encoded
+///
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+
+    return bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+}
+
+/// ## sign
+///
+/// Signs the body of a delivery with the webhook's secret, returning hex encoded HMAC-SHA256
+///
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut hmac = Hmac::new(Sha256::new(), secret.as_bytes());
+    hmac.input(body);
+
+    return hmac
+        .result()
+        .code()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+}
+
+impl Webhook {
+    /// ## Webhook::list
+    ///
+    /// Lists number of webhooks in specified order with specified offset from the database
+    ///
+    pub async fn list(
+        conn: &mut PgConnection,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>
+    ) -> Vec<Webhook> {
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+        SELECT
+            id,
+            url,
+            event_types,
+            created_at
+        FROM
+            webhooks
+        ORDER BY id {}
+        OFFSET {} ROWS
+        LIMIT {};
+        ",
+            order.to_string(),
+            offset,
+            limit
+        );
+
+        return query_as(&sql)
+            .fetch_all(conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## Webhook::insert
+    ///
+    /// Subscribes the url to the events of provided types (all of them when empty),
+    /// generating the secret the deliveries are signed with when none is provided
+    ///
+    /// Errors:
+    /// + when the url is not a http(s) url
+    /// + when one of the event types do not exist
+    ///
+    pub async fn insert(
+        conn: &mut PgConnection,
+        url: &String,
+        event_types: &Vec<String>,
+        secret: Option<&String>
+    ) -> Result<WebhookCredentials, WebhookInsertError> {
+        let is_http = match reqwest::Url::parse(url) {
+            Ok(parsed) => parsed.scheme() == "http" || parsed.scheme() == "https",
+            Err(_) => false
+        };
+
+        if !is_http {
+            return Err(WebhookInsertError::InvalidUrl);
+        }
+
+        if let Some(event_type) = event_types.iter().find(|event_type| !WEBHOOK_EVENT_TYPES.contains(&event_type.as_str())) {
+            return Err(WebhookInsertError::UnknownEventType(event_type.clone()));
+        }
+
+        let secret = match secret {
+            Some(secret) => secret.clone(),
+            None => generate_secret()
+        };
+
+        let sql = "
+        INSERT INTO
+            webhooks (url, event_types, secret)
+        VALUES
+            ($1, $2, $3)
+        RETURNING id, secret;
+        ";
+
+        let result: WebhookCredentials = query_as(sql)
+            .bind(&url)
+            .bind(&event_types)
+            .bind(&secret)
+            .fetch_one(conn)
+            .await
+            .unwrap();
+
+        log_database_interaction::<String>(
+            "Inserting a webhook into the database.",
+            json!({ "id": result.id, "url": url, "event_types": event_types }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(result);
+    }
+
+    /// ## Webhook::delete
+    ///
+    /// Deletes the webhook along with it's pending and dead deliveries
+    ///
+    /// Errors:
+    /// + when the webhook do not exist
+    ///
+    pub async fn delete(
+        conn: &mut PgConnection,
+        id: &i32
+    ) -> Result<(), WebhookDeleteError> {
+        let sql = "DELETE FROM webhooks WHERE id = $1;";
+        let result = query(sql)
+            .bind(&id)
+            .execute(conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookDeleteError::NotFound);
+        }
+
+        log_database_interaction::<String>(
+            "Deleting a webhook from the database.",
+            json!({ "id": id }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+
+    /// ## Webhook::enqueue
+    ///
+    /// Queues a delivery of the event for every webhook subscribed to it's type. <br>
+    /// Should be called with the connection the change is made with,
+    /// so the deliveries are sent only when the change is committed
+    ///
+    /// Errors:
+    /// + when the deliveries cannot be queued, the transaction of the change should be dropped then
+    ///
+    pub async fn enqueue(
+        conn: &mut PgConnection,
+        event_type: &str,
+        data: Value
+    ) -> Result<(), WebhookEnqueueError> {
+        let sql = "
+        INSERT INTO
+            webhook_deliveries (webhook_id, event_type, payload)
+        SELECT
+            id, $1, $2
+        FROM
+            webhooks
+        WHERE
+            cardinality(event_types) = 0 OR $1 = ANY(event_types);
+        ";
+
+        let result = query(sql)
+            .bind(&event_type)
+            .bind(&data)
+            .execute(conn)
+            .await;
+
+        if let Err(err) = result {
+            log_database_interaction(
+                "Queueing webhook deliveries.",
+                json!({ "event_type": event_type }),
+                DatabaseOperationLogStatus::Err(err.to_string()),
+            );
+
+            return Err(WebhookEnqueueError::Database(err.to_string()));
+        }
+
+        return Ok(());
+    }
+}
+
+impl WebhookDelivery {
+    /// ## WebhookDelivery::list
+    ///
+    /// Lists deliveries with provided status (all when None), optionally only of provided webhook
+    ///
+    pub async fn list(
+        conn: &mut PgConnection,
+        status: Option<&String>,
+        webhook_id: Option<i32>,
+        order: Option<Order>,
+        offset: Option<usize>,
+        limit: Option<usize>
+    ) -> Vec<WebhookDelivery> {
+        let order = order.unwrap_or(Order::Ascending);
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(10);
+
+        let sql = format!(
+            "
+        SELECT
+            *
+        FROM
+            webhook_deliveries
+        WHERE
+            ($1::VARCHAR IS NULL OR status = $1)
+            AND ($2::INT IS NULL OR webhook_id = $2)
+        ORDER BY id {}
+        OFFSET {} ROWS
+        LIMIT {};
+        ",
+            order.to_string(),
+            offset,
+            limit
+        );
+
+        return query_as(&sql)
+            .bind(&status)
+            .bind(&webhook_id)
+            .fetch_all(conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## WebhookDelivery::claim_due
+    ///
+    /// Marks pending deliveries whose next attempt is due as "in_flight" for provided number of seconds
+    /// and retrieves them, along with the in flight ones whose lease ended without their outcome being recorded. <br>
+    /// Should be committed before the deliveries are sent, so no locks are held meanwhile
+    ///
+    pub async fn claim_due(
+        conn: &mut PgConnection,
+        limit: i64,
+        lease: i32
+    ) -> Vec<WebhookDispatch> {
+        let sql = "
+        UPDATE
+            webhook_deliveries d
+        SET
+            status = 'in_flight',
+            leased_until = NOW() + make_interval(secs => $2::INT)
+        FROM
+            webhooks w
+        WHERE
+            w.id = d.webhook_id
+            AND d.id IN (
+                SELECT id
+                FROM webhook_deliveries
+                WHERE (status = 'pending' AND next_attempt_at <= NOW())
+                    OR (status = 'in_flight' AND leased_until <= NOW())
+                ORDER BY id
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+        RETURNING
+            d.*,
+            w.url,
+            w.secret;
+        ";
+
+        let mut claimed: Vec<WebhookDispatch> = query_as(sql)
+            .bind(&limit)
+            .bind(&lease)
+            .fetch_all(conn)
+            .await
+            .unwrap();
+
+        claimed.sort_by_key(|dispatch| dispatch.delivery.id);
+
+        return claimed;
+    }
+
+    /// ## WebhookDelivery::delivered
+    ///
+    /// Deletes the successfully delivered delivery
+    ///
+    pub async fn delivered(
+        conn: &mut PgConnection,
+        id: &i32
+    ) {
+        let sql = "DELETE FROM webhook_deliveries WHERE id = $1 AND status = 'in_flight';";
+        query(sql)
+            .bind(&id)
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## WebhookDelivery::failed
+    ///
+    /// Records the failed attempt of the in flight delivery, scheduling the next one with exponential backoff,
+    /// or marking the delivery dead after provided number of attempts
+    ///
+    pub async fn failed(
+        conn: &mut PgConnection,
+        id: &i32,
+        error: &String,
+        max_attempts: i32
+    ) {
+        let sql = "
+        UPDATE
+            webhook_deliveries
+        SET
+            attempts = attempts + 1,
+            last_error = $2,
+            next_attempt_at = NOW() + make_interval(secs => LEAST($3 * POWER(2, attempts), $4)),
+            status = CASE WHEN attempts + 1 >= $5 THEN 'dead' ELSE 'pending' END,
+            leased_until = NULL
+        WHERE
+            id = $1 AND status = 'in_flight';
+        ";
+
+        query(sql)
+            .bind(&id)
+            .bind(&error)
+            .bind(&RETRY_BACKOFF)
+            .bind(&MAX_RETRY_BACKOFF)
+            .bind(&max_attempts)
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    /// ## WebhookDelivery::retry
+    ///
+    /// Schedules a dead delivery to be sent again right away, with it's attempts reset
+    ///
+    /// Errors:
+    /// + when there is no dead delivery with provided id
+    ///
+    pub async fn retry(
+        conn: &mut PgConnection,
+        id: &i32
+    ) -> Result<(), WebhookDeliveryRetryError> {
+        let sql = "
+        UPDATE
+            webhook_deliveries
+        SET
+            status = 'pending',
+            attempts = 0,
+            next_attempt_at = NOW()
+        WHERE
+            id = $1 AND status = 'dead';
+        ";
+
+        let result = query(sql)
+            .bind(&id)
+            .execute(conn)
+            .await
+            .unwrap();
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookDeliveryRetryError::NotFound);
+        }
+
+        log_database_interaction::<String>(
+            "Retrying a dead webhook delivery.",
+            json!({ "id": id }),
+            DatabaseOperationLogStatus::Ok,
+        );
+
+        return Ok(());
+    }
+}
+
+impl WebhookDispatch {
+    /// ## WebhookDispatch::body
+    ///
+    /// Body the delivery is sent with
+    ///
+    pub fn body(&self) -> String {
+        return json!({
+            "id": self.delivery.id,
+            "type": self.delivery.event_type,
+            "created_at": self.delivery.created_at,
+            "data": self.delivery.payload
+        })
+        .to_string();
+    }
+
+    /// ## WebhookDispatch::signature
+    ///
+    /// Signature of the body, sent in the X-Cauth-Signature header
+    ///
+    pub fn signature(&self, body: &str) -> String {
+        return format!("sha256={}", sign(&self.secret, body.as_bytes()));
+    }
+}
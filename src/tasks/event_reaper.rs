@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use crate::{config::CauthConfig, models::event};
+
+/// how often the expired events are deleted
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// ## event_reaper::run
+///
+/// Periodically deletes pending two-phase events older than the configured TTL.
+/// Expired events cannot be committed anymore, this also drops the password hashes they hold
+///
+pub async fn run(config: CauthConfig) {
+    let mut interval = tokio::time::interval(INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let mut db_conn = match config.db_conn.acquire().await {
+            Ok(db_conn) => db_conn,
+            Err(err) => {
+                log::error!("Cannot acquire a database connection to delete expired events: {}", err);
+                continue;
+            }
+        };
+
+        let _ = event::delete_expired(&mut db_conn, config.event_ttl).await;
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use crate::{config::CauthConfig, models::Grant};
+
+/// how often the expired grants are deleted
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// ## grant_reaper::run
+///
+/// Periodically deletes expired group memberships and permission grants.
+/// Expired grants are already ignored during permission resolution,
+/// this only keeps the tables clean
+///
+pub async fn run(config: CauthConfig) {
+    let mut interval = tokio::time::interval(INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let mut db_conn = match config.db_conn.acquire().await {
+            Ok(db_conn) => db_conn,
+            Err(err) => {
+                log::error!("Cannot acquire a database connection to delete expired grants: {}", err);
+                continue;
+            }
+        };
+
+        let _ = Grant::delete_expired(&mut db_conn).await;
+    }
+}
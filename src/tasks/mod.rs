@@ -0,0 +1,20 @@
+pub mod event_reaper;
+pub mod grant_reaper;
+pub mod outbox_publisher;
+pub mod webhook_dispatcher;
+
+use crate::config::CauthConfig;
+
+/// ## spawn_background_tasks
+///
+/// Spawns all the periodic background tasks of the service
+///
+pub fn spawn_background_tasks(config: &CauthConfig) {
+    tokio::spawn(grant_reaper::run(config.clone()));
+    tokio::spawn(event_reaper::run(config.clone()));
+    tokio::spawn(webhook_dispatcher::run(config.clone()));
+
+    if let Some(broker) = &config.broker {
+        tokio::spawn(outbox_publisher::run(config.clone(), broker.clone()));
+    }
+}
@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use crate::{
+    brokers::Broker,
+    config::{BrokerConfig, CauthConfig},
+    models::outbox::OutboxEvent
+};
+
+/// how often the outbox is checked for new events
+const INTERVAL: Duration = Duration::from_secs(1);
+/// maximal number of events published at every tick
+const BATCH_SIZE: i64 = 100;
+
+/// publishes the claimed events in order, stopping at the first failure so the order is kept
+async fn publish(broker: &mut Broker, events: &Vec<OutboxEvent>) -> (Vec<i64>, Option<String>) {
+    let mut published = vec![];
+
+    for event in events {
+        if let Err(err) = broker.publish(event).await {
+            return (published, Some(err));
+        }
+
+        published.push(event.id);
+    }
+
+    return (published, None);
+}
+
+/// ## outbox_publisher::publish_pending
+///
+/// Publishes the events recorded in the outbox in order, deleting the published ones,
+/// returning the number of published events
+///
+/// Errors:
+/// + when the broker do not accept one of the events, it and the ones after it are published again later
+///
+pub async fn publish_pending(config: &CauthConfig, broker: &mut Broker) -> Result<usize, String> {
+    let mut db_conn = match config.db_conn.begin().await {
+        Ok(db_conn) => db_conn,
+        Err(err) => {
+            log::error!("Cannot acquire a database connection to publish domain events: {}", err);
+            return Ok(0);
+        }
+    };
+
+    let events = match OutboxEvent::claim(&mut db_conn, BATCH_SIZE).await {
+        Some(events) if !events.is_empty() => events,
+        // empty, or published by another instance
+        _ => return Ok(0)
+    };
+
+    let (published, error) = publish(broker, &events).await;
+
+    OutboxEvent::delete(&mut db_conn, &published).await;
+
+    if let Err(err) = db_conn.commit().await {
+        log::error!("Cannot delete published domain events from the outbox: {}", err);
+    }
+
+    return match error {
+        Some(err) => Err(err),
+        None => Ok(published.len())
+    };
+}
+
+/// ## outbox_publisher::run
+///
+/// Periodically publishes the domain events recorded in the outbox to the configured broker,
+/// deleting them once the broker accepts them. Events are delivered at least once,
+/// as an event is published again when the outbox cannot be updated after publishing it
+///
+pub async fn run(config: CauthConfig, broker_config: BrokerConfig) {
+    let mut interval = tokio::time::interval(INTERVAL);
+    let mut broker: Option<Broker> = None;
+
+    loop {
+        interval.tick().await;
+
+        if broker.is_none() {
+            broker = match Broker::connect(&broker_config).await {
+                Ok(broker) => Some(broker),
+                Err(err) => {
+                    log::error!("Cannot connect to the broker to publish domain events: {}", err);
+                    continue;
+                }
+            };
+        }
+
+        if let Err(err) = publish_pending(&config, broker.as_mut().unwrap()).await {
+            log::error!("Cannot publish domain event to the broker: {}", err);
+            // connects again at the next tick
+            broker = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex}
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener
+    };
+    use crate::{
+        models::outbox::DomainEvent,
+        util::testing
+    };
+
+    async fn remaining(config: &CauthConfig) -> i64 {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM outbox_events;")
+            .fetch_one(&config.db_conn)
+            .await
+            .unwrap();
+        return count;
+    }
+
+    /// names of the complete RESP commands at the start of the buffer, removing them from it,
+    /// the arguments are expected to have no line breaks
+    fn commands(buffer: &mut Vec<u8>) -> Vec<String> {
+        let text = String::from_utf8_lossy(buffer).to_string();
+        let mut lines = text.split_inclusive("\r\n");
+        let mut names = vec![];
+        let mut consumed = 0;
+
+        'commands: while let Some(header) = lines.next() {
+            if !header.ends_with("\r\n") {
+                break;
+            }
+            let mut length = header.len();
+            let mut command = vec![];
+            let arguments: usize = header[1..header.len() - 2].parse().unwrap();
+            for _ in 0..arguments * 2 {
+                match lines.next() {
+                    Some(line) if line.ends_with("\r\n") => {
+                        length += line.len();
+                        command.push(line.trim_end().to_string());
+                    },
+                    _ => break 'commands
+                }
+            }
+
+            consumed += length;
+            // the lengths and values of the arguments alternate
+            names.push(command[1].to_uppercase());
+        }
+
+        buffer.drain(..consumed);
+        return names;
+    }
+
+    /// starts a server answering like Redis which fails the listed XADD commands (counted from 0),
+    /// returning it's url
+    async fn failing_redis(failing: Vec<usize>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}/", listener.local_addr().unwrap());
+        let attempts = Arc::new(Mutex::new(0));
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let (failing, attempts) = (failing.clone(), attempts.clone());
+
+                tokio::spawn(async move {
+                    let mut buffer = vec![];
+                    let mut chunk = [0u8; 4096];
+                    loop {
+                        let read = match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => read
+                        };
+                        buffer.extend_from_slice(&chunk[..read]);
+
+                        for name in commands(&mut buffer) {
+                            let response = if name == "XADD" {
+                                let attempt = {
+                                    let mut attempts = attempts.lock().unwrap();
+                                    *attempts += 1;
+                                    *attempts - 1
+                                };
+                                match failing.contains(&attempt) {
+                                    true => "-ERR unavailable\r\n",
+                                    false => "$3\r\n1-0\r\n"
+                                }
+                            } else {
+                                "+OK\r\n"
+                            };
+
+                            if socket.write_all(response.as_bytes()).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        return url;
+    }
+
+    #[tokio::test]
+    async fn keeps_the_events_after_a_refused_one() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let config = testing::config(pool.clone(), None);
+
+        let url = failing_redis(vec![1]).await;
+        let broker_config = BrokerConfig::RedisStreams { url, stream: "cauth:events".to_string() };
+
+        for login in ["jane", "john", "joe"] {
+            OutboxEvent::record(&mut conn, DomainEvent::UserRegistered { login: login.to_string() }).await;
+        }
+
+        // the broker refuses the second event, so the ones after it are kept in the outbox
+        let mut broker = Broker::connect(&broker_config).await.unwrap();
+        assert!(publish_pending(&config, &mut broker).await.is_err());
+        assert_eq!(remaining(&config).await, 2);
+
+        let mut broker = Broker::connect(&broker_config).await.unwrap();
+        assert_eq!(publish_pending(&config, &mut broker).await.unwrap(), 2);
+        assert_eq!(remaining(&config).await, 0);
+    }
+
+    #[tokio::test]
+    async fn publishes_the_events_to_the_stream_in_order() {
+        let Some(pool) = testing::database().await else { return; };
+        let Some(url) = testing::redis() else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let config = testing::config(pool.clone(), None);
+
+        let stream = format!("cauth_test:{}:events", std::process::id());
+        let mut redis_conn = redis::Client::open(url.as_str())
+            .unwrap()
+            .get_multiplexed_tokio_connection()
+            .await
+            .unwrap();
+        let _: i64 = redis::cmd("DEL").arg(&stream).query_async(&mut redis_conn).await.unwrap();
+
+        for login in ["jane", "john", "joe"] {
+            OutboxEvent::record(&mut conn, DomainEvent::UserRegistered { login: login.to_string() }).await;
+        }
+
+        let broker_config = BrokerConfig::RedisStreams { url, stream: stream.clone() };
+        let mut broker = Broker::connect(&broker_config).await.unwrap();
+        assert_eq!(publish_pending(&config, &mut broker).await.unwrap(), 3);
+        assert_eq!(publish_pending(&config, &mut broker).await.unwrap(), 0);
+        assert_eq!(remaining(&config).await, 0);
+
+        let entries: Vec<(String, HashMap<String, String>)> = redis::cmd("XRANGE")
+            .arg(&stream)
+            .arg("-")
+            .arg("+")
+            .query_async(&mut redis_conn)
+            .await
+            .unwrap();
+        let _: i64 = redis::cmd("DEL").arg(&stream).query_async(&mut redis_conn).await.unwrap();
+
+        assert!(entries.iter().all(|(_, fields)| fields["type"] == "UserRegistered"));
+
+        let logins: Vec<String> = entries
+            .iter()
+            .map(|(_, fields)| serde_json::from_str::<serde_json::Value>(&fields["data"]).unwrap()["login"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(logins, vec!["jane", "john", "joe"]);
+
+        let ids: Vec<i64> = entries.iter().map(|(_, fields)| fields["id"].parse().unwrap()).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
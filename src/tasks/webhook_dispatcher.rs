@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use crate::{
+    config::CauthConfig,
+    models::webhook::{WebhookDelivery, WebhookDispatch}
+};
+
+/// how often the due deliveries are sent
+const INTERVAL: Duration = Duration::from_secs(5);
+/// how long a receiver can take to respond
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// maximal number of deliveries sent at every tick
+const BATCH_SIZE: i64 = 50;
+/// seconds the claimed deliveries are leased for, longer than sending all of them takes
+const LEASE: i32 = 10 * 60;
+
+/// sends the delivery, returning the reason when the receiver did not accept it
+async fn send(client: &reqwest::Client, dispatch: &WebhookDispatch) -> Result<(), String> {
+    let body = dispatch.body();
+    let signature = dispatch.signature(&body);
+
+    let result = client
+        .post(&dispatch.url)
+        .header("Content-Type", "application/json")
+        .header("X-Cauth-Event", &dispatch.delivery.event_type)
+        .header("X-Cauth-Delivery", dispatch.delivery.id.to_string())
+        .header("X-Cauth-Signature", signature)
+        .body(body)
+        .send()
+        .await;
+
+    return match result {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(format!("Receiver responded with status {}", response.status())),
+        Err(err) => Err(err.to_string())
+    };
+}
+
+/// ## webhook_dispatcher::dispatch
+///
+/// Claims the due deliveries, sends them and records their outcomes,
+/// returning the number of claimed deliveries
+///
+pub async fn dispatch(config: &CauthConfig, client: &reqwest::Client) -> Result<usize, sqlx::Error> {
+    // claimed deliveries are leased rather than locked, so no connection is held while sending them
+    let due = {
+        let mut db_conn = config.db_conn.acquire().await?;
+        WebhookDelivery::claim_due(&mut db_conn, BATCH_SIZE, LEASE).await
+    };
+
+    for dispatch in due.iter() {
+        let outcome = send(client, dispatch).await;
+
+        // every outcome is recorded on it's own, so a failing one does not resend the others
+        let mut db_conn = config.db_conn.acquire().await?;
+        match outcome {
+            Ok(_) => WebhookDelivery::delivered(&mut db_conn, &dispatch.delivery.id).await,
+            Err(error) => {
+                WebhookDelivery::failed(
+                    &mut db_conn,
+                    &dispatch.delivery.id,
+                    &error,
+                    config.webhook_max_attempts
+                )
+                .await
+            }
+        };
+    }
+
+    return Ok(due.len());
+}
+
+/// ## webhook_dispatcher::run
+///
+/// Periodically sends the due webhook deliveries, retrying the failed ones with backoff
+/// until they are dead-lettered after the configured number of attempts
+///
+pub async fn run(config: CauthConfig) {
+    let mut interval = tokio::time::interval(INTERVAL);
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .unwrap();
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = dispatch(&config, &client).await {
+            log::error!("Cannot send webhook deliveries: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener
+    };
+    use crate::{
+        models::webhook::{sign, Webhook},
+        util::testing
+    };
+
+    /// request received by the local receiver
+    struct Received {
+        delivery: String,
+        signature: String,
+        body: String
+    }
+
+    /// starts a receiver responding with the listed statuses and then with 200, returning it's url
+    async fn receiver(statuses: Vec<u16>, received: Arc<Mutex<Vec<Received>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                let mut request = vec![];
+                let mut buffer = [0u8; 4096];
+                let (head, length) = loop {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let head = text[..end].to_string();
+                        let length: usize = header(&head, "content-length").parse().unwrap();
+                        break (head, end + 4 + length);
+                    }
+                };
+                while request.len() < length {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+
+                received.lock().unwrap().push(Received {
+                    delivery: header(&head, "x-cauth-delivery"),
+                    signature: header(&head, "x-cauth-signature"),
+                    body: String::from_utf8_lossy(&request[head.len() + 4..length]).to_string()
+                });
+
+                let status = statuses.next().unwrap_or(200);
+                let response = format!("HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        return url;
+    }
+
+    fn header(head: &str, name: &str) -> String {
+        return head
+            .lines()
+            .find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+            })
+            .unwrap_or_default();
+    }
+
+    #[tokio::test]
+    async fn signs_the_deliveries_and_retries_the_failed_ones() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+        let config = testing::config(pool.clone(), None);
+        let client = reqwest::Client::new();
+
+        let received = Arc::new(Mutex::new(vec![]));
+        let url = receiver(vec![500], received.clone()).await;
+
+        let credentials = Webhook::insert(&mut conn, &url, &vec![], None)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        assert_eq!(credentials.secret.len(), 64);
+        assert!(credentials.secret.chars().all(|char| char.is_ascii_hexdigit()));
+
+        Webhook::enqueue(&mut conn, "user.registered", json!({ "login": "jane" }))
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        // the receiver fails the first attempt
+        assert_eq!(dispatch(&config, &client).await.unwrap(), 1);
+        let (status, attempts, leased_until): (String, i32, Option<chrono::DateTime<chrono::Utc>>) =
+            sqlx::query_as("SELECT status, attempts, leased_until FROM webhook_deliveries;")
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+        assert_eq!(status, "pending");
+        assert_eq!(attempts, 1);
+        assert!(leased_until.is_none());
+
+        // the retry waits for the backoff
+        assert_eq!(dispatch(&config, &client).await.unwrap(), 0);
+
+        sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = NOW();")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(dispatch(&config, &client).await.unwrap(), 1);
+
+        let remaining: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM webhook_deliveries;")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(remaining.0, 0);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].delivery, received[1].delivery);
+        for request in received.iter() {
+            assert_eq!(request.signature, format!("sha256={}", sign(&credentials.secret, request.body.as_bytes())));
+            assert_eq!(serde_json::from_str::<serde_json::Value>(&request.body).unwrap()["data"]["login"], "jane");
+        }
+    }
+
+    #[tokio::test]
+    async fn claims_the_deliveries_whose_lease_ended() {
+        let Some(pool) = testing::database().await else { return; };
+        let mut conn = pool.acquire().await.unwrap();
+
+        Webhook::insert(&mut conn, &"http://127.0.0.1:1/hook".to_string(), &vec![], None)
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+        Webhook::enqueue(&mut conn, "user.deleted", json!({ "login": "jane" }))
+            .await
+            .map_err(|err| err.to_string())
+            .unwrap();
+
+        assert_eq!(WebhookDelivery::claim_due(&mut conn, BATCH_SIZE, LEASE).await.len(), 1);
+        // the claimed delivery is not sent twice while it's leased
+        assert!(WebhookDelivery::claim_due(&mut conn, BATCH_SIZE, LEASE).await.is_empty());
+
+        // the dispatcher stopped before recording the outcome
+        sqlx::query("UPDATE webhook_deliveries SET leased_until = NOW() - INTERVAL '1 second';")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(WebhookDelivery::claim_due(&mut conn, BATCH_SIZE, LEASE).await.len(), 1);
+    }
+}
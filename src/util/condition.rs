@@ -0,0 +1,779 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+/// A parsed grant condition.
+///
+/// Conditions are small boolean expressions written in a CEL-like language, e.g.:
+///
+/// ```text
+/// details.department == "finance" && request.hour >= 8 && request.hour < 18
+/// request.ip in ["10.0.0.1", "10.0.0.2"] || details.email.endsWith("@example.com")
+/// ```
+///
+/// Supported syntax:
+/// + literals: strings ("..." or '...'), numbers, `true`, `false`, `null` and lists (`[a, b]`)
+/// + attribute paths starting with `details` (the user's details) or `request` (request context)
+/// + operators: `==`, `!=`, `<`, `<=`, `>`, `>=`, `in`, `&&`, `||`, `!` and parentheses
+/// + methods: `startsWith`, `endsWith`, `contains` and `size`
+///
+#[derive(Debug, Clone)]
+pub struct Condition {
+    expression: Expression,
+}
+
+#[derive(Debug)]
+pub enum ConditionParseError {
+    /// Returned when the expression contains a character that is not part of the language
+    UnexpectedCharacter(char),
+    /// Returned when a string literal is never closed
+    UnterminatedString,
+    /// Returned when a token appears where it is not allowed
+    UnexpectedToken(String),
+    /// Returned when the expression ends too early
+    UnexpectedEnd,
+    /// Returned when an attribute path does not start with a known root
+    UnknownAttribute(String),
+    /// Returned when an unknown method is called
+    UnknownMethod(String),
+    /// Returned when the expression is nested deeper than MAX_DEPTH
+    TooDeep,
+    /// Returned when an operator or method is used with operands of the wrong type
+    TypeMismatch(String),
+}
+
+impl ToString for ConditionParseError {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::UnexpectedCharacter(c) => format!("Unexpected character '{}'", c),
+            Self::UnterminatedString => "Unterminated string literal".to_string(),
+            Self::UnexpectedToken(token) => format!("Unexpected token '{}'", token),
+            Self::UnexpectedEnd => "Unexpected end of expression".to_string(),
+            Self::UnknownAttribute(name) => format!(
+                "Unknown attribute '{}', attributes have to start with 'details' or 'request'",
+                name
+            ),
+            Self::UnknownMethod(name) => format!("Unknown method '{}'", name),
+            Self::TooDeep => format!("Expression is nested deeper than {} levels", MAX_DEPTH),
+            Self::TypeMismatch(details) => format!("Type mismatch, {}", details),
+        };
+    }
+}
+
+const ATTRIBUTE_ROOTS: [&str; 2] = ["details", "request"];
+const METHODS: [&str; 4] = ["startsWith", "endsWith", "contains", "size"];
+/// Limits nesting of parentheses, lists, negations, method calls and chained operators,
+/// so that parsing and evaluating an expression cannot overflow the stack
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Str(String),
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl ToString for Token {
+    fn to_string(&self) -> String {
+        return match self {
+            Self::Str(value) => format!("\"{}\"", value),
+            Self::Number(value) => value.to_string(),
+            Self::Ident(value) => value.clone(),
+            Self::Op(value) => value.to_string(),
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expression {
+    Literal(Value),
+    List(Vec<Expression>),
+    Attribute(Vec<String>),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Compare(&'static str, Box<Expression>, Box<Expression>),
+    Method(String, Box<Expression>, Vec<Expression>),
+}
+
+impl Condition {
+    /// ## Condition::parse
+    ///
+    /// Parses a condition expression
+    ///
+    /// Errors:
+    /// + when the expression is not valid
+    ///
+    pub fn parse(source: &str) -> Result<Self, ConditionParseError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, position: 0, depth: 0 };
+        let expression = parser.parse_or()?;
+
+        if let Some(token) = parser.peek() {
+            return Err(ConditionParseError::UnexpectedToken(token.to_string()));
+        }
+
+        expect_type(&expression, &[Type::Bool], "the condition")?;
+
+        return Ok(Self { expression });
+    }
+
+    /// ## Condition::try_evaluate
+    ///
+    /// Evaluates the condition against provided attributes,
+    /// returning None when it cannot be evaluated (e.g. an attribute has an unexpected type)
+    ///
+    pub fn try_evaluate(&self, attributes: &Value) -> Option<bool> {
+        return evaluate(&self.expression, attributes)?.as_bool();
+    }
+}
+
+/// ## condition_attributes
+///
+/// Builds the attributes that conditions are evaluated against
+///
+pub fn condition_attributes(
+    details: &Value,
+    ip: &Option<String>,
+    time: &DateTime<Utc>,
+    resource: &Option<String>,
+) -> Value {
+    let request = json!({
+        "ip": ip,
+        "time": time.to_rfc3339(),
+        "hour": time.format("%H").to_string().parse::<u32>().unwrap(),
+        "minute": time.format("%M").to_string().parse::<u32>().unwrap(),
+        "weekday": time.format("%a").to_string(),
+        "resource": resource,
+    });
+
+    return json!({
+        "details": details,
+        "request": request
+    });
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ConditionParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            let mut value = String::new();
+            i += 1;
+
+            loop {
+                match chars.get(i) {
+                    None => return Err(ConditionParseError::UnterminatedString),
+                    Some('\\') => {
+                        match chars.get(i + 1) {
+                            Some(escaped) => value.push(*escaped),
+                            None => return Err(ConditionParseError::UnterminatedString),
+                        };
+                        i += 2;
+                    }
+                    Some(ch) if *ch == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(ch) => {
+                        value.push(*ch);
+                        i += 1;
+                    }
+                }
+            }
+
+            tokens.push(Token::Str(value));
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let raw: String = chars[start..i].iter().collect();
+
+            match raw.parse::<f64>() {
+                Ok(number) => tokens.push(Token::Number(number)),
+                Err(_) => return Err(ConditionParseError::UnexpectedToken(raw)),
+            };
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        let next = chars.get(i + 1).copied();
+        let op: &'static str = match (c, next) {
+            ('=', Some('=')) => "==",
+            ('!', Some('=')) => "!=",
+            ('<', Some('=')) => "<=",
+            ('>', Some('=')) => ">=",
+            ('&', Some('&')) => "&&",
+            ('|', Some('|')) => "||",
+            ('<', _) => "<",
+            ('>', _) => ">",
+            ('!', _) => "!",
+            ('(', _) => "(",
+            (')', _) => ")",
+            ('[', _) => "[",
+            (']', _) => "]",
+            (',', _) => ",",
+            ('.', _) => ".",
+            _ => return Err(ConditionParseError::UnexpectedCharacter(c)),
+        };
+
+        i += op.len();
+        tokens.push(Token::Op(op));
+    }
+
+    return Ok(tokens);
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    /// enters a nested level of the expression, the caller leaves it by decreasing the depth
+    fn enter(&mut self) -> Result<(), ConditionParseError> {
+        self.depth += 1;
+
+        if self.depth > MAX_DEPTH {
+            return Err(ConditionParseError::TooDeep);
+        }
+
+        return Ok(());
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.position);
+    }
+
+    fn next(&mut self) -> Result<Token, ConditionParseError> {
+        let token = match self.tokens.get(self.position) {
+            Some(token) => token.clone(),
+            None => return Err(ConditionParseError::UnexpectedEnd),
+        };
+        self.position += 1;
+
+        return Ok(token);
+    }
+
+    fn consume_op(&mut self, op: &'static str) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.position += 1;
+            return true;
+        }
+
+        return false;
+    }
+
+    fn expect_op(&mut self, op: &'static str) -> Result<(), ConditionParseError> {
+        let token = self.next()?;
+
+        if token != Token::Op(op) {
+            return Err(ConditionParseError::UnexpectedToken(token.to_string()));
+        }
+
+        return Ok(());
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, ConditionParseError> {
+        let depth = self.depth;
+        self.enter()?;
+        let mut left = self.parse_and()?;
+
+        // every chained operand nests the expression one level deeper
+        while self.consume_op("||") {
+            self.enter()?;
+            let right = self.parse_and()?;
+            left = Expression::Or(Box::new(left), Box::new(right));
+        }
+
+        self.depth = depth;
+        return Ok(left);
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, ConditionParseError> {
+        let depth = self.depth;
+        let mut left = self.parse_not()?;
+
+        while self.consume_op("&&") {
+            self.enter()?;
+            let right = self.parse_not()?;
+            left = Expression::And(Box::new(left), Box::new(right));
+        }
+
+        self.depth = depth;
+        return Ok(left);
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, ConditionParseError> {
+        if self.consume_op("!") {
+            self.enter()?;
+            let inner = self.parse_not()?;
+            self.depth -= 1;
+
+            return Ok(Expression::Not(Box::new(inner)));
+        }
+
+        return self.parse_comparison();
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expression, ConditionParseError> {
+        let left = self.parse_postfix()?;
+
+        let op: &'static str = match self.peek() {
+            Some(Token::Op(op)) if ["==", "!=", "<", "<=", ">", ">="].contains(op) => op,
+            Some(Token::Ident(ident)) if ident == "in" => "in",
+            _ => return Ok(left),
+        };
+        self.position += 1;
+
+        let right = self.parse_postfix()?;
+
+        return Ok(Expression::Compare(op, Box::new(left), Box::new(right)));
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expression, ConditionParseError> {
+        let depth = self.depth;
+        let mut expression = self.parse_primary()?;
+
+        while self.consume_op(".") {
+            let name = match self.next()? {
+                Token::Ident(name) => name,
+                token => return Err(ConditionParseError::UnexpectedToken(token.to_string())),
+            };
+
+            if !self.consume_op("(") {
+                // plain attribute access, only allowed on attribute paths
+                match &mut expression {
+                    Expression::Attribute(path) => path.push(name),
+                    _ => return Err(ConditionParseError::UnexpectedToken(name)),
+                };
+                continue;
+            }
+
+            if !METHODS.contains(&name.as_str()) {
+                return Err(ConditionParseError::UnknownMethod(name));
+            }
+            self.enter()?;
+
+            let mut arguments = vec![];
+            if !self.consume_op(")") {
+                loop {
+                    arguments.push(self.parse_or()?);
+
+                    if self.consume_op(")") {
+                        break;
+                    }
+                    self.expect_op(",")?;
+                }
+            }
+
+            expression = Expression::Method(name, Box::new(expression), arguments);
+        }
+
+        self.depth = depth;
+        return Ok(expression);
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ConditionParseError> {
+        let token = self.next()?;
+
+        return match token {
+            Token::Str(value) => Ok(Expression::Literal(json!(value))),
+            Token::Number(value) => Ok(Expression::Literal(json!(value))),
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Ok(Expression::Literal(json!(true))),
+                "false" => Ok(Expression::Literal(json!(false))),
+                "null" => Ok(Expression::Literal(Value::Null)),
+                root if ATTRIBUTE_ROOTS.contains(&root) => Ok(Expression::Attribute(vec![ident])),
+                _ => Err(ConditionParseError::UnknownAttribute(ident)),
+            },
+            Token::Op("(") => {
+                let inner = self.parse_or()?;
+                self.expect_op(")")?;
+
+                Ok(inner)
+            }
+            Token::Op("[") => {
+                let mut items = vec![];
+
+                if !self.consume_op("]") {
+                    loop {
+                        items.push(self.parse_or()?);
+
+                        if self.consume_op("]") {
+                            break;
+                        }
+                        self.expect_op(",")?;
+                    }
+                }
+
+                Ok(Expression::List(items))
+            }
+            token => Err(ConditionParseError::UnexpectedToken(token.to_string())),
+        };
+    }
+}
+
+/// Type of an expression known before evaluation, attributes can hold any type
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Bool,
+    Number,
+    String,
+    List,
+    Null,
+    Any,
+}
+
+/// infers the type of an expression, rejecting operands which can never have the expected type
+fn infer_type(expression: &Expression) -> Result<Type, ConditionParseError> {
+    return match expression {
+        Expression::Literal(Value::Bool(_)) => Ok(Type::Bool),
+        Expression::Literal(Value::Number(_)) => Ok(Type::Number),
+        Expression::Literal(Value::String(_)) => Ok(Type::String),
+        Expression::Literal(_) => Ok(Type::Null),
+        Expression::List(items) => {
+            for item in items {
+                infer_type(item)?;
+            }
+
+            Ok(Type::List)
+        }
+        Expression::Attribute(_) => Ok(Type::Any),
+        Expression::Not(inner) => {
+            expect_type(inner, &[Type::Bool], "'!'")?;
+
+            Ok(Type::Bool)
+        }
+        Expression::And(left, right) | Expression::Or(left, right) => {
+            expect_type(left, &[Type::Bool], "'&&' and '||'")?;
+            expect_type(right, &[Type::Bool], "'&&' and '||'")?;
+
+            Ok(Type::Bool)
+        }
+        Expression::Compare(op, left, right) => {
+            match *op {
+                "==" | "!=" => {
+                    infer_type(left)?;
+                    infer_type(right)?;
+                }
+                "in" => {
+                    infer_type(left)?;
+                    expect_type(right, &[Type::List], "'in'")?;
+                }
+                _ => {
+                    let left_type = expect_type(left, &[Type::Number, Type::String], op)?;
+                    let right_type = expect_type(right, &[Type::Number, Type::String], op)?;
+
+                    if left_type != Type::Any && right_type != Type::Any && left_type != right_type {
+                        return Err(ConditionParseError::TypeMismatch(format!(
+                            "'{}' compares {:?} to {:?}",
+                            op, left_type, right_type
+                        )));
+                    }
+                }
+            };
+
+            Ok(Type::Bool)
+        }
+        Expression::Method(name, target, arguments) => {
+            // size takes no argument, the other methods take a single one
+            let (targets, argument, output): (&[Type], Option<&[Type]>, Type) = match name.as_str() {
+                "startsWith" | "endsWith" => (&[Type::String], Some(&[Type::String]), Type::Bool),
+                "contains" => (&[Type::String, Type::List], Some(&[Type::Bool, Type::Number, Type::String, Type::List, Type::Null]), Type::Bool),
+                _ => (&[Type::String, Type::List], None, Type::Number),
+            };
+
+            expect_type(target, targets, name)?;
+
+            match (argument, arguments.as_slice()) {
+                (Some(expected), [value]) => {
+                    expect_type(value, expected, name)?;
+                }
+                (None, []) => (),
+                _ => {
+                    return Err(ConditionParseError::TypeMismatch(format!(
+                        "'{}' takes {} argument(s)",
+                        name,
+                        if argument.is_some() { 1 } else { 0 }
+                    )))
+                }
+            };
+
+            Ok(output)
+        }
+    };
+}
+
+/// infers the type of an expression, failing unless it can be one of the expected types
+fn expect_type(expression: &Expression, expected: &[Type], used_by: &str) -> Result<Type, ConditionParseError> {
+    let actual = infer_type(expression)?;
+
+    if actual != Type::Any && !expected.contains(&actual) {
+        return Err(ConditionParseError::TypeMismatch(format!(
+            "{} expects {:?} but got {:?}",
+            used_by, expected, actual
+        )));
+    }
+
+    return Ok(actual);
+}
+
+fn evaluate(expression: &Expression, attributes: &Value) -> Option<Value> {
+    return match expression {
+        Expression::Literal(value) => Some(value.clone()),
+        Expression::List(items) => {
+            let mut values = vec![];
+            for item in items {
+                values.push(evaluate(item, attributes)?);
+            }
+
+            Some(Value::Array(values))
+        }
+        Expression::Attribute(path) => {
+            let mut current = attributes;
+            for segment in path {
+                current = current.get(segment).unwrap_or(&Value::Null);
+            }
+
+            Some(current.clone())
+        }
+        Expression::Not(inner) => Some(json!(!evaluate(inner, attributes)?.as_bool()?)),
+        Expression::And(left, right) => {
+            if !evaluate(left, attributes)?.as_bool()? {
+                return Some(json!(false));
+            }
+
+            Some(json!(evaluate(right, attributes)?.as_bool()?))
+        }
+        Expression::Or(left, right) => {
+            if evaluate(left, attributes)?.as_bool()? {
+                return Some(json!(true));
+            }
+
+            Some(json!(evaluate(right, attributes)?.as_bool()?))
+        }
+        Expression::Compare(op, left, right) => {
+            let left = evaluate(left, attributes)?;
+            let right = evaluate(right, attributes)?;
+
+            Some(json!(compare(op, &left, &right)?))
+        }
+        Expression::Method(name, target, arguments) => {
+            let target = evaluate(target, attributes)?;
+            let mut values = vec![];
+            for argument in arguments {
+                values.push(evaluate(argument, attributes)?);
+            }
+
+            call_method(name, &target, &values)
+        }
+    };
+}
+
+fn compare(op: &str, left: &Value, right: &Value) -> Option<bool> {
+    return match op {
+        "==" => Some(values_equal(left, right)),
+        "!=" => Some(!values_equal(left, right)),
+        "in" => Some(
+            right
+                .as_array()?
+                .iter()
+                .any(|item| values_equal(left, item)),
+        ),
+        _ => {
+            let ordering = if let (Some(l), Some(r)) = (left.as_f64(), right.as_f64()) {
+                l.partial_cmp(&r)?
+            } else {
+                left.as_str()?.cmp(right.as_str()?)
+            };
+
+            match op {
+                "<" => Some(ordering.is_lt()),
+                "<=" => Some(ordering.is_le()),
+                ">" => Some(ordering.is_gt()),
+                ">=" => Some(ordering.is_ge()),
+                _ => None,
+            }
+        }
+    };
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    // numbers are compared by value so 8 == 8.0
+    if let (Some(l), Some(r)) = (left.as_f64(), right.as_f64()) {
+        return l == r;
+    }
+
+    return left == right;
+}
+
+fn call_method(name: &str, target: &Value, arguments: &[Value]) -> Option<Value> {
+    return match (name, arguments) {
+        ("startsWith", [prefix]) => Some(json!(target.as_str()?.starts_with(prefix.as_str()?))),
+        ("endsWith", [suffix]) => Some(json!(target.as_str()?.ends_with(suffix.as_str()?))),
+        ("contains", [item]) => match target {
+            Value::String(value) => Some(json!(value.contains(item.as_str()?))),
+            Value::Array(items) => Some(json!(items.iter().any(|i| values_equal(i, item)))),
+            _ => None,
+        },
+        ("size", []) => match target {
+            Value::String(value) => Some(json!(value.chars().count())),
+            Value::Array(items) => Some(json!(items.len())),
+            Value::Object(map) => Some(json!(map.len())),
+            _ => None,
+        },
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn attributes() -> Value {
+        let time = Utc.with_ymd_and_hms(2025, 1, 31, 9, 30, 0).unwrap();
+        let details = json!({
+            "department": "finance",
+            "email": "jane@example.com",
+            "level": 3,
+            "tags": ["oncall", "eu"]
+        });
+
+        return condition_attributes(&details, &Some("10.0.0.7".to_string()), &time, &None);
+    }
+
+    /// evaluation errors (e.g. comparing a string to a number) make the condition false, as for allow rules
+    fn evaluate(source: &str) -> bool {
+        return Condition::parse(source).unwrap().try_evaluate(&attributes()).unwrap_or(false);
+    }
+
+    #[test]
+    fn evaluates_attributes_and_methods() {
+        assert!(evaluate("details.department == \"finance\" && request.hour >= 8 && request.hour < 18"));
+        assert!(evaluate("request.ip in ['10.0.0.1', '10.0.0.7']"));
+        assert!(evaluate("details.email.endsWith(\"@example.com\")"));
+        assert!(evaluate("details.tags.contains(\"eu\") && details.tags.size() == 2"));
+        assert!(evaluate("details.level == 3.0"));
+        assert!(evaluate("details.missing == null"));
+        assert!(!evaluate("request.weekday == \"Sat\""));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(evaluate("true || false && false"));
+        assert!(!evaluate("(true || false) && false"));
+        assert!(evaluate("false && false || true"));
+    }
+
+    #[test]
+    fn not_applies_to_the_comparison() {
+        assert!(evaluate("!details.level == 4"));
+        assert!(evaluate("!!true"));
+        assert!(!evaluate("!(true || false)"));
+    }
+
+    #[test]
+    fn type_errors_make_the_condition_false() {
+        assert!(!evaluate("details.department < 1"));
+        assert!(!evaluate("details.department && true"));
+        assert!(!evaluate("!details.level"));
+        assert!(!evaluate("details.level.startsWith(\"3\")"));
+        assert!(!evaluate("details.level in details.department"));
+        // the condition has to be a boolean
+        assert!(!evaluate("details.level"));
+
+        let condition = Condition::parse("details.department < 1").unwrap();
+        assert_eq!(condition.try_evaluate(&attributes()), None);
+        let condition = Condition::parse("details.level < 5").unwrap();
+        assert_eq!(condition.try_evaluate(&attributes()), Some(true));
+    }
+
+    #[test]
+    fn rejects_type_mismatches_up_front() {
+        for source in [
+            "\"finance\"",
+            "details.level + 1 == 2",
+            "1 && true",
+            "details.a == 1 || 2",
+            "!\"a\"",
+            "\"a\" < 1",
+            "true < false",
+            "details.a in \"abc\"",
+            "details.a.startsWith(1)",
+            "details.a.startsWith()",
+            "details.a.size(1)",
+            "true.size() == 1",
+            "[1, 2].endsWith(\"2\")",
+        ] {
+            assert!(Condition::parse(source).is_err(), "{} should be rejected", source);
+        }
+
+        assert!(matches!(Condition::parse("1 && true"), Err(ConditionParseError::TypeMismatch(_))));
+        assert!(matches!(Condition::parse("details.a.size(1)"), Err(ConditionParseError::TypeMismatch(_))));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!(matches!(Condition::parse("details.a == \"b"), Err(ConditionParseError::UnterminatedString)));
+        assert!(matches!(Condition::parse("details.a == #"), Err(ConditionParseError::UnexpectedCharacter('#'))));
+        assert!(matches!(Condition::parse("details.a =="), Err(ConditionParseError::UnexpectedEnd)));
+        assert!(matches!(Condition::parse("user.a == 1"), Err(ConditionParseError::UnknownAttribute(_))));
+        assert!(matches!(Condition::parse("details.a.trim()"), Err(ConditionParseError::UnknownMethod(_))));
+        assert!(matches!(Condition::parse("details.a == 1 1"), Err(ConditionParseError::UnexpectedToken(_))));
+        assert!(matches!(Condition::parse("(details.a == 1"), Err(ConditionParseError::UnexpectedEnd)));
+        assert!(matches!(Condition::parse("[1, 2"), Err(ConditionParseError::UnexpectedEnd)));
+        assert!(matches!(Condition::parse("1.2.3 == 1"), Err(ConditionParseError::UnexpectedToken(_))));
+        assert!(matches!(Condition::parse(""), Err(ConditionParseError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn rejects_deeply_nested_expressions() {
+        let nested = format!("{}true{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(matches!(Condition::parse(&nested), Err(ConditionParseError::TooDeep)));
+
+        let lists = format!("{}1{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert!(matches!(Condition::parse(&lists), Err(ConditionParseError::TooDeep)));
+
+        let negations = format!("{}true", "!".repeat(100_000));
+        assert!(matches!(Condition::parse(&negations), Err(ConditionParseError::TooDeep)));
+
+        let chained = vec!["true"; 100_000].join(" && ");
+        assert!(matches!(Condition::parse(&chained), Err(ConditionParseError::TooDeep)));
+
+        let methods = format!("details.email{}", ".size()".repeat(100_000));
+        assert!(matches!(Condition::parse(&methods), Err(ConditionParseError::TooDeep)));
+    }
+
+    #[test]
+    fn accepts_nesting_within_the_limit() {
+        let nested = format!("{}true{}", "(".repeat(20), ")".repeat(20));
+        assert!(evaluate(&nested));
+
+        let chained = vec!["true"; 40].join(" && ");
+        assert!(evaluate(&chained));
+    }
+}
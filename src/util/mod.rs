@@ -1,4 +1,6 @@
+pub mod condition;
 pub mod io;
 pub mod logging;
 pub mod string;
-
+#[cfg(test)]
+pub mod testing;
@@ -0,0 +1,120 @@
+//! Helpers of the tests which need a database.
+//!
+//! They run against the Postgres server under `CAUTH_TEST_DATABASE_URL`
+//! (e.g. `postgres://postgres@localhost:5432/postgres`), each test gets a fresh database
+//! with all of the migrations applied. The tests are skipped when the variable is not set.
+//! The tests publishing to a broker also need the Redis server under `CAUTH_TEST_REDIS_URL`
+//! (e.g. `redis://localhost:6379/`).
+
+use std::{
+    fs,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    raw_sql,
+    Connection,
+    PgConnection,
+    PgPool,
+};
+use tokio::sync::OnceCell;
+use crate::config::{BrokerConfig, CauthConfig, DefaultGroups};
+
+static DATABASES: AtomicUsize = AtomicUsize::new(0);
+static CLEANED_UP: OnceCell<()> = OnceCell::const_new();
+
+/// ## database
+///
+/// Creates a fresh database with all of the migrations applied,
+/// None when `CAUTH_TEST_DATABASE_URL` is not set
+///
+pub async fn database() -> Option<PgPool> {
+    let url = match std::env::var("CAUTH_TEST_DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("CAUTH_TEST_DATABASE_URL is not set, skipping the database test");
+            return None;
+        }
+    };
+    let options = PgConnectOptions::from_str(&url).unwrap();
+    let mut server = PgConnection::connect_with(&options).await.unwrap();
+
+    // databases of the previous runs are dropped by the first test of a run
+    let pid = std::process::id();
+    CLEANED_UP.get_or_init(|| async {
+        let mut conn = PgConnection::connect_with(&options).await.unwrap();
+        let stale: Vec<(String,)> = sqlx::query_as("SELECT datname FROM pg_database WHERE datname LIKE 'cauth_test_%'")
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+
+        for (name,) in stale {
+            if !name.starts_with(&format!("cauth_test_{}_", pid)) {
+                raw_sql(&format!("DROP DATABASE IF EXISTS \"{}\" WITH (FORCE);", name))
+                    .execute(&mut conn)
+                    .await
+                    .unwrap();
+            }
+        }
+    })
+    .await;
+
+    let name = format!("cauth_test_{}_{}", pid, DATABASES.fetch_add(1, Ordering::SeqCst));
+    raw_sql(&format!("CREATE DATABASE \"{}\";", name))
+        .execute(&mut server)
+        .await
+        .unwrap();
+
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect_with(options.database(&name))
+        .await
+        .unwrap();
+
+    let mut migrations: Vec<_> = fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    migrations.sort();
+
+    for migration in migrations {
+        let sql = fs::read_to_string(&migration).unwrap();
+        raw_sql(&sql)
+            .execute(&pool)
+            .await
+            .unwrap_or_else(|err| panic!("{}: {}", migration.display(), err));
+    }
+
+    return Some(pool);
+}
+
+/// ## redis
+///
+/// Url of the Redis server the tests publish to,
+/// None when `CAUTH_TEST_REDIS_URL` is not set
+///
+pub fn redis() -> Option<String> {
+    return match std::env::var("CAUTH_TEST_REDIS_URL") {
+        Ok(url) => Some(url),
+        Err(_) => {
+            eprintln!("CAUTH_TEST_REDIS_URL is not set, skipping the broker test");
+            None
+        }
+    };
+}
+
+/// ## config
+///
+/// Builds the config with the default settings and provided pool
+///
+pub fn config(db_conn: PgPool, broker: Option<BrokerConfig>) -> CauthConfig {
+    return CauthConfig {
+        db_conn,
+        port: 0,
+        default_groups: DefaultGroups::default(),
+        event_ttl: 15 * 60,
+        webhook_max_attempts: 3,
+        broker,
+    };
+}
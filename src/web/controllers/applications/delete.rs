@@ -0,0 +1,97 @@
+use actix_web::{
+    delete,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Path,
+        Data,
+        Query
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
+        application::{
+            Application,
+            ApplicationDeleteError
+        },
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+}
+
+type PathData = String;
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "An application with this name do not exist."
+        }))
+    );
+}
+
+#[delete("/applications/{name}")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>,
+    name: Path<PathData>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:applications:delete",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    let result = Application::delete(
+        &mut db_conn,
+        &name
+    )
+    .await;
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            ApplicationDeleteError::NotFound => return not_found_error()
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Json,
+        Data,
+        Query
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
+        application::{
+            Application,
+            ApplicationInsertError
+        },
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+}
+
+#[derive(Deserialize)]
+struct JsonData {
+    name: String,
+    namespace: String,
+    description: String
+}
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn name_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NAME_ERROR",
+            "details": "An application with this name already exist."
+        }))
+    );
+}
+
+fn namespace_taken_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NAMESPACE_TAKEN",
+            "details": "This namespace is owned by another application."
+        }))
+    );
+}
+
+fn invalid_namespace_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "INVALID_NAMESPACE",
+            "details": "Namespace has to be non empty, at most 255 chars long and cannot contain \":\"."
+        }))
+    );
+}
+
+fn reserved_namespace_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "RESERVED_NAMESPACE",
+            "details": "The \"cauth\" namespace is reserved for the service itself."
+        }))
+    );
+}
+
+#[post("/applications")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:applications:post",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    let result = Application::insert(
+        &mut db_conn,
+        &json.name,
+        &json.namespace,
+        &json.description
+    )
+    .await;
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            ApplicationInsertError::NameError => return name_error(),
+            ApplicationInsertError::NamespaceTaken => return namespace_taken_error(),
+            ApplicationInsertError::InvalidNamespace => return invalid_namespace_error(),
+            ApplicationInsertError::ReservedNamespace => return reserved_namespace_error()
+        }
+    }
+}
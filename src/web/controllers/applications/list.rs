@@ -0,0 +1,70 @@
+use actix_web::{
+    Responder,
+    HttpRequest,
+    get,
+    http::StatusCode,
+    web::{
+        Query,
+        Data
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        Order,
+        application::Application,
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+    order_in: Option<Order>,
+    page: Option<usize>
+}
+
+#[get("/applications")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:applications:get",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    let result = Application::list(
+        &mut db_conn,
+        query.order_in,
+        Some(query.page.unwrap_or(0) * 10),
+        Some(10)
+    )
+    .await;
+
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(result))
+    );
+}
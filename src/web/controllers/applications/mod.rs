@@ -0,0 +1,5 @@
+pub mod list;
+pub mod insert;
+pub mod delete;
+pub mod grant_admin;
+pub mod revoke_admin;
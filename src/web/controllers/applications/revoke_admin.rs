@@ -0,0 +1,111 @@
+use actix_web::{
+    delete,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Query,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
+        application::{
+            Application,
+            ApplicationRevokeAdminError
+        },
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+type PathData = (String, String);
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "Application with specified name do not exist"
+        }))
+    );
+}
+
+fn not_granted_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_GRANTED",
+            "details": "User with specified login do not administer this application"
+        }))
+    );
+}
+
+#[delete("/applications/{name}/admins/{login}")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>,
+    path: Path<PathData>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:applications:update",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    let (application_name, user_login) = path.into_inner();
+
+    let result = Application::revoke_admin(
+        &mut db_conn,
+        &application_name,
+        &user_login
+    )
+    .await;
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            ApplicationRevokeAdminError::NotFound => return not_found_error(),
+            ApplicationRevokeAdminError::NotGranted => return not_granted_error()
+        }
+    };
+}
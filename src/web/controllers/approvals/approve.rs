@@ -0,0 +1,153 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Query,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        event::change_request::{
+            ChangeRequest,
+            ChangeRequestApproval,
+            ChangeRequestApproveError
+        },
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::{
+        controllers::{
+            approvals::not_found_error,
+            events::failure_error
+        },
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+type PathData = i32;
+
+fn ok(approval: ChangeRequestApproval) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(approval))
+    );
+}
+
+fn self_approval_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "SELF_APPROVAL",
+            "details": "Changes cannot be approved by the user who requested them."
+        }))
+    );
+}
+
+fn missing_requester_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "MISSING_REQUESTER",
+            "details": "Changes without a requester cannot be approved."
+        }))
+    );
+}
+
+fn already_approved_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "ALREADY_APPROVED",
+            "details": "This change was already approved by the user."
+        }))
+    );
+}
+
+#[post("/approvals/{id}/approve")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    path: Path<PathData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .begin()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:approvals:update",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    // the session exists, as it has the permission
+    let (approver, context) = LoginSession::get_user_with_context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await
+    .ok()
+    .unwrap();
+
+    let result = ChangeRequest::approve(
+        &mut db_conn,
+        &path.into_inner(),
+        &approver,
+        &context,
+        &data
+    )
+    .await;
+
+    // nothing is recorded when the approval fails
+    let approval = match result {
+        Ok(approval) => approval,
+        Err(error) => match error {
+            ChangeRequestApproveError::NotFound => return not_found_error(),
+            ChangeRequestApproveError::SelfApproval => return self_approval_error(),
+            ChangeRequestApproveError::MissingRequester => return missing_requester_error(),
+            ChangeRequestApproveError::Unauthorized => return ServerResponse::new(
+                StatusCode::UNAUTHORIZED,
+                None
+            ),
+            ChangeRequestApproveError::AlreadyApproved => return already_approved_error(),
+            ChangeRequestApproveError::Operation(failure) => return failure_error(failure, json!({}))
+        }
+    };
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error committing changes: {}", err);
+        }
+    };
+
+    return ok(approval);
+}
@@ -0,0 +1,71 @@
+use actix_web::{
+    Responder,
+    HttpRequest,
+    get,
+    http::StatusCode,
+    web::{
+        Query,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        login_session::LoginSession,
+        RequestContext,
+        event::ChangeRequest
+    },
+    web::{
+        controllers::approvals::not_found_error,
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+type PathData = i32;
+
+#[get("/approvals/{id}")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    path: Path<PathData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:approvals:get",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    let change_request = match ChangeRequest::retrieve(&mut db_conn, &path.into_inner()).await {
+        Ok(change_request) => change_request,
+        Err(_) => return not_found_error()
+    };
+
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(change_request.summarize().await))
+    );
+}
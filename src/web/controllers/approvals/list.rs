@@ -0,0 +1,76 @@
+use actix_web::{
+    Responder,
+    HttpRequest,
+    get,
+    http::StatusCode,
+    web::{
+        Query,
+        Data
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        Order,
+        login_session::LoginSession,
+        RequestContext,
+        event::ChangeRequest
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+    order_in: Option<Order>,
+    page: Option<usize>
+}
+
+#[get("/approvals")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:approvals:get",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    let change_requests = ChangeRequest::list(
+        &mut db_conn,
+        query.order_in,
+        Some(query.page.unwrap_or(0) * 10),
+        Some(10)
+    )
+    .await;
+
+    let mut result = Vec::new();
+
+    for change_request in change_requests {
+        result.push(change_request.summarize().await);
+    }
+
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(result))
+    );
+}
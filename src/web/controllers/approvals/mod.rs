@@ -0,0 +1,30 @@
+pub mod list;
+pub mod get;
+pub mod approve;
+pub mod reject;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use crate::web::ServerResponse;
+
+/// reports that the operation was stored as a change request instead of being applied
+pub fn change_request_created(change_request_id: i32) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::ACCEPTED,
+        Some(json!({
+            "code": "APPROVAL_REQUIRED",
+            "details": "This operation requires approvals, it will be applied once it is approved",
+            "change_request_id": change_request_id
+        }))
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "A change request with this id do not exist."
+        }))
+    );
+}
@@ -0,0 +1,83 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Query,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        event::ChangeRequest,
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::{
+        controllers::approvals::not_found_error,
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+type PathData = i32;
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+#[post("/approvals/{id}/reject")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    path: Path<PathData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:approvals:update",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    // the session exists, as it has the permission
+    let rejected_by = LoginSession::retrieve(&mut db_conn, &query.session_token)
+        .await
+        .ok()
+        .unwrap()
+        .user_login;
+
+    return match ChangeRequest::reject(&mut db_conn, &path.into_inner(), &rejected_by).await {
+        Ok(_) => ok(),
+        Err(_) => not_found_error()
+    };
+}
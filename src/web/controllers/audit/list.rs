@@ -0,0 +1,87 @@
+use actix_web::{
+    Responder,
+    HttpRequest,
+    get,
+    http::StatusCode,
+    web::{
+        Query,
+        Data
+    }
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        Order,
+        login_session::LoginSession,
+        RequestContext,
+        AuditEntry,
+        AuditFilter
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+    actor: Option<String>,
+    /// either the whole action or just the resource, e.g. "user_group"
+    action: Option<String>,
+    target: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    order_in: Option<Order>,
+    page: Option<usize>
+}
+
+#[get("/audit")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:audit:get",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    let filter = AuditFilter {
+        actor: query.actor.clone(),
+        action: query.action.clone(),
+        target: query.target.clone(),
+        since: query.since,
+        until: query.until
+    };
+
+    let entries = AuditEntry::list(
+        &mut db_conn,
+        &filter,
+        query.order_in,
+        Some(query.page.unwrap_or(0) * 10),
+        Some(10)
+    )
+    .await;
+
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(entries))
+    );
+}
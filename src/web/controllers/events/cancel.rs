@@ -1,20 +1,31 @@
 use actix_web::{
     post,
     Responder,
-    http::StatusCode, 
+    http::StatusCode,
     web::{
         Json,
-        Data
+        Data,
+        Path
     }
 };
 use serde::Deserialize;
 use serde_json::json;
 use crate::{
     config::CauthConfig,
-    models::{
-        event::{user_register::UserRegisterEventCancelError, UserRegisterEvent}, login_session::LoginSession, user::User
+    models::event::{
+        with_event_type,
+        stored_event::StoredEventCancelError,
+        PendingEvent,
+        StoredEvent
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            events::unknown_event_type_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -23,6 +34,8 @@ struct JsonData {
     key: String
 }
 
+type PathData = String;
+
 fn ok() -> ServerResponse {
     return ServerResponse::new(
         StatusCode::OK,
@@ -50,19 +63,19 @@ fn unauthorized_error() -> ServerResponse {
     );
 }
 
-#[post("/events/users/register/cancel")]
-pub async fn controller(
-    json: Json<JsonData>,
-    data: Data<CauthConfig>
-) -> impl Responder {
+async fn cancel<E: PendingEvent>(
+    json: &JsonData,
+    data: &CauthConfig
+) -> ServerResponse {
     // these will never error
     let mut db_conn = data.db_conn
         .acquire()
         .await
         .unwrap();
 
-    let result = UserRegisterEvent::cancel(
+    let result = StoredEvent::cancel(
         &mut db_conn,
+        E::TYPE,
         &json.id,
         &json.key
     )
@@ -71,8 +84,25 @@ pub async fn controller(
     match result {
         Ok(_) => return ok(),
         Err(error) => match error {
-            UserRegisterEventCancelError::NotFound => return not_found_error(),
-            UserRegisterEventCancelError::Unauthorized => return unauthorized_error()
+            StoredEventCancelError::NotFound => return not_found_error(),
+            StoredEventCancelError::Unauthorized => return unauthorized_error(),
+            StoredEventCancelError::Webhook(error) => return webhook_error(error),
+            StoredEventCancelError::Database(details) => return database_error(details)
         }
     }
 }
+
+#[post("/events/{event_type:.+}/cancel")]
+pub async fn controller(
+    path: Path<PathData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    let event_type = path.into_inner();
+
+    return with_event_type!(
+        &event_type,
+        cancel(&json, &data),
+        unknown_event_type_error(&event_type)
+    );
+}
@@ -1,33 +1,37 @@
 use actix_web::{
     post,
     Responder,
-    http::StatusCode, 
+    http::StatusCode,
     web::{
         Json,
-        Data
+        Data,
+        Path
     }
 };
 use serde::Deserialize;
 use serde_json::json;
 use crate::{
     config::CauthConfig,
-    models::{
-        user::User,
-        event::user_delete::{
-            UserDeleteEvent,
-            UserDeleteEventCancelError
-        },
-        login_session::LoginSession
+    models::event::{
+        batch::EventBatchCancelError,
+        EventBatch
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
 struct JsonData {
-    id: i32,
     key: String
 }
 
+type PathData = i32;
+
 fn ok() -> ServerResponse {
     return ServerResponse::new(
         StatusCode::OK,
@@ -40,7 +44,7 @@ fn not_found_error() -> ServerResponse {
         StatusCode::BAD_REQUEST,
         Some(json!({
             "code": "NOT_FOUND",
-            "details": "Event with this id do not exist"
+            "details": "Batch with this id do not exist"
         }))
     );
 }
@@ -55,8 +59,9 @@ fn unauthorized_error() -> ServerResponse {
     );
 }
 
-#[post("/events/users/delete/cancel")]
+#[post("/events/batches/{id}/cancel")]
 pub async fn controller(
+    path: Path<PathData>,
     json: Json<JsonData>,
     data: Data<CauthConfig>
 ) -> impl Responder {
@@ -66,9 +71,9 @@ pub async fn controller(
         .await
         .unwrap();
 
-    let result = UserDeleteEvent::cancel(
+    let result = EventBatch::cancel(
         &mut db_conn,
-        &json.id,
+        &path.into_inner(),
         &json.key
     )
     .await;
@@ -76,8 +81,10 @@ pub async fn controller(
     match result {
         Ok(_) => return ok(),
         Err(error) => match error {
-            UserDeleteEventCancelError::NotFound => return not_found_error(),
-            UserDeleteEventCancelError::Unauthorized => return unauthorized_error()
+            EventBatchCancelError::NotFound => return not_found_error(),
+            EventBatchCancelError::Unauthorized => return unauthorized_error(),
+            EventBatchCancelError::Webhook(error) => return webhook_error(error),
+            EventBatchCancelError::Database(details) => return database_error(details)
         }
     }
 }
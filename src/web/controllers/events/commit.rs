@@ -0,0 +1,158 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Json,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        event::{
+            with_event_type,
+            stored_event::StoredEventCommitError,
+            PendingEvent,
+            StoredEvent
+        },
+        audit::AuditEntry,
+        RequestContext
+    },
+    web::{
+        controllers::{
+            events::{
+                event_error,
+                unknown_event_type_error
+            },
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct JsonData {
+    id: i32,
+    key: String
+}
+
+type PathData = String;
+
+fn ok(output: serde_json::Value) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        if output.is_null() { None } else { Some(output) }
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "Event with this id do not exist"
+        }))
+    );
+}
+
+fn unauthorized_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::UNAUTHORIZED,
+        Some(json!({
+            "code": "UNAUTHORIZED",
+            "details": "You are not authorized to do that!"
+        }))
+    );
+}
+
+fn expired_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "EXPIRED",
+            "details": "Event with this id has expired"
+        }))
+    );
+}
+
+fn already_committed_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "ALREADY_COMMITTED",
+            "details": "Event with this id was already committed and its result cannot be returned again"
+        }))
+    );
+}
+
+async fn commit<E: PendingEvent>(
+    json: &JsonData,
+    data: &CauthConfig,
+    context: &RequestContext
+) -> ServerResponse {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .begin()
+        .await
+        .unwrap();
+
+    // the changes are made by the user who prepared the event
+    let actor = StoredEvent::retrieve(&mut db_conn, E::TYPE, &json.id)
+        .await
+        .ok()
+        .and_then(|event| event.actor);
+    AuditEntry::identify(&mut db_conn, actor.as_ref(), context.ip.as_ref()).await;
+
+    let result = StoredEvent::commit::<E>(
+        &mut db_conn,
+        &json.id,
+        &json.key,
+        data.event_ttl,
+        data
+    )
+    .await;
+
+    let output = match result {
+        Ok(output) => output,
+        // the transaction is dropped, so the event is kept
+        Err(error) => match error {
+            StoredEventCommitError::NotFound => return not_found_error(),
+            StoredEventCommitError::Unauthorized => return unauthorized_error(),
+            StoredEventCommitError::Expired => return expired_error(),
+            StoredEventCommitError::AlreadyCommitted => return already_committed_error(),
+            StoredEventCommitError::Operation(error) => return event_error(error),
+            StoredEventCommitError::Webhook(error) => return webhook_error(error)
+        }
+    };
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error while commiting changes to the database: {}", err);
+        }
+    };
+
+    return ok(output);
+}
+
+#[post("/events/{event_type:.+}/commit")]
+pub async fn controller(
+    req: HttpRequest,
+    path: Path<PathData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    let event_type = path.into_inner();
+    let context = RequestContext::from_request(&req);
+
+    return with_event_type!(
+        &event_type,
+        commit(&json, &data, &context),
+        unknown_event_type_error(&event_type)
+    );
+}
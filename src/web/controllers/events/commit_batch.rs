@@ -0,0 +1,146 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Json,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::{
+    json,
+    Value
+};
+use crate::{
+    config::CauthConfig,
+    models::{
+        event::{
+            batch::EventBatchCommitError,
+            EventBatch
+        },
+        audit::AuditEntry,
+        RequestContext
+    },
+    web::{
+        controllers::{
+            events::failure_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct JsonData {
+    key: String
+}
+
+type PathData = i32;
+
+fn ok(results: Vec<Value>) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!({ "results": results }))
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "Batch with this id do not exist"
+        }))
+    );
+}
+
+fn unauthorized_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::UNAUTHORIZED,
+        Some(json!({
+            "code": "UNAUTHORIZED",
+            "details": "You are not authorized to do that!"
+        }))
+    );
+}
+
+fn expired_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "EXPIRED",
+            "details": "Batch with this id has expired"
+        }))
+    );
+}
+
+fn already_committed_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "ALREADY_COMMITTED",
+            "details": "Batch with this id was already committed and its result cannot be returned again"
+        }))
+    );
+}
+
+#[post("/events/batches/{id}/commit")]
+pub async fn controller(
+    req: HttpRequest,
+    path: Path<PathData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .begin()
+        .await
+        .unwrap();
+
+    let id = path.into_inner();
+
+    // the changes are made by the user who prepared the batch
+    let actor = EventBatch::retrieve(&mut db_conn, &id)
+        .await
+        .ok()
+        .and_then(|batch| batch.actor);
+    let context = RequestContext::from_request(&req);
+    AuditEntry::identify(&mut db_conn, actor.as_ref(), context.ip.as_ref()).await;
+
+    let result = EventBatch::commit(
+        &mut db_conn,
+        &id,
+        &json.key,
+        data.event_ttl,
+        &data
+    )
+    .await;
+
+    let results = match result {
+        Ok(results) => results,
+        // the transaction is dropped, so none of the events is applied and the batch is kept
+        Err(error) => match error {
+            EventBatchCommitError::NotFound => return not_found_error(),
+            EventBatchCommitError::Unauthorized => return unauthorized_error(),
+            EventBatchCommitError::Expired => return expired_error(),
+            EventBatchCommitError::AlreadyCommitted => return already_committed_error(),
+            EventBatchCommitError::Operation { index, event_type, failure } => return failure_error(
+                failure,
+                json!({ "index": index, "event_type": event_type })
+            ),
+            EventBatchCommitError::Webhook(error) => return webhook_error(error)
+        }
+    };
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error while commiting changes to the database: {}", err);
+        }
+    };
+
+    return ok(results);
+}
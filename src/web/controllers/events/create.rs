@@ -0,0 +1,133 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Json,
+        Data,
+        Path,
+        Query
+    }
+};
+use serde::Deserialize;
+use serde_json::{
+    json,
+    Value
+};
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        RequestContext,
+        event::{
+            with_event_type,
+            EventCaller,
+            EventCredentials,
+            stored_event::StoredEventInsertResult,
+            PendingEvent,
+            StoredEvent
+        }
+    },
+    web::{
+        controllers::{
+            approvals::change_request_created,
+            events::{
+                failure_error,
+                unknown_event_type_error
+            }
+        },
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: Option<String>
+}
+
+type PathData = String;
+
+fn ok(credentials: EventCredentials) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(credentials))
+    );
+}
+
+fn invalid_input_error(details: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "INVALID_INPUT",
+            "details": details
+        }))
+    );
+}
+
+async fn create<E: PendingEvent>(
+    json: Value,
+    caller: &EventCaller,
+    data: &CauthConfig
+) -> ServerResponse {
+    let input: E::Input = match serde_json::from_value(json) {
+        Ok(input) => input,
+        Err(err) => return invalid_input_error(err.to_string())
+    };
+
+    // these will never error
+    let mut db_conn = data.db_conn
+        .begin()
+        .await
+        .unwrap();
+
+    // the changes (e.g. stored change requests) are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, caller.session_token.as_ref(), &caller.context).await;
+
+    let result = StoredEvent::insert::<E>(
+        &mut db_conn,
+        input,
+        caller
+    )
+    .await;
+
+    let result = match result {
+        Ok(result) => result,
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error committing changes: {}", err);
+        }
+    };
+
+    return match result {
+        StoredEventInsertResult::Pending(credentials) => ok(credentials),
+        StoredEventInsertResult::ChangeRequest { change_request_id } => change_request_created(change_request_id)
+    };
+}
+
+/// registered after the commit and cancel routes, as the event type can contain slashes
+#[post("/events/{event_type:.+}")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    path: Path<PathData>,
+    json: Json<Value>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    let event_type = path.into_inner();
+
+    let caller = EventCaller {
+        session_token: query.into_inner().session_token,
+        context: RequestContext::from_request(&req)
+    };
+
+    return with_event_type!(
+        &event_type,
+        create(json.into_inner(), &caller, &data),
+        unknown_event_type_error(&event_type)
+    );
+}
@@ -0,0 +1,143 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Json,
+        Data,
+        Query
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        RequestContext,
+        event::{
+            batch::{
+                EventBatchEntry,
+                EventBatchInsertError
+            },
+            EventBatch,
+            EventCaller,
+            EventCredentials
+        }
+    },
+    web::{
+        controllers::events::failure_error,
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: Option<String>
+}
+
+#[derive(Deserialize)]
+struct JsonData {
+    events: Vec<EventBatchEntry>
+}
+
+fn ok(credentials: EventCredentials) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(credentials))
+    );
+}
+
+fn empty_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "EMPTY",
+            "details": "A batch has to contain at least one event"
+        }))
+    );
+}
+
+fn unknown_event_type_error(index: usize, event_type: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "UNKNOWN_EVENT_TYPE",
+            "details": format!("Event type \"{}\" do not exist", event_type),
+            "index": index
+        }))
+    );
+}
+
+fn approval_required_error(index: usize, event_type: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "APPROVAL_REQUIRED",
+            "details": format!("Event of type \"{}\" requires approvals and cannot be batched", event_type),
+            "index": index
+        }))
+    );
+}
+
+fn invalid_input_error(index: usize, details: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "INVALID_INPUT",
+            "details": details,
+            "index": index
+        }))
+    );
+}
+
+#[post("/events/batches")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .begin()
+        .await
+        .unwrap();
+
+    let caller = EventCaller {
+        session_token: query.into_inner().session_token,
+        context: RequestContext::from_request(&req)
+    };
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, caller.session_token.as_ref(), &caller.context).await;
+
+    let result = EventBatch::insert(
+        &mut db_conn,
+        json.into_inner().events,
+        &caller
+    )
+    .await;
+
+    let credentials = match result {
+        Ok(credentials) => credentials,
+        // the transaction is dropped, so none of the events is kept
+        Err(error) => match error {
+            EventBatchInsertError::Empty => return empty_error(),
+            EventBatchInsertError::UnknownEventType { index, event_type } => return unknown_event_type_error(index, event_type),
+            EventBatchInsertError::InvalidInput { index, details } => return invalid_input_error(index, details),
+            EventBatchInsertError::Operation { index, failure } => return failure_error(failure, json!({ "index": index })),
+            EventBatchInsertError::ApprovalRequired { index, event_type } => return approval_required_error(index, event_type)
+        }
+    };
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error committing changes: {}", err);
+        }
+    };
+
+    return ok(credentials);
+}
@@ -0,0 +1,93 @@
+use actix_web::{
+    Responder,
+    HttpRequest,
+    get,
+    http::StatusCode,
+    web::{
+        Query,
+        Data
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        Order,
+        login_session::LoginSession,
+        RequestContext,
+        event::{
+            stored_event::StoredEventFilter,
+            StoredEvent
+        }
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+    login: Option<String>,
+    min_age: Option<u64>,
+    max_age: Option<u64>,
+    order_in: Option<Order>,
+    page: Option<usize>
+}
+
+#[get("/events")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:events:get",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    let query = query.into_inner();
+    let filter = StoredEventFilter {
+        event_type: query.event_type,
+        login: query.login,
+        min_age: query.min_age,
+        max_age: query.max_age
+    };
+
+    let events = StoredEvent::list(
+        &mut db_conn,
+        &filter,
+        query.order_in,
+        Some(query.page.unwrap_or(0) * 10),
+        Some(10)
+    )
+    .await;
+
+    let mut result = Vec::new();
+
+    for event in events {
+        result.push(event.summarize(data.event_ttl).await);
+    }
+
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(result))
+    );
+}
@@ -0,0 +1,58 @@
+pub mod create;
+pub mod commit;
+pub mod cancel;
+pub mod create_batch;
+pub mod commit_batch;
+pub mod cancel_batch;
+pub mod list;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use crate::{
+    models::event::{
+        EventError,
+        EventErrorKind,
+        EventFailure
+    },
+    web::ServerResponse
+};
+
+fn event_error<E: EventError>(error: E) -> ServerResponse {
+    return failure_error(EventFailure::from_error(&error), json!({}));
+}
+
+/// reports the failure along with provided fields
+pub(crate) fn failure_error(failure: EventFailure, fields: serde_json::Value) -> ServerResponse {
+    let status = match failure.kind {
+        EventErrorKind::BadRequest => StatusCode::BAD_REQUEST,
+        EventErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+        EventErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let mut body = json!({
+        "code": failure.code,
+        "details": failure.details
+    });
+    for extra in [failure.fields, fields] {
+        if let Some(extra) = extra.as_object() {
+            for (name, value) in extra {
+                body[name] = value.clone();
+            }
+        }
+    }
+
+    return ServerResponse::new(
+        status,
+        Some(body)
+    );
+}
+
+fn unknown_event_type_error(event_type: &str) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::NOT_FOUND,
+        Some(json!({
+            "code": "UNKNOWN_EVENT_TYPE",
+            "details": format!("Event type \"{}\" do not exist", event_type)
+        }))
+    );
+}
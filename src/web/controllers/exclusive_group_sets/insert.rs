@@ -0,0 +1,133 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Json,
+        Data,
+        Query
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
+        exclusive_group_set::{
+            ExclusiveGroupSet,
+            ExclusiveGroupSetInsertError
+        },
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+}
+
+#[derive(Deserialize)]
+struct JsonData {
+    name: String,
+    description: String,
+    groups: Vec<String>
+}
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn name_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NAME_ERROR",
+            "details": "An exclusive group set with this name already exist."
+        }))
+    );
+}
+
+fn group_not_found_error(group_name: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "GROUP_NOT_FOUND",
+            "details": format!("Group \"{}\" do not exist.", group_name)
+        }))
+    );
+}
+
+fn too_few_groups_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "TOO_FEW_GROUPS",
+            "details": "At least two groups have to be listed."
+        }))
+    );
+}
+
+#[post("/exclusive-group-sets")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .begin()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:groups:post",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    let result = ExclusiveGroupSet::insert(
+        &mut db_conn,
+        &json.name,
+        &json.description,
+        &json.groups
+    )
+    .await;
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error committing changes: {}", err);
+        }
+    };
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            ExclusiveGroupSetInsertError::NameError => return name_error(),
+            ExclusiveGroupSetInsertError::GroupNotFound(group_name) => return group_not_found_error(group_name),
+            ExclusiveGroupSetInsertError::TooFewGroups => return too_few_groups_error()
+        }
+    }
+}
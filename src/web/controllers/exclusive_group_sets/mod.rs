@@ -0,0 +1,4 @@
+pub mod list;
+pub mod insert;
+pub mod delete;
+pub mod list_violations;
@@ -1,6 +1,7 @@
 use actix_web::{
     delete,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Path,
@@ -13,13 +14,22 @@ use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
         group::{
             Group,
             GroupDeleteError
         },
-        login_session::LoginSession
+        login_session::LoginSession,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -46,8 +56,19 @@ fn not_found_error() -> ServerResponse {
     )
 }
 
+fn protected_entity_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "PROTECTED_ENTITY",
+            "details": "This group is protected and cannot be deleted."
+        }))
+    );
+}
+
 #[delete("/groups/{name}")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     data: Data<CauthConfig>,
     name: Path<PathData>
@@ -58,10 +79,19 @@ pub async fn controller(
         .await
         .unwrap();
 
-    let permitted = LoginSession::has_permission(
+    // app admins are permitted inside their application's namespace
+    let permitted = LoginSession::has_permission_in(
         &mut db_conn,
         &query.session_token,
-        "cauth:groups:delete"
+        "cauth:groups:delete",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &[&*name]
     )
     .await;
 
@@ -72,6 +102,9 @@ pub async fn controller(
         );
     }
 
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
     let result = Group::delete(
         &mut db_conn,
         &name
@@ -88,7 +121,10 @@ pub async fn controller(
     match result {
         Ok(_) => return ok(),
         Err(error) => match error {
-            GroupDeleteError::NotFound => return not_found_error()
+            GroupDeleteError::NotFound => return not_found_error(),
+            GroupDeleteError::Protected => return protected_entity_error(),
+            GroupDeleteError::Webhook(error) => return webhook_error(error),
+            GroupDeleteError::Database(details) => return database_error(details)
         }
     }
 }
@@ -0,0 +1,129 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Query,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        group::{
+            Group,
+            GroupGrantManagerError
+        },
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+type PathData = (String, String);
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "Group with specified name do not exist"
+        }))
+    );
+}
+
+fn user_not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "USER_NOT_FOUND",
+            "details": "User with specified login do not exist"
+        }))
+    );
+}
+
+fn already_granted_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "ALREADY_GRANTED",
+            "details": "User with specified login already manages this group"
+        }))
+    );
+}
+
+#[post("/groups/{name}/managers/{login}")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>,
+    path: Path<PathData>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:users:update",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    let (group_name, user_login) = path.into_inner();
+
+    // managers are named in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    let result = Group::grant_manager(
+        &mut db_conn,
+        &group_name,
+        &user_login,
+        &context.organization
+    )
+    .await;
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            GroupGrantManagerError::NotFound => return not_found_error(),
+            GroupGrantManagerError::UserNotFound => return user_not_found_error(),
+            GroupGrantManagerError::AlreadyGranted => return already_granted_error()
+        }
+    };
+}
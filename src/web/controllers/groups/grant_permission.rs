@@ -1,25 +1,46 @@
 use actix_web::{
     post,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Query,
         Data,
-        Path
+        Path,
+        Json
     }
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        event::{
+            group_grant_permission::{
+                GroupGrantPermissionEvent,
+                GroupGrantPermissionEventInput
+            },
+            ChangeRequest,
+            EventCaller
+        },
         group::{
             Group,
             GroupGrantError
         },
-        login_session::LoginSession
+        login_session::LoginSession,
+        GrantEffect,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            approvals::change_request_created,
+            events::failure_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -27,6 +48,13 @@ struct QueryData {
     session_token: String
 }
 
+#[derive(Deserialize)]
+struct JsonData {
+    effect: Option<GrantEffect>,
+    condition: Option<String>,
+    expires_at: Option<DateTime<Utc>>
+}
+
 type PathData = (String, String);
 
 fn ok() -> ServerResponse {
@@ -56,11 +84,23 @@ fn permission_not_found_error() -> ServerResponse {
     );
 }
 
+fn invalid_condition_error(details: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "INVALID_CONDITION",
+            "details": details
+        }))
+    );
+}
+
 #[post("/groups/{name}/{permission_name}")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     data: Data<CauthConfig>,
-    path: Path<PathData>
+    path: Path<PathData>,
+    json: Option<Json<JsonData>>
 ) -> impl Responder {
     // these will never error
     let mut db_conn = data.db_conn
@@ -68,10 +108,20 @@ pub async fn controller(
         .await
         .unwrap();
 
+    let (group_name, permission_name) = path.into_inner();
+
+    // app admins are permitted inside their application's namespace
     let permitted = LoginSession::has_permission(
         &mut db_conn,
         &query.session_token,
-        "cauth:group:update"
+        "cauth:groups:update",
+        &RequestContext::from_request(&req)
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &[&group_name, &permission_name]
     )
     .await;
 
@@ -82,12 +132,65 @@ pub async fn controller(
         );
     }
 
-    let (group_name, permission_name) = path.into_inner();
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    let json = json.map(|json| json.into_inner());
+    let effect = json
+        .as_ref()
+        .and_then(|json| json.effect)
+        .unwrap_or(GrantEffect::Allow);
+    let expires_at = json
+        .as_ref()
+        .and_then(|json| json.expires_at);
+    let condition = json.and_then(|json| json.condition);
+
+    // operations requiring approvals are stored as change requests instead
+    let caller = EventCaller {
+        session_token: Some(query.session_token.clone()),
+        context: RequestContext::from_request(&req)
+    };
+    let input = GroupGrantPermissionEventInput {
+        group: group_name.clone(),
+        permission: permission_name.clone(),
+        effect: Some(effect),
+        condition: condition.clone(),
+        expires_at
+    };
+
+    let change_request_id = match ChangeRequest::intercept::<GroupGrantPermissionEvent>(&mut db_conn, input, &caller).await {
+        Ok(change_request_id) => change_request_id,
+        // the operation is not applied when it cannot be checked against the approval rules
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    if let Some(change_request_id) = change_request_id {
+        match db_conn.commit().await {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("Error committing changes: {}", err);
+            }
+        };
+
+        return change_request_created(change_request_id);
+    }
+
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await;
 
     let result = Group::grant_permission(
         &mut db_conn,
         &group_name,
-        &permission_name
+        &permission_name,
+        effect,
+        condition.as_ref(),
+        expires_at,
+        &context.organization
     )
     .await;
 
@@ -102,7 +205,9 @@ pub async fn controller(
         Ok(_) => return ok(),
         Err(error) => match error {
             GroupGrantError::NotFound => return not_found_error(),
-            GroupGrantError::PermissionNotFound => return permission_not_found_error()
+            GroupGrantError::PermissionNotFound => return permission_not_found_error(),
+            GroupGrantError::InvalidCondition(details) => return invalid_condition_error(details),
+            GroupGrantError::Database(details) => return database_error(details)
         }
     };
 }
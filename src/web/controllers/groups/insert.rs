@@ -1,6 +1,7 @@
 use actix_web::{
     post,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Json,
@@ -13,13 +14,33 @@ use serde::Deserialize;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
+        event::{
+            group_create::{
+                GroupCreateEvent,
+                GroupCreateEventInput
+            },
+            ChangeRequest,
+            EventCaller
+        },
         group::{
             Group,
             GroupInsertError
         },
-        login_session::LoginSession
+        login_session::LoginSession,
+        Application,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            approvals::change_request_created,
+            events::failure_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -62,8 +83,19 @@ fn permission_not_found_error() -> ServerResponse {
 
 }
 
+fn reserved_namespace_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "RESERVED_NAMESPACE",
+            "details": "The \"cauth\" namespace is reserved for the service itself."
+        }))
+    );
+}
+
 #[post("/groups")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     json: Json<JsonData>,
     data: Data<CauthConfig>
@@ -74,10 +106,22 @@ pub async fn controller(
         .await
         .unwrap();
 
-    let permitted = LoginSession::has_permission(
+    // app admins are permitted when the group and all of its permissions are inside their application's namespace
+    let names: Vec<&String> = std::iter::once(&json.name)
+        .chain(json.permissions.iter())
+        .collect();
+    let permitted = LoginSession::has_permission_in(
         &mut db_conn,
         &query.session_token,
-        "cauth:groups:post"
+        "cauth:groups:post",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &names
     )
     .await;
 
@@ -88,11 +132,55 @@ pub async fn controller(
         );
     }
 
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    if Application::is_reserved(&json.name) {
+        return reserved_namespace_error();
+    }
+
+    // operations requiring approvals are stored as change requests instead
+    let caller = EventCaller {
+        session_token: Some(query.session_token.clone()),
+        context: RequestContext::from_request(&req)
+    };
+    let input = GroupCreateEventInput {
+        name: json.name.clone(),
+        description: json.description.clone(),
+        permissions: json.permissions.clone()
+    };
+
+    let change_request_id = match ChangeRequest::intercept::<GroupCreateEvent>(&mut db_conn, input, &caller).await {
+        Ok(change_request_id) => change_request_id,
+        // the operation is not applied when it cannot be checked against the approval rules
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    if let Some(change_request_id) = change_request_id {
+        match db_conn.commit().await {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("Error committing changes: {}", err);
+            }
+        };
+
+        return change_request_created(change_request_id);
+    }
+
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
     let result = Group::insert(
         &mut db_conn,
         &json.name,
         &json.description,
-        &json.permissions
+        &json.permissions,
+        &context.organization
     )
     .await;
 
@@ -107,7 +195,9 @@ pub async fn controller(
         Ok(_) => return ok(), 
         Err(error) => match error {
             GroupInsertError::NameError => return name_error(),
-            GroupInsertError::PermissionNotFound => return permission_not_found_error()
+            GroupInsertError::PermissionNotFound => return permission_not_found_error(),
+            GroupInsertError::Webhook(error) => return webhook_error(error),
+            GroupInsertError::Database(details) => return database_error(details)
         }
     }
 }
@@ -1,5 +1,6 @@
 use actix_web::{
     Responder,
+    HttpRequest,
     get,
     http::StatusCode,
     web::{
@@ -14,7 +15,8 @@ use crate::{
     models::{
         Order,
         group::Group,
-        login_session::LoginSession
+        login_session::LoginSession,
+        RequestContext
     },
     web::ServerResponse
 };
@@ -28,6 +30,7 @@ struct QueryData {
 
 #[get("/groups")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     data: Data<CauthConfig>
 ) -> impl Responder {
@@ -40,7 +43,8 @@ pub async fn controller(
     let permitted = LoginSession::has_permission(
         &mut db_conn,
         &query.session_token,
-        "cauth:groups:get"
+        "cauth:groups:get",
+        &RequestContext::from_request(&req)
     )
     .await;
 
@@ -51,11 +55,20 @@ pub async fn controller(
         );
     }
       
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
     let result = Group::list(
         &mut db_conn,
         query.order_in,
         Some(query.page.unwrap_or(0) * 10),
-        Some(10)
+        Some(10),
+        &context.organization
     )
     .await
     .unwrap();
@@ -0,0 +1,102 @@
+use actix_web::{
+    Responder,
+    HttpRequest,
+    get,
+    http::StatusCode,
+    web::{
+        Path,
+        Query,
+        Data
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        Order,
+        group::{
+            Group,
+            GroupRetrieveError
+        },
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+    order_in: Option<Order>,
+    page: Option<usize>
+}
+
+type PathData = String;
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "A group with this name do not exist."
+        }))
+    );
+}
+
+#[get("/groups/{name}/members")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>,
+    name: Path<PathData>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:groups:get",
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    let result = Group::list_members(
+        &mut db_conn,
+        &name,
+        query.order_in,
+        Some(query.page.unwrap_or(0) * 10),
+        Some(10),
+        &context.organization
+    )
+    .await;
+
+    match result {
+        Ok(result) => return ServerResponse::new(
+            StatusCode::OK,
+            Some(json!(result))
+        ),
+        Err(error) => match error {
+            GroupRetrieveError::NotFound => return not_found_error()
+        }
+    }
+}
@@ -3,3 +3,8 @@ pub mod insert;
 pub mod delete;
 pub mod grant_permission;
 pub mod revoke_permission;
+pub mod update;
+pub mod list_members;
+pub mod list_managers;
+pub mod grant_manager;
+pub mod revoke_manager;
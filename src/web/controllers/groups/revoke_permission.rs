@@ -1,6 +1,7 @@
 use actix_web::{
     delete,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Query,
@@ -13,13 +14,30 @@ use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        event::{
+            group_revoke_permission::{
+                GroupRevokePermissionEvent,
+                GroupRevokePermissionEventInput
+            },
+            ChangeRequest,
+            EventCaller
+        },
         group::{
             Group,
             GroupRevokeError
         },
-        login_session::LoginSession
+        login_session::LoginSession,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            approvals::change_request_created,
+            events::failure_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -67,8 +85,19 @@ fn permission_not_found_error() -> ServerResponse {
 }
 
 
+fn protected_entity_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "PROTECTED_ENTITY",
+            "details": "Protected permissions cannot be revoked from a protected group."
+        }))
+    );
+}
+
 #[delete("/groups/{name}/{permission_name}")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     data: Data<CauthConfig>,
     path: Path<PathData>
@@ -79,10 +108,20 @@ pub async fn controller(
         .await
         .unwrap();
 
+    let (group_name, permission_name) = path.into_inner();
+
+    // app admins are permitted inside their application's namespace
     let permitted = LoginSession::has_permission(
         &mut db_conn,
         &query.session_token,
-        "cauth:group:update"
+        "cauth:groups:update",
+        &RequestContext::from_request(&req)
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &[&group_name, &permission_name]
     )
     .await;
 
@@ -93,12 +132,50 @@ pub async fn controller(
         );
     }
 
-    let (group_name, permission_name) = path.into_inner();
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+
+    // operations requiring approvals are stored as change requests instead
+    let caller = EventCaller {
+        session_token: Some(query.session_token.clone()),
+        context: RequestContext::from_request(&req)
+    };
+    let input = GroupRevokePermissionEventInput {
+        group: group_name.clone(),
+        permission: permission_name.clone()
+    };
+
+    let change_request_id = match ChangeRequest::intercept::<GroupRevokePermissionEvent>(&mut db_conn, input, &caller).await {
+        Ok(change_request_id) => change_request_id,
+        // the operation is not applied when it cannot be checked against the approval rules
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    if let Some(change_request_id) = change_request_id {
+        match db_conn.commit().await {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("Error committing changes: {}", err);
+            }
+        };
+
+        return change_request_created(change_request_id);
+    }
+
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await;
 
     let result = Group::revoke_permission(
         &mut db_conn,
         &group_name,
-        &permission_name
+        &permission_name,
+        &context.organization
     )
     .await;
 
@@ -114,7 +191,9 @@ pub async fn controller(
         Err(error) => match error {
             GroupRevokeError::NotFound => return not_found_error(),
             GroupRevokeError::PermissionNotFound => return permission_not_found_error(),
-            GroupRevokeError::PermissionNotGranted => return permission_not_granted_error()
+            GroupRevokeError::PermissionNotGranted => return permission_not_granted_error(),
+            GroupRevokeError::Protected => return protected_entity_error(),
+            GroupRevokeError::Database(details) => return database_error(details)
         }
     };
 }
@@ -1,45 +1,109 @@
 pub mod permissions;
 pub mod groups;
+pub mod exclusive_group_sets;
+pub mod applications;
+pub mod organizations;
 pub mod users;
+pub mod events;
+pub mod approvals;
+pub mod webhooks;
+pub mod audit;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use crate::web::ServerResponse;
 
 pub use self::{
     permissions::{
         list::controller as ListPermissionsController,
         insert::controller as InsertPermissionController,
-        delete::controller as DeletePermissionController
+        delete::controller as DeletePermissionController,
+        update::controller as UpdatePermissionController,
+        list_groups::controller as ListGroupsPermissionController,
+        list_users::controller as ListUsersPermissionController
     },
     groups::{
         list::controller as ListGroupsController,
         insert::controller as InsertGroupController,
         delete::controller as DeleteGroupController,
+        update::controller as UpdateGroupController,
+        list_members::controller as ListMembersGroupController,
+        list_managers::controller as ListManagersGroupController,
+        grant_manager::controller as GrantManagerGroupController,
+        revoke_manager::controller as RevokeManagerGroupController,
         grant_permission::controller as GrantPermissionGroupController,
         revoke_permission::controller as RevokePermissionGroupController
     },
+    exclusive_group_sets::{
+        list::controller as ListExclusiveGroupSetsController,
+        insert::controller as InsertExclusiveGroupSetController,
+        delete::controller as DeleteExclusiveGroupSetController,
+        list_violations::controller as ListViolationsExclusiveGroupSetController
+    },
+    applications::{
+        list::controller as ListApplicationsController,
+        insert::controller as InsertApplicationController,
+        delete::controller as DeleteApplicationController,
+        grant_admin::controller as GrantAdminApplicationController,
+        revoke_admin::controller as RevokeAdminApplicationController
+    },
+    organizations::{
+        list::controller as ListOrganizationsController,
+        insert::controller as InsertOrganizationController,
+        delete::controller as DeleteOrganizationController,
+        list_members::controller as ListMembersOrganizationController,
+        add_member::controller as AddMemberOrganizationController,
+        remove_member::controller as RemoveMemberOrganizationController
+    },
     users::{
         insert::controller as InsertUserController,
         delete::controller as DeleteUserController,
         get::controller as GetUserController,
         get_permission::controller as GetPermissionUserController,
+        list_permissions::controller as ListPermissionsUserController,
+        check_permissions::controller as CheckPermissionsUserController,
+        explain_permission::controller as ExplainPermissionUserController,
+        list_organizations::controller as ListOrganizationsUserController,
+        set_organization::controller as SetOrganizationUserController,
         login::controller as LoginUserController,
         logout::controller as LogoutUserController,
         grant_group::controller as GrantGroupUserController,
         revoke_group::controller as RevokeGroupUserController,
-        event::{
-            register::{
-                create::controller as UserRegisterEventCreateController,
-                commit::controller as UserRegisterEventCommitController,
-                cancel::controller as UserRegisterEventCancelController
-            },
-            login::{
-                create::controller as UserLoginEventCreateController,
-                commit::controller as UserLoginEventCommitController,
-                cancel::controller as UserLoginEventCancelController
-            },
-            delete::{
-                create::controller as UserDeleteEventCreateController,
-                commit::controller as UserDeleteEventCommitController,
-                cancel::controller as UserDeleteEventCancelController
-            }
-        }
-    }
+        grant_permission::controller as GrantPermissionUserController,
+        revoke_permission::controller as RevokePermissionUserController
+    },
+    events::{
+        create::controller as CreateEventController,
+        commit::controller as CommitEventController,
+        cancel::controller as CancelEventController,
+        create_batch::controller as CreateEventBatchController,
+        commit_batch::controller as CommitEventBatchController,
+        cancel_batch::controller as CancelEventBatchController,
+        list::controller as ListEventsController
+    },
+    approvals::{
+        list::controller as ListApprovalsController,
+        get::controller as GetApprovalController,
+        approve::controller as ApproveApprovalController,
+        reject::controller as RejectApprovalController
+    },
+    webhooks::{
+        list::controller as ListWebhooksController,
+        insert::controller as CreateWebhookController,
+        delete::controller as DeleteWebhookController,
+        list_deliveries::controller as ListWebhookDeliveriesController,
+        retry_delivery::controller as RetryWebhookDeliveryController
+    },
+    audit::list::controller as ListAuditController
 };
+
+/// reports that the changes were rolled back because the database cannot save them
+pub fn database_error(details: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Some(json!({
+            "code": "DATABASE_ERROR",
+            "details": details
+        }))
+    );
+}
@@ -0,0 +1,132 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Query,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        organization::{
+            DEFAULT_ORGANIZATION,
+            Organization,
+            OrganizationAddMemberError
+        },
+        login_session::LoginSession,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+type PathData = (String, String);
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "Organization with specified name do not exist"
+        }))
+    );
+}
+
+fn user_not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "USER_NOT_FOUND",
+            "details": "User with specified login do not exist"
+        }))
+    );
+}
+
+fn already_member_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "ALREADY_MEMBER",
+            "details": "User with specified login already belongs to this organization"
+        }))
+    );
+}
+
+#[post("/organizations/{name}/members/{login}")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>,
+    path: Path<PathData>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let (organization_name, user_login) = path.into_inner();
+    let context = RequestContext::from_request(&req);
+
+    // members are managed inside of the organization itself or by the default one
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:organizations:update",
+        &context,
+        &organization_name
+    )
+    .await
+    || LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:organizations:update",
+        &context,
+        DEFAULT_ORGANIZATION
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &context).await;
+
+    let result = Organization::add_member(
+        &mut db_conn,
+        &organization_name,
+        &user_login
+    )
+    .await;
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            OrganizationAddMemberError::NotFound => return not_found_error(),
+            OrganizationAddMemberError::UserNotFound => return user_not_found_error(),
+            OrganizationAddMemberError::AlreadyMember => return already_member_error()
+        }
+    };
+}
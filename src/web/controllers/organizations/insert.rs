@@ -1,6 +1,7 @@
 use actix_web::{
     post,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Json,
@@ -13,40 +14,49 @@ use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
-        event::{user_delete::UserDeleteEventInsertError, EventCredentials, UserDeleteEvent}, login_session::LoginSession, user::User
+        audit::AuditEntry,
+        organization::{
+            DEFAULT_ORGANIZATION,
+            Organization,
+            OrganizationInsertError
+        },
+        login_session::LoginSession,
+        RequestContext
     },
     web::ServerResponse
 };
 
 #[derive(Deserialize)]
 struct QueryData {
-    session_token: String
+    session_token: String,
 }
 
 #[derive(Deserialize)]
 struct JsonData {
-    login: String
+    name: String,
+    description: String
 }
 
-fn ok(credentials: EventCredentials) -> ServerResponse {
+fn ok() -> ServerResponse {
     return ServerResponse::new(
         StatusCode::OK,
-        Some(json!(credentials))
+        None
     );
 }
 
-fn user_not_found_error() -> ServerResponse {
+fn name_error() -> ServerResponse {
     return ServerResponse::new(
         StatusCode::BAD_REQUEST,
         Some(json!({
-            "code": "USER_NOT_FOUND",
-            "details": "User with this login do not exist"
+            "code": "NAME_ERROR",
+            "details": "Either organization name or description is too long or organization with this name already exist."
         }))
     );
 }
 
-#[post("/events/users/delete")]
+#[post("/organizations")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     json: Json<JsonData>,
     data: Data<CauthConfig>
@@ -57,41 +67,39 @@ pub async fn controller(
         .await
         .unwrap();
 
-    let has_permission = LoginSession::has_permission(
+    let permitted = LoginSession::has_permission_in(
         &mut db_conn,
         &query.session_token,
-        &"cauth:users:delete".to_string()
+        "cauth:organizations:post",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
     )
     .await;
 
-    let logged_user = LoginSession::retrieve(
-        &mut db_conn,
-        &query.session_token
-    ).await;
-
-    if !has_permission && logged_user.is_err() { 
+    if !permitted {
         return ServerResponse::new(
             StatusCode::UNAUTHORIZED,
             None
         );
     }
-    let has_same_username = logged_user.unwrap().user_login == json.login;
 
-    let permitted = has_permission || has_same_username;
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
 
-    if !permitted {
-        return ServerResponse::new(
-            StatusCode::UNAUTHORIZED,
-            None
-        );
-    }
-    
-    let result = UserDeleteEvent::insert(
+    let result = Organization::insert(
         &mut db_conn,
-        &json.login
+        &json.name,
+        &json.description
     )
     .await;
-    
+
+    match result {
+        Ok(_) => (),
+        Err(error) => match error {
+            OrganizationInsertError::NameError => return name_error()
+        }
+    };
+
     match db_conn.commit().await {
         Ok(_) => (),
         Err(err) => {
@@ -99,10 +107,5 @@ pub async fn controller(
         }
     };
 
-    match result {
-        Ok(credentials) => return ok(credentials),
-        Err(error) => match error {
-            UserDeleteEventInsertError::UserNotFound => return user_not_found_error()
-        }
-    };
+    return ok();
 }
@@ -0,0 +1,6 @@
+pub mod list;
+pub mod insert;
+pub mod delete;
+pub mod list_members;
+pub mod add_member;
+pub mod remove_member;
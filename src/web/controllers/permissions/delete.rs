@@ -1,6 +1,7 @@
 use actix_web::{
     delete,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Path,
@@ -13,13 +14,19 @@ use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
         permission::{
             Permission,
             PermissionDeleteError
         },
-        login_session::LoginSession
+        login_session::LoginSession,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::database_error,
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -46,8 +53,19 @@ fn not_found() -> ServerResponse {
     );
 }
 
+fn protected_entity_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "PROTECTED_ENTITY",
+            "details": "This permission is protected and cannot be deleted."
+        }))
+    );
+}
+
 #[delete("/permissions/{name}")]
 pub async fn controller(
+  req: HttpRequest,
   query: Query<QueryData>,
   data: Data<CauthConfig>,
   name: Path<PathData>
@@ -58,10 +76,19 @@ pub async fn controller(
         .await
         .unwrap();
 
-    let permitted = LoginSession::has_permission(
+    // app admins are permitted inside their application's namespace
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:permissions:delete",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await
+    || LoginSession::can_administer(
         &mut db_conn,
         &query.session_token,
-        "cauth:permissions:delete"
+        &[&*name]
     )
     .await;
 
@@ -72,6 +99,9 @@ pub async fn controller(
         );
     }
 
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
     let result = Permission::delete(
         &mut db_conn,
         &name.into_inner()
@@ -81,7 +111,9 @@ pub async fn controller(
     match result {
         Ok(_) => return ok(),
         Err(error) => match error {
-            PermissionDeleteError::NotFound => not_found()
+            PermissionDeleteError::NotFound => not_found(),
+            PermissionDeleteError::Protected => protected_entity_error(),
+            PermissionDeleteError::Database(details) => database_error(details)
         }
     }
 }
@@ -1,6 +1,7 @@
 use actix_web::{
     post,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Json,
@@ -13,13 +14,32 @@ use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
+        event::{
+            permission_create::{
+                PermissionCreateEvent,
+                PermissionCreateEventInput
+            },
+            ChangeRequest,
+            EventCaller
+        },
         permission::{
             Permission,
             PermissionInsertError
         },
-        login_session::LoginSession
+        login_session::LoginSession,
+        Application,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            approvals::change_request_created,
+            events::failure_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -50,8 +70,29 @@ fn name_error() -> ServerResponse {
     );
 }
 
+fn invalid_name_error(details: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "INVALID_NAME",
+            "details": details
+        }))
+    );
+}
+
+fn reserved_namespace_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "RESERVED_NAMESPACE",
+            "details": "The \"cauth\" namespace is reserved for the service itself."
+        }))
+    );
+}
+
 #[post("/permissions")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     json: Json<JsonData>,
     data: Data<CauthConfig>
@@ -62,10 +103,19 @@ pub async fn controller(
         .await
         .unwrap();
 
-    let permitted = LoginSession::has_permission(
+    // app admins are permitted inside their application's namespace
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:permissions:post",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await
+    || LoginSession::can_administer(
         &mut db_conn,
         &query.session_token,
-        "cauth:permissions:post"
+        &[&json.name]
     )
     .await;
 
@@ -76,6 +126,33 @@ pub async fn controller(
         );
     }
 
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    if Application::is_reserved(&json.name) {
+        return reserved_namespace_error();
+    }
+
+    // operations requiring approvals are stored as change requests instead
+    let caller = EventCaller {
+        session_token: Some(query.session_token.clone()),
+        context: RequestContext::from_request(&req)
+    };
+    let input = PermissionCreateEventInput {
+        name: json.name.clone(),
+        description: json.description.clone()
+    };
+
+    let change_request_id = match ChangeRequest::intercept::<PermissionCreateEvent>(&mut db_conn, input, &caller).await {
+        Ok(change_request_id) => change_request_id,
+        // the operation is not applied when it cannot be checked against the approval rules
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    if let Some(change_request_id) = change_request_id {
+        return change_request_created(change_request_id);
+    }
+
     let result = Permission::insert(
     &mut db_conn,
     &json.name,
@@ -86,7 +163,9 @@ pub async fn controller(
     match result {
         Ok(_) => return ok(),
         Err(error) => match error {
-            PermissionInsertError::NameError => return name_error()
+            PermissionInsertError::NameError => return name_error(),
+            PermissionInsertError::Database(details) => return database_error(details),
+            error @ PermissionInsertError::InvalidName => return invalid_name_error(error.to_string())
         }
     }
 }
@@ -1,5 +1,6 @@
 use actix_web::{
     Responder,
+    HttpRequest,
     get,
     http::StatusCode,
     web::{
@@ -14,7 +15,8 @@ use crate::{
     models::{
         Order,
         permission::Permission,
-        login_session::LoginSession
+        login_session::LoginSession,
+        RequestContext
     },
     web::ServerResponse
 };
@@ -28,6 +30,7 @@ struct QueryData {
 
 #[get("/permissions")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     data: Data<CauthConfig>
 ) -> impl Responder {
@@ -39,7 +42,8 @@ pub async fn controller(
     let permitted = LoginSession::has_permission(
         &mut db_conn,
         &query.session_token,
-        "cauth:permissions:get"
+        "cauth:permissions:get",
+        &RequestContext::from_request(&req)
     )
     .await;
 
@@ -1,3 +1,6 @@
 pub mod list;
 pub mod insert;
 pub mod delete;
+pub mod update;
+pub mod list_groups;
+pub mod list_users;
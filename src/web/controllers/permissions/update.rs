@@ -0,0 +1,170 @@
+use actix_web::{
+    patch,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Path,
+        Json,
+        Data,
+        Query
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
+        permission::{
+            Permission,
+            PermissionUpdateError
+        },
+        login_session::LoginSession,
+        Application,
+        RequestContext
+    },
+    web::{
+        controllers::{
+            database_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+}
+
+#[derive(Deserialize)]
+struct JsonData {
+    name: Option<String>,
+    description: Option<String>
+}
+
+type PathData = String;
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "A permission with this name do not exist."
+        }))
+    );
+}
+
+fn name_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NAME_ERROR",
+            "details": "A permission with that name already exists."
+        }))
+    );
+}
+
+fn invalid_name_error(details: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "INVALID_NAME",
+            "details": details
+        }))
+    );
+}
+
+fn protected_entity_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "PROTECTED_ENTITY",
+            "details": "This permission is protected and cannot be renamed."
+        }))
+    );
+}
+
+fn reserved_namespace_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "RESERVED_NAMESPACE",
+            "details": "The \"cauth\" namespace is reserved for the service itself."
+        }))
+    );
+}
+
+#[patch("/permissions/{name}")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>,
+    name: Path<PathData>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    // app admins are permitted inside their application's namespace
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:permissions:update",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &[&*name, json.name.as_ref().unwrap_or(&name)]
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    if json.name.as_ref().is_some_and(|name| Application::is_reserved(name)) {
+        return reserved_namespace_error();
+    }
+
+    let result = Permission::update(
+        &mut db_conn,
+        &name,
+        json.name.as_ref(),
+        json.description.as_ref()
+    )
+    .await;
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            PermissionUpdateError::NotFound => return not_found_error(),
+            PermissionUpdateError::NameError => return name_error(),
+            error @ PermissionUpdateError::InvalidName => return invalid_name_error(error.to_string()),
+            PermissionUpdateError::Protected => return protected_entity_error(),
+            PermissionUpdateError::Webhook(error) => return webhook_error(error),
+            PermissionUpdateError::Database(details) => return database_error(details)
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Json,
+        Query,
+        Data
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        login_session::{
+            LoginSession,
+            LoginSessionGetUserError
+        },
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PermissionCheckData {
+    Name(String),
+    WithResource {
+        name: String,
+        resource: Option<String>
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonData {
+    permissions: Vec<PermissionCheckData>
+}
+
+fn ok(results: HashMap<String, bool>) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!({
+            "results": results
+        }))
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "The session associated with this token was not found"
+        }))
+    );
+}
+
+#[post("/user/permissions/check")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    // permissions are resolved in the session's active organization
+    let (user, context) = match LoginSession::get_user_with_context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await {
+        Ok(result) => result,
+        Err(error) => match error {
+            LoginSessionGetUserError::NotFound => return not_found_error()
+        }
+    };
+
+    let permissions: Vec<(String, Option<String>)> = json
+        .into_inner()
+        .permissions
+        .into_iter()
+        .map(|permission| match permission {
+            PermissionCheckData::Name(name) => (name, None),
+            PermissionCheckData::WithResource { name, resource } => (name, resource)
+        })
+        .collect();
+
+    let decisions = user
+        .check_permissions(
+            &mut db_conn,
+            &permissions,
+            &context
+        )
+        .await;
+
+    let mut results = HashMap::new();
+
+    for ((name, resource), decision) in permissions.iter().zip(decisions) {
+        let key = match resource {
+            Some(resource) => format!("{}@{}", name, resource),
+            None => name.clone()
+        };
+
+        results.insert(key, decision.allowed);
+    }
+
+    return ok(results);
+}
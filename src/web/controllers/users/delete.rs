@@ -1,6 +1,7 @@
 use actix_web::{
     delete,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Path,
@@ -13,14 +14,31 @@ use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        event::{
+            user_delete::{
+                UserDeleteEvent,
+                UserDeleteEventInput
+            },
+            ChangeRequest,
+            EventCaller
+        },
         user::{
             User,
             UserDeleteError
         },
-        event::UserDeleteEvent,
-        login_session::LoginSession
+        login_session::LoginSession,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            approvals::change_request_created,
+            events::failure_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -47,8 +65,19 @@ fn not_found_error() -> ServerResponse {
     );
 }
 
+fn protected_entity_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "PROTECTED_ENTITY",
+            "details": "This user is the last member of a protected group."
+        }))
+    );
+}
+
 #[delete("/users/{login}")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     path: Path<PathData>,
     data: Data<CauthConfig>
@@ -64,7 +93,8 @@ pub async fn controller(
     let has_permission = LoginSession::has_permission(
         &mut db_conn,
         &query.session_token,
-        &"cauth:users:delete".to_string()
+        &"cauth:users:delete".to_string(),
+        &RequestContext::from_request(&req)
     )
     .await;
 
@@ -89,7 +119,36 @@ pub async fn controller(
             None
         );
     }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
     
+    // operations requiring approvals are stored as change requests instead
+    let caller = EventCaller {
+        session_token: Some(query.session_token.clone()),
+        context: RequestContext::from_request(&req)
+    };
+    let input = UserDeleteEventInput {
+        login: login.clone()
+    };
+
+    let change_request_id = match ChangeRequest::intercept::<UserDeleteEvent>(&mut db_conn, input, &caller).await {
+        Ok(change_request_id) => change_request_id,
+        // the operation is not applied when it cannot be checked against the approval rules
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    if let Some(change_request_id) = change_request_id {
+        match db_conn.commit().await {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("Error committing changes: {}", err);
+            }
+        };
+
+        return change_request_created(change_request_id);
+    }
+
     let result = User::delete(
         &mut db_conn,
         login
@@ -106,7 +165,10 @@ pub async fn controller(
     match result {
         Ok(_) => return ok(),
         Err(error) => match error {
-            UserDeleteError::NotFound => return not_found_error()
+            UserDeleteError::NotFound => return not_found_error(),
+            UserDeleteError::Protected => return protected_entity_error(),
+            UserDeleteError::Webhook(error) => return webhook_error(error),
+            UserDeleteError::Database(details) => return database_error(details)
         }
     };
 }
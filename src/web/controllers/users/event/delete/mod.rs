@@ -1,3 +0,0 @@
-pub mod create;
-pub mod commit;
-pub mod cancel;
@@ -1,3 +0,0 @@
-pub mod register;
-pub mod login;
-pub mod delete;
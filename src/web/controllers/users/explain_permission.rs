@@ -0,0 +1,106 @@
+use actix_web::{
+    get,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Query,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        login_session::LoginSession,
+        user::User,
+        PermissionExplanation,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String,
+    ip: Option<String>,
+    resource: Option<String>
+}
+
+type PathData = (String, String);
+
+fn ok(explanation: PermissionExplanation) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(explanation))
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "User with specified login do not exist"
+        }))
+    );
+}
+
+#[get("/users/{name}/permissions/{permission_name}/explain")]
+pub async fn controller(
+    req: HttpRequest,
+    data: Data<CauthConfig>,
+    query: Query<QueryData>,
+    path: Path<PathData>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let context = RequestContext::from_request(&req);
+
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:permissions:explain",
+        &context
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    let (user_login, permission_name) = path.into_inner();
+
+    let user = match User::retrieve(&mut db_conn, &user_login).await {
+        Ok(user) => user,
+        Err(_) => return not_found_error()
+    };
+
+    // the decision is explained as if the user made the request themself inside the active organization,
+    // optionally from provided ip and against provided resource
+    let mut context = LoginSession::context(&mut db_conn, &query.session_token, &context)
+        .await
+        .with_resource(query.resource.clone());
+    if query.ip.is_some() {
+        context.ip = query.ip.clone();
+    }
+
+    let explanation = user
+        .explain_permission(
+            &mut db_conn,
+            permission_name,
+            &context
+        )
+        .await;
+
+    return ok(explanation);
+}
@@ -1,5 +1,6 @@
 use actix_web::{
     Responder,
+    HttpRequest,
     get,
     http::StatusCode,
     web::{
@@ -14,7 +15,8 @@ use crate::{
     config::CauthConfig,
     models::{
         user::User,
-        login_session::LoginSession
+        login_session::LoginSession,
+        RequestContext
     },
     web::ServerResponse
 };
@@ -28,6 +30,7 @@ type PathData = String;
 
 #[get("/user/permissions/{permission_name}")]
 pub async fn controller(
+    req: HttpRequest,
     path: Path<PathData>,
     query: Query<QueryData>,
     data: Data<CauthConfig>
@@ -40,18 +43,29 @@ pub async fn controller(
 
     let permission_name = path.into_inner();
 
-    let result = LoginSession::has_permission(
+    let result = LoginSession::check_permission(
         &mut db_conn,
         &query.session_token,
-        &permission_name
+        &permission_name,
+        &RequestContext::from_request(&req)
     )
     .await;
 
-    return ServerResponse::new(
-        StatusCode::OK,
-        Some(json!({
-            "has": result
-        }))
-    );
+    return match result {
+        Ok(decision) => ServerResponse::new(
+            StatusCode::OK,
+            Some(json!({
+                "has": decision.allowed,
+                "denied_by": decision.denied_by
+            }))
+        ),
+        Err(_) => ServerResponse::new(
+            StatusCode::OK,
+            Some(json!({
+                "has": false,
+                "denied_by": null
+            }))
+        )
+    };
 }
 
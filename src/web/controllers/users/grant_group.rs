@@ -1,21 +1,43 @@
 use actix_web::{
     post,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Query,
         Data,
-        Path
+        Path,
+        Json
     }
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        event::{
+            user_grant_group::{
+                UserGrantGroupEvent,
+                UserGrantGroupEventInput
+            },
+            ChangeRequest,
+            EventCaller
+        },
+        RequestContext,
+        SodConflict,
         login_session::LoginSession, user::{User, UserGrantError}
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            approvals::change_request_created,
+            events::failure_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -23,6 +45,11 @@ struct QueryData {
     session_token: String
 }
 
+#[derive(Deserialize)]
+struct JsonData {
+    expires_at: Option<DateTime<Utc>>
+}
+
 type PathData = (String, String);
 
 fn ok() -> ServerResponse {
@@ -52,11 +79,35 @@ fn group_not_found_error() -> ServerResponse {
     );
 }
 
+fn sod_conflict_error(conflict: SodConflict) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "SOD_CONFLICT",
+            "details": conflict.to_string(),
+            "set_name": conflict.set_name,
+            "groups": conflict.groups
+        }))
+    );
+}
+
+fn not_member_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_MEMBER",
+            "details": "User with specified login do not belong to the active organization"
+        }))
+    );
+}
+
 #[post("/users/{name}/{permission_name}")]
 pub async fn controller(
+    req: HttpRequest,
     data: Data<CauthConfig>,
     query: Query<QueryData>,
-    path: Path<PathData>
+    path: Path<PathData>,
+    json: Option<Json<JsonData>>
 ) -> impl Responder {
     // these will never error
     let mut db_conn = data.db_conn
@@ -64,10 +115,29 @@ pub async fn controller(
         .await
         .unwrap();
 
+    let (user_login, group_name) = path.into_inner();
+    let context = RequestContext::from_request(&req);
+
+    // without "cauth:users:update" only the groups the user can delegate
+    // or the groups inside the user's application namespace can be granted
     let permitted = LoginSession::has_permission(
         &mut db_conn,
         &query.session_token,
-        "cauth:users:update"
+        "cauth:users:update",
+        &context
+    )
+    .await
+    || LoginSession::can_delegate_group(
+        &mut db_conn,
+        &query.session_token,
+        &group_name,
+        &context
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &[&group_name]
     )
     .await;
 
@@ -78,12 +148,53 @@ pub async fn controller(
         );
     }
 
-    let (user_login, group_name) = path.into_inner();
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &context).await;
+
+    let expires_at = json.and_then(|json| json.expires_at);
+
+    // operations requiring approvals are stored as change requests instead
+    let caller = EventCaller {
+        session_token: Some(query.session_token.clone()),
+        context: RequestContext::from_request(&req)
+    };
+    let input = UserGrantGroupEventInput {
+        login: user_login.clone(),
+        group: group_name.clone(),
+        expires_at
+    };
+
+    let change_request_id = match ChangeRequest::intercept::<UserGrantGroupEvent>(&mut db_conn, input, &caller).await {
+        Ok(change_request_id) => change_request_id,
+        // the operation is not applied when it cannot be checked against the approval rules
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    if let Some(change_request_id) = change_request_id {
+        match db_conn.commit().await {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("Error committing changes: {}", err);
+            }
+        };
+
+        return change_request_created(change_request_id);
+    }
+
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &context
+    )
+    .await;
 
     let result = User::grant_group(
         &mut db_conn,
         &user_login,
-        &group_name
+        &group_name,
+        expires_at,
+        &context.organization
     )
     .await;
 
@@ -99,6 +210,10 @@ pub async fn controller(
         Err(error) => match error {
             UserGrantError::NotFound => return not_found_error(),
             UserGrantError::GroupNotFound => return group_not_found_error(),
+            UserGrantError::SodConflict(conflict) => return sod_conflict_error(conflict),
+            UserGrantError::NotMember => return not_member_error(),
+            UserGrantError::Webhook(error) => return webhook_error(error),
+            UserGrantError::Database(details) => return database_error(details),
         }
     }
 }
@@ -0,0 +1,179 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Query,
+        Data,
+        Path,
+        Json
+    }
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        login_session::LoginSession,
+        user::{User, UserGrantPermissionError},
+        GrantEffect,
+        RequestContext
+    },
+    web::{
+        controllers::database_error,
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+#[derive(Deserialize)]
+struct JsonData {
+    effect: Option<GrantEffect>,
+    condition: Option<String>,
+    expires_at: Option<DateTime<Utc>>
+}
+
+type PathData = (String, String);
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "User with specified login do not exist"
+        }))
+    );
+}
+
+fn permission_not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "PERMISSION_NOT_FOUND",
+            "details": "Permission with specified name do not exist"
+        }))
+    );
+}
+
+fn invalid_condition_error(details: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "INVALID_CONDITION",
+            "details": details
+        }))
+    );
+}
+
+fn not_member_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_MEMBER",
+            "details": "User with specified login do not belong to the active organization"
+        }))
+    );
+}
+
+#[post("/users/{name}/permissions/{permission_name}")]
+pub async fn controller(
+    req: HttpRequest,
+    data: Data<CauthConfig>,
+    query: Query<QueryData>,
+    path: Path<PathData>,
+    json: Option<Json<JsonData>>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .begin()
+        .await
+        .unwrap();
+
+    let (user_login, permission_name) = path.into_inner();
+
+    // app admins are permitted inside their application's namespace
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:users:update",
+        &RequestContext::from_request(&req)
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &[&permission_name]
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    let json = json.map(|json| json.into_inner());
+    let effect = json
+        .as_ref()
+        .and_then(|json| json.effect)
+        .unwrap_or(GrantEffect::Allow);
+    let expires_at = json
+        .as_ref()
+        .and_then(|json| json.expires_at);
+    let condition = json.and_then(|json| json.condition);
+
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    let result = User::grant_permission(
+        &mut db_conn,
+        &user_login,
+        &permission_name,
+        effect,
+        condition.as_ref(),
+        expires_at,
+        &context.organization
+    )
+    .await;
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error committing changes: {}", err);
+        }
+    };
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            UserGrantPermissionError::NotFound => return not_found_error(),
+            UserGrantPermissionError::PermissionNotFound => return permission_not_found_error(),
+            UserGrantPermissionError::InvalidCondition(details) => return invalid_condition_error(details),
+            UserGrantPermissionError::NotMember => return not_member_error(),
+            UserGrantPermissionError::Database(details) => return database_error(details)
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use actix_web::{
     post,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Json,
@@ -13,14 +14,36 @@ use serde_json::{
     Value
 };
 use crate::{
-    config::CauthConfig,
+    config::{
+        CauthConfig,
+        RegistrationSource
+    },
     models::{
+        event::{
+            user_register::{
+                UserRegisterEvent,
+                UserRegisterEventInput
+            },
+            ChangeRequest,
+            EventCaller
+        },
+        audit::AuditEntry,
+        RequestContext,
+        SodConflict,
         user::{
             User,
             UserInsertError
         },
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            approvals::change_request_created,
+            events::failure_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -57,17 +80,69 @@ fn cannot_hash_error(details: String) -> ServerResponse {
     );
 }
 
+fn group_not_found_error(group_name: String) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Some(json!({
+            "code": "GROUP_NOT_FOUND",
+            "details": format!("Default group \"{}\" do not exist", group_name)
+        }))
+    );
+}
+
+fn sod_conflict_error(conflict: SodConflict) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Some(json!({
+            "code": "SOD_CONFLICT",
+            "details": conflict.to_string(),
+            "set_name": conflict.set_name,
+            "groups": conflict.groups
+        }))
+    );
+}
+
 #[post("/users")]
 pub async fn controller(
+    req: HttpRequest,
     json: Json<JsonData>,
     data: Data<CauthConfig>
 ) -> impl Responder {
     // these will never error
     let mut db_conn = data.db_conn
-        .acquire()
+        .begin()
         .await
         .unwrap();
     
+    // operations requiring approvals are stored as change requests instead
+    let caller = EventCaller {
+        session_token: None,
+        context: RequestContext::from_request(&req)
+    };
+    AuditEntry::identify(&mut db_conn, Some(&json.login), caller.context.ip.as_ref()).await;
+    let input = UserRegisterEventInput {
+        login: json.login.clone(),
+        password: json.password.clone(),
+        details: json.details.clone()
+    };
+
+    let change_request_id = match ChangeRequest::intercept::<UserRegisterEvent>(&mut db_conn, input, &caller).await {
+        Ok(change_request_id) => change_request_id,
+        // the operation is not applied when it cannot be checked against the approval rules
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    if let Some(change_request_id) = change_request_id {
+        match db_conn.commit().await {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("Error committing changes: {}", err);
+            }
+        };
+
+        return change_request_created(change_request_id);
+    }
+
     let details = json.details
         .clone()
         .unwrap_or(json!({}));
@@ -76,15 +151,30 @@ pub async fn controller(
         &mut db_conn, 
         &json.login, 
         &json.password, 
-        &details
+        &details,
+        data.default_groups.for_source(RegistrationSource::Direct)
     )
     .await;
     
     match result {
-        Ok(_) => return ok(),
+        Ok(_) => (),
         Err(error) => match error {
             UserInsertError::NameError => return name_error(),
-            UserInsertError::CannotHash(details) => return cannot_hash_error(details)
+            UserInsertError::CannotHash(details) => return cannot_hash_error(details),
+            // the transaction is dropped, so the user is not created
+            UserInsertError::GroupNotFound(group_name) => return group_not_found_error(group_name),
+            UserInsertError::SodConflict(conflict) => return sod_conflict_error(conflict),
+            UserInsertError::Webhook(error) => return webhook_error(error),
+            UserInsertError::Database(details) => return database_error(details)
         }
     };
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error committing changes: {}", err);
+        }
+    };
+
+    return ok();
 }
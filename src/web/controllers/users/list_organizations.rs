@@ -0,0 +1,87 @@
+use actix_web::{
+    Responder,
+    get,
+    http::StatusCode,
+    web::{
+        Query,
+        Data
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        organization::Organization,
+        login_session::{
+            LoginSession,
+            LoginSessionGetUserError
+        }
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+fn ok(active: String, organizations: Vec<String>) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!({
+            "active": active,
+            "organizations": organizations
+        }))
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "The session associated with this token was not found"
+        }))
+    );
+}
+
+#[get("/user/organizations")]
+pub async fn controller(
+    query: Query<QueryData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let user = match LoginSession::get_user(
+        &mut db_conn,
+        &query.session_token
+    )
+    .await {
+        Ok(user) => user,
+        Err(error) => match error {
+            LoginSessionGetUserError::NotFound => return not_found_error()
+        }
+    };
+
+    let active = match LoginSession::get_organization(
+        &mut db_conn,
+        &query.session_token
+    )
+    .await {
+        Ok(active) => active,
+        Err(_) => return not_found_error()
+    };
+
+    let organizations = Organization::list_for_user(
+        &mut db_conn,
+        &user.login
+    )
+    .await;
+
+    return ok(active, organizations);
+}
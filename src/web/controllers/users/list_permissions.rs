@@ -0,0 +1,82 @@
+use actix_web::{
+    Responder,
+    HttpRequest,
+    get,
+    http::StatusCode,
+    web::{
+        Query,
+        Data
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        login_session::{
+            LoginSession,
+            LoginSessionGetUserError
+        },
+        EffectivePermission,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+fn ok(permissions: Vec<EffectivePermission>) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        Some(json!(permissions))
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "The session associated with this token was not found"
+        }))
+    );
+}
+
+#[get("/user/permissions")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    // permissions are resolved in the session's active organization
+    let (user, context) = match LoginSession::get_user_with_context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await {
+        Ok(result) => result,
+        Err(error) => match error {
+            LoginSessionGetUserError::NotFound => return not_found_error()
+        }
+    };
+
+
+    let permissions = user
+        .effective_permissions(
+            &mut db_conn,
+            &context
+        )
+        .await;
+
+    return ok(permissions);
+}
@@ -1,6 +1,7 @@
 use actix_web::{
     post,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Json,
@@ -16,14 +17,28 @@ use crate::{
             User,
             UserLoginError
         },
+        login_session::{
+            LoginSession,
+            LoginSessionSetOrganizationError
+        },
+        audit::AuditEntry,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
 pub struct JsonData {
     login: String,
     password: String,
+    /// organization the session starts in, the default one when not provided
+    organization: Option<String>,
 }
 
 fn ok(token: String) -> ServerResponse {
@@ -65,8 +80,35 @@ fn cannot_hash_error(details: String) -> ServerResponse {
     )
 }
 
+fn not_member_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_MEMBER",
+            "details": "The user do not belong to this organization"
+        }))
+    );
+}
+
+/// records the failed attempt outside of the dropped transaction
+async fn record_failed_login(
+    data: &CauthConfig,
+    login: &String,
+    context: &RequestContext
+) {
+    // this will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    AuditEntry::identify(&mut db_conn, Some(login), context.ip.as_ref()).await;
+    AuditEntry::record(&mut db_conn, "login.failed", Some(login)).await;
+}
+
 #[post("/user")]
 pub async fn controller(
+    req: HttpRequest,
     json: Json<JsonData>,
     data: Data<CauthConfig>
 ) -> impl Responder {
@@ -76,6 +118,9 @@ pub async fn controller(
         .await
         .unwrap();
 
+    let context = RequestContext::from_request(&req);
+    AuditEntry::identify(&mut db_conn, Some(&json.login), context.ip.as_ref()).await;
+
     let result = User::login(
         &mut db_conn,
         &json.login,
@@ -83,6 +128,43 @@ pub async fn controller(
     )
     .await;
 
+    let token = match result {
+        Ok(token) => token,
+        Err(error) => match error {
+            UserLoginError::InvalidCredentials => {
+                drop(db_conn);
+                record_failed_login(&data, &json.login, &context).await;
+                return invalid_credentials_error();
+            },
+            UserLoginError::NotFound => {
+                drop(db_conn);
+                record_failed_login(&data, &json.login, &context).await;
+                return not_found_error();
+            },
+            UserLoginError::CannotHash(details) => return cannot_hash_error(details),
+            UserLoginError::Webhook(error) => return webhook_error(error),
+            UserLoginError::Database(details) => return database_error(details)
+        }
+    };
+
+    // the session is only kept if it can be started in the requested organization
+    if let Some(organization) = &json.organization {
+        let result = LoginSession::set_organization(
+            &mut db_conn,
+            &token,
+            organization
+        )
+        .await;
+
+        match result {
+            Ok(_) => (),
+            Err(error) => match error {
+                LoginSessionSetOrganizationError::NotFound => return not_found_error(),
+                LoginSessionSetOrganizationError::NotMember => return not_member_error()
+            }
+        };
+    }
+
     match db_conn.commit().await {
         Ok(_) => (),
         Err(err) => {
@@ -90,12 +172,5 @@ pub async fn controller(
         }
     };
 
-    match result {
-        Ok(token) => return ok(token),
-        Err(error) => match error {
-            UserLoginError::InvalidCredentials => return invalid_credentials_error(),
-            UserLoginError::NotFound => return not_found_error(),
-            UserLoginError::CannotHash(details) => return cannot_hash_error(details)
-        }
-    };
+    return ok(token);
 }
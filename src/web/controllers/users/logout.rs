@@ -1,6 +1,7 @@
 use actix_web::{
     delete,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Path,
@@ -13,9 +14,17 @@ use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
-        login_session::{LoginSession, LoginSessionDeleteError}, user::User
+        login_session::{LoginSession, LoginSessionDeleteError}, user::User,
+        audit::AuditEntry,
+        RequestContext
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -42,6 +51,7 @@ fn not_found_error() -> ServerResponse {
 
 #[delete("/user")]
 pub async fn controller(
+    req: HttpRequest,
     query: Query<QueryData>,
     data: Data<CauthConfig>
 ) -> impl Responder {
@@ -51,6 +61,12 @@ pub async fn controller(
         .await
         .unwrap();
 
+    // the session's user logs out
+    if let Ok(session) = LoginSession::retrieve(&mut db_conn, &query.session_token).await {
+        let context = RequestContext::from_request(&req);
+        AuditEntry::identify(&mut db_conn, Some(&session.user_login), context.ip.as_ref()).await;
+    }
+
     let result = LoginSession::delete_by_token(
         &mut db_conn,
         &query.session_token
@@ -67,7 +83,9 @@ pub async fn controller(
     match result {
         Ok(_) => return ok(),
         Err(error) => match error {
-            LoginSessionDeleteError::NotFound => return not_found_error()
+            LoginSessionDeleteError::NotFound => return not_found_error(),
+            LoginSessionDeleteError::Webhook(error) => return webhook_error(error),
+            LoginSessionDeleteError::Database(details) => return database_error(details)
         } 
     };
 }
@@ -2,8 +2,14 @@ pub mod insert;
 pub mod delete;
 pub mod get;
 pub mod get_permission;
+pub mod list_permissions;
+pub mod check_permissions;
+pub mod explain_permission;
+pub mod list_organizations;
+pub mod set_organization;
 pub mod login;
 pub mod logout;
 pub mod grant_group;
 pub mod revoke_group;
-pub mod event;
+pub mod grant_permission;
+pub mod revoke_permission;
@@ -1,6 +1,7 @@
 use actix_web::{
     delete,
     Responder,
+    HttpRequest,
     http::StatusCode, 
     web::{
         Query,
@@ -13,9 +14,27 @@ use serde_json::json;
 use crate::{
     config::CauthConfig,
     models::{
+        audit::AuditEntry,
+        event::{
+            user_revoke_group::{
+                UserRevokeGroupEvent,
+                UserRevokeGroupEventInput
+            },
+            ChangeRequest,
+            EventCaller
+        },
+        RequestContext,
         login_session::LoginSession, user::{User, UserRevokeError}
     },
-    web::ServerResponse
+    web::{
+        controllers::{
+            database_error,
+            approvals::change_request_created,
+            events::failure_error,
+            webhooks::webhook_error
+        },
+        ServerResponse
+    }
 };
 
 #[derive(Deserialize)]
@@ -62,8 +81,19 @@ fn not_granted_error() -> ServerResponse {
     );
 }
 
+fn protected_entity_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "PROTECTED_ENTITY",
+            "details": "This user is the last member of this protected group."
+        }))
+    );
+}
+
 #[delete("/users/{name}/{permission_name}")]
 pub async fn controller(
+    req: HttpRequest,
     data: Data<CauthConfig>,
     query: Query<QueryData>,
     path: Path<PathData>
@@ -74,10 +104,29 @@ pub async fn controller(
         .await
         .unwrap();
 
+    let (user_login, group_name) = path.into_inner();
+    let context = RequestContext::from_request(&req);
+
+    // without "cauth:users:update" only the groups the user can delegate
+    // or the groups inside the user's application namespace can be revoked
     let permitted = LoginSession::has_permission(
         &mut db_conn,
         &query.session_token,
-        "cauth:users:update"
+        "cauth:users:update",
+        &context
+    )
+    .await
+    || LoginSession::can_delegate_group(
+        &mut db_conn,
+        &query.session_token,
+        &group_name,
+        &context
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &[&group_name]
     )
     .await;
 
@@ -88,12 +137,49 @@ pub async fn controller(
         );
     }
 
-    let (user_login, group_name) = path.into_inner();
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &context).await;
+
+    // operations requiring approvals are stored as change requests instead
+    let caller = EventCaller {
+        session_token: Some(query.session_token.clone()),
+        context: RequestContext::from_request(&req)
+    };
+    let input = UserRevokeGroupEventInput {
+        login: user_login.clone(),
+        group: group_name.clone()
+    };
+
+    let change_request_id = match ChangeRequest::intercept::<UserRevokeGroupEvent>(&mut db_conn, input, &caller).await {
+        Ok(change_request_id) => change_request_id,
+        // the operation is not applied when it cannot be checked against the approval rules
+        Err(failure) => return failure_error(failure, json!({}))
+    };
+
+    if let Some(change_request_id) = change_request_id {
+        match db_conn.commit().await {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("Error committing changes: {}", err);
+            }
+        };
+
+        return change_request_created(change_request_id);
+    }
+
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &context
+    )
+    .await;
 
     let result = User::revoke_group(
         &mut db_conn,
         &user_login,
-        &group_name
+        &group_name,
+        &context.organization
     )
     .await;
 
@@ -109,7 +195,10 @@ pub async fn controller(
         Err(error) => match error {
             UserRevokeError::NotFound => return not_found_error(),
             UserRevokeError::GroupNotFound => return group_not_found_error(),
-            UserRevokeError::NotGranted => return not_granted_error()
+            UserRevokeError::NotGranted => return not_granted_error(),
+            UserRevokeError::Protected => return protected_entity_error(),
+            UserRevokeError::Webhook(error) => return webhook_error(error),
+            UserRevokeError::Database(details) => return database_error(details)
         }
     }
 }
@@ -0,0 +1,145 @@
+use actix_web::{
+    delete,
+    Responder,
+    HttpRequest,
+    http::StatusCode, 
+    web::{
+        Query,
+        Data,
+        Path
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        login_session::LoginSession,
+        user::{User, UserRevokePermissionError},
+        RequestContext
+    },
+    web::{
+        controllers::database_error,
+        ServerResponse
+    }
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+type PathData = (String, String);
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "User with specified login do not exist"
+        }))
+    );
+}
+
+fn permission_not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "PERMISSION_NOT_FOUND",
+            "details": "Permission with specified name do not exist"
+        }))
+    );
+}
+
+fn not_granted_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_GRANTED",
+            "details": "Provided user was never directly granted this permission"
+        }))
+    );
+}
+
+#[delete("/users/{name}/permissions/{permission_name}")]
+pub async fn controller(
+    req: HttpRequest,
+    data: Data<CauthConfig>,
+    query: Query<QueryData>,
+    path: Path<PathData>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .begin()
+        .await
+        .unwrap();
+
+    let (user_login, permission_name) = path.into_inner();
+
+    // app admins are permitted inside their application's namespace
+    let permitted = LoginSession::has_permission(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:users:update",
+        &RequestContext::from_request(&req)
+    )
+    .await
+    || LoginSession::can_administer(
+        &mut db_conn,
+        &query.session_token,
+        &[&permission_name]
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+
+    // groups and grants are resolved in the session's active organization
+    let context = LoginSession::context(
+        &mut db_conn,
+        &query.session_token,
+        &RequestContext::from_request(&req)
+    )
+    .await;
+
+    let result = User::revoke_permission(
+        &mut db_conn,
+        &user_login,
+        &permission_name,
+        &context.organization
+    )
+    .await;
+
+    match db_conn.commit().await {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("Error committing changes: {}", err);
+        }
+    };
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            UserRevokePermissionError::NotFound => return not_found_error(),
+            UserRevokePermissionError::PermissionNotFound => return permission_not_found_error(),
+            UserRevokePermissionError::NotGranted => return not_granted_error(),
+            UserRevokePermissionError::Database(details) => return database_error(details)
+        }
+    }
+}
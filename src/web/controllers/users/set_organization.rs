@@ -0,0 +1,97 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Json,
+        Query,
+        Data
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        login_session::{
+            LoginSession,
+            LoginSessionSetOrganizationError
+        },
+        audit::AuditEntry,
+        RequestContext
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+#[derive(Deserialize)]
+struct JsonData {
+    name: String
+}
+
+fn ok() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::OK,
+        None
+    );
+}
+
+fn not_found_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_FOUND",
+            "details": "The session associated with this token was not found"
+        }))
+    );
+}
+
+fn not_member_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "NOT_MEMBER",
+            "details": "The user do not belong to this organization"
+        }))
+    );
+}
+
+#[post("/user/organization")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    // the session's user switches the organization
+    if let Ok(session) = LoginSession::retrieve(&mut db_conn, &query.session_token).await {
+        let context = RequestContext::from_request(&req);
+        AuditEntry::identify(&mut db_conn, Some(&session.user_login), context.ip.as_ref()).await;
+    }
+
+    let result = LoginSession::set_organization(
+        &mut db_conn,
+        &query.session_token,
+        &json.name
+    )
+    .await;
+
+    match result {
+        Ok(_) => return ok(),
+        Err(error) => match error {
+            LoginSessionSetOrganizationError::NotFound => return not_found_error(),
+            LoginSessionSetOrganizationError::NotMember => return not_member_error()
+        }
+    };
+}
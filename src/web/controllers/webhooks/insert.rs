@@ -0,0 +1,108 @@
+use actix_web::{
+    post,
+    Responder,
+    HttpRequest,
+    http::StatusCode,
+    web::{
+        Json,
+        Data,
+        Query
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use crate::{
+    config::CauthConfig,
+    models::{
+        audit::AuditEntry,
+        organization::DEFAULT_ORGANIZATION,
+        webhook::WebhookInsertError,
+        login_session::LoginSession,
+        RequestContext,
+        Webhook
+    },
+    web::ServerResponse
+};
+
+#[derive(Deserialize)]
+struct QueryData {
+    session_token: String
+}
+
+#[derive(Deserialize)]
+struct JsonData {
+    url: String,
+    #[serde(default)]
+    event_types: Vec<String>,
+    secret: Option<String>
+}
+
+fn invalid_url_error() -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "INVALID_URL",
+            "details": WebhookInsertError::InvalidUrl.to_string()
+        }))
+    );
+}
+
+fn unknown_event_type_error(error: WebhookInsertError) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::BAD_REQUEST,
+        Some(json!({
+            "code": "UNKNOWN_EVENT_TYPE",
+            "details": error.to_string()
+        }))
+    );
+}
+
+#[post("/webhooks")]
+pub async fn controller(
+    req: HttpRequest,
+    query: Query<QueryData>,
+    json: Json<JsonData>,
+    data: Data<CauthConfig>
+) -> impl Responder {
+    // these will never error
+    let mut db_conn = data.db_conn
+        .acquire()
+        .await
+        .unwrap();
+
+    let permitted = LoginSession::has_permission_in(
+        &mut db_conn,
+        &query.session_token,
+        "cauth:webhooks:post",
+        &RequestContext::from_request(&req),
+        DEFAULT_ORGANIZATION
+    )
+    .await;
+
+    if !permitted {
+        return ServerResponse::new(
+            StatusCode::UNAUTHORIZED,
+            None
+        );
+    }
+
+    // the changes are made by the session's user
+    AuditEntry::identify_session(&mut db_conn, Some(&query.session_token), &RequestContext::from_request(&req)).await;
+
+    let result = Webhook::insert(
+        &mut db_conn,
+        &json.url,
+        &json.event_types,
+        json.secret.as_ref()
+    )
+    .await;
+
+    return match result {
+        Ok(credentials) => ServerResponse::new(
+            StatusCode::OK,
+            Some(json!(credentials))
+        ),
+        Err(WebhookInsertError::InvalidUrl) => invalid_url_error(),
+        Err(error) => unknown_event_type_error(error)
+    };
+}
@@ -0,0 +1,20 @@
+pub mod list;
+pub mod insert;
+pub mod delete;
+pub mod list_deliveries;
+pub mod retry_delivery;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use crate::{models::webhook::WebhookEnqueueError, web::ServerResponse};
+
+/// reports that the change was rolled back because its webhook deliveries cannot be queued
+pub fn webhook_error(error: WebhookEnqueueError) -> ServerResponse {
+    return ServerResponse::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Some(json!({
+            "code": "WEBHOOK_ERROR",
+            "details": error.to_string()
+        }))
+    );
+}
@@ -13,28 +13,66 @@ use crate::{
         ListPermissionsController,
         InsertPermissionController,
         DeletePermissionController,
+        UpdatePermissionController,
+        ListGroupsPermissionController,
+        ListUsersPermissionController,
         ListGroupsController,
         InsertGroupController,
         DeleteGroupController,
+        UpdateGroupController,
+        ListMembersGroupController,
+        ListManagersGroupController,
+        GrantManagerGroupController,
+        RevokeManagerGroupController,
         GrantPermissionGroupController,
         RevokePermissionGroupController,
+        ListExclusiveGroupSetsController,
+        InsertExclusiveGroupSetController,
+        DeleteExclusiveGroupSetController,
+        ListViolationsExclusiveGroupSetController,
+        ListApplicationsController,
+        InsertApplicationController,
+        DeleteApplicationController,
+        GrantAdminApplicationController,
+        RevokeAdminApplicationController,
+        ListOrganizationsController,
+        InsertOrganizationController,
+        DeleteOrganizationController,
+        ListMembersOrganizationController,
+        AddMemberOrganizationController,
+        RemoveMemberOrganizationController,
         InsertUserController,
         DeleteUserController,
         GetUserController,
         GetPermissionUserController,
+        ListPermissionsUserController,
+        CheckPermissionsUserController,
+        ExplainPermissionUserController,
+        ListOrganizationsUserController,
+        SetOrganizationUserController,
         LoginUserController,
         LogoutUserController,
         GrantGroupUserController,
         RevokeGroupUserController,
-        UserRegisterEventCreateController,
-        UserRegisterEventCommitController,
-        UserRegisterEventCancelController,
-        UserLoginEventCreateController,
-        UserLoginEventCancelController,
-        UserLoginEventCommitController,
-        UserDeleteEventCreateController,
-        UserDeleteEventCommitController,
-        UserDeleteEventCancelController,
+        GrantPermissionUserController,
+        RevokePermissionUserController,
+        CommitEventController,
+        CancelEventController,
+        CreateEventController,
+        CreateEventBatchController,
+        CommitEventBatchController,
+        CancelEventBatchController,
+        ListEventsController,
+        ListApprovalsController,
+        GetApprovalController,
+        ApproveApprovalController,
+        RejectApprovalController,
+        ListWebhooksController,
+        CreateWebhookController,
+        DeleteWebhookController,
+        ListWebhookDeliveriesController,
+        RetryWebhookDeliveryController,
+        ListAuditController
     }
 };
 
@@ -46,28 +84,68 @@ pub async fn run_server(config: CauthConfig) -> std::io::Result<()> {
             .service(ListPermissionsController)
             .service(InsertPermissionController)
             .service(DeletePermissionController)
+            .service(UpdatePermissionController)
+            .service(ListGroupsPermissionController)
+            .service(ListUsersPermissionController)
             .service(ListGroupsController)
             .service(InsertGroupController)
             .service(DeleteGroupController)
+            .service(UpdateGroupController)
+            .service(ListMembersGroupController)
+            .service(ListManagersGroupController)
+            .service(GrantManagerGroupController)
+            .service(RevokeManagerGroupController)
             .service(GrantPermissionGroupController)
             .service(RevokePermissionGroupController)
+            .service(ListExclusiveGroupSetsController)
+            .service(InsertExclusiveGroupSetController)
+            .service(DeleteExclusiveGroupSetController)
+            .service(ListViolationsExclusiveGroupSetController)
+            .service(ListApplicationsController)
+            .service(InsertApplicationController)
+            .service(DeleteApplicationController)
+            .service(GrantAdminApplicationController)
+            .service(RevokeAdminApplicationController)
+            .service(ListOrganizationsController)
+            .service(InsertOrganizationController)
+            .service(DeleteOrganizationController)
+            .service(ListMembersOrganizationController)
+            .service(AddMemberOrganizationController)
+            .service(RemoveMemberOrganizationController)
             .service(InsertUserController)
             .service(DeleteUserController)
             .service(GetUserController)
             .service(GetPermissionUserController)
+            .service(ListPermissionsUserController)
+            .service(CheckPermissionsUserController)
+            .service(ExplainPermissionUserController)
+            .service(ListOrganizationsUserController)
+            .service(SetOrganizationUserController)
             .service(LoginUserController)
             .service(LogoutUserController)
             .service(GrantGroupUserController)
             .service(RevokeGroupUserController)
-            .service(UserRegisterEventCreateController)
-            .service(UserRegisterEventCommitController)
-            .service(UserRegisterEventCancelController)
-            .service(UserLoginEventCreateController)
-            .service(UserLoginEventCancelController)
-            .service(UserLoginEventCommitController)
-            .service(UserDeleteEventCreateController)
-            .service(UserDeleteEventCommitController)
-            .service(UserDeleteEventCancelController)
+            .service(GrantPermissionUserController)
+            .service(RevokePermissionUserController)
+            // batches first, the event routes match them too
+            .service(CreateEventBatchController)
+            .service(CommitEventBatchController)
+            .service(CancelEventBatchController)
+            .service(ListEventsController)
+            // commit and cancel first, the create route matches them too
+            .service(CommitEventController)
+            .service(CancelEventController)
+            .service(CreateEventController)
+            .service(ListApprovalsController)
+            .service(GetApprovalController)
+            .service(ApproveApprovalController)
+            .service(RejectApprovalController)
+            .service(ListWebhookDeliveriesController)
+            .service(RetryWebhookDeliveryController)
+            .service(ListWebhooksController)
+            .service(CreateWebhookController)
+            .service(DeleteWebhookController)
+            .service(ListAuditController)
     })
     .bind(("127.0.0.1", config.port))?
     .run()